@@ -0,0 +1,173 @@
+//! Animation module
+//!
+//! This module frames animated GIF and WebP inputs. Animated GIF outputs get every frame
+//! framed and re-assembled; other outputs intentionally use the first frame with a warning.
+
+use crate::image_processor::PhotoMetadata;
+use crate::options::ProcessOptions;
+use crate::resource::Resources;
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, Frame, ImageBuffer, ImageFormat, Rgba, RgbaImage};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Decodes all frames of an animated GIF or WebP image
+///
+/// # Arguments
+/// * `input` - Input file path
+///
+/// # Returns
+/// * `Result<Option<Vec<Frame>>, Box<dyn Error>>` - Frames if the input is animated with
+///   more than one frame, `None` for still images
+///
+/// # Errors
+/// Returns an error if the animation cannot be decoded
+pub fn read_animation(input: &Path) -> Result<Option<Vec<Frame>>, Box<dyn Error>> {
+    let format = match ImageFormat::from_path(input) {
+        Ok(format @ (ImageFormat::Gif | ImageFormat::WebP)) => format,
+        _ => return Ok(None),
+    };
+    let reader = BufReader::new(File::open(input)?);
+    let frames = match format {
+        ImageFormat::Gif => GifDecoder::new(reader)?.into_frames().collect_frames()?,
+        _ => {
+            let decoder = WebPDecoder::new(reader)?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder.into_frames().collect_frames()?
+        }
+    };
+    if frames.len() > 1 {
+        Ok(Some(frames))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Frames every frame of an animation and writes it as an animated GIF
+///
+/// If the output is not a GIF, only the first frame is framed and a warning is printed,
+/// since there is no animated encoder for the other formats.
+///
+/// # Arguments
+/// * `frames` - Decoded animation frames
+/// * `input` - Input file path
+/// * `output` - Output file path
+/// * `metadata` - Resolved metadata of the photo
+/// * `resources` - Font and scaling resources
+/// * `options` - Processing options
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - Ok if successful
+///
+/// # Errors
+/// Returns an error if the frames cannot be framed or the output cannot be written
+pub fn process_animation(
+    frames: Vec<Frame>,
+    input: &Path,
+    output: &Path,
+    metadata: &PhotoMetadata,
+    resources: &Resources,
+    options: &ProcessOptions,
+) -> Result<(), Box<dyn Error>> {
+    let Some(first) = frames.first() else {
+        return Err("Animation has no frames".into());
+    };
+    let width = first.buffer().width();
+    let background =
+        crate::image_processor::bar_background(first.buffer(), input, options.bar_color);
+    let bar = crate::image_processor::render_info_bar(
+        width, input, metadata, resources, options, background,
+    )?;
+
+    if ImageFormat::from_path(output).ok() != Some(ImageFormat::Gif) {
+        println!(
+            "[WARN] Animated input {} framed using its first frame only, use a .gif output to keep the animation",
+            input.display()
+        );
+        let framed = frame_with_bar(first.buffer(), &bar, options);
+        return crate::output::save_image(&framed, output, options);
+    }
+
+    println!(
+        "[INFO] Framing {} animation frames of {}",
+        frames.len(),
+        input.display()
+    );
+    let framed: Vec<Frame> = frames
+        .iter()
+        .map(|frame| {
+            let framed = frame_with_bar(frame.buffer(), &bar, options);
+            Frame::from_parts(framed.to_rgba8(), 0, 0, frame.delay())
+        })
+        .collect();
+    let mut data = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut data);
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(framed)?;
+    }
+    options.sink.write(output, &data)
+}
+
+/// Stacks a frame on top of a pre-rendered bar
+fn frame_with_bar(frame: &RgbaImage, bar: &RgbaImage, options: &ProcessOptions) -> DynamicImage {
+    let mut canvas: RgbaImage = ImageBuffer::from_pixel(
+        frame.width(),
+        frame.height() + bar.height(),
+        Rgba([255, 255, 255, 255]),
+    );
+    image::imageops::overlay(&mut canvas, frame, 0, 0);
+    image::imageops::overlay(&mut canvas, bar, 0, frame.height() as i64);
+    let framed = DynamicImage::ImageRgba8(canvas);
+    let orig = DynamicImage::ImageRgba8(frame.clone());
+    if let Some(canvas) = options.canvas {
+        crate::image_processor::fit_to_canvas(&orig, &framed, canvas, &options.backdrop)
+    } else if options.force_16_9 {
+        crate::image_processor::pad_to_16_9(&orig, &framed, &options.backdrop)
+    } else {
+        framed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Delay;
+
+    #[test]
+    fn test_process_animation_keeps_every_frame() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.gif");
+        let output = dir.path().join("output.gif");
+        let frames = (0..3u8).map(|i| {
+            Frame::from_parts(
+                RgbaImage::from_pixel(64, 48, Rgba([i * 80, 0, 0, 255])),
+                0,
+                0,
+                Delay::from_numer_denom_ms(100, 1),
+            )
+        });
+        GifEncoder::new(File::create(&input).unwrap())
+            .encode_frames(frames)
+            .unwrap();
+
+        let frames = read_animation(&input).unwrap().unwrap();
+        assert_eq!(frames.len(), 3);
+        let options = ProcessOptions {
+            info_height: 20,
+            ..ProcessOptions::default()
+        };
+        let resources = Resources::new(options.info_height, options.fonts_dir.as_deref()).unwrap();
+        let metadata = crate::image_processor::resolve_metadata(&input, &options);
+        process_animation(frames, &input, &output, &metadata, &resources, &options).unwrap();
+
+        let framed = read_animation(&output).unwrap().unwrap();
+        assert_eq!(framed.len(), 3);
+        assert_eq!(framed[0].buffer().dimensions(), (64, 68));
+    }
+}
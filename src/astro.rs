@@ -0,0 +1,116 @@
+//! Astronomy module
+//!
+//! This module computes the sun elevation and moon phase at the place and time of capture, for
+//! annotating landscape and astro frames. It uses low-precision formulas from the Astronomical
+//! Almanac, accurate to about a degree, which is plenty for labeling the light.
+
+use std::f64::consts::PI;
+
+/// Length of the synodic month in days
+const SYNODIC_MONTH: f64 = 29.530588853;
+/// Julian day of the new moon of 2000-01-06 18:14 UTC
+const REFERENCE_NEW_MOON: f64 = 2451550.26;
+
+/// Returns the elevation of the sun above the horizon in degrees
+///
+/// # Arguments
+/// * `julian_day` - Julian day of the capture time
+/// * `latitude` - Latitude in degrees, north positive
+/// * `longitude` - Longitude in degrees, east positive
+pub fn sun_elevation(julian_day: f64, latitude: f64, longitude: f64) -> f64 {
+    let days = julian_day - 2451545.0;
+    let anomaly = (357.529 + 0.98560028 * days).to_radians();
+    let mean_longitude = 280.459 + 0.98564736 * days;
+    let ecliptic_longitude =
+        (mean_longitude + 1.915 * anomaly.sin() + 0.020 * (2.0 * anomaly).sin()).to_radians();
+    let obliquity = (23.439 - 0.00000036 * days).to_radians();
+    let right_ascension = (obliquity.cos() * ecliptic_longitude.sin())
+        .atan2(ecliptic_longitude.cos())
+        .to_degrees();
+    let declination = (obliquity.sin() * ecliptic_longitude.sin()).asin();
+    let sidereal_time = 280.46061837 + 360.98564736629 * days + longitude;
+    let hour_angle = (sidereal_time - right_ascension).to_radians();
+    let latitude = latitude.to_radians();
+    (latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos())
+        .asin()
+        .to_degrees()
+}
+
+/// Names the light of a sun elevation, e.g. `Golden hour` between -4° and 6°
+pub fn light_label(elevation: f64) -> &'static str {
+    match elevation {
+        e if e >= 6.0 => "Daylight",
+        e if e >= -4.0 => "Golden hour",
+        e if e >= -6.0 => "Blue hour",
+        e if e >= -18.0 => "Twilight",
+        _ => "Night",
+    }
+}
+
+/// Returns the moon phase name and illuminated fraction
+///
+/// # Arguments
+/// * `julian_day` - Julian day of the capture time
+///
+/// # Returns
+/// * `(&'static str, f64)` - Phase name and illuminated fraction from 0 to 1
+pub fn moon_phase(julian_day: f64) -> (&'static str, f64) {
+    const PHASES: [&str; 8] = [
+        "New moon",
+        "Waxing crescent",
+        "First quarter",
+        "Waxing gibbous",
+        "Full moon",
+        "Waning gibbous",
+        "Last quarter",
+        "Waning crescent",
+    ];
+    let age = (julian_day - REFERENCE_NEW_MOON).rem_euclid(SYNODIC_MONTH) / SYNODIC_MONTH;
+    let illumination = (1.0 - (2.0 * PI * age).cos()) / 2.0;
+    let index = (age * 8.0).round() as usize % PHASES.len();
+    (PHASES[index], illumination)
+}
+
+/// Returns the astronomy template values of a capture
+///
+/// Provides `{sun}` with the sun elevation, `{light}` with its name and `{moon}` with the moon
+/// phase and illumination.
+///
+/// # Arguments
+/// * `julian_day` - Julian day of the capture time
+/// * `latitude` - Latitude in degrees, north positive
+/// * `longitude` - Longitude in degrees, east positive
+pub fn template_fields(
+    julian_day: f64,
+    latitude: f64,
+    longitude: f64,
+) -> Vec<(&'static str, String)> {
+    let elevation = sun_elevation(julian_day, latitude, longitude);
+    let (phase, illumination) = moon_phase(julian_day);
+    vec![
+        ("sun", format!("{:.0}°", elevation)),
+        ("light", light_label(elevation).to_string()),
+        ("moon", format!("{} {:.0}%", phase, illumination * 100.0)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_of_day::julian_day;
+
+    #[test]
+    fn test_sun_and_moon() {
+        // Solar noon near the June solstice in Greenwich, 90 - 51.5 + 23.4
+        let noon = sun_elevation(julian_day(2024, 6, 21, 12.0), 51.48, 0.0);
+        assert!((noon - 61.9).abs() < 1.0, "{}", noon);
+        let midnight = sun_elevation(julian_day(2024, 6, 21, 0.0), 51.48, 0.0);
+        assert_eq!(light_label(midnight), "Twilight");
+        assert_eq!(light_label(2.0), "Golden hour");
+
+        // Full moon of 2024-04-23 23:49 UTC
+        let (phase, illumination) = moon_phase(julian_day(2024, 4, 23, 23.8));
+        assert_eq!(phase, "Full moon");
+        assert!(illumination > 0.99);
+    }
+}
@@ -0,0 +1,483 @@
+//! Backdrop module
+//!
+//! This module defines the look of the backdrop surrounding the framed photo when it is padded
+//! to a target aspect ratio, and the finishing effects applied to it.
+
+use crate::logo_provider::LogoProvider;
+use clap::ValueEnum;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Pixel, Primitive, Rgba, RgbaImage};
+use num_traits::{NumCast, ToPrimitive};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Area the film grain is applied to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum GrainScope {
+    /// Only the padded backdrop around the framed photo
+    #[default]
+    Background,
+    /// The whole output canvas, including the photo and the bar
+    Canvas,
+}
+
+/// How a user-supplied background image covers the backdrop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum BackgroundFit {
+    /// Scale the image to cover the backdrop, cropping the overflow
+    #[default]
+    Scale,
+    /// Repeat the image at its original size
+    Tile,
+}
+
+/// Position of the framed photo within a padded canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Anchor {
+    /// Centered on both axes
+    Center,
+    /// Top edge, centered horizontally
+    Top,
+    /// Bottom edge, centered horizontally
+    #[default]
+    Bottom,
+    /// Left edge, centered vertically
+    Left,
+    /// Right edge, centered vertically
+    Right,
+    /// Top left corner
+    TopLeft,
+    /// Top right corner
+    TopRight,
+    /// Bottom left corner
+    BottomLeft,
+    /// Bottom right corner
+    BottomRight,
+}
+
+impl Anchor {
+    /// Computes the offset of the framed photo within the canvas
+    ///
+    /// # Arguments
+    /// * `free_width` - Horizontal space left around the framed photo
+    /// * `free_height` - Vertical space left around the framed photo
+    ///
+    /// # Returns
+    /// * `(i64, i64)` - Horizontal and vertical offset in pixels
+    pub fn offset(self, free_width: u32, free_height: u32) -> (i64, i64) {
+        let (horizontal, vertical) = match self {
+            Anchor::Center => (1, 1),
+            Anchor::Top => (1, 0),
+            Anchor::Bottom => (1, 2),
+            Anchor::Left => (0, 1),
+            Anchor::Right => (2, 1),
+            Anchor::TopLeft => (0, 0),
+            Anchor::TopRight => (2, 0),
+            Anchor::BottomLeft => (0, 2),
+            Anchor::BottomRight => (2, 2),
+        };
+        (
+            (free_width * horizontal / 2) as i64,
+            (free_height * vertical / 2) as i64,
+        )
+    }
+}
+
+/// Source the backdrop is filled from
+#[derive(Debug, Clone, Default)]
+pub enum BackdropSource {
+    /// Blurred copy of the original photo
+    #[default]
+    Blur,
+    /// User-supplied image
+    Image {
+        /// Path to the background image
+        path: PathBuf,
+        /// How the image covers the backdrop
+        fit: BackgroundFit,
+    },
+    /// Solid color
+    Color(Rgba<u8>),
+    /// Solid dominant color of the camera brand logo, resolved per photo
+    BrandColor,
+    /// Solid average color of the photo border, extending the photo edges
+    Edge,
+}
+
+/// Options controlling the padded backdrop
+#[derive(Debug, Clone)]
+pub struct BackdropOptions {
+    /// Source the backdrop is filled from
+    pub source: BackdropSource,
+    /// Position of the framed photo within the padded canvas
+    pub anchor: Anchor,
+    /// Film grain intensity between 0 and 1, `None` to disable
+    pub grain: Option<f32>,
+    /// Area the film grain is applied to
+    pub grain_scope: GrainScope,
+    /// Vignette strength between 0 and 1, `None` to disable
+    pub vignette: Option<f32>,
+    /// Vignette falloff exponent, higher values keep the darkening closer to the corners
+    pub vignette_falloff: f32,
+}
+
+impl Default for BackdropOptions {
+    fn default() -> Self {
+        BackdropOptions {
+            source: BackdropSource::Blur,
+            anchor: Anchor::Bottom,
+            grain: None,
+            grain_scope: GrainScope::Background,
+            vignette: None,
+            vignette_falloff: 2.0,
+        }
+    }
+}
+
+impl BackdropOptions {
+    /// Resolves the brand color source of the backdrop for a photo
+    ///
+    /// Falls back to the blurred photo with a warning if no brand logo is found.
+    ///
+    /// # Arguments
+    /// * `input` - Path to the photo
+    /// * `custom_logo` - Optional custom logo replacing the brand logo
+    /// * `logo_provider` - Source of the brand logos
+    pub fn resolve_brand_color(
+        &mut self,
+        input: &Path,
+        custom_logo: Option<&Path>,
+        logo_provider: &dyn LogoProvider,
+    ) {
+        if !matches!(self.source, BackdropSource::BrandColor) {
+            return;
+        }
+        let logo = crate::exif::read_exif_info(input).ok().and_then(|exif| {
+            crate::resource::load_camera_logo(
+                exif.camera_model.trim_matches('"'),
+                custom_logo,
+                logo_provider,
+            )
+            .ok()
+            .flatten()
+        });
+        self.source = match logo.and_then(|logo| dominant_color(&logo.to_rgba8())) {
+            Some(color) => BackdropSource::Color(color),
+            None => {
+                println!("[WARN] No brand logo color found, using blurred photo as backdrop");
+                BackdropSource::Blur
+            }
+        };
+    }
+}
+
+/// Parses a backdrop color given as `brand`, `edge` or a hex color such as `#1a1a1a`
+pub fn parse_pad_color(value: &str) -> Result<BackdropSource, String> {
+    if value.eq_ignore_ascii_case("brand") {
+        return Ok(BackdropSource::BrandColor);
+    }
+    if value.eq_ignore_ascii_case("edge") {
+        return Ok(BackdropSource::Edge);
+    }
+    match crate::theme::parse_hex_color(value) {
+        Ok(Rgba([r, g, b, _])) => Ok(BackdropSource::Color(Rgba([r, g, b, 255]))),
+        Err(_) => Err(format!(
+            "invalid color: {} (expected `brand`, `edge` or a hex color like #1a1a1a)",
+            value
+        )),
+    }
+}
+
+/// Finds the most frequent color among the opaque pixels of a logo
+///
+/// Colors are grouped in coarse buckets so antialiased edges don't split the main color.
+///
+/// # Arguments
+/// * `logo` - Logo image
+///
+/// # Returns
+/// * `Option<Rgba<u8>>` - Average color of the most frequent bucket, `None` if fully transparent
+pub fn dominant_color(logo: &RgbaImage) -> Option<Rgba<u8>> {
+    let mut buckets: HashMap<(u8, u8, u8), (u64, [u64; 3])> = HashMap::new();
+    for pixel in logo.pixels().filter(|pixel| pixel[3] >= 128) {
+        let key = (pixel[0] >> 4, pixel[1] >> 4, pixel[2] >> 4);
+        let (count, sums) = buckets.entry(key).or_default();
+        *count += 1;
+        for c in 0..3 {
+            sums[c] += pixel[c] as u64;
+        }
+    }
+    let (_, (count, sums)) = buckets
+        .into_iter()
+        .max_by_key(|(key, (count, _))| (*count, *key))?;
+    Some(Rgba([
+        (sums[0] / count) as u8,
+        (sums[1] / count) as u8,
+        (sums[2] / count) as u8,
+        255,
+    ]))
+}
+
+/// Averages the pixels along the border of a photo
+///
+/// The border is about 1% of the short edge thick, so single-pixel edge artifacts don't
+/// dominate the color.
+///
+/// # Arguments
+/// * `img` - Photo to sample
+/// * `bottom_only` - Whether to sample only the bottom edge, e.g. for a bar below the photo
+///
+/// # Returns
+/// * `Rgba<u8>` - Opaque average color of the border
+pub fn edge_color(img: &RgbaImage, bottom_only: bool) -> Rgba<u8> {
+    let (width, height) = img.dimensions();
+    let thickness = (width.min(height) / 100).max(1);
+    let mut count = 0u64;
+    let mut sums = [0u64; 3];
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let on_border = if bottom_only {
+            y + thickness >= height
+        } else {
+            x < thickness || y < thickness || x + thickness >= width || y + thickness >= height
+        };
+        if on_border {
+            count += 1;
+            for c in 0..3 {
+                sums[c] += pixel[c] as u64;
+            }
+        }
+    }
+    let count = count.max(1);
+    Rgba([
+        (sums[0] / count) as u8,
+        (sums[1] / count) as u8,
+        (sums[2] / count) as u8,
+        255,
+    ])
+}
+
+/// Renders the backdrop, before effects, for a padded canvas
+///
+/// A background image that cannot be loaded falls back to the blurred photo with a warning.
+///
+/// # Arguments
+/// * `orig_img` - Original photo, used by the blurred backdrop
+/// * `width` - Width of the canvas in pixels
+/// * `height` - Height of the canvas in pixels
+/// * `source` - Source the backdrop is filled from
+///
+/// # Returns
+/// * `DynamicImage` - Backdrop of the canvas size, with the bit depth of the photo if blurred
+pub fn render_backdrop(
+    orig_img: &DynamicImage,
+    width: u32,
+    height: u32,
+    source: &BackdropSource,
+) -> DynamicImage {
+    match source {
+        BackdropSource::Color(color) => {
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, *color))
+        }
+        BackdropSource::Edge => DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            width,
+            height,
+            edge_color(&orig_img.to_rgba8(), false),
+        )),
+        BackdropSource::Blur | BackdropSource::BrandColor => {
+            blurred_backdrop(orig_img, width, height)
+        }
+        BackdropSource::Image { path, fit } => match image::open(path) {
+            Ok(background) => {
+                DynamicImage::ImageRgba8(image_backdrop(&background, width, height, *fit))
+            }
+            Err(e) => {
+                println!(
+                    "[WARN] Failed to load background {}: {}, using blurred photo",
+                    path.display(),
+                    e
+                );
+                blurred_backdrop(orig_img, width, height)
+            }
+        },
+    }
+}
+
+/// Renders a blurred and stretched copy of the photo
+fn blurred_backdrop(orig_img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+    // First resize the original image to have a maximum dimension of 512 pixels (maintaining aspect ratio)
+    let (orig_width, orig_height) = orig_img.dimensions();
+    let scale = 512.0 / (orig_width.max(orig_height) as f32);
+    let small_width = (orig_width as f32 * scale).round().max(1.0) as u32;
+    let small_height = (orig_height as f32 * scale).round().max(1.0) as u32;
+    let small = orig_img.resize_exact(small_width, small_height, FilterType::Triangle);
+    // Apply blur to the resized image
+    let blurred = small.blur(20.0);
+    // Resize to target dimensions
+    blurred.resize_exact(width, height, FilterType::Gaussian)
+}
+
+/// Renders a user-supplied image scaled or tiled over the canvas
+fn image_backdrop(
+    background: &DynamicImage,
+    width: u32,
+    height: u32,
+    fit: BackgroundFit,
+) -> RgbaImage {
+    match fit {
+        BackgroundFit::Scale => background
+            .resize_to_fill(width, height, FilterType::Lanczos3)
+            .to_rgba8(),
+        BackgroundFit::Tile => {
+            let tile = background.to_rgba8();
+            RgbaImage::from_fn(width, height, |x, y| {
+                *tile.get_pixel(x % tile.width(), y % tile.height())
+            })
+        }
+    }
+}
+
+/// Darkens the edges of an image towards its corners
+///
+/// # Arguments
+/// * `img` - Image to darken
+/// * `strength` - Darkening in the corners between 0 (none) and 1 (black)
+/// * `falloff` - Exponent applied to the normalized distance from the center
+pub fn apply_vignette<P: Pixel>(
+    img: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+    strength: f32,
+    falloff: f32,
+) {
+    let strength = strength.clamp(0.0, 1.0);
+    let center_x = img.width() as f32 / 2.0;
+    let center_y = img.height() as f32 / 2.0;
+    let max_distance = (center_x * center_x + center_y * center_y).sqrt().max(1.0);
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let dx = x as f32 + 0.5 - center_x;
+        let dy = y as f32 + 0.5 - center_y;
+        let distance = (dx * dx + dy * dy).sqrt() / max_distance;
+        let factor = 1.0 - strength * distance.powf(falloff.max(0.01));
+        for channel in pixel.channels_mut().iter_mut().take(3) {
+            let value = channel.to_f32().unwrap_or(0.0);
+            *channel = NumCast::from((value * factor).round()).unwrap_or(*channel);
+        }
+    }
+}
+
+/// Adds monochrome film grain to an image
+///
+/// The noise is derived from pixel coordinates so repeated runs produce identical outputs.
+///
+/// # Arguments
+/// * `img` - Image to add the grain to
+/// * `intensity` - Grain intensity between 0 and 1, the maximum offset of a channel value
+pub fn apply_grain<P: Pixel>(img: &mut ImageBuffer<P, Vec<P::Subpixel>>, intensity: f32) {
+    let max = P::Subpixel::DEFAULT_MAX_VALUE.to_f32().unwrap_or(255.0);
+    let amplitude = intensity.clamp(0.0, 1.0) * max;
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let noise = hash_noise(x, y) * 2.0 - 1.0;
+        let offset = noise * amplitude;
+        for channel in pixel.channels_mut().iter_mut().take(3) {
+            let value = (channel.to_f32().unwrap_or(0.0) + offset)
+                .round()
+                .clamp(0.0, max);
+            *channel = NumCast::from(value).unwrap_or(*channel);
+        }
+    }
+}
+
+/// Returns a pseudo-random value in `[0, 1)` for a pixel position
+fn hash_noise(x: u32, y: u32) -> f32 {
+    // SplitMix64 finalizer over the packed coordinates
+    let mut z = ((x as u64) << 32 | y as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_image_backdrop_fits_canvas() {
+        let mut tile = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255]));
+        tile.put_pixel(1, 0, Rgba([255, 255, 255, 255]));
+        let tile = DynamicImage::ImageRgba8(tile);
+
+        let tiled = image_backdrop(&tile, 5, 3, BackgroundFit::Tile);
+        assert_eq!(tiled.dimensions(), (5, 3));
+        assert_eq!(tiled.get_pixel(3, 2)[0], 255);
+        assert_eq!(tiled.get_pixel(4, 2)[0], 0);
+
+        let scaled = image_backdrop(&tile, 16, 9, BackgroundFit::Scale);
+        assert_eq!(scaled.dimensions(), (16, 9));
+    }
+
+    #[test]
+    fn test_dominant_color_and_pad_color() {
+        let mut logo = RgbaImage::from_pixel(10, 10, Rgba([0, 0, 0, 0]));
+        for x in 0..6 {
+            logo.put_pixel(x, 0, Rgba([204, 0, 0, 255]));
+        }
+        logo.put_pixel(9, 9, Rgba([255, 255, 255, 255]));
+        assert_eq!(dominant_color(&logo), Some(Rgba([204, 0, 0, 255])));
+        assert_eq!(dominant_color(&RgbaImage::new(2, 2)), None);
+
+        assert!(matches!(
+            parse_pad_color("#1A2b3c"),
+            Ok(BackdropSource::Color(Rgba([0x1a, 0x2b, 0x3c, 255])))
+        ));
+        assert!(matches!(
+            parse_pad_color("brand"),
+            Ok(BackdropSource::BrandColor)
+        ));
+        assert!(matches!(parse_pad_color("edge"), Ok(BackdropSource::Edge)));
+        assert!(parse_pad_color("#12345").is_err());
+        assert!(parse_pad_color("red").is_err());
+    }
+
+    #[test]
+    fn test_edge_color() {
+        let mut img = RgbaImage::from_pixel(200, 100, Rgba([0, 0, 0, 255]));
+        for x in 0..200 {
+            img.put_pixel(x, 99, Rgba([200, 100, 50, 255]));
+        }
+        assert_eq!(edge_color(&img, true), Rgba([200, 100, 50, 255]));
+        assert!(edge_color(&img, false)[0] < 100);
+    }
+
+    #[test]
+    fn test_anchor_offset() {
+        assert_eq!(Anchor::Bottom.offset(100, 50), (50, 50));
+        assert_eq!(Anchor::Center.offset(100, 50), (50, 25));
+        assert_eq!(Anchor::TopLeft.offset(100, 50), (0, 0));
+        assert_eq!(Anchor::Right.offset(100, 0), (100, 0));
+    }
+
+    #[test]
+    fn test_vignette_darkens_corners() {
+        let mut img = RgbaImage::from_pixel(101, 101, Rgba([200, 200, 200, 255]));
+        apply_vignette(&mut img, 0.5, 2.0);
+        let center = img.get_pixel(50, 50)[0];
+        let edge = img.get_pixel(0, 50)[0];
+        let corner = img.get_pixel(0, 0)[0];
+        assert!(center >= 199);
+        assert!(corner < edge && edge < center);
+        assert!((corner as i32 - 100).abs() <= 2);
+    }
+
+    #[test]
+    fn test_grain_is_subtle_and_deterministic() {
+        let mut first = RgbaImage::from_pixel(32, 32, Rgba([128, 128, 128, 255]));
+        let mut second = first.clone();
+        apply_grain(&mut first, 0.05);
+        apply_grain(&mut second, 0.05);
+        assert_eq!(first, second);
+        assert!(first.pixels().any(|p| p[0] != 128));
+        assert!(first
+            .pixels()
+            .all(|p| (p[0] as i32 - 128).abs() <= 13 && p[0] == p[1] && p[3] == 255));
+    }
+}
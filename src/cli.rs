@@ -2,30 +2,675 @@
 //!
 //! This module defines the command line arguments structure and parsing logic.
 
-use clap::Parser;
+use crate::backdrop::{parse_pad_color, Anchor, BackdropSource, BackgroundFit, GrainScope};
+use crate::dedupe::DuplicateMode;
+use crate::logo_provider::LogoSource;
+use crate::options::{BarColor, MissingExif, ParamElement, SmallImages};
+use crate::output::{ChromaSubsampling, EncoderEffort, OutputFormat, DEFAULT_JPEG_QUALITY};
+use crate::overlay::{parse_overlay_slot, OverlaySlot};
+use crate::print::{parse_bleed, Length, PaperSize};
+use crate::profile::SourceProfile;
+use crate::proof::RenderingIntent;
+use crate::safe_area::SafeArea;
+use crate::sharpness::BlurMode;
+use crate::source::{parse_shard, Shard, DEFAULT_EXTENSIONS};
+use crate::theme::{
+    parse_text_outline, parse_text_pill, parse_text_shadow, LogoOrder, LogoTreatment, ParamField,
+    TextCase, TextOutline, TextPill, TextShadow,
+};
+use crate::time_of_day::DEFAULT_DATE_FORMAT;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 /// Command line interface for the image processing tool
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Cli {
+    /// Subcommand to run instead of framing INPUT into OUTPUT
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Input file or directory path
-    #[arg(value_name = "INPUT")]
-    pub input: PathBuf,
+    #[arg(value_name = "INPUT", required = true)]
+    pub input: Option<PathBuf>,
 
     /// Output file or directory path
-    #[arg(value_name = "OUTPUT")]
-    pub output: PathBuf,
+    #[arg(value_name = "OUTPUT", required = true)]
+    pub output: Option<PathBuf>,
+
+    /// TOML configuration file, e.g. with manual lens labels
+    #[arg(long, value_name = "FILE", env = "LENSIGHT_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Directory with `DejaVuSans.ttf` and `DejaVuSans-Bold.ttf` [default: ./fonts]
+    #[arg(long, value_name = "DIR", env = "LENSIGHT_FONTS_DIR")]
+    pub fonts_dir: Option<PathBuf>,
+
+    /// Directory with `<brand>.png` logos [default: ./logos]
+    #[arg(long, value_name = "DIR", env = "LENSIGHT_LOGOS_DIR")]
+    pub logos_dir: Option<PathBuf>,
+
+    /// Where brand logos are looked up
+    #[arg(long, value_enum, default_value_t = LogoSource::Auto)]
+    pub logo_source: LogoSource,
+
+    /// URL of remote brand logos, with `{brand}` replaced by the lowercase brand name
+    #[arg(long, value_name = "URL", env = "LENSIGHT_LOGO_URL")]
+    pub logo_url: Option<String>,
+
+    /// Never look up fonts and logos in the working directory; use the configured directories
+    /// or the embedded defaults only
+    #[arg(long, env = "LENSIGHT_STATELESS", value_parser = clap::builder::FalseyValueParser::new())]
+    pub stateless: bool,
 
     /// Height of the information bar in pixels
     #[arg(short, long, default_value_t = 180)]
     pub info_height: u32,
 
+    /// Pixel density multiplier of the bar, text and logos for high-DPI screens, e.g. 2
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=4))]
+    pub scale: u32,
+
     /// Force 16:9 aspect ratio for output images
     #[arg(short, long)]
     pub force_16_9: bool,
 
+    /// Exact output dimensions, e.g. `1920x1080`; the framed photo is scaled to fit and padded
+    #[arg(long, value_parser = parse_dimensions, conflicts_with = "force_16_9")]
+    pub canvas: Option<(u32, u32)>,
+
+    /// Size the canvas to a paper at `--dpi` (300 if not given), turned to the orientation of
+    /// the framed photo: a4, a3, 4x6 or 5x7
+    #[arg(long, value_enum, conflicts_with_all = ["canvas", "force_16_9"])]
+    pub paper: Option<PaperSize>,
+
+    /// Position of the framed photo within the padded canvas
+    #[arg(long, value_enum, default_value_t = Anchor::Bottom)]
+    pub anchor: Anchor,
+
+    /// Image used as the padded backdrop instead of the blurred photo
+    #[arg(long)]
+    pub background: Option<PathBuf>,
+
+    /// How the background image covers the backdrop
+    #[arg(long, value_enum, default_value_t = BackgroundFit::Scale, requires = "background")]
+    pub background_fit: BackgroundFit,
+
+    /// Solid backdrop color instead of the blurred photo, `brand` for the logo color, `edge` for
+    /// the photo border color or a hex color
+    #[arg(long, value_name = "COLOR", value_parser = parse_pad_color, conflicts_with = "background")]
+    pub pad_color: Option<BackdropSource>,
+
+    /// Film grain intensity between 0 and 1 (e.g. 0.03) to hide banding in the padded backdrop
+    #[arg(long, value_parser = parse_unit_interval)]
+    pub grain: Option<f32>,
+
+    /// Area the film grain is applied to
+    #[arg(long, value_enum, default_value_t = GrainScope::Background, requires = "grain")]
+    pub grain_scope: GrainScope,
+
+    /// Vignette strength between 0 and 1 darkening the corners of the padded backdrop
+    #[arg(long, value_parser = parse_unit_interval)]
+    pub vignette: Option<f32>,
+
+    /// Vignette falloff exponent, higher values keep the darkening closer to the corners
+    #[arg(long, default_value_t = 2.0, requires = "vignette")]
+    pub vignette_falloff: f32,
+
     /// Path to a custom logo file
     #[arg(long)]
     pub logo: Option<PathBuf>,
+
+    /// Caption rendered below the shooting parameters, supports tokens such as `{filename}`,
+    /// `{filesize}`, `{dimensions}`, `{camera}` and `{iso}`
+    #[arg(long)]
+    pub caption: Option<String>,
+
+    /// Annotation appended to the lens line, e.g. "via M42 adapter"
+    #[arg(long)]
+    pub lens_suffix: Option<String>,
+
+    /// Show where the photo was taken below the lens, e.g. `Kyoto, Japan`, from the GPS position
+    /// and a bundled offline city table; coordinates are shown when no city is near
+    #[arg(long)]
+    pub show_location: bool,
+
+    /// Append the capture date to the shooting parameters, formatted with strftime-style
+    /// specifiers: `%Y`, `%y`, `%m`, `%d`, `%e`, `%B`, `%b`, `%A`, `%a`, `%H`, `%I`, `%M`, `%S`,
+    /// `%p` and `%%`
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1, default_missing_value = DEFAULT_DATE_FORMAT)]
+    pub show_date: Option<String>,
+
+    /// Show an attribution line below the parameters from the EXIF Copyright notice, or from
+    /// Artist and the capture year, e.g. `© 2024 Jane Doe`
+    #[arg(long)]
+    pub show_copyright: bool,
+
+    /// Copyright notice of the attribution line, for cameras that don't store one, e.g.
+    /// "Jane Doe"; implies `--show-copyright`
+    #[arg(long, value_name = "TEXT")]
+    pub copyright: Option<String>,
+
+    /// Template of the shooting parameters, e.g. "{focal}mm | f/{aperture} | ISO {iso}"; only `|`
+    /// separates fields, which wrap and drop one by one, and fields whose tokens have no value
+    /// are left out
+    #[arg(long, value_name = "TEMPLATE")]
+    pub params_template: Option<String>,
+
+    /// Elements appended to the shooting parameters when EXIF has them, e.g. `ev,flash`; one of
+    /// altitude, depth, ev, flash, white-balance and metering
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub param_elements: Vec<ParamElement>,
+
+    /// Parameter fields dropped, first to last, while the parameters don't fit next to the lens
+    #[arg(long, value_enum, value_delimiter = ',', default_values = ["date", "extras", "iso", "shutter"])]
+    pub drop_order: Vec<ParamField>,
+
+    /// Camera model overriding or filling EXIF, e.g. "Nikon FM2" for film scans
+    #[arg(long)]
+    pub camera: Option<String>,
+
+    /// Lens model overriding or filling EXIF, e.g. "Helios 44-2 58mm f/2"
+    #[arg(long)]
+    pub lens: Option<String>,
+
+    /// Focal length in millimeters overriding or filling EXIF, e.g. "58"
+    #[arg(long)]
+    pub focal: Option<String>,
+
+    /// Aperture f-number overriding or filling EXIF, e.g. "2.8"
+    #[arg(long)]
+    pub aperture: Option<String>,
+
+    /// Exposure time overriding or filling EXIF, e.g. "1/125"
+    #[arg(long)]
+    pub shutter: Option<String>,
+
+    /// ISO sensitivity overriding or filling EXIF, e.g. "400"
+    #[arg(long)]
+    pub iso: Option<String>,
+
+    /// Film stock available to templates as `{film}`, e.g. "Kodak Portra 400"
+    #[arg(long)]
+    pub film_stock: Option<String>,
+
+    /// Film developer available to templates as `{developer}`
+    #[arg(long)]
+    pub developer: Option<String>,
+
+    /// Scan resolution available to templates as `{scan}`, e.g. "3200 dpi"
+    #[arg(long)]
+    pub scan_resolution: Option<String>,
+
+    /// CSV file mapping file names to camera, lens and settings values that override or fill
+    /// EXIF, with a `filename` column and optional `camera`, `lens`, `focal`, `aperture`,
+    /// `shutter`, `iso`, `film_stock`, `developer` and `scan_resolution` columns
+    #[arg(long, value_name = "CSV")]
+    pub metadata_csv: Option<PathBuf>,
+
+    /// Multiply the focal length and aperture by the teleconverter named in the lens model, for
+    /// bodies that record the values of the bare lens
+    #[arg(long)]
+    pub apply_teleconverter: bool,
+
+    /// Fill EXIF fields missing from a file with the fields of the previous file in the batch
+    #[arg(long)]
+    pub inherit_exif: bool,
+
+    /// Salvage the readable fields of malformed EXIF blocks instead of treating them as missing
+    #[arg(long)]
+    pub tolerant_exif: bool,
+
+    /// Editor the inputs were exported from, to find the metadata it drops on export
+    #[arg(long, value_enum)]
+    pub source_profile: Option<SourceProfile>,
+
+    /// Fill EXIF fields missing from a file with the EXIF of a same-stem RAW or HEIC file
+    #[arg(long)]
+    pub companion_exif: bool,
+
+    /// Draw the text over the photo instead of adding a bar, camera and lens bottom-left and
+    /// settings bottom-right unless slots are assigned with `--overlay-slot`
+    #[arg(long)]
+    pub overlay: bool,
+
+    /// Template of an overlay slot as `SLOT=TEMPLATE`, e.g. `top-right=© Jane Doe`; slots are
+    /// top-left, top-right, bottom-left, bottom-right and bottom-center. Implies `--overlay`
+    #[arg(long, value_name = "SLOT=TEMPLATE", value_parser = parse_overlay_slot)]
+    pub overlay_slot: Vec<(OverlaySlot, String)>,
+
+    /// Directory of transparent PNG frames, e.g. sponsor logos or the event hashtag, layered
+    /// over the outputs in rotation; a `schedule.toml` with `[[slot]]` entries (`start`, `end`,
+    /// `frame`) picks the frame from the capture time instead
+    #[arg(long, value_name = "DIR")]
+    pub event_pack: Option<PathBuf>,
+
+    /// Background color of the information bar, `edge` extends the bottom edge of the photo and
+    /// `time-of-day` tints the bar warm, cool or dark from the capture hour
+    #[arg(long, value_enum, default_value_t = BarColor::White)]
+    pub bar_color: BarColor,
+
+    /// Draw the layout boxes, margins and rule-of-thirds guides on the output
+    #[arg(long)]
+    pub debug_layout: bool,
+
+    /// Draw the safe area of a social platform over the output, dimming the cropped or covered
+    /// regions
+    #[arg(long, value_enum)]
+    pub safe_area: Option<SafeArea>,
+
+    /// Bleed added around the framed output for lab printing, up to 1 inch in `mm`, `cm` or `in`,
+    /// e.g. `3mm`; the edge pixels are extended past the trim line
+    #[arg(long, value_name = "LENGTH", value_parser = parse_bleed)]
+    pub bleed: Option<Length>,
+
+    /// Draw crop marks outside the bleed, in line with the trim edges
+    #[arg(long)]
+    pub crop_marks: bool,
+
+    /// Print resolution in pixels per inch, used to convert `--bleed` (300 if not given) and
+    /// written to JPEG outputs
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    pub dpi: Option<u16>,
+
+    /// Convert the outputs from sRGB to the ICC profile of a printer and paper, for labs
+    /// printing without color management (requires the `color-management` feature)
+    #[arg(long, value_name = "FILE")]
+    pub print_profile: Option<PathBuf>,
+
+    /// Rendering intent of the conversion to `--print-profile`
+    #[arg(long, value_enum, default_value_t = RenderingIntent::Relative, requires = "print_profile")]
+    pub rendering_intent: RenderingIntent,
+
+    /// Bar rendered for images without readable EXIF
+    #[arg(long, value_enum, default_value_t = MissingExif::Blank)]
+    pub missing_exif: MissingExif,
+
+    /// Handling of images whose long edge is below `--min-size`, such as thumbnails
+    #[arg(long, value_enum)]
+    pub small_images: Option<SmallImages>,
+
+    /// Minimum long edge in pixels for images to be framed as they are
+    #[arg(long, default_value_t = 600)]
+    pub min_size: u32,
+
+    /// Show the file name on the placeholder bar of images without EXIF
+    #[arg(long)]
+    pub placeholder_filename: bool,
+
+    /// Casing of the camera model text
+    #[arg(long, value_enum, default_value_t = TextCase::AsIs)]
+    pub camera_case: TextCase,
+
+    /// Casing of the lens model text
+    #[arg(long, value_enum, default_value_t = TextCase::AsIs)]
+    pub lens_case: TextCase,
+
+    /// Casing of the shooting parameters text
+    #[arg(long, value_enum, default_value_t = TextCase::AsIs)]
+    pub params_case: TextCase,
+
+    /// Path to a secondary logo (e.g. a studio mark) drawn next to the brand logo
+    #[arg(long)]
+    pub secondary_logo: Option<PathBuf>,
+
+    /// Order of the brand logo and the secondary logo
+    #[arg(long, value_enum, default_value_t = LogoOrder::BrandFirst)]
+    pub logo_order: LogoOrder,
+
+    /// Gap between the brand logo and the secondary logo in pixels
+    #[arg(long, default_value_t = 24)]
+    pub logo_gap: u32,
+
+    /// Opacity of the brand logo, from 0.0 (invisible) to 1.0 (opaque)
+    #[arg(long, default_value_t = 1.0, value_parser = parse_unit_interval)]
+    pub logo_opacity: f32,
+
+    /// Color treatment of the brand logo
+    #[arg(long, value_enum, default_value_t = LogoTreatment::Color)]
+    pub logo_treatment: LogoTreatment,
+
+    /// Maximum total width of the logos in pixels; logos are also kept clear of the text
+    #[arg(long)]
+    pub logo_max_width: Option<u32>,
+
+    /// Shift of all text from its font-metrics position in pixels, negative moves it up
+    #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+    pub baseline_offset: i32,
+
+    /// Drop shadow behind the text as `OFFSET_X,OFFSET_Y,BLUR[,COLOR]` with a blur of 0 to 64, e.g.
+    /// `2,2,3,#000000a0`
+    #[arg(long, value_name = "SHADOW", value_parser = parse_text_shadow)]
+    pub text_shadow: Option<TextShadow>,
+
+    /// Outline around the text as `WIDTH[,COLOR]`, e.g. `2,#000000`
+    #[arg(long, value_name = "OUTLINE", value_parser = parse_text_outline)]
+    pub text_outline: Option<TextOutline>,
+
+    /// Rounded pill behind each text block as `COLOR[,PADDING]`, e.g. `#00000080,12`
+    #[arg(long, value_name = "PILL", value_parser = parse_text_pill)]
+    pub text_pill: Option<TextPill>,
+
+    /// Convert every output of a directory to this format, replacing the input extensions
+    #[arg(long, value_enum)]
+    pub convert_to: Option<OutputFormat>,
+
+    /// Encode the final image in this format, whatever the output file extension
+    #[arg(long, value_enum, conflicts_with = "convert_to")]
+    pub output_format: Option<OutputFormat>,
+
+    /// Effort of the PNG, lossy WebP and AVIF encoders, trading encoding time for file size
+    #[arg(long, value_enum, default_value_t = EncoderEffort::Default)]
+    pub effort: EncoderEffort,
+
+    /// Chroma subsampling of JPEG output
+    #[arg(long, value_enum, default_value_t = ChromaSubsampling::Yuv420)]
+    pub chroma_subsampling: ChromaSubsampling,
+
+    /// Quality of JPEG, lossy WebP and AVIF output, from 1 (smallest files) to 100 (best quality)
+    #[arg(long, default_value_t = DEFAULT_JPEG_QUALITY, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub quality: u8,
+
+    /// Encode WebP outputs lossy at `--quality` instead of lossless (requires the `lossy-webp`
+    /// feature)
+    #[arg(long)]
+    pub lossy_webp: bool,
+
+    /// Additional long-edge sizes exported next to each output, e.g. `2048,1080`
+    #[arg(long, value_delimiter = ',', value_parser = clap::value_parser!(u32).range(1..))]
+    pub export_sizes: Vec<u32>,
+
+    /// Run as a background job: lower the process priority and encode the export sizes one
+    /// after the other, so the workstation stays usable during long batches
+    #[arg(long)]
+    pub low_priority: bool,
+
+    /// Skip the check that the destination has room for the estimated size of the outputs
+    /// before a batch starts
+    #[arg(long)]
+    pub no_space_check: bool,
+
+    /// Guarantee the inputs are left untouched: refuse outputs that overlap the input tree and
+    /// subcommands that change files in place
+    #[arg(long, global = true)]
+    pub paranoid: bool,
+
+    /// Also export the rendered bar alone as a transparent PNG layer next to each output
+    #[arg(long)]
+    pub bar_layer: bool,
+
+    /// Render only the information bar (using the input photo for EXIF) instead of the framed photo
+    #[arg(long)]
+    pub bar_only: bool,
+
+    /// Width of the bar in pixels when rendering only the bar, defaults to the photo width
+    #[arg(long, requires = "bar_only")]
+    pub bar_width: Option<u32>,
+
+    /// Lock the bar layout of a sorted sequence to its first frame to avoid jitter in timelapses
+    #[arg(long)]
+    pub lock_layout: bool,
+
+    /// Extensions of the files picked up from an input directory, matched case-insensitively
+    #[arg(long, value_delimiter = ',', default_values_t = DEFAULT_EXTENSIONS.iter().map(|ext| ext.to_string()))]
+    pub extensions: Vec<String>,
+
+    /// Also pick up files of an input directory whose content matches one of the extensions,
+    /// such as exports without an extension
+    #[arg(long)]
+    pub sniff: bool,
+
+    /// Process only one part of a batch, e.g. `2/5` for the second of five, so the same command
+    /// can run on several machines; each image always falls in the same part
+    #[arg(long, value_name = "INDEX/COUNT", value_parser = parse_shard)]
+    pub shard: Option<Shard>,
+
+    /// Read INPUT as a list of image paths, one per line, or from standard input if INPUT is `-`
+    #[arg(long)]
+    pub files_from: bool,
+
+    /// Write the outputs into this zip archive instead of OUTPUT, named relative to OUTPUT
+    #[arg(long, value_name = "ARCHIVE", conflicts_with = "video")]
+    pub zip: Option<PathBuf>,
+
+    /// Pipe the framed outputs of a directory to ffmpeg to produce a slideshow video
+    #[arg(long, value_name = "VIDEO")]
+    pub video: Option<PathBuf>,
+
+    /// Seconds every photo stays on screen in the slideshow video
+    #[arg(long, default_value_t = 3.0, value_parser = parse_seconds)]
+    pub video_seconds: f32,
+
+    /// Size of the slideshow video, frames are letterboxed to fit
+    #[arg(long, value_parser = parse_dimensions, default_value = "1920x1080")]
+    pub video_size: (u32, u32),
+
+    /// Warn about photos whose color profile gamut significantly exceeds sRGB
+    #[arg(long)]
+    pub gamut_check: bool,
+
+    /// Apply a mild automatic levels correction to the photo before framing
+    #[arg(long)]
+    pub enhance: bool,
+
+    /// Detect near-duplicate frames of a batch, such as bursts, and skip or report them
+    #[arg(long, value_enum)]
+    pub duplicates: Option<DuplicateMode>,
+
+    /// Maximum number of differing perceptual hash bits (0 to 64) between near-duplicates
+    #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u32).range(0..=64))]
+    pub duplicate_threshold: u32,
+
+    /// Check the sharpness of every photo of a batch and skip or flag the blurry ones
+    #[arg(long, value_enum)]
+    pub blur_check: Option<BlurMode>,
+
+    /// Sharpness score (variance of the Laplacian) below which a photo counts as blurry
+    #[arg(long, default_value_t = 100.0)]
+    pub sharpness_threshold: f64,
+
+    /// Fully decode every photo of a batch first and report the truncated or corrupt ones
+    #[arg(long)]
+    pub verify_decode: bool,
+
+    /// Copy the EXIF of the input into JPEG outputs, including the GPS position unless
+    /// `--strip-private` is given
+    #[arg(long)]
+    pub keep_exif: bool,
+
+    /// Also copy the XMP packet of the input into JPEG outputs
+    #[arg(long)]
+    pub keep_xmp: bool,
+
+    /// Leave the GPS position, serial numbers and owner name out of the EXIF copied to outputs
+    #[arg(long)]
+    pub strip_private: bool,
+}
+
+/// Parses a value between 0 and 1
+pub fn parse_unit_interval(value: &str) -> Result<f32, String> {
+    let value: f32 = value
+        .parse()
+        .map_err(|_| format!("invalid number: {}", value))?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(format!("{} is not between 0 and 1", value));
+    }
+    Ok(value)
+}
+
+/// Parses a positive duration in seconds, e.g. `2.5`
+pub fn parse_seconds(value: &str) -> Result<f32, String> {
+    let seconds: f32 = value
+        .parse()
+        .map_err(|_| format!("invalid number: {}", value))?;
+    if !seconds.is_finite() || seconds <= 0.0 {
+        return Err(format!("{} is not a positive number of seconds", value));
+    }
+    Ok(seconds)
+}
+
+/// Parses dimensions given as `WIDTHxHEIGHT`, e.g. `1920x1080`
+///
+/// # Arguments
+/// * `value` - Dimensions string
+///
+/// # Returns
+/// * `Result<(u32, u32), String>` - Width and height if valid
+pub fn parse_dimensions(value: &str) -> Result<(u32, u32), String> {
+    let (width, height) = value
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", value))?;
+    let width: u32 = width
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid width: {}", e))?;
+    let height: u32 = height
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid height: {}", e))?;
+    if width == 0 || height == 0 {
+        return Err("dimensions must be greater than zero".to_string());
+    }
+    Ok((width, height))
+}
+
+/// Subcommands of the image processing tool
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Build a side-by-side comparison strip with each photo's settings beneath it
+    Compare {
+        /// Photos to compare followed by the output file path
+        #[arg(value_name = "PATHS", num_args = 3.., required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Height of every photo in the strip, defaults to the smallest input height
+        #[arg(long)]
+        height: Option<u32>,
+
+        /// Gap between the photos in pixels
+        #[arg(long, default_value_t = 32)]
+        gap: u32,
+
+        /// Height of the information bar in pixels
+        #[arg(short, long, default_value_t = 180)]
+        info_height: u32,
+    },
+
+    /// Download brand logos into a logos directory, named as the renderer expects
+    FetchLogos {
+        /// Brands to fetch, e.g. `leica,olympus`
+        #[arg(long, value_delimiter = ',', required = true)]
+        brands: Vec<String>,
+
+        /// Logos directory to install into
+        #[arg(long, value_name = "DIR", default_value = "./logos")]
+        dir: PathBuf,
+
+        /// URL of a logo, with `{brand}` replaced by the lowercase brand name
+        #[arg(long, value_name = "URL", env = "LENSIGHT_LOGO_URL")]
+        url: String,
+
+        /// Install every brand without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Replace logo files that already exist
+        #[arg(long)]
+        overwrite: bool,
+    },
+
+    /// Fully decode the photos of a directory and report the truncated or corrupt ones
+    Check {
+        /// Directory of photos to check
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+    },
+
+    /// Rename photos from a template of their EXIF and file facts
+    Rename {
+        /// Directory of photos to rename
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+
+        /// Template of the new file names, e.g. `{date}_{camera}_{counter}`
+        #[arg(long)]
+        pattern: String,
+
+        /// Print the new names without renaming anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Write small unframed JPEG proxies of a directory, for culling before framing
+    Thumbs {
+        /// Directory of photos
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+
+        /// Directory receiving the proxies
+        #[arg(value_name = "OUTPUT")]
+        output: PathBuf,
+
+        /// Longest edge of the proxies in pixels
+        #[arg(long, default_value_t = 400, value_parser = clap::value_parser!(u32).range(16..))]
+        size: u32,
+
+        /// Quality of the proxies, from 1 to 100
+        #[arg(long, default_value_t = DEFAULT_JPEG_QUALITY, value_parser = clap::value_parser!(u8).range(1..=100))]
+        quality: u8,
+    },
+
+    /// Write artist and copyright EXIF fields into JPEG files, in place or into copies
+    Retag {
+        /// JPEG file or directory of JPEG files to retag
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Photographer written to the Artist field
+        #[arg(long)]
+        artist: Option<String>,
+
+        /// Notice written to the Copyright field
+        #[arg(long)]
+        copyright: Option<String>,
+
+        /// Directory receiving the retagged copies, `PATH_retagged` next to PATH by default
+        #[arg(long, value_name = "DIR")]
+        output: Option<PathBuf>,
+
+        /// Update the files in place instead of writing retagged copies
+        #[arg(long, conflicts_with = "output")]
+        in_place: bool,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dimensions() {
+        assert_eq!(parse_dimensions("1920x1080"), Ok((1920, 1080)));
+        assert_eq!(parse_dimensions("800X600"), Ok((800, 600)));
+        assert!(parse_dimensions("1920").is_err());
+        assert!(parse_dimensions("0x1080").is_err());
+        assert!(parse_dimensions("ax1080").is_err());
+    }
+
+    #[test]
+    fn test_parse_unit_interval() {
+        assert_eq!(parse_unit_interval("0.05"), Ok(0.05));
+        assert!(parse_unit_interval("1.5").is_err());
+        assert!(parse_unit_interval("grain").is_err());
+    }
+
+    #[test]
+    fn test_parse_seconds() {
+        assert_eq!(parse_seconds("2.5"), Ok(2.5));
+        assert!(parse_seconds("0").is_err());
+        assert!(parse_seconds("-1").is_err());
+        assert!(parse_seconds("inf").is_err());
+        assert!(parse_seconds("NaN").is_err());
+    }
 }
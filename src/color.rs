@@ -0,0 +1,141 @@
+//! Color management module
+//!
+//! This module checks whether an image was encoded in a color space wider than sRGB. The
+//! framed output is written without an ICC profile, so browsers interpret it as sRGB and
+//! wide-gamut colors visibly shift.
+
+use exif::{In, Reader, Tag};
+use std::path::Path;
+
+/// Gamut area ratio to sRGB above which an image is flagged
+const GAMUT_WARNING_RATIO: f64 = 1.1;
+
+/// D50-adapted chromaticities of the sRGB primaries, as stored in ICC profiles
+const SRGB_PRIMARIES: [(f64, f64); 3] = [(0.6484, 0.3309), (0.3212, 0.5978), (0.1559, 0.0660)];
+
+/// Checks whether an image uses a color space significantly wider than sRGB
+///
+/// The embedded ICC profile primaries are compared to the sRGB primaries. Images without an
+/// ICC profile are flagged when EXIF marks them as uncalibrated Adobe RGB.
+///
+/// # Arguments
+/// * `input` - Path to the image file
+///
+/// # Returns
+/// * `Option<String>` - Warning describing the gamut if it exceeds sRGB
+pub fn check_gamut(input: &Path) -> Option<String> {
+    let data = std::fs::read(input).ok()?;
+    if let Some(profile) = crate::jpeg::icc_profile(&data) {
+        let primaries = profile_primaries(&profile)?;
+        let ratio = triangle_area(&primaries) / triangle_area(&SRGB_PRIMARIES);
+        if ratio > GAMUT_WARNING_RATIO {
+            return Some(format!(
+                "embedded color profile gamut is {:.0}% of sRGB, colors may shift in web browsers",
+                ratio * 100.0
+            ));
+        }
+        return None;
+    }
+
+    let exif = Reader::new()
+        .read_from_container(&mut std::io::Cursor::new(&data))
+        .ok()?;
+    let uncalibrated = exif
+        .get_field(Tag::ColorSpace, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        == Some(0xFFFF);
+    let adobe_rgb = exif
+        .get_field(Tag::InteroperabilityIndex, In::PRIMARY)
+        .map(|field| field.display_value().to_string().contains("R03"))
+        .unwrap_or(false);
+    if uncalibrated && adobe_rgb {
+        return Some(
+            "EXIF marks the image as Adobe RGB, colors may shift in web browsers".to_string(),
+        );
+    }
+    None
+}
+
+/// Reads the chromaticities of the red, green and blue colorants of an ICC profile
+///
+/// # Arguments
+/// * `profile` - ICC profile data
+///
+/// # Returns
+/// * `Option<[(f64, f64); 3]>` - xy chromaticities, `None` for profiles without colorant tags
+fn profile_primaries(profile: &[u8]) -> Option<[(f64, f64); 3]> {
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes = profile.get(offset..offset + 4)?;
+        Some(u32::from_be_bytes(bytes.try_into().ok()?))
+    };
+    let tag_count = read_u32(128)? as usize;
+    let find_xyz = |signature: &[u8; 4]| -> Option<(f64, f64)> {
+        let entry = (0..tag_count.min(256))
+            .map(|i| 132 + i * 12)
+            .find(|&entry| profile.get(entry..entry + 4) == Some(signature.as_slice()))?;
+        let offset = read_u32(entry + 4)? as usize;
+        if profile.get(offset..offset + 4) != Some(b"XYZ ".as_slice()) {
+            return None;
+        }
+        let fixed = |i: usize| read_u32(offset + 8 + i * 4).map(|v| v as i32 as f64 / 65536.0);
+        let (x, y, z) = (fixed(0)?, fixed(1)?, fixed(2)?);
+        let sum = x + y + z;
+        if sum <= 0.0 {
+            return None;
+        }
+        Some((x / sum, y / sum))
+    };
+    Some([find_xyz(b"rXYZ")?, find_xyz(b"gXYZ")?, find_xyz(b"bXYZ")?])
+}
+
+/// Computes the area of a triangle in the xy chromaticity plane
+fn triangle_area(points: &[(f64, f64); 3]) -> f64 {
+    let [(x1, y1), (x2, y2), (x3, y3)] = *points;
+    ((x1 * (y2 - y3) + x2 * (y3 - y1) + x3 * (y1 - y2)) / 2.0).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile_with_primaries(primaries: [[f64; 3]; 3]) -> Vec<u8> {
+        let mut profile = vec![0u8; 132];
+        profile[128..132].copy_from_slice(&3u32.to_be_bytes());
+        let data_start = 132 + 3 * 12;
+        let mut data = Vec::new();
+        for (i, signature) in [b"rXYZ", b"gXYZ", b"bXYZ"].iter().enumerate() {
+            profile.extend_from_slice(*signature);
+            profile.extend_from_slice(&((data_start + data.len()) as u32).to_be_bytes());
+            profile.extend_from_slice(&20u32.to_be_bytes());
+            data.extend_from_slice(b"XYZ \0\0\0\0");
+            for value in primaries[i] {
+                data.extend_from_slice(&((value * 65536.0).round() as i32).to_be_bytes());
+            }
+        }
+        profile.extend_from_slice(&data);
+        profile
+    }
+
+    #[test]
+    fn test_profile_gamut_ratio() {
+        let srgb = profile_with_primaries([
+            [0.4361, 0.2225, 0.0139],
+            [0.3851, 0.7169, 0.0971],
+            [0.1431, 0.0606, 0.7141],
+        ]);
+        let primaries = profile_primaries(&srgb).unwrap();
+        let ratio = triangle_area(&primaries) / triangle_area(&SRGB_PRIMARIES);
+        assert!((ratio - 1.0).abs() < 0.01);
+
+        let adobe_rgb = profile_with_primaries([
+            [0.6097, 0.3111, 0.0195],
+            [0.2053, 0.6257, 0.0609],
+            [0.1492, 0.0632, 0.7446],
+        ]);
+        let primaries = profile_primaries(&adobe_rgb).unwrap();
+        let ratio = triangle_area(&primaries) / triangle_area(&SRGB_PRIMARIES);
+        assert!(ratio > GAMUT_WARNING_RATIO);
+
+        assert!(profile_primaries(&[0u8; 64]).is_none());
+    }
+}
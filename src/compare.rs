@@ -0,0 +1,97 @@
+//! Comparison module
+//!
+//! This module builds side-by-side comparison strips of several photos, each framed with its
+//! own information bar, e.g. for lens comparisons or ISO tests.
+
+use crate::options::ProcessOptions;
+use crate::resource::Resources;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba, RgbaImage};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Builds a comparison strip of several photos
+///
+/// # Arguments
+/// * `inputs` - Paths to the photos, in display order
+/// * `output` - Output file path
+/// * `height` - Height of every photo in the strip, defaults to the smallest input height
+/// * `gap` - Gap between the photos and around the strip in pixels
+/// * `options` - Processing options used to frame every photo
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - Ok if successful
+///
+/// # Errors
+/// Returns an error if any photo cannot be opened or framed, or the strip cannot be saved
+pub fn compare_images(
+    inputs: &[PathBuf],
+    output: &Path,
+    height: Option<u32>,
+    gap: u32,
+    options: &ProcessOptions,
+) -> Result<(), Box<dyn Error>> {
+    if inputs.is_empty() {
+        return Err("No photos to compare".into());
+    }
+
+    let images = inputs
+        .iter()
+        .map(|path| crate::source::open_image(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let height = height.unwrap_or_else(|| {
+        images
+            .iter()
+            .map(|img| img.height())
+            .min()
+            .unwrap_or_default()
+    });
+
+    let resources = Resources::new(options.info_height, options.fonts_dir.as_deref())?;
+    let mut frames = Vec::with_capacity(images.len());
+    for (img, path) in images.iter().zip(inputs) {
+        println!("[INFO] Framing {} for comparison", path.display());
+        let resized = scale_to_height(img, height);
+        let metadata = crate::image_processor::resolve_metadata(path, options);
+        frames.push(crate::image_processor::add_info_bar(
+            resized, path, &metadata, &resources, options,
+        )?);
+    }
+
+    let frame_height = height + options.info_height;
+    let width =
+        frames.iter().map(|frame| frame.width()).sum::<u32>() + gap * (frames.len() as u32 + 1);
+    let mut strip: RgbaImage =
+        ImageBuffer::from_pixel(width, frame_height + gap * 2, Rgba([255, 255, 255, 255]));
+    let mut x = gap;
+    for frame in &frames {
+        image::imageops::overlay(&mut strip, &frame.to_rgba8(), x as i64, gap as i64);
+        x += frame.width() + gap;
+    }
+
+    crate::output::save_image(&DynamicImage::ImageRgba8(strip), output, options)?;
+    println!("[INFO] Saved comparison of {} photos", frames.len());
+    Ok(())
+}
+
+/// Scales an image to the given height while keeping its aspect ratio
+fn scale_to_height(img: &DynamicImage, height: u32) -> DynamicImage {
+    let (orig_width, orig_height) = img.dimensions();
+    if orig_height == height {
+        return img.clone();
+    }
+    let width = ((orig_width as u64 * height as u64) / orig_height as u64).max(1) as u32;
+    img.resize_exact(width, height, FilterType::Lanczos3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_to_height() {
+        let img = DynamicImage::new_rgb8(400, 300);
+        assert_eq!(scale_to_height(&img, 150).dimensions(), (200, 150));
+        assert_eq!(scale_to_height(&img, 300).dimensions(), (400, 300));
+    }
+}
@@ -0,0 +1,298 @@
+//! Configuration file module
+//!
+//! This module loads the `--config` TOML file holding settings that are too structured for
+//! command line flags, such as the labels of manual lenses and per-brand styles.
+
+use crate::exif::{ExifInfo, UNKNOWN};
+use crate::options::ProcessOptions;
+use crate::theme::StyleOverrides;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+/// Settings read from the configuration file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Lens labels keyed by focal length in millimeters, e.g. `58 = "Helios 44-2 58mm"`
+    #[serde(default)]
+    pub manual_lenses: HashMap<String, String>,
+    /// Styles keyed by lowercase camera brand, e.g. `[brands.leica]`
+    #[serde(default)]
+    pub brands: HashMap<String, BrandStyle>,
+    /// Rules disabling the watermark of matching photos, e.g. `[[watermark_opt_out]]`
+    #[serde(default)]
+    pub watermark_opt_out: Vec<WatermarkOptOut>,
+}
+
+/// Rule disabling the watermark of photos whose metadata matches
+///
+/// The watermark is the caption, the overlay text, the secondary logo and the copyright
+/// attribution. Every criterion set must equal the EXIF field, ignoring case and surrounding
+/// spaces.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatermarkOptOut {
+    /// EXIF Artist, e.g. a licensed client
+    pub artist: Option<String>,
+    /// EXIF Copyright
+    pub copyright: Option<String>,
+}
+
+impl WatermarkOptOut {
+    /// Returns whether the rule matches the rights fields of a photo
+    ///
+    /// # Arguments
+    /// * `artist` - EXIF Artist of the photo
+    /// * `copyright` - EXIF Copyright of the photo
+    pub fn matches(&self, artist: Option<&str>, copyright: Option<&str>) -> bool {
+        let criterion_matches = |criterion: &Option<String>, value: Option<&str>| match criterion {
+            Some(criterion) => {
+                value.is_some_and(|value| value.trim().eq_ignore_ascii_case(criterion.trim()))
+            }
+            None => true,
+        };
+        (self.artist.is_some() || self.copyright.is_some())
+            && criterion_matches(&self.artist, artist)
+            && criterion_matches(&self.copyright, copyright)
+    }
+
+    /// Removes the watermark from the options of an opted-out photo
+    ///
+    /// # Arguments
+    /// * `options` - Options to strip the watermark from
+    pub fn strip(options: &mut ProcessOptions) {
+        options.caption = None;
+        options.show_copyright = false;
+        options.copyright = None;
+        options.theme.secondary_logo = None;
+        if let Some(slots) = options.overlay.as_mut() {
+            slots.clear();
+        }
+    }
+}
+
+/// Default style of the photos taken with a camera brand
+///
+/// Brand styles apply on top of the command line options, and sidecars apply on top of brand
+/// styles.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrandStyle {
+    /// Height of the information bar in pixels
+    pub info_height: Option<u32>,
+    /// Caption rendered below the shooting parameters
+    pub caption: Option<String>,
+    /// Style overrides
+    #[serde(default)]
+    pub style: StyleOverrides,
+}
+
+impl BrandStyle {
+    /// Applies the brand style on top of the batch options
+    ///
+    /// # Arguments
+    /// * `options` - Options to override
+    pub fn apply(&self, options: &mut ProcessOptions) {
+        if let Some(info_height) = self.info_height {
+            options.info_height = info_height;
+        }
+        if let Some(caption) = &self.caption {
+            options.caption = Some(caption.clone());
+        }
+        self.style.apply(&mut options.theme);
+    }
+}
+
+impl Config {
+    /// Loads a configuration file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the TOML file
+    ///
+    /// # Returns
+    /// * `Result<Config, Box<dyn Error>>` - Loaded configuration
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed, a manual lens key is not a focal
+    /// length or a watermark opt-out rule has no criteria
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = std::fs::read_to_string(path)?;
+        let config: Config = toml::from_str(&content)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        if let Some(key) = config
+            .manual_lenses
+            .keys()
+            .find(|key| parse_focal_length(key).is_none())
+        {
+            return Err(format!(
+                "Invalid config {}: manual lens key '{}' is not a focal length",
+                path.display(),
+                key
+            )
+            .into());
+        }
+        if let Some(idx) = config
+            .watermark_opt_out
+            .iter()
+            .position(|rule| rule.artist.is_none() && rule.copyright.is_none())
+        {
+            return Err(format!(
+                "Invalid config {}: watermark opt-out rule {} has no criteria",
+                path.display(),
+                idx + 1
+            )
+            .into());
+        }
+        Ok(config)
+    }
+
+    /// Returns the brand styles keyed by lowercase brand
+    pub fn brand_styles(&self) -> HashMap<String, BrandStyle> {
+        self.brands
+            .iter()
+            .map(|(brand, style)| (brand.to_lowercase(), style.clone()))
+            .collect()
+    }
+
+    /// Returns the manual lens labels
+    pub fn manual_lenses(&self) -> ManualLenses {
+        ManualLenses {
+            labels: self
+                .manual_lenses
+                .iter()
+                .filter_map(|(focal, label)| Some((parse_focal_length(focal)?, label.clone())))
+                .collect(),
+        }
+    }
+}
+
+/// Default lens labels for manual lenses, keyed by focal length
+///
+/// A manual lens is detected when EXIF has a focal length but no lens model, which is what
+/// bodies write when the focal length of an unchipped lens is entered in the camera menu.
+#[derive(Debug, Clone, Default)]
+pub struct ManualLenses {
+    labels: Vec<(f32, String)>,
+}
+
+impl ManualLenses {
+    /// Returns the label of the manual lens used for a photo
+    ///
+    /// # Arguments
+    /// * `exif` - EXIF information of the photo
+    ///
+    /// # Returns
+    /// * `Option<&str>` - Label if the lens is missing and the focal length has one
+    pub fn label_for(&self, exif: &ExifInfo) -> Option<&str> {
+        let lens = exif.lens_model.trim_matches('"').trim();
+        if !(lens.is_empty() || lens == UNKNOWN) {
+            return None;
+        }
+        let focal = parse_focal_length(&exif.focal_length)?;
+        self.labels
+            .iter()
+            .find(|(length, _)| (length - focal).abs() < 0.05)
+            .map(|(_, label)| label.as_str())
+    }
+}
+
+/// Parses a focal length such as `58`, `58mm` or `"58 mm"`
+fn parse_focal_length(value: &str) -> Option<f32> {
+    value
+        .replace('"', "")
+        .trim()
+        .trim_end_matches("mm")
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_lens_label() {
+        let config: Config = toml::from_str(
+            r#"
+            [manual_lenses]
+            58 = "Helios 44-2 58mm"
+            "35mm" = "Jupiter-12"
+            "#,
+        )
+        .unwrap();
+        let lenses = config.manual_lenses();
+        let exif = ExifInfo {
+            camera_model: "ILCE-7M3".to_string(),
+            lens_model: UNKNOWN.to_string(),
+            focal_length: "58 mm".to_string(),
+            aperture: "2".to_string(),
+            shutter_speed: "1/125".to_string(),
+            iso: "100".to_string(),
+            gps: None,
+            date_time: None,
+            artist: None,
+            copyright: None,
+        };
+        assert_eq!(lenses.label_for(&exif), Some("Helios 44-2 58mm"));
+
+        let unknown_focal = ExifInfo {
+            focal_length: "50 mm".to_string(),
+            ..exif.clone()
+        };
+        assert_eq!(lenses.label_for(&unknown_focal), None);
+
+        let chipped = ExifInfo {
+            lens_model: "FE 55mm F1.8 ZA".to_string(),
+            ..exif
+        };
+        assert_eq!(lenses.label_for(&chipped), None);
+    }
+
+    #[test]
+    fn test_watermark_opt_out() {
+        let config: Config = toml::from_str(
+            r#"
+            [[watermark_opt_out]]
+            artist = "Acme Studio"
+            "#,
+        )
+        .unwrap();
+        let rule = &config.watermark_opt_out[0];
+        assert!(rule.matches(Some("ACME studio "), None));
+        assert!(!rule.matches(Some("Jane Doe"), Some("Acme Studio")));
+        assert!(!rule.matches(None, None));
+        assert!(!WatermarkOptOut::default().matches(Some("Acme Studio"), None));
+
+        let mut options = ProcessOptions {
+            caption: Some("© Jane Doe".to_string()),
+            show_copyright: true,
+            copyright: Some("Jane Doe".to_string()),
+            ..ProcessOptions::default()
+        };
+        WatermarkOptOut::strip(&mut options);
+        assert!(options.caption.is_none());
+        assert!(!options.show_copyright);
+        assert!(options.copyright.is_none());
+    }
+
+    #[test]
+    fn test_brand_style_apply() {
+        let config: Config = toml::from_str(
+            r#"
+            [brands.Leica]
+            info_height = 120
+            [brands.Leica.style]
+            camera_case = "upper"
+            "#,
+        )
+        .unwrap();
+        let styles = config.brand_styles();
+        let mut options = ProcessOptions::default();
+        styles["leica"].apply(&mut options);
+        assert_eq!(options.info_height, 120);
+        assert_eq!(options.theme.camera_case, crate::theme::TextCase::Upper);
+    }
+}
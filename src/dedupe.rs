@@ -0,0 +1,113 @@
+//! Duplicate detection module
+//!
+//! This module finds near-duplicate frames in a batch, such as the shots of a burst, with a
+//! perceptual difference hash. Only neighbors in processing order are compared, which is where
+//! bursts end up in a sorted folder, so the pass stays linear in the size of the batch.
+
+use crate::source::InputItem;
+use clap::ValueEnum;
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+/// What to do with near-duplicate frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DuplicateMode {
+    /// Process only the first frame of every cluster
+    Skip,
+    /// Process every frame and list the clusters
+    Report,
+}
+
+/// Returns the 64-bit difference hash of an image
+///
+/// The image is reduced to 9x8 grayscale pixels, and every bit tells whether a pixel is brighter
+/// than its right neighbor. Similar images have hashes with few differing bits.
+pub fn difference_hash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let brighter = small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | brighter as u64;
+        }
+    }
+    hash
+}
+
+/// Groups consecutive near-duplicate hashes into clusters
+///
+/// # Arguments
+/// * `hashes` - Hashes in processing order, `None` for images that cannot be read
+/// * `threshold` - Maximum number of differing bits to the first frame of a cluster
+///
+/// # Returns
+/// * `Vec<Vec<usize>>` - Indices of the clusters with more than one frame
+pub fn cluster_hashes(hashes: &[Option<u64>], threshold: u32) -> Vec<Vec<usize>> {
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+    for (idx, hash) in hashes.iter().enumerate() {
+        let first = current.first().and_then(|&first| hashes[first]);
+        match (first, hash) {
+            (Some(first), Some(hash)) if (first ^ hash).count_ones() <= threshold => {
+                current.push(idx);
+            }
+            _ => {
+                if current.len() > 1 {
+                    clusters.push(std::mem::take(&mut current));
+                }
+                current = vec![idx];
+            }
+        }
+    }
+    if current.len() > 1 {
+        clusters.push(current);
+    }
+    clusters
+}
+
+/// Finds the near-duplicate clusters of a batch
+///
+/// # Arguments
+/// * `items` - Images of the batch in processing order
+/// * `threshold` - Maximum number of differing hash bits to the first frame of a cluster
+///
+/// # Returns
+/// * `Vec<Vec<usize>>` - Indices of the clusters with more than one frame
+pub fn find_duplicates(items: &[InputItem], threshold: u32) -> Vec<Vec<usize>> {
+    println!(
+        "[INFO] Hashing {} image(s) to find near-duplicates",
+        items.len()
+    );
+    let hashes: Vec<Option<u64>> = items
+        .iter()
+        .map(|item| {
+            crate::source::open_image(&item.path)
+                .ok()
+                .map(|img| difference_hash(&img))
+        })
+        .collect();
+    cluster_hashes(&hashes, threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_duplicate_clusters() {
+        let gradient = RgbImage::from_fn(90, 80, |x, y| Rgb([(x * 2 + y) as u8, 0, 0]));
+        let mut brighter = gradient.clone();
+        brighter
+            .pixels_mut()
+            .for_each(|p| p[0] = p[0].saturating_add(10));
+        let flipped = image::imageops::flip_horizontal(&gradient);
+        let hashes = [gradient, brighter, flipped]
+            .map(|img| Some(difference_hash(&DynamicImage::ImageRgb8(img))));
+        assert_eq!(hashes[0], hashes[1]);
+        assert!((hashes[0].unwrap() ^ hashes[2].unwrap()).count_ones() > 32);
+
+        let hashes = [hashes[0], hashes[1], hashes[2], None, hashes[2], hashes[2]];
+        assert_eq!(cluster_hashes(&hashes, 6), vec![vec![0, 1], vec![4, 5]]);
+    }
+}
@@ -0,0 +1,97 @@
+//! Enhancement module
+//!
+//! This module applies a mild automatic levels correction to straight-out-of-camera photos
+//! before framing. It only stretches the tonal range to the black and white points, never
+//! changing the look the way a creative edit would.
+
+use image::RgbaImage;
+
+/// Share of pixels allowed to clip at either end of the tonal range
+const CLIP_FRACTION: f64 = 0.005;
+/// Highest black point and lowest white point, limiting the stretch to a mild correction
+const MAX_BLACK_POINT: u8 = 32;
+const MIN_WHITE_POINT: u8 = 223;
+
+/// Stretches the tonal range of a photo to its black and white points
+///
+/// The points are the luminance levels clipping 0.5% of the pixels, limited so the correction
+/// stays mild. All channels share the same mapping, so hues are kept.
+///
+/// # Arguments
+/// * `img` - Photo to correct in place
+pub fn auto_levels(img: &mut RgbaImage) {
+    let mut histogram = [0u64; 256];
+    for pixel in img.pixels() {
+        histogram[luma(pixel.0) as usize] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    if total == 0 {
+        return;
+    }
+    let clip = (total as f64 * CLIP_FRACTION) as u64;
+    let black = clip_level(&histogram, clip, 0..256).min(MAX_BLACK_POINT);
+    let white = clip_level(&histogram, clip, (0..256).rev()).max(MIN_WHITE_POINT);
+    if black == 0 && white == 255 {
+        return;
+    }
+    let scale = 255.0 / (white - black) as f32;
+    let mut lut = [0u8; 256];
+    for (value, mapped) in lut.iter_mut().enumerate() {
+        *mapped = ((value as f32 - black as f32) * scale)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+    }
+    for pixel in img.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = lut[pixel[c] as usize];
+        }
+    }
+}
+
+/// Returns the first level, in the order given, past which more than `clip` pixels are counted
+fn clip_level(histogram: &[u64; 256], clip: u64, levels: impl Iterator<Item = usize>) -> u8 {
+    let mut count = 0;
+    for level in levels {
+        count += histogram[level];
+        if count > clip {
+            return level as u8;
+        }
+    }
+    0
+}
+
+/// Returns the Rec. 601 luminance of a pixel
+fn luma([r, g, b, _]: [u8; 4]) -> u8 {
+    (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_auto_levels() {
+        let mut flat = RgbaImage::from_fn(100, 1, |x, _| {
+            let v = 60 + x as u8;
+            Rgba([v, v, v, 255])
+        });
+        auto_levels(&mut flat);
+        // Levels 60 to 159 are stretched with the limited points 32 and 223
+        assert_eq!(flat.get_pixel(0, 0)[0], 37);
+        assert_eq!(flat.get_pixel(99, 0)[0], 170);
+
+        // 1% of the pixels at each end already reach black and white
+        let mut full = RgbaImage::from_fn(1000, 1, |x, _| {
+            let v = match x {
+                0..=9 => 0,
+                990.. => 255,
+                _ => 128,
+            };
+            Rgba([v, v, v, 255])
+        });
+        let original = full.clone();
+        auto_levels(&mut full);
+        assert_eq!(full, original);
+    }
+}
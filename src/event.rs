@@ -0,0 +1,256 @@
+//! Event frame module
+//!
+//! This module layers the frames of an event pack over the finished outputs of a live photo
+//! wall. A pack is a directory of transparent PNG frames, such as sponsor logos or the event
+//! hashtag, drawn over the whole output including the information bar. Frames rotate
+//! round-robin, or follow the capture time windows of an optional `schedule.toml`.
+
+use image::{imageops, DynamicImage};
+use serde::Deserialize;
+use std::error::Error;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Name of the schedule file of an event pack
+pub const SCHEDULE_FILE: &str = "schedule.toml";
+
+/// Frame of an event pack
+#[derive(Debug)]
+pub struct EventFrame {
+    /// File name of the frame, e.g. `sponsor_a.png`
+    pub name: String,
+    /// Frame image, transparent where the photo shows through
+    pub image: DynamicImage,
+}
+
+/// Capture time window showing one frame of the pack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleSlot {
+    /// Start of the window in minutes after midnight, included
+    pub start: u32,
+    /// End of the window in minutes after midnight, excluded; before `start` past midnight
+    pub end: u32,
+    /// Index of the frame in the pack
+    pub frame: usize,
+}
+
+impl ScheduleSlot {
+    /// Returns whether the window contains a time of day
+    ///
+    /// # Arguments
+    /// * `minute` - Minutes after midnight
+    pub fn contains(&self, minute: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleFile {
+    #[serde(default)]
+    slot: Vec<ScheduleEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleEntry {
+    start: String,
+    end: String,
+    frame: String,
+}
+
+/// Frames of an event pack and the rotation between them
+///
+/// Photos captured in a window of the schedule get the frame of that window. Other photos,
+/// and photos without a capture time, take the frames in file name order, one after the other.
+#[derive(Debug)]
+pub struct EventPack {
+    /// Frames in file name order
+    pub frames: Vec<EventFrame>,
+    /// Capture time windows, the first matching window wins
+    pub schedule: Vec<ScheduleSlot>,
+    next: AtomicUsize,
+}
+
+impl EventPack {
+    /// Loads the PNG frames and the schedule of an event pack directory
+    ///
+    /// # Arguments
+    /// * `dir` - Directory with the frames and an optional `schedule.toml`
+    ///
+    /// # Returns
+    /// * `Result<Self, Box<dyn Error>>` - Loaded event pack
+    ///
+    /// # Errors
+    /// Returns an error if the directory has no PNG frame, a frame cannot be decoded or the
+    /// schedule is invalid
+    pub fn load(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+            })
+            .collect();
+        paths.sort();
+        if paths.is_empty() {
+            return Err(format!("Event pack {} has no PNG frame", dir.display()).into());
+        }
+        let mut frames = Vec::with_capacity(paths.len());
+        for path in paths {
+            let image = image::open(&path)
+                .map_err(|e| format!("Invalid event frame {}: {}", path.display(), e))?;
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            frames.push(EventFrame { name, image });
+        }
+
+        let schedule_path = dir.join(SCHEDULE_FILE);
+        let schedule = if schedule_path.is_file() {
+            let content = std::fs::read_to_string(&schedule_path)?;
+            parse_schedule(&content, &frames)
+                .map_err(|e| format!("Invalid schedule {}: {}", schedule_path.display(), e))?
+        } else {
+            Vec::new()
+        };
+        Ok(EventPack {
+            frames,
+            schedule,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Selects the frame of the next photo
+    ///
+    /// # Arguments
+    /// * `capture_minute` - Capture time in minutes after midnight, `None` if unknown
+    ///
+    /// # Returns
+    /// * `&EventFrame` - Frame of the scheduled window, or the next frame in rotation
+    pub fn select(&self, capture_minute: Option<u32>) -> &EventFrame {
+        let scheduled = capture_minute.and_then(|minute| {
+            self.schedule
+                .iter()
+                .find(|slot| slot.contains(minute))
+                .map(|slot| slot.frame)
+        });
+        let idx = scheduled
+            .unwrap_or_else(|| self.next.fetch_add(1, Ordering::Relaxed) % self.frames.len());
+        &self.frames[idx]
+    }
+
+    /// Layers the frame of a photo over its finished output
+    ///
+    /// The frame is stretched to the output size, so packs should be drawn at the aspect
+    /// ratio of the outputs.
+    ///
+    /// # Arguments
+    /// * `img` - Finished output, bar included
+    /// * `input` - Path to the photo, for its capture time
+    ///
+    /// # Returns
+    /// * `DynamicImage` - Output with the frame on top, at the bit depth of `img`
+    pub fn apply(&self, img: DynamicImage, input: &Path) -> DynamicImage {
+        let frame = self.select(crate::exif::read_capture_minute(input));
+        println!("[INFO] Event frame: {}", frame.name);
+        let frame =
+            frame
+                .image
+                .resize_exact(img.width(), img.height(), imageops::FilterType::CatmullRom);
+        match img {
+            DynamicImage::ImageRgba16(mut canvas) => {
+                imageops::overlay(&mut canvas, &frame.to_rgba16(), 0, 0);
+                DynamicImage::ImageRgba16(canvas)
+            }
+            img => {
+                let mut canvas = img.to_rgba8();
+                imageops::overlay(&mut canvas, &frame.to_rgba8(), 0, 0);
+                DynamicImage::ImageRgba8(canvas)
+            }
+        }
+    }
+}
+
+/// Parses a time of day such as `18:30` into minutes after midnight
+fn parse_clock(value: &str) -> Option<u32> {
+    let (hour, minute) = value.trim().split_once(':')?;
+    let hour: u32 = hour.parse().ok()?;
+    let minute: u32 = minute.parse().ok()?;
+    (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+}
+
+/// Parses the `[[slot]]` windows of a schedule file
+fn parse_schedule(content: &str, frames: &[EventFrame]) -> Result<Vec<ScheduleSlot>, String> {
+    let file: ScheduleFile = toml::from_str(content).map_err(|e| e.to_string())?;
+    file.slot
+        .iter()
+        .enumerate()
+        .map(|(idx, entry)| {
+            let clock = |value: &str| {
+                parse_clock(value)
+                    .ok_or_else(|| format!("slot {} has an invalid time '{}'", idx + 1, value))
+            };
+            let frame = frames
+                .iter()
+                .position(|frame| frame.name == entry.frame)
+                .ok_or_else(|| {
+                    format!("slot {} names a missing frame '{}'", idx + 1, entry.frame)
+                })?;
+            Ok(ScheduleSlot {
+                start: clock(&entry.start)?,
+                end: clock(&entry.end)?,
+                frame,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_event_pack_rotation_and_schedule() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, color) in [("a.png", [255, 0, 0, 255]), ("b.png", [0, 0, 255, 0])] {
+            RgbaImage::from_pixel(4, 4, Rgba(color))
+                .save(dir.path().join(name))
+                .unwrap();
+        }
+        std::fs::write(
+            dir.path().join(SCHEDULE_FILE),
+            "[[slot]]\nstart = \"22:00\"\nend = \"02:00\"\nframe = \"b.png\"\n",
+        )
+        .unwrap();
+
+        let pack = EventPack::load(dir.path()).unwrap();
+        assert_eq!(pack.frames.len(), 2);
+        let names: Vec<&str> = (0..3).map(|_| pack.select(None).name.as_str()).collect();
+        assert_eq!(names, ["a.png", "b.png", "a.png"]);
+        assert_eq!(pack.select(Some(23 * 60)).name, "b.png");
+        assert_eq!(pack.select(Some(60)).name, "b.png");
+        assert_eq!(pack.select(Some(12 * 60)).name, "b.png");
+
+        let framed = pack.apply(
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 6, Rgba([0, 255, 0, 255]))),
+            &dir.path().join("missing.jpg"),
+        );
+        assert_eq!((framed.width(), framed.height()), (8, 6));
+        assert_eq!(framed.to_rgba8().get_pixel(3, 3), &Rgba([255, 0, 0, 255]));
+
+        std::fs::write(
+            dir.path().join(SCHEDULE_FILE),
+            "[[slot]]\nstart = \"25:00\"\nend = \"02:00\"\nframe = \"b.png\"\n",
+        )
+        .unwrap();
+        assert!(EventPack::load(dir.path()).is_err());
+    }
+}
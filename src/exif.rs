@@ -2,13 +2,22 @@
 //!
 //! This module handles reading and processing EXIF metadata from image files.
 
-use exif::{In, Reader, Tag};
+use exif::{Context, Exif, Field, In, Reader, Tag, Value};
 use std::fs::File;
-use std::io::BufReader;
-use std::path::Path;
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Value of fields missing from EXIF
+pub const UNKNOWN: &str = "Unknown";
+
+/// Extensions of RAW and HEIC siblings checked for companion EXIF, in lookup order
+const COMPANION_EXTENSIONS: &[&str] = &[
+    "dng", "cr3", "cr2", "nef", "arw", "raf", "rw2", "orf", "pef", "srw", "heic", "heif",
+];
 
 /// Structure containing camera and image metadata
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ExifInfo {
     /// Camera model name
     pub camera_model: String,
@@ -22,10 +31,196 @@ pub struct ExifInfo {
     pub shutter_speed: String,
     /// ISO sensitivity value
     pub iso: String,
+    /// GPS latitude and longitude in signed degrees, `None` if the photo has no position
+    pub gps: Option<(f64, f64)>,
+    /// Capture date time such as `2024:06:01 19:42:10`, `None` if EXIF has none
+    pub date_time: Option<String>,
+    /// Photographer of the Artist field, `None` if EXIF has none
+    pub artist: Option<String>,
+    /// Notice of the Copyright field, `None` if EXIF has none
+    pub copyright: Option<String>,
+}
+
+impl ExifInfo {
+    /// Fills the fields missing from this EXIF information with the fields of another
+    ///
+    /// # Arguments
+    /// * `other` - EXIF information to take missing fields from
+    ///
+    /// # Returns
+    /// * `usize` - Number of fields filled
+    pub fn fill_missing_from(&mut self, other: &ExifInfo) -> usize {
+        let mut filled = 0;
+        for (field, value) in [
+            (&mut self.camera_model, &other.camera_model),
+            (&mut self.lens_model, &other.lens_model),
+            (&mut self.focal_length, &other.focal_length),
+            (&mut self.aperture, &other.aperture),
+            (&mut self.shutter_speed, &other.shutter_speed),
+            (&mut self.iso, &other.iso),
+        ] {
+            if field == UNKNOWN && value != UNKNOWN {
+                field.clone_from(value);
+                filled += 1;
+            }
+        }
+        if self.gps.is_none() && other.gps.is_some() {
+            self.gps = other.gps;
+            filled += 1;
+        }
+        for (field, value) in [
+            (&mut self.date_time, &other.date_time),
+            (&mut self.artist, &other.artist),
+            (&mut self.copyright, &other.copyright),
+        ] {
+            if field.is_none() && value.is_some() {
+                field.clone_from(value);
+                filled += 1;
+            }
+        }
+        filled
+    }
+
+    /// Returns whether every field is known
+    pub fn is_complete(&self) -> bool {
+        [
+            &self.camera_model,
+            &self.lens_model,
+            &self.focal_length,
+            &self.aperture,
+            &self.shutter_speed,
+            &self.iso,
+        ]
+        .iter()
+        .all(|field| *field != UNKNOWN)
+    }
+
+    /// Multiplies the focal length and aperture by a teleconverter factor
+    ///
+    /// For bodies that record the values of the bare lens. Fields that aren't numbers are kept.
+    ///
+    /// # Arguments
+    /// * `factor` - Magnification of the teleconverter, e.g. `1.4`
+    pub fn apply_teleconverter(&mut self, factor: f32) {
+        let number = |value: &str| -> Option<f32> {
+            value
+                .replace('"', "")
+                .trim()
+                .trim_end_matches("mm")
+                .trim()
+                .parse()
+                .ok()
+        };
+        if let Some(focal) = number(&self.focal_length) {
+            self.focal_length = format!("{:.0} mm", focal * factor);
+        }
+        if let Some(aperture) = number(&self.aperture) {
+            let aperture = format!("{:.1}", aperture * factor);
+            self.aperture = aperture.trim_end_matches(".0").to_string();
+        }
+    }
+}
+
+/// Detects a teleconverter from the lens model and returns its magnification
+///
+/// Bodies append the teleconverter to the lens model, e.g. `+ EXTENDER RF1.4x`,
+/// `+ 1.4X Teleconverter` or `+ TC-14E`. A magnification such as `1-5x` of a macro lens is
+/// not a teleconverter, so the lens model must also mention one.
+///
+/// # Arguments
+/// * `lens_model` - Lens model read from EXIF
+///
+/// # Returns
+/// * `Option<f32>` - Magnification of the teleconverter, e.g. `1.4`
+pub fn teleconverter_factor(lens_model: &str) -> Option<f32> {
+    let lower = lens_model.to_lowercase();
+    let mentions_converter = lower.contains('+')
+        || ["extender", "converter", "tc"]
+            .iter()
+            .any(|keyword| lower.contains(keyword));
+    if !mentions_converter {
+        return None;
+    }
+    // Nikon style TC-14 / TC-17 / TC-20
+    if let Some(pos) = lower.find("tc-") {
+        let digits: String = lower[pos + 3..]
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect();
+        if let Ok(value) = digits.parse::<f32>() {
+            if digits.len() == 2 {
+                return Some(value / 10.0);
+            }
+        }
+    }
+    // Generic 1.4x / 2x / 2.0× after the `+` if there is one
+    let tail = lower.rsplit('+').next().unwrap_or(&lower);
+    let chars: Vec<char> = tail.chars().collect();
+    for (idx, c) in chars.iter().enumerate() {
+        if !matches!(c, 'x' | '×') || idx == 0 {
+            continue;
+        }
+        let start = chars[..idx]
+            .iter()
+            .rposition(|c| !(c.is_ascii_digit() || *c == '.'))
+            .map_or(0, |pos| pos + 1);
+        let number: String = chars[start..idx].iter().collect();
+        if let Ok(factor) = number.parse::<f32>() {
+            if factor > 1.0 && factor <= 3.0 {
+                return Some(factor);
+            }
+        }
+    }
+    None
+}
+
+/// EXIF information carried across the files of a sorted batch
+///
+/// Bursts from lens adapters or film scans often only carry full metadata on the first frame.
+/// Every file inherits the fields it is missing from the previous file, and clones share the
+/// same memory.
+#[derive(Debug, Clone, Default)]
+pub struct ExifInheritance {
+    previous: Arc<Mutex<Option<ExifInfo>>>,
+}
+
+impl ExifInheritance {
+    /// Fills missing fields from the previous file and remembers the result for the next one
+    ///
+    /// # Arguments
+    /// * `exif` - EXIF information of the current file, `None` if it has no readable EXIF
+    ///
+    /// # Returns
+    /// * `Option<ExifInfo>` - Merged EXIF information, `None` if there is nothing to inherit
+    pub fn inherit(&self, exif: Option<ExifInfo>) -> Option<ExifInfo> {
+        let mut previous = self.previous.lock().unwrap_or_else(|e| e.into_inner());
+        let merged = match (exif, previous.as_ref()) {
+            (Some(mut exif), Some(previous)) => {
+                let filled = exif.fill_missing_from(previous);
+                if filled > 0 {
+                    println!(
+                        "[INFO] Inherited {} EXIF field(s) from the previous file",
+                        filled
+                    );
+                }
+                exif
+            }
+            (Some(exif), None) => exif,
+            (None, Some(previous)) => {
+                println!("[INFO] No readable EXIF, inheriting all fields from the previous file");
+                previous.clone()
+            }
+            (None, None) => return None,
+        };
+        *previous = Some(merged.clone());
+        Some(merged)
+    }
 }
 
 /// Reads EXIF information from an image file
 ///
+/// Bodies that leave `LensModel` empty get the lens decoded from their MakerNote.
+///
 /// # Arguments
 /// * `file_path` - Path to the image file
 ///
@@ -35,27 +230,645 @@ pub struct ExifInfo {
 /// # Errors
 /// Returns an error if the file cannot be opened or if EXIF data cannot be read
 pub fn read_exif_info(file_path: &Path) -> Result<ExifInfo, Box<dyn std::error::Error>> {
+    let exif = read_exif(file_path)?;
+    let mut info = exif_info_from(|tag| exif.get_field(tag, In::PRIMARY));
+    if info.lens_model.trim_matches('"').trim().is_empty() || info.lens_model == UNKNOWN {
+        if let Some(lens) = crate::makernote::lens_model(&exif) {
+            info.lens_model = lens;
+        }
+    }
+    Ok(info)
+}
+
+/// Reads the EXIF block of an image file
+///
+/// RAF and CR3 files, which have no EXIF block the reader understands, fall back to the EXIF of
+/// their preview or metadata boxes.
+///
+/// # Arguments
+/// * `file_path` - Path to the image file
+///
+/// # Returns
+/// * `Result<Exif, Box<dyn std::error::Error>>` - EXIF of the file
+///
+/// # Errors
+/// Returns an error if the file cannot be read or has no readable EXIF
+pub fn read_exif(file_path: &Path) -> Result<Exif, Box<dyn std::error::Error>> {
     let file = File::open(file_path)?;
-    let mut buf_reader = BufReader::new(&file);
-    let exif_reader = Reader::new();
-    let exif = exif_reader.read_from_container(&mut buf_reader)?;
+    match Reader::new().read_from_container(&mut BufReader::new(&file)) {
+        Ok(exif) => Ok(exif),
+        Err(e) if crate::raw::is_raw(file_path) => {
+            crate::raw::read_exif(&std::fs::read(file_path)?).ok_or_else(|| e.into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
 
+/// Reads whatever EXIF fields are still readable from a file whose EXIF block is malformed
+///
+/// # Arguments
+/// * `file_path` - Path to the image file
+///
+/// # Returns
+/// * `Option<ExifInfo>` - Partial EXIF information with unreadable fields left unknown, `None`
+///   if no field could be salvaged
+pub fn salvage_exif_info(file_path: &Path) -> Option<ExifInfo> {
+    let fields = crate::salvage::salvage_file(file_path)?;
+    Some(exif_info_from(|tag| {
+        fields.iter().find(|field| field.tag == tag)
+    }))
+}
+
+/// Builds the EXIF information from a field lookup, leaving missing fields unknown
+fn exif_info_from<'a>(lookup: impl Fn(Tag) -> Option<&'a Field>) -> ExifInfo {
     let get_field = |tag: Tag| -> String {
-        exif.get_field(tag, In::PRIMARY)
+        lookup(tag)
             .map(|field| field.display_value().to_string())
-            .unwrap_or_else(|| "Unknown".to_string())
+            .unwrap_or_else(|| UNKNOWN.to_string())
     };
-
-    Ok(ExifInfo {
+    let text_field = |tag: Tag| -> Option<String> {
+        let value = lookup(tag)?.display_value().to_string();
+        Some(value.trim_matches('"').trim().to_string()).filter(|value| !value.is_empty())
+    };
+    ExifInfo {
         camera_model: get_field(Tag::Model),
         lens_model: get_field(Tag::LensModel),
         focal_length: get_field(Tag::FocalLength),
         aperture: get_field(Tag::FNumber),
         shutter_speed: get_field(Tag::ExposureTime),
         iso: get_field(Tag::PhotographicSensitivity),
+        gps: gps_position(&lookup),
+        date_time: lookup(Tag::DateTimeOriginal)
+            .or_else(|| lookup(Tag::DateTime))
+            .map(|field| {
+                field
+                    .display_value()
+                    .to_string()
+                    .trim_matches('"')
+                    .to_string()
+            }),
+        artist: text_field(Tag::Artist),
+        copyright: text_field(Tag::Copyright),
+    }
+}
+
+/// Reads the EXIF information of a RAW or HEIC file sharing the stem of an image
+///
+/// Edited exports often lose the lens data that the original next to them still has.
+///
+/// # Arguments
+/// * `file_path` - Path to the image file
+///
+/// # Returns
+/// * `Option<(PathBuf, ExifInfo)>` - Path and EXIF information of the first sibling with
+///   readable EXIF, `None` if there is none
+pub fn read_companion_exif(file_path: &Path) -> Option<(PathBuf, ExifInfo)> {
+    COMPANION_EXTENSIONS
+        .iter()
+        .flat_map(|ext| [ext.to_string(), ext.to_uppercase()])
+        .map(|ext| file_path.with_extension(ext))
+        .filter(|companion| companion.is_file())
+        .find_map(|companion| {
+            let exif = read_exif_info(&companion).ok()?;
+            Some((companion, exif))
+        })
+}
+
+/// Reads the camera manufacturer from an image file
+///
+/// # Arguments
+/// * `file_path` - Path to the image file
+///
+/// # Returns
+/// * `Option<String>` - Manufacturer without quotes, e.g. `FUJIFILM`, if EXIF has one
+pub fn read_camera_make(file_path: &Path) -> Option<String> {
+    let exif = read_exif(file_path).ok()?;
+    let field = exif.get_field(Tag::Make, In::PRIMARY)?;
+    let make = field.display_value().to_string();
+    Some(make.trim_matches('"').trim().to_string()).filter(|make| !make.is_empty())
+}
+
+/// Encodes EXIF fields as a TIFF block, with text fields of the primary image replaced
+///
+/// Fields of a type the encoder doesn't know are left out. The thumbnail is kept if `keep` accepts
+/// its JPEGInterchangeFormat field.
+///
+/// # Arguments
+/// * `exif` - Existing EXIF to start from, `None` to start empty
+/// * `updates` - Text fields to set, e.g. `(Tag::Artist, "Jane Doe")`
+/// * `keep` - Filter of the existing fields to carry over
+///
+/// # Returns
+/// * `Result<Vec<u8>, Box<dyn std::error::Error>>` - EXIF block starting with the TIFF header
+///
+/// # Errors
+/// Returns an error if the fields cannot be encoded
+pub fn encode_exif(
+    exif: Option<&Exif>,
+    updates: &[(Tag, String)],
+    keep: &dyn Fn(&Field) -> bool,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let updated: Vec<Field> = updates
+        .iter()
+        .map(|(tag, text)| Field {
+            tag: *tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![text.clone().into_bytes()]),
+        })
+        .collect();
+    let mut writer = exif::experimental::Writer::new();
+    for field in &updated {
+        writer.push_field(field);
+    }
+    let thumbnail = exif.and_then(|exif| {
+        let offset = exif
+            .get_field(Tag::JPEGInterchangeFormat, In::THUMBNAIL)
+            .filter(|field| keep(field))?
+            .value
+            .get_uint(0)? as usize;
+        let length = exif
+            .get_field(Tag::JPEGInterchangeFormatLength, In::THUMBNAIL)?
+            .value
+            .get_uint(0)? as usize;
+        exif.buf().get(offset..offset.checked_add(length)?)
+    });
+    if let Some(exif) = exif {
+        for field in exif.fields() {
+            let replaced =
+                field.ifd_num == In::PRIMARY && updates.iter().any(|(tag, _)| *tag == field.tag);
+            let thumbnail_field = field.ifd_num != In::PRIMARY && thumbnail.is_none();
+            let unknown = matches!(field.value, Value::Unknown(..));
+            if !replaced && !thumbnail_field && !unknown && keep(field) {
+                writer.push_field(field);
+            }
+        }
+    }
+    if let Some(thumbnail) = thumbnail {
+        writer.set_jpeg(thumbnail, In::THUMBNAIL);
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    writer.write(&mut tiff, exif.is_some_and(|exif| exif.little_endian()))?;
+    Ok(tiff.into_inner())
+}
+
+/// Encodes the EXIF of an image for its framed output
+///
+/// The fields describing the stored pixels, i.e. the orientation, the pixel dimensions and the
+/// thumbnail, are left out since they no longer match the output. The maker note is left out
+/// too, with a warning, since its offsets are relative to the original EXIF block and break
+/// when it moves. In privacy mode, the GPS position, serial numbers and owner name are left out
+/// as well.
+///
+/// # Arguments
+/// * `file_path` - Path to the image file
+/// * `strip_private` - Whether to leave out the fields identifying the photographer
+///
+/// # Returns
+/// * `Option<Vec<u8>>` - EXIF block starting with the TIFF header, `None` if the image has no EXIF
+pub fn output_exif(file_path: &Path, strip_private: bool) -> Option<Vec<u8>> {
+    const STALE_TAGS: [Tag; 3] = [Tag::Orientation, Tag::PixelXDimension, Tag::PixelYDimension];
+    let exif = read_exif(file_path).ok()?;
+    if !strip_private && exif.get_field(Tag::MakerNote, In::PRIMARY).is_some() {
+        println!(
+            "[WARN] Leaving the maker note of {} out of the output EXIF, it cannot be moved intact",
+            file_path.display()
+        );
+    }
+    let keep = |field: &Field| {
+        field.ifd_num == In::PRIMARY
+            && field.tag != Tag::MakerNote
+            && !STALE_TAGS.contains(&field.tag)
+            && !(strip_private && is_private(field.tag))
+    };
+    match encode_exif(Some(&exif), &[], &keep) {
+        Ok(tiff) => Some(tiff),
+        Err(e) => {
+            println!(
+                "[WARN] Failed to copy the EXIF of {}: {}",
+                file_path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Returns whether a field identifies the photographer, their location or their equipment
+pub fn is_private(tag: Tag) -> bool {
+    const PRIVATE_TAGS: [Tag; 4] = [
+        Tag::BodySerialNumber,
+        Tag::LensSerialNumber,
+        Tag::CameraOwnerName,
+        Tag::MakerNote,
+    ];
+    tag.context() == Context::Gps || PRIVATE_TAGS.contains(&tag)
+}
+
+/// Reads a text field of the primary image, such as Artist or Copyright
+///
+/// # Arguments
+/// * `file_path` - Path to the image file
+/// * `tag` - Tag of the field
+///
+/// # Returns
+/// * `Option<String>` - Trimmed value, `None` if the field is missing or empty
+pub fn read_text_field(file_path: &Path, tag: Tag) -> Option<String> {
+    let exif = read_exif(file_path).ok()?;
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let value = field.display_value().to_string();
+    Some(value.trim_matches('"').trim().to_string()).filter(|value| !value.is_empty())
+}
+
+/// Builds the attribution line of a photo, e.g. `© 2024 Jane Doe`
+///
+/// Uses the copyright notice, or the artist with the capture year. A notice without a
+/// copyright sign gets one, and only the photographer part of a notice with an editor part is
+/// kept.
+///
+/// # Arguments
+/// * `copyright` - Copyright notice, from EXIF or `--copyright`
+/// * `artist` - EXIF Artist
+/// * `date_time` - Capture date time, for the year of an artist attribution
+///
+/// # Returns
+/// * `Option<String>` - Attribution line, `None` without a notice or artist
+pub fn format_attribution(
+    copyright: Option<&str>,
+    artist: Option<&str>,
+    date_time: Option<&str>,
+) -> Option<String> {
+    let notice = |text: &str| {
+        let lower = text.to_lowercase();
+        if text.starts_with('©') || lower.starts_with("(c)") || lower.starts_with("copyright") {
+            text.to_string()
+        } else {
+            format!("© {}", text)
+        }
+    };
+    if let Some(copyright) = copyright
+        .and_then(|copyright| copyright.split("\", \"").next())
+        .map(str::trim)
+        .filter(|copyright| !copyright.is_empty())
+    {
+        return Some(notice(copyright));
+    }
+    let artist = artist.map(str::trim).filter(|artist| !artist.is_empty())?;
+    let year = date_time
+        .and_then(crate::time_of_day::parse_date_time)
+        .map(|(year, ..)| year);
+    Some(match year {
+        Some(year) => format!("© {} {}", year, artist),
+        None => notice(artist),
     })
 }
 
+/// Reads the local capture hour of an image from DateTimeOriginal
+///
+/// # Arguments
+/// * `file_path` - Path to the image file
+///
+/// # Returns
+/// * `Option<u32>` - Hour of the day, `None` if the capture time is missing
+pub fn read_capture_hour(file_path: &Path) -> Option<u32> {
+    let exif = read_exif(file_path).ok()?;
+    let field = exif
+        .get_field(Tag::DateTimeOriginal, In::PRIMARY)
+        .or_else(|| exif.get_field(Tag::DateTime, In::PRIMARY))?;
+    crate::time_of_day::parse_hour(&field.display_value().to_string())
+}
+
+/// Reads the local capture time of an image from DateTimeOriginal
+///
+/// # Arguments
+/// * `file_path` - Path to the image file
+///
+/// # Returns
+/// * `Option<u32>` - Minutes after midnight, `None` if the capture time is missing
+pub fn read_capture_minute(file_path: &Path) -> Option<u32> {
+    let exif = read_exif(file_path).ok()?;
+    let field = exif
+        .get_field(Tag::DateTimeOriginal, In::PRIMARY)
+        .or_else(|| exif.get_field(Tag::DateTime, In::PRIMARY))?;
+    crate::time_of_day::parse_minute_of_day(&field.display_value().to_string())
+}
+
+/// Reads the optional EXIF fields exposed as template tokens
+///
+/// Provides `{direction}` and `{bearing}` from GPSImgDirection, `{distance}` from
+/// SubjectDistance, `{altitude}` from GPSAltitude, `{depth}` from WaterDepth, `{ev}` from
+/// ExposureBiasValue, `{flash}` when Flash says it fired, `{wb}` from WhiteBalance, `{metering}`
+/// from MeteringMode, `{technique}` for composites and `{frame}` from ImageNumber. With the `astro`
+/// feature, also provides `{sun}`, `{light}` and `{moon}` from the GPS position and capture time.
+/// Fields missing from EXIF are left out. The online weather tokens come from
+/// `read_weather_fields`.
+///
+/// # Arguments
+/// * `file_path` - Path to the image file
+///
+/// # Returns
+/// * `Vec<(&'static str, String)>` - Token names and values
+pub fn read_extra_fields(file_path: &Path) -> Vec<(&'static str, String)> {
+    let Ok(data) = std::fs::read(file_path) else {
+        return Vec::new();
+    };
+    let Ok(exif) = read_exif(file_path) else {
+        return Vec::new();
+    };
+    let mut fields = Vec::new();
+    let date_time = field_value(&exif, Tag::DateTimeOriginal)
+        .or_else(|| field_value(&exif, Tag::DateTime))
+        .map(|value| value.display_as(Tag::DateTime).to_string());
+    if let Some(date) = date_time.as_deref().and_then(format_date) {
+        fields.push(("date", date));
+    }
+    if let Some(bearing) = rational_field(&exif, Tag::GPSImgDirection) {
+        fields.push(("direction", compass_point(bearing).to_string()));
+        fields.push(("bearing", format!("{:.0}°", bearing)));
+    }
+    if let Some(Value::Rational(values)) = field_value(&exif, Tag::SubjectDistance) {
+        if let Some(distance) = values.first().and_then(|r| format_distance(r.num, r.denom)) {
+            fields.push(("distance", distance));
+        }
+    }
+    if let Some(altitude) = rational_field(&exif, Tag::GPSAltitude) {
+        let below_sea_level =
+            field_value(&exif, Tag::GPSAltitudeRef).and_then(|value| value.get_uint(0)) == Some(1);
+        let altitude = if below_sea_level { -altitude } else { altitude };
+        fields.push(("altitude", format_altitude(altitude)));
+    }
+    if let Some(Value::SRational(values)) = field_value(&exif, Tag::WaterDepth) {
+        if let Some(depth) = values.first().filter(|r| r.denom != 0) {
+            fields.push(("depth", format!("{:.1} m", depth.to_f64())));
+        }
+    }
+    if let Some(Value::SRational(values)) = field_value(&exif, Tag::ExposureBiasValue) {
+        if let Some(bias) = values.first().filter(|r| r.denom != 0) {
+            fields.push(("ev", format_exposure_bias(bias.to_f64())));
+        }
+    }
+    let uint = |tag: Tag| field_value(&exif, tag).and_then(|value| value.get_uint(0));
+    // Bit 0 of Flash is set when the flash fired
+    if uint(Tag::Flash).is_some_and(|flash| flash & 1 == 1) {
+        fields.push(("flash", "Flash".to_string()));
+    }
+    match uint(Tag::WhiteBalance) {
+        Some(0) => fields.push(("wb", "Auto".to_string())),
+        Some(1) => fields.push(("wb", "Manual".to_string())),
+        _ => {}
+    }
+    if let Some(metering) = uint(Tag::MeteringMode).and_then(metering_label) {
+        fields.push(("metering", metering.to_string()));
+    }
+    let panorama_xmp = crate::jpeg::xmp_packet(&data).is_some_and(|xmp| {
+        xmp.contains("GPano:ProjectionType") || xmp.contains("GPano:UsePanoramaViewer")
+    });
+    if let Some(technique) = technique_label(
+        uint(Tag::CustomRendered),
+        uint(Tag::CompositeImage),
+        uint(Tag::SourceImageNumberOfCompositeImage),
+        panorama_xmp,
+    ) {
+        fields.push(("technique", technique));
+    }
+    // ImageNumber (TIFF/EP), written by some bodies to the 0th or Exif IFD
+    let image_number = [Context::Tiff, Context::Exif]
+        .into_iter()
+        .find_map(|context| uint(Tag(context, 0x9211)));
+    if let Some(number) = image_number {
+        fields.push(("frame", crate::template::format_thousands(number as u64)));
+    }
+    #[cfg(feature = "astro")]
+    if let Some((latitude, longitude)) = gps_position(|tag| exif.get_field(tag, In::PRIMARY)) {
+        if let Some(julian_day) = capture_julian_day(&exif, longitude) {
+            fields.extend(crate::astro::template_fields(
+                julian_day, latitude, longitude,
+            ));
+        }
+    }
+    fields
+}
+
+/// Looks up the weather at the GPS position and capture time of a photo
+///
+/// Provides `{weather}` and `{temperature}`. The position is sent to the weather archive, so call
+/// this only when a template asks for these tokens. Values are kept per file for the rest of the
+/// run, so a photo rendered several times is looked up once.
+///
+/// # Arguments
+/// * `file_path` - Path to the image file
+///
+/// # Returns
+/// * `Vec<(&'static str, String)>` - Token names and values, empty without GPS position or date
+#[cfg(feature = "online")]
+pub fn read_weather_fields(file_path: &Path) -> Vec<(&'static str, String)> {
+    type Fields = Vec<(&'static str, String)>;
+    static CACHE: std::sync::OnceLock<Mutex<std::collections::HashMap<PathBuf, Fields>>> =
+        std::sync::OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(fields) = cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(file_path)
+    {
+        return fields.clone();
+    }
+    let fields = read_exif(file_path)
+        .ok()
+        .and_then(|exif| {
+            let (latitude, longitude) = gps_position(|tag| exif.get_field(tag, In::PRIMARY))?;
+            let julian_day = capture_julian_day(&exif, longitude)?;
+            Some(crate::weather::template_fields(
+                julian_day, latitude, longitude,
+            ))
+        })
+        .unwrap_or_default();
+    cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(file_path.to_path_buf(), fields.clone());
+    fields
+}
+
+/// Returns the GPS latitude and longitude in signed degrees from a field lookup
+fn gps_position<'a>(lookup: impl Fn(Tag) -> Option<&'a Field>) -> Option<(f64, f64)> {
+    let degrees = |tag: Tag, reference: Tag, negative: char| -> Option<f64> {
+        let Value::Rational(parts) = &lookup(tag)?.value else {
+            return None;
+        };
+        let value = parts
+            .iter()
+            .take(3)
+            .zip([1.0, 60.0, 3600.0])
+            .filter(|(part, _)| part.denom != 0)
+            .map(|(part, unit)| part.num as f64 / part.denom as f64 / unit)
+            .sum::<f64>();
+        let reference = lookup(reference)?.display_value().to_string();
+        Some(if reference.contains(negative) {
+            -value
+        } else {
+            value
+        })
+    };
+    Some((
+        degrees(Tag::GPSLatitude, Tag::GPSLatitudeRef, 'S')?,
+        degrees(Tag::GPSLongitude, Tag::GPSLongitudeRef, 'W')?,
+    ))
+}
+
+/// Returns the Julian day of the capture time
+///
+/// Prefers the UTC GPS time stamp, then the original date time with its UTC offset. Without an
+/// offset, the local time is assumed to be the mean solar time of the longitude.
+#[cfg(any(feature = "astro", feature = "online"))]
+fn capture_julian_day(exif: &Exif, longitude: f64) -> Option<f64> {
+    use crate::time_of_day::{julian_day, parse_date_time, parse_utc_offset};
+
+    let text = |tag: Tag| {
+        exif.get_field(tag, In::PRIMARY)
+            .map(|field| field.display_value().to_string())
+    };
+    if let (Some(date), Some(Value::Rational(time))) = (
+        text(Tag::GPSDateStamp),
+        field_value(exif, Tag::GPSTimeStamp),
+    ) {
+        let time = time
+            .iter()
+            .map(|part| format!("{}", part.to_f64()))
+            .collect::<Vec<_>>()
+            .join(":");
+        if let Some((year, month, day, hours)) =
+            parse_date_time(&format!("{} {}", date.replace('-', ":"), time))
+        {
+            return Some(julian_day(year, month, day, hours));
+        }
+    }
+    let (year, month, day, hours) = parse_date_time(&text(Tag::DateTimeOriginal)?)?;
+    let offset = text(Tag::OffsetTimeOriginal)
+        .and_then(|offset| parse_utc_offset(&offset))
+        .unwrap_or(longitude / 15.0);
+    Some(julian_day(year, month, day, hours - offset))
+}
+
+/// Names the composite technique of a photo
+///
+/// Uses the panorama XMP of Google and Samsung cameras, the CustomRendered values written by
+/// phones for HDR and panoramas, and the CompositeImage tag of EXIF 2.32. Vendor MakerNotes
+/// aren't decoded, so pixel shift is only named when the body also sets CompositeImage.
+///
+/// # Arguments
+/// * `custom_rendered` - CustomRendered tag value
+/// * `composite` - CompositeImage tag value
+/// * `sources` - Number of source images used by the composite
+/// * `panorama_xmp` - Whether the XMP describes a panorama
+fn technique_label(
+    custom_rendered: Option<u32>,
+    composite: Option<u32>,
+    sources: Option<u32>,
+    panorama_xmp: bool,
+) -> Option<String> {
+    if panorama_xmp {
+        return Some("Panorama".to_string());
+    }
+    match custom_rendered {
+        Some(2 | 3) => return Some("HDR".to_string()),
+        Some(6) => return Some("Panorama".to_string()),
+        Some(7) => return Some("Portrait HDR".to_string()),
+        _ => {}
+    }
+    match (composite, sources) {
+        (Some(2 | 3), Some(sources)) if sources > 1 => {
+            Some(format!("Composite of {} frames", sources))
+        }
+        (Some(2 | 3), _) => Some("Composite".to_string()),
+        _ => None,
+    }
+}
+
+/// Returns the value of a primary image field
+fn field_value(exif: &Exif, tag: Tag) -> Option<&Value> {
+    exif.get_field(tag, In::PRIMARY).map(|field| &field.value)
+}
+
+/// Returns the first rational of a field as a float
+fn rational_field(exif: &Exif, tag: Tag) -> Option<f64> {
+    match field_value(exif, tag)? {
+        Value::Rational(values) => values
+            .first()
+            .filter(|r| r.denom != 0)
+            .map(|r| r.num as f64 / r.denom as f64),
+        _ => None,
+    }
+}
+
+/// Converts a compass bearing in degrees to one of the eight compass points
+fn compass_point(bearing: f64) -> &'static str {
+    const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+    let index = (bearing.rem_euclid(360.0) / 45.0).round() as usize % POINTS.len();
+    POINTS[index]
+}
+
+/// Formats an exposure compensation in stops, e.g. `+0.7 EV`, `-1 EV` or `0 EV`
+fn format_exposure_bias(stops: f64) -> String {
+    let rounded = (stops * 10.0).round() / 10.0;
+    if rounded == 0.0 {
+        return "0 EV".to_string();
+    }
+    let value = format!("{:+.1}", rounded);
+    format!("{} EV", value.trim_end_matches(".0"))
+}
+
+/// Names a MeteringMode value, `None` for unknown and other modes
+fn metering_label(mode: u32) -> Option<&'static str> {
+    match mode {
+        1 => Some("Average"),
+        2 => Some("Center-weighted"),
+        3 => Some("Spot"),
+        4 => Some("Multi-spot"),
+        5 => Some("Matrix"),
+        6 => Some("Partial"),
+        _ => None,
+    }
+}
+
+/// Formats an altitude in whole meters with thousands separators, e.g. `2,345 m`
+fn format_altitude(meters: f64) -> String {
+    let sign = if meters.round() < 0.0 { "-" } else { "" };
+    format!(
+        "{}{} m",
+        sign,
+        crate::template::format_thousands(meters.abs().round() as u64)
+    )
+}
+
+/// Formats the date of an EXIF date time such as `2024:06:01 19:42:10` as `2024-06-01`
+fn format_date(date_time: &str) -> Option<String> {
+    let date = date_time.trim_matches('"').trim().split(' ').next()?;
+    let parts: Vec<&str> = date.split([':', '-']).collect();
+    match parts[..] {
+        [year, month, day]
+            if year.len() == 4
+                && month.len() == 2
+                && day.len() == 2
+                && parts
+                    .iter()
+                    .all(|part| part.bytes().all(|b| b.is_ascii_digit()))
+                && parts.iter().any(|part| part.bytes().any(|b| b != b'0')) =>
+        {
+            Some(format!("{}-{}-{}", year, month, day))
+        }
+        _ => None,
+    }
+}
+
+/// Formats a subject distance rational, `∞` for infinity and `None` when unknown
+fn format_distance(num: u32, denom: u32) -> Option<String> {
+    match (num, denom) {
+        (u32::MAX, _) => Some("∞".to_string()),
+        (0, _) | (_, 0) => None,
+        (num, denom) => Some(format!("{:.1} m", num as f64 / denom as f64)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -69,6 +882,10 @@ mod tests {
             aperture: "Unknown".to_string(),
             shutter_speed: "Unknown".to_string(),
             iso: "Unknown".to_string(),
+            gps: None,
+            date_time: None,
+            artist: None,
+            copyright: None,
         };
 
         assert_eq!(exif.camera_model, "Unknown");
@@ -78,4 +895,162 @@ mod tests {
         assert_eq!(exif.shutter_speed, "Unknown");
         assert_eq!(exif.iso, "Unknown");
     }
+
+    #[test]
+    fn test_extra_field_formatting() {
+        assert_eq!(compass_point(0.0), "N");
+        assert_eq!(compass_point(44.0), "NE");
+        assert_eq!(compass_point(350.0), "N");
+        assert_eq!(compass_point(225.0), "SW");
+        assert_eq!(format_distance(u32::MAX, 1).as_deref(), Some("∞"));
+        assert_eq!(format_distance(32, 10).as_deref(), Some("3.2 m"));
+        assert_eq!(format_distance(0, 1), None);
+        assert_eq!(format_altitude(2345.4), "2,345 m");
+        assert_eq!(format_altitude(-28.0), "-28 m");
+        assert_eq!(format_altitude(-0.2), "0 m");
+        assert_eq!(
+            format_date("2024:06:01 19:42:10").as_deref(),
+            Some("2024-06-01")
+        );
+        assert_eq!(
+            format_date("2024-06-01 19:42:10").as_deref(),
+            Some("2024-06-01")
+        );
+        assert_eq!(format_date("0000:00:00 00:00:00"), None);
+        assert_eq!(format_date("    :  :     :  :  "), None);
+
+        assert_eq!(
+            technique_label(Some(3), None, None, false).as_deref(),
+            Some("HDR")
+        );
+        assert_eq!(
+            technique_label(Some(0), None, None, true).as_deref(),
+            Some("Panorama")
+        );
+        assert_eq!(
+            technique_label(None, Some(3), Some(5), false).as_deref(),
+            Some("Composite of 5 frames")
+        );
+        assert_eq!(technique_label(Some(0), Some(1), None, false), None);
+    }
+
+    #[test]
+    fn test_teleconverter() {
+        assert_eq!(
+            teleconverter_factor("RF100-500mm F4.5-7.1 L IS USM + EXTENDER RF1.4x"),
+            Some(1.4)
+        );
+        assert_eq!(
+            teleconverter_factor("FE 70-200mm F2.8 GM OSS + 2X Teleconverter"),
+            Some(2.0)
+        );
+        assert_eq!(
+            teleconverter_factor("AF-S NIKKOR 500mm f/5.6E PF + TC-14E III"),
+            Some(1.4)
+        );
+        assert_eq!(
+            teleconverter_factor("MP-E65mm f/2.8 1-5x Macro Photo"),
+            None
+        );
+        assert_eq!(
+            teleconverter_factor("XF100-400mmF4.5-5.6 R LM OIS WR"),
+            None
+        );
+
+        let mut exif = ExifInfo {
+            camera_model: "X-H2S".to_string(),
+            lens_model: "XF100-400mm + XF1.4X TC".to_string(),
+            focal_length: "400 mm".to_string(),
+            aperture: "5.6".to_string(),
+            shutter_speed: "1/1000".to_string(),
+            iso: "800".to_string(),
+            gps: None,
+            date_time: None,
+            artist: None,
+            copyright: None,
+        };
+        exif.apply_teleconverter(1.4);
+        assert_eq!(exif.focal_length, "560 mm");
+        assert_eq!(exif.aperture, "7.8");
+    }
+
+    #[test]
+    fn test_exif_inheritance() {
+        let exif = |lens: &str, iso: &str| ExifInfo {
+            camera_model: "X-T5".to_string(),
+            lens_model: lens.to_string(),
+            focal_length: "58".to_string(),
+            aperture: "2".to_string(),
+            shutter_speed: "1/125".to_string(),
+            iso: iso.to_string(),
+            gps: None,
+            date_time: None,
+            artist: None,
+            copyright: None,
+        };
+        let inheritance = ExifInheritance::default();
+        assert!(inheritance.inherit(None).is_none());
+
+        let first = inheritance
+            .inherit(Some(exif("Helios 44-2", "400")))
+            .unwrap();
+        assert_eq!(first.lens_model, "Helios 44-2");
+        let second = inheritance.inherit(Some(exif(UNKNOWN, "800"))).unwrap();
+        assert_eq!(second.lens_model, "Helios 44-2");
+        assert_eq!(second.iso, "800");
+        let third = inheritance.inherit(None).unwrap();
+        assert_eq!(third.iso, "800");
+    }
+
+    #[test]
+    fn test_exposure_fields() {
+        assert_eq!(format_exposure_bias(0.7), "+0.7 EV");
+        assert_eq!(format_exposure_bias(2.0 / 3.0), "+0.7 EV");
+        assert_eq!(format_exposure_bias(-1.0), "-1 EV");
+        assert_eq!(format_exposure_bias(0.01), "0 EV");
+        assert_eq!(metering_label(5), Some("Matrix"));
+        assert_eq!(metering_label(255), None);
+    }
+
+    #[test]
+    fn test_format_attribution() {
+        let date = Some("2024:06:01 19:42:10");
+        assert_eq!(
+            format_attribution(Some("Jane Doe"), None, None).as_deref(),
+            Some("© Jane Doe")
+        );
+        assert_eq!(
+            format_attribution(Some("Copyright 2023 Jane Doe"), Some("Jane"), date).as_deref(),
+            Some("Copyright 2023 Jane Doe")
+        );
+        assert_eq!(
+            format_attribution(Some("Jane Doe\", \"Studio"), None, None).as_deref(),
+            Some("© Jane Doe")
+        );
+        assert_eq!(
+            format_attribution(Some(" "), Some("Jane Doe"), date).as_deref(),
+            Some("© 2024 Jane Doe")
+        );
+        assert_eq!(format_attribution(None, None, date), None);
+    }
+
+    #[test]
+    fn test_exif_completeness() {
+        let exif = |lens: &str| ExifInfo {
+            camera_model: "EOS R6".to_string(),
+            lens_model: lens.to_string(),
+            focal_length: "50".to_string(),
+            aperture: "1.8".to_string(),
+            shutter_speed: "1/200".to_string(),
+            iso: "200".to_string(),
+            gps: None,
+            date_time: None,
+            artist: None,
+            copyright: None,
+        };
+        let mut edited = exif(UNKNOWN);
+        assert!(!edited.is_complete());
+        assert_eq!(edited.fill_missing_from(&exif("RF50mm F1.8 STM")), 1);
+        assert!(edited.is_complete());
+    }
 }
@@ -0,0 +1,111 @@
+//! Reverse geocoding module
+//!
+//! This module names the place a photo was taken from its GPS position, offline, with the
+//! nearest city of a bundled table of major cities and travel destinations.
+
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+/// Bundled city table, with `name`, `country`, `latitude` and `longitude` columns
+const CITIES: &str = include_str!("places/cities.csv");
+
+/// Farthest a photo may be from a city to be named after it, in kilometers
+pub const MAX_DISTANCE_KM: f64 = 50.0;
+
+/// Mean radius of the Earth in kilometers
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// City of the bundled table
+#[derive(Debug, Clone, Deserialize)]
+pub struct City {
+    /// City name in English, e.g. `Kyoto`
+    pub name: String,
+    /// Country name in English, e.g. `Japan`
+    pub country: String,
+    /// Latitude in signed degrees
+    pub latitude: f64,
+    /// Longitude in signed degrees
+    pub longitude: f64,
+}
+
+/// Returns the cities of the bundled table, parsed on first use
+pub fn cities() -> &'static [City] {
+    static TABLE: OnceLock<Vec<City>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        csv::Reader::from_reader(CITIES.as_bytes())
+            .deserialize()
+            .filter_map(Result::ok)
+            .collect()
+    })
+}
+
+/// Returns the great-circle distance between two positions in kilometers
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Finds the nearest city of a position
+///
+/// # Arguments
+/// * `latitude` - Latitude in signed degrees
+/// * `longitude` - Longitude in signed degrees
+///
+/// # Returns
+/// * `Option<&City>` - Nearest city, `None` if no city is within `MAX_DISTANCE_KM`
+pub fn nearest_city(latitude: f64, longitude: f64) -> Option<&'static City> {
+    cities()
+        .iter()
+        .map(|city| {
+            let distance = distance_km((latitude, longitude), (city.latitude, city.longitude));
+            (city, distance)
+        })
+        .filter(|(_, distance)| *distance <= MAX_DISTANCE_KM)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(city, _)| city)
+}
+
+/// Formats a position as degrees with hemispheres, e.g. `35.0116°N, 135.7681°E`
+pub fn format_coordinates(latitude: f64, longitude: f64) -> String {
+    format!(
+        "{:.4}°{}, {:.4}°{}",
+        latitude.abs(),
+        if latitude < 0.0 { 'S' } else { 'N' },
+        longitude.abs(),
+        if longitude < 0.0 { 'W' } else { 'E' }
+    )
+}
+
+/// Returns the location line of a position
+///
+/// # Arguments
+/// * `latitude` - Latitude in signed degrees
+/// * `longitude` - Longitude in signed degrees
+///
+/// # Returns
+/// * `String` - Nearest city and country, e.g. `Kyoto, Japan`, or the coordinates when no city
+///   is near
+pub fn location_label(latitude: f64, longitude: f64) -> String {
+    match nearest_city(latitude, longitude) {
+        Some(city) => format!("{}, {}", city.name, city.country),
+        None => format_coordinates(latitude, longitude),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_label() {
+        assert!(cities().len() > 300);
+        assert_eq!(location_label(34.9671, 135.7727), "Kyoto, Japan");
+        assert_eq!(location_label(-33.8568, 151.2153), "Sydney, Australia");
+        assert_eq!(location_label(0.0, -30.0), "0.0000°N, 30.0000°W");
+        let paris_london = distance_km((48.8566, 2.3522), (51.5074, -0.1278));
+        assert!((paris_london - 344.0).abs() < 2.0);
+    }
+}
@@ -4,21 +4,30 @@
 //! and adjusting aspect ratios.
 
 use image::imageops::FilterType;
-use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba, RgbaImage};
-use imageproc::drawing::draw_text_mut;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Pixel, Rgba, RgbaImage};
+use imageproc::drawing::{draw_hollow_rect_mut, draw_line_segment_mut, draw_text_mut};
+use imageproc::rect::Rect as DrawRect;
 use rusttype::{Font, Scale};
 use std::path::Path;
 
+use crate::backdrop::{BackdropOptions, GrainScope};
+use crate::exif::ExifInfo;
+use crate::layout::{compute_bar_layout, BarLayout, BarMetrics, Rect};
+use crate::options::{BarColor, MissingExif, ProcessOptions};
+use crate::profile::SourceProfile;
 use crate::resource::Resources;
+use crate::template::TemplateContext;
+use crate::theme::{LogoOrder, LogoTreatment, ParamField, TextPill, Theme};
+use crate::time_of_day::TimeOfDay;
 
 /// Adds an information bar to the bottom of an image
 ///
 /// # Arguments
 /// * `img` - The input image
 /// * `input_path` - Path to the input image file
-/// * `info_height` - Height of the information bar in pixels
+/// * `metadata` - Resolved metadata of the photo
 /// * `resources` - Font and scaling resources
-/// * `custom_logo_path` - Optional path to a custom logo file
+/// * `options` - Processing options
 ///
 /// # Returns
 /// * `Result<DynamicImage, Box<dyn std::error::Error>>` - Image with information bar if successful
@@ -28,114 +37,859 @@ use crate::resource::Resources;
 pub fn add_info_bar(
     img: DynamicImage,
     input_path: &Path,
-    info_height: u32,
+    metadata: &PhotoMetadata,
     resources: &Resources,
-    custom_logo_path: Option<&Path>,
+    options: &ProcessOptions,
 ) -> Result<DynamicImage, Box<dyn std::error::Error>> {
     let (width, height) = img.dimensions();
-    let padding = 32u32;
+    let photo = img.to_rgba8();
+    let background = bar_background(&photo, input_path, options.bar_color);
     let mut new_img: RgbaImage =
-        ImageBuffer::from_pixel(width, height + info_height, Rgba([255, 255, 255, 255]));
-    image::imageops::overlay(&mut new_img, &img.to_rgba8(), 0, 0);
+        ImageBuffer::from_pixel(width, height + options.info_height, background);
+    image::imageops::overlay(&mut new_img, &photo, 0, 0);
+    if options.debug_layout {
+        draw_thirds_grid(&mut new_img, width, height);
+    }
+
+    println!("[INFO] Processing image: {}x{}", width, height);
+    let bar = render_info_bar(width, input_path, metadata, resources, options, background)?;
+    image::imageops::overlay(&mut new_img, &bar, 0, height as i64);
+    if let DynamicImage::ImageRgba16(deep_photo) = &img {
+        // Keep the photo at 16 bits; the flat bar has no gradients to band
+        let mut deep_img = DynamicImage::ImageRgba8(new_img).to_rgba16();
+        if !options.debug_layout {
+            image::imageops::overlay(&mut deep_img, deep_photo, 0, 0);
+        }
+        return Ok(DynamicImage::ImageRgba16(deep_img));
+    }
+    Ok(DynamicImage::ImageRgba8(new_img))
+}
 
-    if let Ok(exif_info) = crate::exif::read_exif_info(input_path) {
+/// Returns the background color of the bar below a photo
+///
+/// # Arguments
+/// * `photo` - Photo the bar is attached to
+/// * `input_path` - Path to the input image file, used to read the capture time
+/// * `bar_color` - Background color mode of the bar
+pub fn bar_background(photo: &RgbaImage, input_path: &Path, bar_color: BarColor) -> Rgba<u8> {
+    match bar_color {
+        BarColor::White => Rgba([255, 255, 255, 255]),
+        BarColor::Edge => crate::backdrop::edge_color(photo, true),
+        BarColor::TimeOfDay => match crate::exif::read_capture_hour(input_path) {
+            Some(hour) => {
+                let time_of_day = TimeOfDay::from_hour(hour);
+                println!(
+                    "[INFO] Captured at {:02}h ({:?}), tinting the bar",
+                    hour, time_of_day
+                );
+                time_of_day.bar_color()
+            }
+            None => {
+                println!("[WARN] Capture time not found in EXIF, using a white bar");
+                Rgba([255, 255, 255, 255])
+            }
+        },
+    }
+}
+
+/// Returns the primary and secondary text colors readable on a bar background
+///
+/// Transparent backgrounds, used for bar layers, keep the dark text.
+fn text_colors(background: Rgba<u8>) -> (Rgba<u8>, Rgba<u8>) {
+    let luma =
+        0.299 * background[0] as f32 + 0.587 * background[1] as f32 + 0.114 * background[2] as f32;
+    if background[3] > 0 && luma < 128.0 {
+        (Rgba([255, 255, 255, 255]), Rgba([200, 200, 200, 255]))
+    } else {
+        (Rgba([0, 0, 0, 255]), Rgba([80, 80, 80, 255]))
+    }
+}
+
+/// Fills EXIF fields an editor dropped on export from the original or the XMP packet
+///
+/// # Arguments
+/// * `input_path` - Path to the exported image
+/// * `profile` - Editor the image was exported from
+/// * `exif_info` - EXIF information read so far
+///
+/// # Returns
+/// * `Option<ExifInfo>` - EXIF information with the fields that could be found filled
+fn fill_from_export_source(
+    input_path: &Path,
+    profile: SourceProfile,
+    mut exif_info: Option<ExifInfo>,
+) -> Option<ExifInfo> {
+    let originals = profile
+        .original_paths(input_path)
+        .into_iter()
+        .filter_map(|path| {
+            let exif = crate::exif::read_exif_info(&path).ok();
+            exif.map(|exif| (path.clone(), exif))
+                .or_else(|| crate::exif::read_companion_exif(&path))
+        })
+        .map(|(path, exif)| (path.display().to_string(), exif));
+    let packet = std::fs::read(input_path)
+        .ok()
+        .and_then(|data| crate::jpeg::xmp_packet(&data))
+        .map(|packet| ("the XMP packet".to_string(), crate::xmp::exif_info(&packet)));
+    for (source, other) in originals.chain(packet) {
+        match exif_info.as_mut() {
+            Some(exif) => {
+                let filled = exif.fill_missing_from(&other);
+                if filled > 0 {
+                    println!("[INFO] Filled {} EXIF field(s) from {}", filled, source);
+                }
+            }
+            None => {
+                println!("[INFO] No readable EXIF, using {}", source);
+                exif_info = Some(other);
+            }
+        }
+        if exif_info.as_ref().is_some_and(ExifInfo::is_complete) {
+            break;
+        }
+    }
+    exif_info
+}
+
+/// Metadata of a photo, resolved once and shared by every render of the photo
+#[derive(Debug, Clone)]
+pub struct PhotoMetadata {
+    /// EXIF information, `None` if there is none
+    pub exif: Option<ExifInfo>,
+    /// File and film template values
+    pub context: TemplateContext,
+}
+
+/// Resolves the EXIF information and template values of a photo
+///
+/// Applies the metadata table, manual lens labels and EXIF inheritance on top of the EXIF read
+/// from the file. Inheritance moves on to the next file on every call, so resolve each photo
+/// once and pass the result to all of its renders.
+///
+/// # Arguments
+/// * `input_path` - Path to the input image file
+/// * `options` - Processing options
+///
+/// # Returns
+/// * `PhotoMetadata` - EXIF information and template values of the photo
+pub fn resolve_metadata(input_path: &Path, options: &ProcessOptions) -> PhotoMetadata {
+    let mut exif_info = match crate::exif::read_exif_info(input_path) {
+        Ok(exif) => Some(exif),
+        Err(_) if options.tolerant_exif => {
+            let salvaged = crate::exif::salvage_exif_info(input_path);
+            if salvaged.is_some() {
+                println!("[WARN] Malformed EXIF, using the fields that could be salvaged");
+            }
+            salvaged
+        }
+        Err(_) => None,
+    };
+    if exif_info.is_none() {
+        if let Some((sidecar, sidecar_exif)) = crate::xmp::read_sidecar(input_path) {
+            println!("[INFO] No readable EXIF, using {}", sidecar.display());
+            exif_info = Some(sidecar_exif);
+        }
+    }
+    if options.companion_exif && !exif_info.as_ref().is_some_and(ExifInfo::is_complete) {
+        if let Some((companion, companion_exif)) = crate::exif::read_companion_exif(input_path) {
+            exif_info = Some(match exif_info {
+                Some(mut exif) => {
+                    let filled = exif.fill_missing_from(&companion_exif);
+                    println!(
+                        "[INFO] Filled {} EXIF field(s) from {}",
+                        filled,
+                        companion.display()
+                    );
+                    exif
+                }
+                None => {
+                    println!("[INFO] No readable EXIF, using {}", companion.display());
+                    companion_exif
+                }
+            });
+        }
+    }
+    if let Some(profile) = options.source_profile {
+        if !exif_info.as_ref().is_some_and(ExifInfo::is_complete) {
+            exif_info = fill_from_export_source(input_path, profile, exif_info);
+        }
+    }
+    if !options.exif_overrides.is_empty() {
+        exif_info = Some(options.exif_overrides.apply(exif_info));
+    }
+    let mut film = options.film.clone();
+    if let Some(row) = options
+        .metadata
+        .as_ref()
+        .and_then(|table| table.row_for(input_path))
+    {
+        exif_info = Some(row.apply(exif_info));
+        film.override_with(&row.film());
+    }
+    if let Some(exif) = exif_info.as_mut() {
+        if let Some(label) = options.manual_lenses.label_for(exif) {
+            println!("[INFO] Manual lens detected, using lens label: {}", label);
+            exif.lens_model = label.to_string();
+        }
+    }
+    let mut context = TemplateContext::for_file(input_path).with_film(&film);
+    if let Some(exif) = exif_info.as_mut() {
+        if let Some(factor) = crate::exif::teleconverter_factor(&exif.lens_model) {
+            context.set("teleconverter", format!("{}×", factor));
+            if options.apply_teleconverter {
+                println!(
+                    "[INFO] Teleconverter detected, applying {}× to focal length and aperture",
+                    factor
+                );
+                exif.apply_teleconverter(factor);
+            }
+        }
+    }
+    for (token, value) in crate::exif::read_extra_fields(input_path) {
+        context.set(token, value);
+    }
+    #[cfg(feature = "online")]
+    let weather = if options.weather_lookup() {
+        crate::exif::read_weather_fields(input_path)
+    } else {
+        Vec::new()
+    };
+    #[cfg(not(feature = "online"))]
+    let weather = Vec::new();
+    context.set_weather(weather);
+    let exif_info = match &options.exif_inheritance {
+        Some(inheritance) => inheritance.inherit(exif_info),
+        None => exif_info,
+    };
+    PhotoMetadata {
+        exif: exif_info,
+        context,
+    }
+}
+
+/// Renders the information bar on its own canvas
+///
+/// # Arguments
+/// * `width` - Width of the bar in pixels
+/// * `input_path` - Path to the input image file
+/// * `metadata` - Resolved metadata of the photo
+/// * `resources` - Font and scaling resources
+/// * `options` - Processing options
+/// * `background` - Background color of the bar, use a transparent color for a layer
+///
+/// # Returns
+/// * `Result<RgbaImage, Box<dyn std::error::Error>>` - Rendered bar, empty if EXIF cannot be read
+///
+/// # Errors
+/// Returns an error if the bar cannot be rendered
+pub fn render_info_bar(
+    width: u32,
+    input_path: &Path,
+    metadata: &PhotoMetadata,
+    resources: &Resources,
+    options: &ProcessOptions,
+    background: Rgba<u8>,
+) -> Result<RgbaImage, Box<dyn std::error::Error>> {
+    let info_height = options.info_height;
+    let theme = &options.theme;
+    let density = options.scale.max(1);
+    let padding = 32 * density;
+    let gap = 8 * density;
+    let mut new_img: RgbaImage = ImageBuffer::from_pixel(width, info_height, background);
+    let ink = text_colors(background);
+
+    let context = &metadata.context;
+    if let Some(exif_info) = &metadata.exif {
         let camera_model = exif_info.camera_model.trim_matches('"');
         let lens_model = exif_info.lens_model.trim_matches('"');
+        let camera_text = theme.camera_case.apply(camera_model);
+        let lens_text = match options.lens_suffix.as_deref() {
+            Some(suffix) => theme.lens_case.apply(&format!("{} {}", lens_model, suffix)),
+            None => theme.lens_case.apply(lens_model),
+        };
 
-        println!("[INFO] Processing image: {}x{}", width, height);
         println!("[INFO] Camera: {}, Lens: {}", camera_model, lens_model);
+        let location = match exif_info.gps {
+            Some((latitude, longitude)) if options.show_location => {
+                let location = crate::geocode::location_label(latitude, longitude);
+                println!("[INFO] Location: {}", location);
+                location
+            }
+            _ => String::new(),
+        };
+
+        let exif_context = context.clone().with_exif(exif_info);
+        let params_template = options
+            .params_template
+            .as_deref()
+            .unwrap_or(DEFAULT_PARAMS_TEMPLATE);
+        let mut param_fields = render_params(params_template, &exif_context);
+        for element in &options.param_elements {
+            if let Some(value) = context.get(element.token()) {
+                let text = match element.label() {
+                    Some(label) => format!("{} {}", label, value),
+                    None => value.to_string(),
+                };
+                param_fields.push((ParamField::Extras, text));
+            }
+        }
+        if let (Some(format), Some(date_time)) = (&options.date_format, &exif_info.date_time) {
+            if let Some(date) = crate::time_of_day::format_date_time(date_time, format) {
+                param_fields.push((ParamField::Date, date));
+            }
+        }
+        println!(
+            "[INFO] Camera settings: {}",
+            theme.params_case.apply(&join_params(&param_fields))
+        );
+
+        // Try to load the logos, but continue even if it fails
+        let mut sources = Vec::new();
+        if let Ok(Some(logo)) = crate::resource::load_camera_logo(
+            camera_model,
+            options.logo.as_deref(),
+            options.logo_provider.as_ref(),
+        ) {
+            sources.push((logo, true));
+        }
+        if let Some(secondary_path) = theme.secondary_logo.as_deref() {
+            if let Ok(Some(logo)) = crate::resource::load_secondary_logo(secondary_path) {
+                match theme.logo_order {
+                    LogoOrder::BrandFirst => sources.push((logo, false)),
+                    LogoOrder::SecondaryFirst => sources.insert(0, (logo, false)),
+                }
+            }
+        }
 
-        let camera_text_height = resources.scale_bold.y.ceil() as u32;
-        let lens_text_height = resources.scale_regular.y.ceil() as u32;
-        let total_text_height = camera_text_height + lens_text_height + 8;
-        let left_text_top = height + (info_height - total_text_height) / 2;
-        let camera_y = left_text_top;
-        let lens_y = camera_y + camera_text_height + 8;
-        draw_text_mut(
+        let caption = options
+            .caption
+            .as_deref()
+            .map(|caption| exif_context.render(caption))
+            .unwrap_or_default();
+        let caption = caption.as_str();
+        let attribution = if options.show_copyright {
+            let copyright = options.copyright.as_ref().or(exif_info.copyright.as_ref());
+            crate::exif::format_attribution(
+                copyright.map(String::as_str),
+                exif_info.artist.as_deref(),
+                exif_info.date_time.as_deref(),
+            )
+            .unwrap_or_default()
+        } else {
+            String::new()
+        };
+        let logo_sizes: Vec<((u32, u32), bool)> = sources
+            .iter()
+            .map(|(logo, is_brand)| (logo.dimensions(), *is_brand))
+            .collect();
+        let content = BarContent {
+            camera: &camera_text,
+            lens: &lens_text,
+            location: &location,
+            params: &param_fields,
+            caption,
+            attribution: &attribution,
+            logos: &logo_sizes,
+        };
+        let measure = measure_bar(&content, resources, theme, width, info_height, density);
+        for field in &measure.dropped {
+            println!(
+                "[INFO] Dropped {:?} from the parameters to fit the bar",
+                field
+            );
+        }
+        if measure.params_lines > 1 {
+            println!("[INFO] Parameters text wrapped onto two lines to fit the bar");
+        }
+        let metrics = measure.metrics;
+        let layout = match &options.layout_lock {
+            Some(lock) => lock.get_or_compute(&metrics),
+            None => compute_bar_layout(&metrics),
+        };
+
+        if let Some(pill) = theme.text_pill {
+            draw_pill(&mut new_img, &layout.left, &pill);
+            draw_pill(&mut new_img, &layout.params, &pill);
+        }
+
+        let left_scale = |scale: Scale| Scale {
+            x: scale.x * measure.left_scale,
+            y: scale.y * measure.left_scale,
+        };
+        let left_offset = |height: u32| ((height + gap) as f32 * measure.left_scale).round() as i32;
+        let left_line = |font: &Font, scale: Scale, text: &str| {
+            truncate_text(font, left_scale(scale), text, layout.left.width)
+        };
+        let camera_y = layout.left.y;
+        let lens_y = camera_y + left_offset(measure.camera_height);
+        draw_styled_text(
             &mut new_img,
-            Rgba([0, 0, 0, 255]),
-            padding as i32,
-            camera_y as i32,
-            resources.scale_bold,
+            theme,
+            ink.0,
+            (layout.left.x, camera_y),
+            left_scale(resources.scale_bold),
             &resources.font_bold,
-            camera_model,
+            &left_line(&resources.font_bold, resources.scale_bold, &camera_text),
         );
-        draw_text_mut(
-            &mut new_img,
-            Rgba([80, 80, 80, 255]),
-            padding as i32,
-            lens_y as i32,
-            resources.scale_regular,
-            &resources.font_regular,
-            lens_model,
-        );
-
-        let params = format!(
-            "{}mm | f{} | {}/s | ISO {}",
-            exif_info
-                .focal_length
-                .trim_end_matches(" mm")
-                .replace('"', ""),
-            exif_info.aperture.replace('"', ""),
-            exif_info.shutter_speed.replace('"', ""),
-            exif_info.iso.replace('"', "")
-        );
-        println!("[INFO] Camera settings: {}", params);
-
-        let param_width = text_width(&resources.font_regular, resources.scale_regular, &params);
-        let param_x = width as i32 - padding as i32 - param_width;
-        let param_y =
-            height as i32 + (info_height as i32 - resources.scale_regular.y.ceil() as i32) / 2;
-        draw_text_mut(
+        draw_styled_text(
             &mut new_img,
-            Rgba([0, 0, 0, 255]),
-            param_x,
-            param_y,
-            resources.scale_regular,
+            theme,
+            ink.1,
+            (layout.left.x, lens_y),
+            left_scale(resources.scale_regular),
             &resources.font_regular,
-            &params,
+            &left_line(&resources.font_regular, resources.scale_regular, &lens_text),
         );
+        if !location.is_empty() {
+            draw_styled_text(
+                &mut new_img,
+                theme,
+                ink.1,
+                (layout.left.x, lens_y + left_offset(measure.line_height)),
+                left_scale(resources.scale_regular),
+                &resources.font_regular,
+                &left_line(&resources.font_regular, resources.scale_regular, &location),
+            );
+        }
 
-        // Try to load and draw logo, but continue even if it fails
-        if let Ok(Some(logo)) = crate::resource::load_camera_logo(camera_model, custom_logo_path) {
-            let logo_target_height = (info_height as f32 * 0.65).round() as u32;
-            let logo = logo.resize(
-                logo.width() * logo_target_height / logo.height(),
-                logo_target_height,
-                image::imageops::FilterType::Lanczos3,
+        let params_scale = Scale {
+            x: resources.scale_regular.x * layout.params_scale,
+            y: resources.scale_regular.y * layout.params_scale,
+        };
+        for (index, (line, line_width)) in measure
+            .right_lines
+            .iter()
+            .zip(&measure.right_widths)
+            .enumerate()
+        {
+            let scaled_width = (*line_width as f32 * layout.params_scale).round() as i32;
+            let (line, line_x) = if scaled_width > layout.params.width as i32 {
+                let line = truncate_text(
+                    &resources.font_regular,
+                    params_scale,
+                    line,
+                    layout.params.width,
+                );
+                (line, layout.params.x)
+            } else {
+                (line.clone(), layout.params.right() - scaled_width)
+            };
+            let line_y = layout.params.y
+                + ((measure.line_height + gap) as f32 * index as f32 * layout.params_scale).round()
+                    as i32;
+            let color = if index < measure.params_lines {
+                ink.0
+            } else {
+                ink.1
+            };
+            draw_styled_text(
+                &mut new_img,
+                theme,
+                color,
+                (line_x, line_y),
+                params_scale,
+                &resources.font_regular,
+                &line,
             );
-            let logo_rgba = logo.to_rgba8();
-            let logo_x = (width / 2).saturating_sub(logo_rgba.width() / 2);
-            let logo_y = height + (info_height - logo_rgba.height()) / 2;
-            for y in 0..logo_rgba.height() {
-                for x in 0..logo_rgba.width() {
-                    let pixel = logo_rgba.get_pixel(x, y);
-                    let dst = new_img.get_pixel_mut(logo_x + x, logo_y + y);
-                    let alpha = pixel[3] as f32 / 255.0;
-                    for c in 0..3 {
-                        dst[c] =
-                            ((pixel[c] as f32 * alpha) + (dst[c] as f32 * (1.0 - alpha))) as u8;
-                    }
-                    dst[3] = 255;
+        }
+
+        if let Some(logo_box) = layout.logo {
+            let mut logo_x = logo_box.x.max(0) as u32;
+            for (logo, is_brand) in &sources {
+                let (height, logo_y) = if *is_brand {
+                    place_logo(&logo_box, info_height, theme.logo_scale, theme.logo_offset)
+                } else {
+                    (logo_box.height, logo_box.y.max(0) as u32)
+                };
+                let mut logo_rgba = resize_logo(logo, height);
+                if *is_brand {
+                    apply_logo_treatment(&mut logo_rgba, theme.logo_treatment, theme.logo_opacity);
                 }
+                blend_logo(&mut new_img, &logo_rgba, logo_x, logo_y);
+                logo_x = logo_x
+                    .saturating_add(logo_rgba.width())
+                    .saturating_add(theme.logo_gap);
             }
             println!("[INFO] Logo added successfully");
         }
+        if options.debug_layout {
+            draw_debug_layout(&mut new_img, &layout, padding);
+        }
+    } else if options.missing_exif == MissingExif::Placeholder {
+        println!("[WARN] Failed to read EXIF information from image, rendering placeholder bar");
+        draw_placeholder(
+            &mut new_img,
+            input_path,
+            resources,
+            options,
+            padding,
+            context,
+            ink,
+        );
     } else {
         println!("[WARN] Failed to read EXIF information from image");
     }
-    Ok(DynamicImage::ImageRgba8(new_img))
+    Ok(new_img)
+}
+
+/// Draws a rounded pill behind a text block
+///
+/// The pill extends the text extents by the pill padding and its corner radius is half its
+/// height, so single lines get fully rounded ends.
+///
+/// # Arguments
+/// * `canvas` - Image to draw on
+/// * `extents` - Measured extents of the text block
+/// * `pill` - Color and padding of the pill
+pub fn draw_pill(canvas: &mut RgbaImage, extents: &Rect, pill: &TextPill) {
+    if extents.width == 0 || extents.height == 0 {
+        return;
+    }
+    let padding = pill.padding as f32;
+    let left = extents.x as f32 - padding;
+    let top = extents.y as f32 - padding;
+    let right = extents.right() as f32 + padding;
+    let bottom = (extents.y + extents.height as i32) as f32 + padding;
+    let radius = ((bottom - top) / 2.0).min((right - left) / 2.0);
+
+    let x_range = left.max(0.0) as u32..(right.ceil() as u32).min(canvas.width());
+    let y_range = top.max(0.0) as u32..(bottom.ceil() as u32).min(canvas.height());
+    for y in y_range {
+        for x in x_range.clone() {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            // Distance from the pixel to the straight inner rectangle of the rounded shape
+            let dx = (left + radius - px).max(px - (right - radius)).max(0.0);
+            let dy = (top + radius - py).max(py - (bottom - radius)).max(0.0);
+            if dx * dx + dy * dy <= radius * radius {
+                canvas.get_pixel_mut(x, y).blend(&pill.color);
+            }
+        }
+    }
+}
+
+/// Draws text with the shadow and outline of the theme
+///
+/// # Arguments
+/// * `canvas` - Image to draw on
+/// * `theme` - Theme holding the text effects
+/// * `color` - Text color
+/// * `position` - Top-left position of the text
+/// * `scale` - Font scale
+/// * `font` - Font
+/// * `text` - Text to draw
+pub fn draw_styled_text(
+    canvas: &mut RgbaImage,
+    theme: &Theme,
+    color: Rgba<u8>,
+    (x, y): (i32, i32),
+    scale: Scale,
+    font: &Font,
+    text: &str,
+) {
+    let y = y + theme.baseline_offset;
+    let outline_offsets: Vec<(i32, i32)> = match theme.text_outline {
+        Some(outline) => {
+            let width = outline.width as i32;
+            (-width..=width)
+                .flat_map(|dx| (-width..=width).map(move |dy| (dx, dy)))
+                .filter(|&(dx, dy)| (dx, dy) != (0, 0) && dx * dx + dy * dy <= width * width)
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    if let Some(shadow) = theme.text_shadow {
+        // Render the shadow on a small layer around the text so blurring stays cheap
+        let margin = (shadow.blur * 3.0).ceil() as i32
+            + theme.text_outline.map_or(0, |o| o.width as i32)
+            + 2;
+        let layer_width = text_width(font, scale, text).max(0) + margin * 2;
+        let layer_height = text_height(font, scale) as i32 + margin * 2;
+        let mut layer = RgbaImage::new(layer_width as u32, layer_height as u32);
+        for (dx, dy) in outline_offsets.iter().copied().chain([(0, 0)]) {
+            draw_text_mut(
+                &mut layer,
+                shadow.color,
+                margin + dx,
+                margin + dy,
+                scale,
+                font,
+                text,
+            );
+        }
+        if shadow.blur > 0.0 {
+            layer = image::imageops::blur(&layer, shadow.blur);
+        }
+        image::imageops::overlay(
+            canvas,
+            &layer,
+            (x + shadow.offset_x - margin) as i64,
+            (y + shadow.offset_y - margin) as i64,
+        );
+    }
+    if let Some(outline) = theme.text_outline {
+        for (dx, dy) in &outline_offsets {
+            draw_text_mut(canvas, outline.color, x + dx, y + dy, scale, font, text);
+        }
+    }
+    draw_text_mut(canvas, color, x, y, scale, font, text);
+}
+
+/// Color of the margin guides drawn by `--debug-layout`
+const DEBUG_MARGIN_COLOR: Rgba<u8> = Rgba([255, 0, 255, 255]);
+
+/// Draws the layout boxes and margins of the bar, for designing layouts
+///
+/// Text blocks are outlined in red, the parameters block in blue, the logo row in green and the
+/// side margins in magenta.
+///
+/// # Arguments
+/// * `canvas` - Bar to draw on
+/// * `layout` - Resolved layout of the bar
+/// * `padding` - Horizontal padding from the bar edge
+fn draw_debug_layout(canvas: &mut RgbaImage, layout: &BarLayout, padding: u32) {
+    let outline = |canvas: &mut RgbaImage, rect: &Rect, color: Rgba<u8>| {
+        if rect.width > 0 && rect.height > 0 {
+            draw_hollow_rect_mut(
+                canvas,
+                DrawRect::at(rect.x, rect.y).of_size(rect.width, rect.height),
+                color,
+            );
+        }
+    };
+    outline(canvas, &layout.left, Rgba([255, 0, 0, 255]));
+    outline(canvas, &layout.params, Rgba([0, 0, 255, 255]));
+    if let Some(logo) = &layout.logo {
+        outline(canvas, logo, Rgba([0, 160, 0, 255]));
+    }
+    let height = canvas.height() as f32;
+    for x in [padding, canvas.width().saturating_sub(padding)] {
+        draw_line_segment_mut(
+            canvas,
+            (x as f32, 0.0),
+            (x as f32, height),
+            DEBUG_MARGIN_COLOR,
+        );
+    }
+}
+
+/// Draws rule-of-thirds guides over the photo area of a canvas
+fn draw_thirds_grid(canvas: &mut RgbaImage, width: u32, height: u32) {
+    let (width, height) = (width as f32, height as f32);
+    for i in 1..3 {
+        let x = width * i as f32 / 3.0;
+        let y = height * i as f32 / 3.0;
+        draw_line_segment_mut(canvas, (x, 0.0), (x, height), DEBUG_MARGIN_COLOR);
+        draw_line_segment_mut(canvas, (0.0, y), (width, y), DEBUG_MARGIN_COLOR);
+    }
+}
+
+/// Draws a minimal bar with the caption and optionally the file name, for images without EXIF
+///
+/// # Arguments
+/// * `canvas` - Bar to draw on
+/// * `input_path` - Path to the input image file
+/// * `resources` - Font and scaling resources
+/// * `options` - Processing options
+/// * `padding` - Horizontal padding from the bar edge
+/// * `context` - Template values of the image
+/// * `ink` - Primary and secondary text colors
+fn draw_placeholder(
+    canvas: &mut RgbaImage,
+    input_path: &Path,
+    resources: &Resources,
+    options: &ProcessOptions,
+    padding: u32,
+    context: &TemplateContext,
+    ink: (Rgba<u8>, Rgba<u8>),
+) {
+    let caption = options
+        .caption
+        .as_deref()
+        .map(|caption| context.render(caption))
+        .unwrap_or_default();
+    let caption = caption.as_str();
+    let file_name = if options.placeholder_filename {
+        input_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let mut lines = Vec::new();
+    if !caption.is_empty() {
+        lines.push((caption, resources.scale_bold, &resources.font_bold, ink.0));
+    }
+    if !file_name.is_empty() {
+        lines.push((
+            file_name.as_str(),
+            resources.scale_regular,
+            &resources.font_regular,
+            ink.1,
+        ));
+    }
+    if lines.is_empty() {
+        return;
+    }
+
+    let line_gap = 8 * options.scale.max(1) as i32;
+    let total_height: i32 = lines
+        .iter()
+        .map(|(_, scale, font, _)| text_height(font, *scale) as i32)
+        .sum::<i32>()
+        + line_gap * (lines.len() as i32 - 1);
+    let mut y = (options.info_height as i32 - total_height) / 2;
+    for (text, scale, font, color) in lines {
+        draw_styled_text(
+            canvas,
+            &options.theme,
+            color,
+            (padding as i32, y),
+            scale,
+            font,
+            text,
+        );
+        y += text_height(font, scale) as i32 + line_gap;
+    }
+}
+
+/// Returns the width of a logo scaled to the target height, at least one pixel
+///
+/// # Arguments
+/// * `(width, height)` - Dimensions of the logo image
+/// * `target_height` - Height of the scaled logo in pixels
+fn scaled_width((width, height): (u32, u32), target_height: u32) -> u32 {
+    let width = width as u64 * target_height as u64 / height.max(1) as u64;
+    width.clamp(1, u32::MAX as u64) as u32
+}
+
+/// Returns the height of a logo slot scaled by an optical scale factor, kept within the bar
+///
+/// # Arguments
+/// * `slot_height` - Height of the logo slot in pixels
+/// * `scale` - Optical scale factor of the logo
+/// * `bar_height` - Height of the info bar in pixels
+fn optical_height(slot_height: u32, scale: f32, bar_height: u32) -> u32 {
+    ((slot_height as f32 * scale.max(0.0)).round() as u32).clamp(1, bar_height.max(1))
+}
+
+/// Places a logo in its slot with an optical scale factor and a vertical offset
+///
+/// The scaled logo stays centered on the slot, is moved by `offset` slot heights and is kept
+/// within the bar.
+///
+/// # Arguments
+/// * `slot` - Logo slot computed by the layout
+/// * `bar_height` - Height of the info bar in pixels
+/// * `scale` - Optical scale factor of the logo
+/// * `offset` - Vertical offset as a fraction of the slot height, positive moves down
+///
+/// # Returns
+/// * `(u32, u32)` - Height and top position of the logo in pixels
+fn place_logo(slot: &Rect, bar_height: u32, scale: f32, offset: f32) -> (u32, u32) {
+    let height = optical_height(slot.height, scale, bar_height);
+    let center = slot.y as f32 + slot.height as f32 * (0.5 + offset);
+    let top = (center - height as f32 / 2.0).round();
+    (
+        height,
+        top.clamp(0.0, bar_height.saturating_sub(height) as f32) as u32,
+    )
+}
+
+/// Resizes a logo to the target height while keeping its aspect ratio
+///
+/// # Arguments
+/// * `logo` - Logo image
+/// * `target_height` - Height of the resized logo in pixels
+///
+/// # Returns
+/// * `RgbaImage` - Resized logo
+fn resize_logo(logo: &DynamicImage, target_height: u32) -> RgbaImage {
+    logo.resize(
+        scaled_width(logo.dimensions(), target_height),
+        target_height,
+        FilterType::Lanczos3,
+    )
+    .to_rgba8()
+}
+
+/// Applies the color treatment and opacity to a logo in place
+///
+/// # Arguments
+/// * `logo` - Logo image
+/// * `treatment` - Color treatment to apply
+/// * `opacity` - Opacity multiplier, clamped to 0.0..=1.0
+fn apply_logo_treatment(logo: &mut RgbaImage, treatment: LogoTreatment, opacity: f32) {
+    let opacity = opacity.clamp(0.0, 1.0);
+    for pixel in logo.pixels_mut() {
+        let luma = (0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32)
+            .round() as u8;
+        match treatment {
+            LogoTreatment::Color => {}
+            LogoTreatment::Grayscale => {
+                pixel[0] = luma;
+                pixel[1] = luma;
+                pixel[2] = luma;
+            }
+            LogoTreatment::Monochrome => {
+                let value = if luma < 128 { 0 } else { 255 };
+                pixel[0] = value;
+                pixel[1] = value;
+                pixel[2] = value;
+            }
+        }
+        pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    }
+}
+
+/// Alpha blends a logo onto the canvas at the given position
+///
+/// # Arguments
+/// * `canvas` - Image to draw on, may be transparent
+/// * `logo` - Logo image
+/// * `logo_x` - Left position of the logo
+/// * `logo_y` - Top position of the logo
+///
+/// Pixels falling outside the canvas are clipped.
+fn blend_logo(canvas: &mut RgbaImage, logo: &RgbaImage, logo_x: u32, logo_y: u32) {
+    for y in 0..logo.height() {
+        for x in 0..logo.width() {
+            let pixel = logo.get_pixel(x, y);
+            let Some(dst) =
+                canvas.get_pixel_mut_checked(logo_x.saturating_add(x), logo_y.saturating_add(y))
+            else {
+                continue;
+            };
+            let alpha = pixel[3] as f32 / 255.0;
+            let dst_alpha = dst[3] as f32 / 255.0 * (1.0 - alpha);
+            let out_alpha = alpha + dst_alpha;
+            if out_alpha <= 0.0 {
+                continue;
+            }
+            for c in 0..3 {
+                dst[c] = ((pixel[c] as f32 * alpha + dst[c] as f32 * dst_alpha) / out_alpha) as u8;
+            }
+            dst[3] = (out_alpha * 255.0).round() as u8;
+        }
+    }
 }
 
 /// Pads an image to achieve 16:9 aspect ratio
 ///
 /// # Arguments
-/// * `orig_img` - Original image for background blur
+/// * `orig_img` - Original image for the blurred backdrop
 /// * `img_with_bar` - Image with information bar
+/// * `backdrop` - Backdrop options
 ///
 /// # Returns
 /// * `DynamicImage` - Padded image with 16:9 aspect ratio
-pub fn pad_to_16_9(orig_img: &DynamicImage, img_with_bar: &DynamicImage) -> DynamicImage {
+pub fn pad_to_16_9(
+    orig_img: &DynamicImage,
+    img_with_bar: &DynamicImage,
+    backdrop: &BackdropOptions,
+) -> DynamicImage {
     let (width, height) = img_with_bar.dimensions();
     let target_ratio = 16.0 / 9.0;
     let cur_ratio = width as f32 / height as f32;
@@ -149,24 +903,368 @@ pub fn pad_to_16_9(orig_img: &DynamicImage, img_with_bar: &DynamicImage) -> Dyna
         let new_width = ((height as f32) * target_ratio).ceil() as u32;
         (new_width, height)
     };
-    // First resize the original image to have a maximum dimension of 512 pixels (maintaining aspect ratio)
-    let (orig_width, orig_height) = orig_img.dimensions();
-    let scale = 512.0 / (orig_width.max(orig_height) as f32);
-    let small_width = (orig_width as f32 * scale).round().max(1.0) as u32;
-    let small_height = (orig_height as f32 * scale).round().max(1.0) as u32;
-    let small = orig_img.resize_exact(small_width, small_height, FilterType::Triangle);
-    // Apply blur to the resized image
-    let blurred = small.blur(20.0);
-    // Resize to target dimensions
-    let bg = blurred.resize_exact(new_width, new_height, FilterType::Gaussian);
-    let mut new_img = bg.to_rgba8();
-    // Calculate position for the image with bar (centered horizontally, aligned to bottom)
-    let x_offset = ((new_width as i32 - width as i32) / 2).max(0) as i64;
-    let y_offset = (new_height as i32 - height as i32).max(0) as i64;
-    image::imageops::overlay(&mut new_img, &img_with_bar.to_rgba8(), x_offset, y_offset);
+    compose_on_backdrop(orig_img, img_with_bar, new_width, new_height, backdrop)
+}
+
+/// Fits an image into a canvas of exact dimensions
+///
+/// The image with bar is scaled, up or down, to fit within the canvas while keeping its aspect
+/// ratio, and the remaining space is filled with the backdrop.
+///
+/// # Arguments
+/// * `orig_img` - Original image for the blurred backdrop
+/// * `img_with_bar` - Image with information bar
+/// * `canvas` - Width and height of the canvas in pixels
+/// * `backdrop` - Backdrop options
+///
+/// # Returns
+/// * `DynamicImage` - Image of exactly the canvas dimensions
+pub fn fit_to_canvas(
+    orig_img: &DynamicImage,
+    img_with_bar: &DynamicImage,
+    canvas: (u32, u32),
+    backdrop: &BackdropOptions,
+) -> DynamicImage {
+    let (canvas_width, canvas_height) = canvas;
+    let (width, height) = img_with_bar.dimensions();
+    let scale = (canvas_width as f32 / width as f32).min(canvas_height as f32 / height as f32);
+    let fitted_width = ((width as f32 * scale).round() as u32).clamp(1, canvas_width);
+    let fitted_height = ((height as f32 * scale).round() as u32).clamp(1, canvas_height);
+    let fitted = if (fitted_width, fitted_height) == (width, height) {
+        img_with_bar.clone()
+    } else {
+        img_with_bar.resize_exact(fitted_width, fitted_height, FilterType::Lanczos3)
+    };
+    compose_on_backdrop(orig_img, &fitted, canvas_width, canvas_height, backdrop)
+}
+
+/// Places an image on a backdrop of the given size at the configured anchor
+fn compose_on_backdrop(
+    orig_img: &DynamicImage,
+    img: &DynamicImage,
+    canvas_width: u32,
+    canvas_height: u32,
+    backdrop: &BackdropOptions,
+) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let new_img =
+        crate::backdrop::render_backdrop(orig_img, canvas_width, canvas_height, &backdrop.source);
+    let offset = backdrop.anchor.offset(
+        canvas_width.saturating_sub(width),
+        canvas_height.saturating_sub(height),
+    );
+    // 16-bit images stay 16-bit, so the blurred and vignetted gradients don't band
+    if let DynamicImage::ImageRgba16(img) = img {
+        let mut new_img = new_img.to_rgba16();
+        finish_backdrop(&mut new_img, img, offset, backdrop);
+        return DynamicImage::ImageRgba16(new_img);
+    }
+    let mut new_img = new_img.to_rgba8();
+    finish_backdrop(&mut new_img, &img.to_rgba8(), offset, backdrop);
     DynamicImage::ImageRgba8(new_img)
 }
 
+/// Applies the backdrop effects and places the image at its offset
+fn finish_backdrop<P: Pixel>(
+    canvas: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+    img: &ImageBuffer<P, Vec<P::Subpixel>>,
+    (x_offset, y_offset): (i64, i64),
+    backdrop: &BackdropOptions,
+) {
+    if let Some(strength) = backdrop.vignette {
+        crate::backdrop::apply_vignette(canvas, strength, backdrop.vignette_falloff);
+    }
+    if let (Some(intensity), GrainScope::Background) = (backdrop.grain, backdrop.grain_scope) {
+        crate::backdrop::apply_grain(canvas, intensity);
+    }
+    image::imageops::overlay(canvas, img, x_offset, y_offset);
+}
+
+/// Content of an information bar, before measurement
+#[derive(Debug, Clone, Copy)]
+pub struct BarContent<'a> {
+    /// Camera line, with the theme casing applied
+    pub camera: &'a str,
+    /// Lens line, with the theme casing applied
+    pub lens: &'a str,
+    /// Location line below the lens, empty for none
+    pub location: &'a str,
+    /// Shooting parameter fields and their text, in display order
+    pub params: &'a [(ParamField, String)],
+    /// Caption below the parameters, empty for none
+    pub caption: &'a str,
+    /// Attribution line below the caption, e.g. `© 2024 Jane Doe`, empty for none
+    pub attribution: &'a str,
+    /// Dimensions of the logos in drawing order, and whether each is the brand logo
+    pub logos: &'a [((u32, u32), bool)],
+}
+
+/// Measured information bar, ready to be laid out and drawn
+#[derive(Debug, Clone)]
+pub struct BarMeasure {
+    /// Sizes of the bar elements, the input of a layout
+    pub metrics: BarMetrics,
+    /// Lines of the right column, the parameters on one or two lines followed by the caption
+    pub right_lines: Vec<String>,
+    /// Widths of the right column lines in pixels, before any layout shrinking
+    pub right_widths: Vec<i32>,
+    /// Number of parameter lines at the start of `right_lines`
+    pub params_lines: usize,
+    /// Parameter fields dropped to fit the bar, in drop order
+    pub dropped: Vec<ParamField>,
+    /// Height of the camera line in pixels
+    pub camera_height: u32,
+    /// Height of the lens line and of the right column lines in pixels
+    pub line_height: u32,
+    /// Scale of the left text block, below 1 when a location line has to fit in the bar
+    pub left_scale: f32,
+}
+
+/// Measures the elements of an information bar without drawing or printing anything
+///
+/// Parameters are wrapped and dropped to fit here, so the returned metrics are what
+/// `compute_bar_layout`, or any other layout, places. Layouts can then be checked with
+/// `BarLayout::overflows` without rendering pixels. The caller reports the dropped fields.
+///
+/// # Arguments
+/// * `content` - Text and logos of the bar
+/// * `resources` - Fonts and text scales
+/// * `theme` - Theme with the logo sizing and the parameters casing and drop order
+/// * `width` - Width of the bar in pixels
+/// * `info_height` - Height of the bar in pixels
+/// * `density` - Pixel density multiplier of the spacing, 1 for standard density
+///
+/// # Returns
+/// * `BarMeasure` - Measured bar
+pub fn measure_bar(
+    content: &BarContent,
+    resources: &Resources,
+    theme: &Theme,
+    width: u32,
+    info_height: u32,
+    density: u32,
+) -> BarMeasure {
+    let padding = 32 * density;
+    let gap = 8 * density;
+    let (font, scale) = (&resources.font_regular, resources.scale_regular);
+    let camera_height = text_height(&resources.font_bold, resources.scale_bold);
+    let line_height = text_height(font, scale);
+    let mut left_height = camera_height + line_height + gap;
+    let mut left_scale = 1.0;
+    if !content.location.is_empty() {
+        // Three lines only fit the height of the bar with the left block shrunk
+        let natural_height = left_height + line_height + gap;
+        left_scale = (info_height.saturating_sub(gap * 2) as f32 / natural_height as f32).min(1.0);
+        left_height = (natural_height as f32 * left_scale).round() as u32;
+    }
+    let left_width = text_width(&resources.font_bold, resources.scale_bold, content.camera)
+        .max(text_width(font, scale, content.lens))
+        .max(text_width(font, scale, content.location))
+        .max(0) as f32
+        * left_scale;
+    let left_width = left_width.round() as u32;
+    // The layout leaves the parameters at least a third of the content width
+    let content_width = width.saturating_sub(padding * 3);
+    let params_available = content_width - left_width.min(content_width * 2 / 3);
+    let (mut right_lines, dropped) =
+        fit_params(font, scale, content.params, theme, params_available);
+    let params_lines = right_lines.len();
+    if !content.caption.is_empty() {
+        right_lines.push(content.caption.to_string());
+    }
+    if !content.attribution.is_empty() {
+        right_lines.push(content.attribution.to_string());
+    }
+    let right_widths: Vec<i32> = right_lines
+        .iter()
+        .map(|line| text_width(font, scale, line))
+        .collect();
+
+    let logo_target_height = if content.logos.is_empty() {
+        0
+    } else {
+        (info_height as f32 * 0.65).round() as u32
+    };
+    let metrics = BarMetrics {
+        width,
+        top: 0,
+        info_height,
+        padding,
+        left_width,
+        left_height,
+        params_width: right_widths.iter().copied().max().unwrap_or(0).max(0) as u32,
+        params_height: (line_height + gap) * right_lines.len() as u32 - gap,
+        logo_width: content
+            .logos
+            .iter()
+            .map(|&(dimensions, is_brand)| {
+                let height = if is_brand {
+                    optical_height(logo_target_height, theme.logo_scale, info_height)
+                } else {
+                    logo_target_height
+                };
+                scaled_width(dimensions, height)
+            })
+            .sum(),
+        logo_gap: theme.logo_gap * (content.logos.len() as u32).saturating_sub(1),
+        logo_height: logo_target_height,
+        logo_max_width: theme.logo_max_width,
+    };
+    BarMeasure {
+        metrics,
+        right_lines,
+        right_widths,
+        params_lines,
+        dropped,
+        camera_height,
+        line_height,
+        left_scale,
+    }
+}
+
+/// Separator between the shooting parameters
+const PARAMS_SEPARATOR: &str = " | ";
+
+/// Template of the shooting parameters when `--params-template` is not given
+pub const DEFAULT_PARAMS_TEMPLATE: &str = "{focal}mm | f{aperture} | {shutter}/s | ISO {iso}";
+
+/// Renders the shooting parameters template into fields
+///
+/// The template is split at `|` into fields, which wrap and drop one by one; other separators
+/// such as `·` stay inside a field, so a template without `|` is a single field. A field takes
+/// the kind of the first exposure token it uses, `Extras` otherwise, and is left out when one of
+/// its tokens has no value, including EXIF fields read as `Unknown`.
+///
+/// # Arguments
+/// * `template` - Parameters template, e.g. `{focal}mm | f/{aperture}`
+/// * `context` - Template values, with the EXIF fields
+///
+/// # Returns
+/// * `Vec<(ParamField, String)>` - Rendered fields in template order
+pub fn render_params(template: &str, context: &TemplateContext) -> Vec<(ParamField, String)> {
+    template
+        .split('|')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .filter_map(|part| {
+            if !context.resolves(part) {
+                return None;
+            }
+            let tokens = crate::template::tokens(part);
+            let kind = tokens
+                .iter()
+                .find_map(|token| match *token {
+                    "focal" => Some(ParamField::Focal),
+                    "aperture" => Some(ParamField::Aperture),
+                    "shutter" => Some(ParamField::Shutter),
+                    "iso" => Some(ParamField::Iso),
+                    _ => None,
+                })
+                .unwrap_or(ParamField::Extras);
+            Some((kind, context.render(part)))
+        })
+        .collect()
+}
+
+/// Wraps the shooting parameters onto two lines if they are wider than the available width
+///
+/// The text is broken at the separator that balances both lines best. Text without separators
+/// is kept on one line and left to the layout to shrink.
+///
+/// # Arguments
+/// * `font` - Font of the parameters text
+/// * `scale` - Scale of the parameters text
+/// * `params` - Parameters text, fields joined by `" | "`
+/// * `max_width` - Available width in pixels
+///
+/// # Returns
+/// * `Vec<String>` - One or two lines
+pub fn wrap_params(font: &Font, scale: Scale, params: &str, max_width: u32) -> Vec<String> {
+    if text_width(font, scale, params) <= max_width as i32 {
+        return vec![params.to_string()];
+    }
+    params
+        .match_indices(PARAMS_SEPARATOR)
+        .map(|(at, _)| {
+            let (first, second) = (&params[..at], &params[at + PARAMS_SEPARATOR.len()..]);
+            let widest = text_width(font, scale, first).max(text_width(font, scale, second));
+            (widest, vec![first.to_string(), second.to_string()])
+        })
+        .min_by_key(|(widest, _)| *widest)
+        .map_or_else(|| vec![params.to_string()], |(_, lines)| lines)
+}
+
+/// Joins shooting parameter fields with the parameters separator
+fn join_params(fields: &[(ParamField, String)]) -> String {
+    fields
+        .iter()
+        .map(|(_, value)| value.as_str())
+        .collect::<Vec<_>>()
+        .join(PARAMS_SEPARATOR)
+}
+
+/// Fits the shooting parameters into the available width
+///
+/// The parameters are wrapped onto two lines first. While they still don't fit, the fields of
+/// the theme drop order are removed one after the other, never leaving the parameters empty.
+///
+/// # Arguments
+/// * `font` - Font of the parameters text
+/// * `scale` - Scale of the parameters text
+/// * `fields` - Parameter fields and their text, in display order
+/// * `theme` - Theme with the parameters casing and drop order
+/// * `max_width` - Available width in pixels
+///
+/// # Returns
+/// * `(Vec<String>, Vec<ParamField>)` - One or two lines, still wider than `max_width` if nothing
+///   more can be dropped, and the fields dropped
+pub fn fit_params(
+    font: &Font,
+    scale: Scale,
+    fields: &[(ParamField, String)],
+    theme: &Theme,
+    max_width: u32,
+) -> (Vec<String>, Vec<ParamField>) {
+    let mut fields = fields.to_vec();
+    let mut drop_order = theme.drop_order.iter();
+    let mut dropped = Vec::new();
+    loop {
+        let params = theme.params_case.apply(&join_params(&fields));
+        let lines = wrap_params(font, scale, &params, max_width);
+        if lines
+            .iter()
+            .all(|line| text_width(font, scale, line) <= max_width as i32)
+        {
+            return (lines, dropped);
+        }
+        let droppable = drop_order.by_ref().find(|field| {
+            fields.iter().any(|(kind, _)| kind == *field)
+                && fields.iter().any(|(kind, _)| kind != *field)
+        });
+        let Some(field) = droppable else {
+            return (lines, dropped);
+        };
+        fields.retain(|(kind, _)| kind != field);
+        dropped.push(*field);
+    }
+}
+
+/// Calculates the line height of text from the ascent and descent of a font
+///
+/// Text is drawn with its ascent line at the given y position, so the line spans this height
+/// below it whatever the proportions of the font.
+///
+/// # Arguments
+/// * `font` - Font to use for rendering
+/// * `scale` - Scale factor for the font
+///
+/// # Returns
+/// * `u32` - Height of a line of text in pixels
+pub fn text_height(font: &Font, scale: Scale) -> u32 {
+    let v_metrics = font.v_metrics(scale);
+    (v_metrics.ascent - v_metrics.descent).ceil().max(0.0) as u32
+}
+
 /// Calculates the width of text when rendered with a specific font and scale
 ///
 /// # Arguments
@@ -176,7 +1274,7 @@ pub fn pad_to_16_9(orig_img: &DynamicImage, img_with_bar: &DynamicImage) -> Dyna
 ///
 /// # Returns
 /// * `i32` - Width of the text in pixels
-fn text_width(font: &Font, scale: Scale, text: &str) -> i32 {
+pub fn text_width(font: &Font, scale: Scale, text: &str) -> i32 {
     use rusttype::point;
     let v_metrics = font.v_metrics(scale);
     let glyphs: Vec<_> = font
@@ -193,6 +1291,38 @@ fn text_width(font: &Font, scale: Scale, text: &str) -> i32 {
     }
 }
 
+/// Shortens text with an ellipsis until it fits a width
+///
+/// # Arguments
+/// * `font` - Font to use for rendering
+/// * `scale` - Scale factor for the font
+/// * `text` - Text to shorten
+/// * `max_width` - Available width in pixels
+///
+/// # Returns
+/// * `String` - Text unchanged if it fits, otherwise its longest prefix that fits followed by
+///   `…` (`...` if the font lacks the glyph), empty if not even the ellipsis fits
+pub fn truncate_text(font: &Font, scale: Scale, text: &str, max_width: u32) -> String {
+    let fits = |text: &str| text_width(font, scale, text) <= max_width as i32;
+    if fits(text) {
+        return text.to_string();
+    }
+    let ellipsis = if font.glyph('…').id().0 != 0 {
+        "…"
+    } else {
+        "..."
+    };
+    let mut end = text.len();
+    while let Some((index, _)) = text[..end].char_indices().next_back() {
+        end = index;
+        let candidate = format!("{}{}", text[..end].trim_end(), ellipsis);
+        if fits(&candidate) {
+            return candidate;
+        }
+    }
+    String::new()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -215,6 +1345,193 @@ mod tests {
         // Test with different scales
         let scale_large = Scale { x: 48.0, y: 48.0 };
         assert!(text_width(&font, scale_large, "Test") > text_width(&font, scale, "Test"));
+
+        let text = "50mm | f1.8 | 1/250/s";
+        assert_eq!(truncate_text(&font, scale, text, 10_000), text);
+        let half = text_width(&font, scale, text) as u32 / 2;
+        let truncated = truncate_text(&font, scale, text, half);
+        assert!(truncated.ends_with('…'));
+        assert!(text_width(&font, scale, &truncated) <= half as i32);
+        assert_eq!(truncate_text(&font, scale, text, 1), "");
+    }
+
+    #[test]
+    fn test_wrap_params() {
+        let font_data = include_bytes!("../fonts/DejaVuSans.ttf");
+        let font = Font::try_from_vec(font_data.to_vec()).unwrap();
+        let scale = Scale { x: 24.0, y: 24.0 };
+        let params = "50mm | f1.8 | 1/250/s | ISO 100";
+        assert_eq!(wrap_params(&font, scale, params, 10_000), vec![params]);
+        assert_eq!(
+            wrap_params(&font, scale, params, 200),
+            vec!["50mm | f1.8", "1/250/s | ISO 100"]
+        );
+        assert_eq!(wrap_params(&font, scale, "ISO 100", 10), vec!["ISO 100"]);
+    }
+
+    #[test]
+    fn test_render_params() {
+        let mut context = TemplateContext::default();
+        for (token, value) in [("focal", "50"), ("aperture", "1.8"), ("shutter", "1/250")] {
+            context.set(token, value.to_string());
+        }
+        context.set("iso", "100".to_string());
+        assert_eq!(
+            join_params(&render_params(DEFAULT_PARAMS_TEMPLATE, &context)),
+            "50mm | f1.8 | 1/250/s | ISO 100"
+        );
+
+        let fields = render_params("ISO {iso} | {ev} | {focal}mm · f/{aperture}", &context);
+        assert_eq!(
+            fields,
+            vec![
+                (ParamField::Iso, "ISO 100".to_string()),
+                (ParamField::Focal, "50mm · f/1.8".to_string()),
+            ]
+        );
+
+        // Fields read as unknown are left out like missing ones
+        context.set("iso", crate::exif::UNKNOWN.to_string());
+        assert_eq!(
+            join_params(&render_params(DEFAULT_PARAMS_TEMPLATE, &context)),
+            "50mm | f1.8 | 1/250/s"
+        );
+        // Without `|` the whole template is one field
+        let fields = render_params("{focal}mm · f/{aperture} · ISO {iso}", &context);
+        assert!(fields.is_empty());
+    }
+
+    #[test]
+    fn test_fit_params() {
+        let font_data = include_bytes!("../fonts/DejaVuSans.ttf");
+        let font = Font::try_from_vec(font_data.to_vec()).unwrap();
+        let scale = Scale { x: 24.0, y: 24.0 };
+        let fields = vec![
+            (ParamField::Focal, "50mm".to_string()),
+            (ParamField::Aperture, "f1.8".to_string()),
+            (ParamField::Shutter, "1/250/s".to_string()),
+            (ParamField::Iso, "ISO 100".to_string()),
+        ];
+        let max_width = text_width(&font, scale, "50mm | f1.8") as u32;
+        let mut theme = Theme::default();
+        let (lines, dropped) = fit_params(&font, scale, &fields, &theme, max_width);
+        assert!(lines.iter().all(|line| !line.contains("ISO")));
+        assert!(dropped.contains(&ParamField::Iso));
+        assert!(lines
+            .iter()
+            .all(|line| text_width(&font, scale, line) <= max_width as i32));
+
+        // The last field is kept even if it doesn't fit
+        theme.drop_order = vec![ParamField::Iso, ParamField::Focal];
+        assert_eq!(
+            fit_params(&font, scale, &fields[3..], &theme, 1),
+            (vec!["ISO 100".to_string()], vec![])
+        );
+        theme.drop_order.clear();
+        let (lines, dropped) = fit_params(&font, scale, &fields, &theme, max_width);
+        assert_eq!(lines, vec!["50mm | f1.8", "1/250/s | ISO 100"]);
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn test_measure_bar() {
+        let resources = Resources::new(180, Some(Path::new("./fonts"))).unwrap();
+        let params = vec![
+            (ParamField::Focal, "50mm".to_string()),
+            (ParamField::Aperture, "f1.8".to_string()),
+            (ParamField::Shutter, "1/250/s".to_string()),
+            (ParamField::Iso, "ISO 100".to_string()),
+        ];
+        let logos = [((400, 100), true)];
+        let content = BarContent {
+            camera: "Canon EOS R5",
+            lens: "RF24-105mm F4 L IS USM",
+            location: "",
+            params: &params,
+            caption: "Kyoto",
+            attribution: "",
+            logos: &logos,
+        };
+        let theme = Theme::default();
+
+        let wide = measure_bar(&content, &resources, &theme, 6000, 180, 1);
+        assert_eq!(wide.params_lines, 1);
+        assert_eq!(wide.right_lines.last().unwrap(), "Kyoto");
+        assert_eq!(wide.metrics.logo_width, 468);
+        assert!(!compute_bar_layout(&wide.metrics).overflows(&wide.metrics));
+
+        let narrow = measure_bar(&content, &resources, &theme, 1200, 180, 1);
+        assert_eq!(narrow.params_lines, 2);
+        assert!(!compute_bar_layout(&narrow.metrics).overflows(&narrow.metrics));
+
+        let located = measure_bar(
+            &BarContent {
+                location: "Kyoto, Japan",
+                ..content
+            },
+            &resources,
+            &theme,
+            6000,
+            180,
+            1,
+        );
+        assert!(located.left_scale < 1.0);
+        assert!(located.metrics.left_height <= 180 - 16);
+    }
+
+    #[test]
+    fn test_text_height() {
+        let font_data = include_bytes!("../fonts/DejaVuSans.ttf");
+        let font = Font::try_from_vec(font_data.to_vec()).unwrap();
+        let scale = Scale { x: 24.0, y: 24.0 };
+        let v_metrics = font.v_metrics(scale);
+        assert_eq!(
+            text_height(&font, scale),
+            (v_metrics.ascent - v_metrics.descent).ceil() as u32
+        );
+        assert!(text_height(&font, Scale { x: 48.0, y: 48.0 }) > text_height(&font, scale));
+    }
+
+    #[test]
+    fn test_apply_logo_treatment() {
+        let mut logo = RgbaImage::from_pixel(2, 2, Rgba([200, 30, 30, 255]));
+        apply_logo_treatment(&mut logo, LogoTreatment::Grayscale, 0.5);
+        let pixel = logo.get_pixel(0, 0);
+        assert_eq!(pixel[0], pixel[1]);
+        assert_eq!(pixel[1], pixel[2]);
+        assert_eq!(pixel[3], 128);
+
+        let mut logo = RgbaImage::from_pixel(1, 1, Rgba([200, 30, 30, 255]));
+        apply_logo_treatment(&mut logo, LogoTreatment::Monochrome, 1.0);
+        assert_eq!(*logo.get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_blend_logo_clips_to_canvas() {
+        let mut canvas = RgbaImage::from_pixel(4, 4, Rgba([255, 255, 255, 255]));
+        let logo = RgbaImage::from_pixel(3, 3, Rgba([0, 0, 0, 255]));
+        blend_logo(&mut canvas, &logo, 2, 2);
+        blend_logo(&mut canvas, &logo, 100, 0);
+        assert_eq!(*canvas.get_pixel(3, 3), Rgba([0, 0, 0, 255]));
+        assert_eq!(*canvas.get_pixel(1, 1), Rgba([255, 255, 255, 255]));
+
+        assert_eq!(scaled_width((1, 4000), 10), 1);
+    }
+
+    #[test]
+    fn test_place_logo() {
+        let slot = Rect {
+            x: 0,
+            y: 30,
+            width: 100,
+            height: 40,
+        };
+        assert_eq!(place_logo(&slot, 100, 1.0, 0.0), (40, 30));
+        assert_eq!(place_logo(&slot, 100, 1.5, 0.0), (60, 20));
+        assert_eq!(place_logo(&slot, 100, 1.0, -0.25), (40, 20));
+        // Kept within the bar
+        assert_eq!(place_logo(&slot, 100, 1.0, 2.0), (40, 60));
+        assert_eq!(place_logo(&slot, 100, 4.0, 0.0), (100, 0));
     }
 
     #[test]
@@ -224,13 +1541,36 @@ mod tests {
         let img_4_3 = DynamicImage::new_rgba8(1600, 1200);
 
         // Test with already 16:9 image
-        let result = pad_to_16_9(&img_16_9, &img_16_9);
+        let result = pad_to_16_9(&img_16_9, &img_16_9, &BackdropOptions::default());
         assert_eq!(result.dimensions(), (1920, 1080));
 
         // Test with 4:3 image
-        let result = pad_to_16_9(&img_4_3, &img_4_3);
+        let result = pad_to_16_9(&img_4_3, &img_4_3, &BackdropOptions::default());
         let (width, height) = result.dimensions();
         let ratio = width as f32 / height as f32;
         assert!((ratio - 16.0 / 9.0).abs() < 0.01);
     }
+
+    #[test]
+    fn test_fit_to_canvas() {
+        let backdrop = BackdropOptions::default();
+        let img_4_3 = DynamicImage::new_rgba8(160, 120);
+        assert_eq!(
+            fit_to_canvas(&img_4_3, &img_4_3, (192, 108), &backdrop).dimensions(),
+            (192, 108)
+        );
+        assert_eq!(
+            fit_to_canvas(&img_4_3, &img_4_3, (40, 40), &backdrop).dimensions(),
+            (40, 40)
+        );
+
+        // 16-bit images keep their depth on the blurred and vignetted backdrop
+        let deep = DynamicImage::new_rgba16(160, 120);
+        let backdrop = BackdropOptions {
+            vignette: Some(0.5),
+            ..BackdropOptions::default()
+        };
+        let fitted = fit_to_canvas(&deep, &deep, (192, 108), &backdrop);
+        assert_eq!(fitted.color(), image::ColorType::Rgba16);
+    }
 }
@@ -0,0 +1,97 @@
+//! Integrity check module
+//!
+//! This module fully decodes input images up front, so truncated or corrupt files, e.g. from an
+//! interrupted card copy, are reported together instead of surfacing one by one mid-run.
+
+use crate::report::BatchReport;
+use crate::source::{InputItem, Source};
+use std::error::Error;
+use std::path::Path;
+
+/// Fully decodes an image and checks that a JPEG file is complete
+///
+/// # Arguments
+/// * `path` - Path of the image
+///
+/// # Returns
+/// * `Result<(), String>` - Ok if the image is intact, otherwise what is wrong with it
+pub fn verify_decode(path: &Path) -> Result<(), String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    if crate::jpeg::is_truncated(&data) {
+        return Err("truncated JPEG, the end-of-image marker is missing".to_string());
+    }
+    crate::source::open_image(path)
+        .map(|_| ())
+        .map_err(|e| format!("corrupt image: {}", e))
+}
+
+/// Removes the images that fail to decode from a batch, recording them as failures
+///
+/// # Arguments
+/// * `items` - Images of the batch, the broken ones are removed
+/// * `report` - Report receiving the broken images
+pub fn remove_broken(items: &mut Vec<InputItem>, report: &mut BatchReport) {
+    items.retain(|item| match verify_decode(&item.path) {
+        Ok(()) => true,
+        Err(reason) => {
+            println!("[WARN] {} is broken: {}", item.path.display(), reason);
+            report.failures.push((item.path.clone(), reason));
+            false
+        }
+    });
+}
+
+/// Checks that every image of a source decodes
+///
+/// # Arguments
+/// * `source` - Images to check
+///
+/// # Returns
+/// * `Result<BatchReport, Box<dyn Error>>` - Number of intact images and the broken ones
+///
+/// # Errors
+/// Returns an error if the source cannot be read
+pub fn check_source(source: &dyn Source) -> Result<BatchReport, Box<dyn Error>> {
+    let mut items = source.items()?;
+    let mut report = BatchReport::default();
+    remove_broken(&mut items, &mut report);
+    report.processed = items.len();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::DirectorySource;
+
+    #[test]
+    fn test_check_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut data = Vec::new();
+        image::RgbImage::from_fn(64, 64, |x, y| image::Rgb([x as u8 * 4, y as u8 * 4, 0]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut data),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        std::fs::write(dir.path().join("intact.jpg"), &data).unwrap();
+        std::fs::write(dir.path().join("cut.jpg"), &data[..data.len() - 2]).unwrap();
+        std::fs::write(dir.path().join("garbage.jpg"), b"not a jpeg").unwrap();
+        assert!(!crate::jpeg::is_truncated(&data));
+        assert!(crate::jpeg::is_truncated(&data[..data.len() / 2]));
+
+        let source = DirectorySource {
+            root: dir.path().to_path_buf(),
+            extensions: vec!["jpg".to_string()],
+            sniff: false,
+        };
+        let report = check_source(&source).unwrap();
+        assert_eq!(report.processed, 1);
+        let broken: Vec<_> = report
+            .failures
+            .iter()
+            .map(|(path, _)| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(broken, ["cut.jpg", "garbage.jpg"]);
+    }
+}
@@ -0,0 +1,217 @@
+//! JPEG container module
+//!
+//! This module walks the marker segments of JPEG files to access embedded metadata such as
+//! ICC profiles without decoding the image.
+
+use std::error::Error;
+use std::ops::Range;
+
+/// Start of scan marker, the entropy-coded image data follows it
+const SOS: u8 = 0xDA;
+/// End of image marker
+const EOI: u8 = 0xD9;
+/// APP0 marker used for the JFIF header
+const APP0: u8 = 0xE0;
+/// APP1 marker used for EXIF and XMP metadata
+pub const APP1: u8 = 0xE1;
+/// APP2 marker used for ICC profiles
+pub const APP2: u8 = 0xE2;
+
+/// Marker segment of a JPEG file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment<'a> {
+    /// Marker byte following `0xFF`
+    pub marker: u8,
+    /// Payload of the segment, without the marker and length bytes
+    pub data: &'a [u8],
+}
+
+/// Lists the marker segments preceding the image data of a JPEG file
+///
+/// # Arguments
+/// * `data` - Content of the JPEG file
+///
+/// # Returns
+/// * `Vec<Segment>` - Segments in file order, empty if the data is not a JPEG file
+pub fn segments(data: &[u8]) -> Vec<Segment<'_>> {
+    segment_spans(data)
+        .into_iter()
+        .map(|(marker, span)| Segment {
+            marker,
+            data: &data[span.start + 4..span.end],
+        })
+        .collect()
+}
+
+/// Lists the markers and byte ranges, marker included, of the segments preceding the image data
+fn segment_spans(data: &[u8]) -> Vec<(u8, Range<usize>)> {
+    let mut segments = Vec::new();
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return segments;
+    }
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            break;
+        }
+        let marker = data[pos + 1];
+        if marker == 0xFF {
+            // Fill byte before a marker
+            pos += 1;
+            continue;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        if length < 2 || pos + 2 + length > data.len() {
+            break;
+        }
+        segments.push((marker, pos..pos + 2 + length));
+        if marker == SOS {
+            break;
+        }
+        pos += 2 + length;
+    }
+    segments
+}
+
+/// Returns whether a JPEG file ends before its end-of-image marker
+///
+/// Entropy-coded data never holds `0xFF 0xD9`, so a file cut short in its image data has no
+/// EOI after the first scan. Data trailing the EOI, such as MPF previews, is allowed.
+///
+/// # Arguments
+/// * `data` - Content of the JPEG file
+///
+/// # Returns
+/// * `bool` - Whether the file is a truncated JPEG file, `false` for other formats
+pub fn is_truncated(data: &[u8]) -> bool {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return false;
+    }
+    match segment_spans(data)
+        .into_iter()
+        .find(|(marker, _)| *marker == SOS)
+    {
+        Some((_, span)) => !data[span.end..].windows(2).any(|w| w == [0xFF, EOI]),
+        None => true,
+    }
+}
+
+/// Signature starting the payload of an EXIF APP1 segment
+const EXIF_SIGNATURE: &[u8] = b"Exif\0\0";
+/// Signature starting the payload of an XMP APP1 segment
+pub const XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// Replaces the EXIF block of a JPEG file, inserting it after the JFIF header if there is none
+///
+/// # Arguments
+/// * `data` - Content of the JPEG file
+/// * `tiff` - New EXIF block, starting with the TIFF header
+///
+/// # Returns
+/// * `Result<Vec<u8>, Box<dyn Error>>` - Content of the updated JPEG file
+///
+/// # Errors
+/// Returns an error if the data is not a JPEG file or the block exceeds a segment
+pub fn with_exif(data: &[u8], tiff: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let spans = segment_spans(data);
+    let image_start = match spans.last() {
+        Some((SOS, span)) => span.start,
+        _ => return Err("Not a JPEG file".into()),
+    };
+    let length = u16::try_from(2 + EXIF_SIGNATURE.len() + tiff.len())
+        .map_err(|_| "EXIF block is too large for a JPEG segment")?;
+    let mut exif = vec![0xFF, APP1];
+    exif.extend_from_slice(&length.to_be_bytes());
+    exif.extend_from_slice(EXIF_SIGNATURE);
+    exif.extend_from_slice(tiff);
+
+    let mut output = Vec::with_capacity(data.len() + exif.len());
+    output.extend_from_slice(&data[..2]);
+    let mut pending = Some(exif);
+    for (marker, span) in &spans[..spans.len() - 1] {
+        let segment = &data[span.clone()];
+        if *marker == APP1 && segment[4..].starts_with(EXIF_SIGNATURE) {
+            continue;
+        }
+        if *marker != APP0 {
+            output.extend(pending.take().unwrap_or_default());
+        }
+        output.extend_from_slice(segment);
+    }
+    output.extend(pending.take().unwrap_or_default());
+    output.extend_from_slice(&data[image_start..]);
+    Ok(output)
+}
+
+/// Extracts the XMP packet embedded in a JPEG file
+///
+/// # Arguments
+/// * `data` - Content of the JPEG file
+///
+/// # Returns
+/// * `Option<String>` - XMP packet if the file has one
+pub fn xmp_packet(data: &[u8]) -> Option<String> {
+    segments(data)
+        .into_iter()
+        .filter(|segment| segment.marker == APP1)
+        .find_map(|segment| segment.data.strip_prefix(XMP_SIGNATURE))
+        .map(|packet| String::from_utf8_lossy(packet).into_owned())
+}
+
+/// Extracts the ICC profile embedded in a JPEG file
+///
+/// Profiles larger than a segment are split in several `ICC_PROFILE` APP2 chunks, which are
+/// reassembled in sequence order.
+///
+/// # Arguments
+/// * `data` - Content of the JPEG file
+///
+/// # Returns
+/// * `Option<Vec<u8>>` - ICC profile if there is one
+pub fn icc_profile(data: &[u8]) -> Option<Vec<u8>> {
+    const SIGNATURE: &[u8] = b"ICC_PROFILE\0";
+    let mut chunks: Vec<(u8, &[u8])> = segments(data)
+        .into_iter()
+        .filter(|segment| segment.marker == APP2 && segment.data.starts_with(SIGNATURE))
+        .filter(|segment| segment.data.len() > SIGNATURE.len() + 2)
+        .map(|segment| {
+            (
+                segment.data[SIGNATURE.len()],
+                &segment.data[SIGNATURE.len() + 2..],
+            )
+        })
+        .collect();
+    if chunks.is_empty() {
+        return None;
+    }
+    chunks.sort_by_key(|(sequence, _)| *sequence);
+    Some(
+        chunks
+            .into_iter()
+            .flat_map(|(_, chunk)| chunk.to_vec())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icc_profile_chunks_are_reassembled() {
+        let mut data = vec![0xFF, 0xD8];
+        for (sequence, chunk) in [(2u8, b"world".as_slice()), (1u8, b"hello ".as_slice())] {
+            let mut payload = b"ICC_PROFILE\0".to_vec();
+            payload.extend_from_slice(&[sequence, 2]);
+            payload.extend_from_slice(chunk);
+            data.extend_from_slice(&[0xFF, APP2]);
+            data.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+            data.extend_from_slice(&payload);
+        }
+        data.extend_from_slice(&[0xFF, SOS, 0x00, 0x02, 0x12, 0x34]);
+
+        assert_eq!(segments(&data).len(), 3);
+        assert_eq!(icc_profile(&data), Some(b"hello world".to_vec()));
+        assert_eq!(icc_profile(b"not a jpeg"), None);
+    }
+}
@@ -0,0 +1,439 @@
+//! Layout module
+//!
+//! This module computes where the elements of the information bar are placed and resolves
+//! collisions between the left text block, the center logos and the right parameters before
+//! anything is drawn.
+
+use std::sync::{Arc, Mutex};
+
+/// Minimum logo scale before the logo is dropped instead of shrunk further
+const MIN_LOGO_SCALE: f32 = 0.5;
+/// Minimum parameter text scale before the text is truncated instead of shrunk further
+const MIN_PARAMS_SCALE: f32 = 0.6;
+
+/// Axis-aligned rectangle in canvas coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    /// Left position in pixels
+    pub x: i32,
+    /// Top position in pixels
+    pub y: i32,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+}
+
+impl Rect {
+    /// Returns the right edge of the rectangle
+    pub fn right(&self) -> i32 {
+        self.x + self.width as i32
+    }
+
+    /// Returns the bottom edge of the rectangle
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height as i32
+    }
+
+    /// Returns whether the rectangle overlaps another one
+    ///
+    /// # Arguments
+    /// * `other` - Rectangle to test against
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+}
+
+/// Measured sizes of the information bar elements
+#[derive(Debug, Clone, Copy)]
+pub struct BarMetrics {
+    /// Width of the bar in pixels
+    pub width: u32,
+    /// Top position of the bar in pixels
+    pub top: u32,
+    /// Height of the bar in pixels
+    pub info_height: u32,
+    /// Horizontal padding between elements and the bar edges
+    pub padding: u32,
+    /// Width of the left text block (camera and lens lines)
+    pub left_width: u32,
+    /// Height of the left text block
+    pub left_height: u32,
+    /// Width of the parameters text
+    pub params_width: u32,
+    /// Height of the parameters text
+    pub params_height: u32,
+    /// Total width of the logos at the preferred logo height, excluding gaps
+    pub logo_width: u32,
+    /// Total width of the gaps between logos
+    pub logo_gap: u32,
+    /// Preferred logo height, zero if there is no logo
+    pub logo_height: u32,
+    /// Optional maximum width of the logo row
+    pub logo_max_width: Option<u32>,
+}
+
+/// Resolved positions of the information bar elements
+#[derive(Debug, Clone, Copy)]
+pub struct BarLayout {
+    /// Box of the left text block, lines wider than the box are truncated with an ellipsis
+    pub left: Rect,
+    /// Box of the parameters text, lines wider than the box are truncated with an ellipsis
+    pub params: Rect,
+    /// Scale factor applied to the parameters text to make it fit
+    pub params_scale: f32,
+    /// Box of the logo row, `None` if there is no logo or it was dropped
+    pub logo: Option<Rect>,
+}
+
+impl BarLayout {
+    /// Returns whether the boxes of the layout overlap each other or leave the bar
+    ///
+    /// Works on the layout alone, so layouts can be checked for overflow without drawing.
+    ///
+    /// # Arguments
+    /// * `metrics` - Metrics the layout was computed from
+    pub fn overflows(&self, metrics: &BarMetrics) -> bool {
+        let bar = Rect {
+            x: 0,
+            y: metrics.top as i32,
+            width: metrics.width,
+            height: metrics.info_height,
+        };
+        let boxes: Vec<&Rect> = [Some(&self.left), Some(&self.params), self.logo.as_ref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        let outside = boxes.iter().any(|rect| {
+            rect.x < bar.x
+                || rect.y < bar.y
+                || rect.right() > bar.right()
+                || rect.bottom() > bar.bottom()
+        });
+        let overlapping = boxes.iter().enumerate().any(|(index, rect)| {
+            boxes[index + 1..]
+                .iter()
+                .any(|other| rect.intersects(other))
+        });
+        outside || overlapping
+    }
+}
+
+/// Bar geometry a locked layout is only valid for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LockKey {
+    width: u32,
+    top: u32,
+    info_height: u32,
+    has_logo: bool,
+}
+
+impl LockKey {
+    fn of(metrics: &BarMetrics) -> Self {
+        Self {
+            width: metrics.width,
+            top: metrics.top,
+            info_height: metrics.info_height,
+            has_logo: metrics.logo_height > 0 && metrics.logo_width > 0,
+        }
+    }
+}
+
+/// Layout shared across a sequence of frames
+///
+/// The first frame computes the layout and every following frame with the same bar geometry
+/// and logo presence reuses it, so text and logo positions don't jitter between frames of a
+/// timelapse. Clones share the same locked layout.
+#[derive(Debug, Clone, Default)]
+pub struct LayoutLock {
+    layout: Arc<Mutex<Option<(LockKey, BarLayout)>>>,
+}
+
+impl LayoutLock {
+    /// Returns the locked layout, computing and locking it on first use
+    ///
+    /// A frame whose bar size or logo presence differs from the first frame gets its own layout
+    /// with a warning, as the locked one would place its elements off the bar.
+    ///
+    /// # Arguments
+    /// * `metrics` - Measured sizes of the bar elements of the current frame
+    ///
+    /// # Returns
+    /// * `BarLayout` - Layout of the first frame, or of the current frame if they don't match
+    pub fn get_or_compute(&self, metrics: &BarMetrics) -> BarLayout {
+        let key = LockKey::of(metrics);
+        let mut layout = self.layout.lock().unwrap_or_else(|e| e.into_inner());
+        let (locked_key, locked) = *layout.get_or_insert_with(|| {
+            println!("[INFO] Locking layout to the first frame of the sequence");
+            (key, compute_bar_layout(metrics))
+        });
+        if locked_key != key {
+            println!(
+                "[WARN] Frame bar is {}x{} {} a logo, the locked layout is for {}x{} {} a logo, laying it out on its own",
+                key.width,
+                key.info_height,
+                if key.has_logo { "with" } else { "without" },
+                locked_key.width,
+                locked_key.info_height,
+                if locked_key.has_logo { "with" } else { "without" },
+            );
+            return compute_bar_layout(metrics);
+        }
+        locked
+    }
+}
+
+/// Computes the information bar layout and resolves collisions
+///
+/// The parameters text is shrunk if it collides with the left text block, down to a minimum
+/// scale past which its box is narrowed to the free width and its lines are truncated. The left
+/// text block is narrowed the same way when it would leave the parameters no room. The logos
+/// are shrunk to fit between both text columns, or dropped if they would become too small.
+///
+/// # Arguments
+/// * `metrics` - Measured sizes of the bar elements
+///
+/// # Returns
+/// * `BarLayout` - Positions of the bar elements
+pub fn compute_bar_layout(metrics: &BarMetrics) -> BarLayout {
+    let padding = metrics.padding as i32;
+    let top = metrics.top as i32;
+    let info_height = metrics.info_height as i32;
+
+    // The left block keeps at least two thirds of the content width, the parameters the rest
+    let content_width = metrics.width.saturating_sub(metrics.padding * 3);
+    let params_min_width = (metrics.params_width as f32 * MIN_PARAMS_SCALE).round() as u32;
+    let left_max_width = content_width
+        .saturating_sub(params_min_width)
+        .max(content_width * 2 / 3);
+    if metrics.left_width > left_max_width {
+        println!("[INFO] Camera and lens text truncated to leave room for the parameters");
+    }
+    let left = Rect {
+        x: padding,
+        y: top + (info_height - metrics.left_height as i32) / 2,
+        width: metrics.left_width.min(left_max_width),
+        height: metrics.left_height,
+    };
+
+    let available = (metrics.width as i32 - padding - left.right() - padding).max(0) as u32;
+    // Shrink to the free width beside the left block and to the height of the bar
+    let fit = |size: u32, room: u32| {
+        if size > room {
+            room as f32 / size as f32
+        } else {
+            1.0
+        }
+    };
+    let params_scale = fit(metrics.params_width, available)
+        .min(fit(metrics.params_height, metrics.info_height))
+        .max(MIN_PARAMS_SCALE);
+    let mut params_width = (metrics.params_width as f32 * params_scale).round() as u32;
+    if params_width > available {
+        println!("[INFO] Parameters text truncated to fit beside the lens text");
+        params_width = available;
+    }
+    let params_height = (metrics.params_height as f32 * params_scale).round() as u32;
+    let params = Rect {
+        x: metrics.width as i32 - padding - params_width as i32,
+        y: top + (info_height - params_height as i32) / 2,
+        width: params_width,
+        height: params_height,
+    };
+    if params_scale < 1.0 {
+        println!(
+            "[INFO] Parameters text shrunk to {:.0}% to fit the bar",
+            params_scale * 100.0
+        );
+    }
+
+    let logo = layout_logo(metrics, &left, &params);
+    BarLayout {
+        left,
+        params,
+        params_scale,
+        logo,
+    }
+}
+
+/// Places the logo row centered between the text columns
+fn layout_logo(metrics: &BarMetrics, left: &Rect, params: &Rect) -> Option<Rect> {
+    if metrics.logo_height == 0 || metrics.logo_width == 0 {
+        return None;
+    }
+    let padding = metrics.padding as i32;
+    let center = metrics.width as i32 / 2;
+    let free_half_width = (center - left.right() - padding)
+        .min(params.x - padding - center)
+        .max(0) as u32;
+    let mut max_width = free_half_width * 2;
+    if let Some(logo_max_width) = metrics.logo_max_width {
+        max_width = max_width.min(logo_max_width);
+    }
+
+    let logo_height = fit_logo_height(
+        metrics.logo_height,
+        metrics.logo_width,
+        max_width.saturating_sub(metrics.logo_gap),
+    );
+    if (logo_height as f32) < metrics.logo_height as f32 * MIN_LOGO_SCALE {
+        println!("[WARN] No room left for the logo between text columns, skipping logo");
+        return None;
+    }
+    if logo_height < metrics.logo_height {
+        println!(
+            "[INFO] Logo downscaled to {}px height to fit between text columns",
+            logo_height
+        );
+    }
+
+    let logo_width = (metrics.logo_width as u64 * logo_height as u64 / metrics.logo_height as u64)
+        as u32
+        + metrics.logo_gap;
+    Some(Rect {
+        x: center - logo_width as i32 / 2,
+        y: metrics.top as i32 + (metrics.info_height as i32 - logo_height as i32) / 2,
+        width: logo_width,
+        height: logo_height,
+    })
+}
+
+/// Computes the logo height that keeps a row of logos within a maximum width
+///
+/// # Arguments
+/// * `target_height` - Preferred logo height in pixels
+/// * `row_width` - Total width of the logos when rendered at `target_height`
+/// * `max_width` - Maximum total width available for the logos
+///
+/// # Returns
+/// * `u32` - Logo height, `target_height` if the row already fits
+fn fit_logo_height(target_height: u32, row_width: u32, max_width: u32) -> u32 {
+    if row_width <= max_width {
+        return target_height;
+    }
+    (target_height as u64 * max_width as u64 / row_width as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics() -> BarMetrics {
+        BarMetrics {
+            width: 2000,
+            top: 1000,
+            info_height: 180,
+            padding: 32,
+            left_width: 500,
+            left_height: 134,
+            params_width: 600,
+            params_height: 54,
+            logo_width: 300,
+            logo_gap: 0,
+            logo_height: 117,
+            logo_max_width: None,
+        }
+    }
+
+    #[test]
+    fn test_fit_logo_height() {
+        assert_eq!(fit_logo_height(100, 300, 400), 100);
+        assert_eq!(fit_logo_height(100, 400, 200), 50);
+        assert_eq!(fit_logo_height(100, 400, 0), 0);
+    }
+
+    #[test]
+    fn test_layout_without_collisions() {
+        let layout = compute_bar_layout(&metrics());
+        let logo = layout.logo.unwrap();
+        assert_eq!(layout.params_scale, 1.0);
+        assert_eq!(logo.height, 117);
+        assert!(!logo.intersects(&layout.left));
+        assert!(!logo.intersects(&layout.params));
+        assert!(!layout.left.intersects(&layout.params));
+        assert!(!layout.overflows(&metrics()));
+    }
+
+    #[test]
+    fn test_layout_shrinks_and_drops_logo() {
+        let layout = compute_bar_layout(&BarMetrics {
+            width: 1500,
+            ..metrics()
+        });
+        let logo = layout.logo.unwrap();
+        assert!(logo.height < 117);
+        assert!(!logo.intersects(&layout.left));
+        assert!(!logo.intersects(&layout.params));
+
+        let layout = compute_bar_layout(&BarMetrics {
+            width: 1250,
+            ..metrics()
+        });
+        assert!(layout.logo.is_none());
+    }
+
+    #[test]
+    fn test_layout_lock_reuses_first_layout() {
+        let lock = LayoutLock::default();
+        let first = lock.get_or_compute(&metrics());
+        let second = lock.clone().get_or_compute(&BarMetrics {
+            params_width: 400,
+            ..metrics()
+        });
+        assert_eq!(first.params, second.params);
+        assert_eq!(first.logo, second.logo);
+
+        // Frames with another bar width or without a logo aren't forced into the locked layout
+        let wider = BarMetrics {
+            width: 3000,
+            ..metrics()
+        };
+        let third = lock.get_or_compute(&wider);
+        assert_ne!(first.params, third.params);
+        assert!(!third.overflows(&wider));
+        let no_logo = BarMetrics {
+            logo_width: 0,
+            logo_height: 0,
+            ..metrics()
+        };
+        assert!(lock.get_or_compute(&no_logo).logo.is_none());
+        // The lock still holds the first layout
+        assert_eq!(lock.get_or_compute(&metrics()).params, first.params);
+    }
+
+    #[test]
+    fn test_layout_shrinks_params() {
+        let layout = compute_bar_layout(&BarMetrics {
+            width: 1000,
+            ..metrics()
+        });
+        assert!(layout.params_scale < 1.0);
+        assert!(!layout.left.intersects(&layout.params));
+        assert!(layout.logo.is_none());
+
+        // Past the minimum text scale the parameters box is narrowed for truncation
+        let narrow = BarMetrics {
+            width: 700,
+            ..metrics()
+        };
+        let layout = compute_bar_layout(&narrow);
+        assert_eq!(layout.params_scale, MIN_PARAMS_SCALE);
+        assert_eq!(layout.left.width, (700 - 32 * 3) * 2 / 3);
+        assert_eq!(layout.params.width, 700 - 32 * 3 - layout.left.width);
+        assert!(!layout.overflows(&narrow));
+
+        // A left block wider than the bar is narrowed, leaving a third to the parameters
+        let crowded = BarMetrics {
+            width: 600,
+            left_width: 700,
+            ..metrics()
+        };
+        let layout = compute_bar_layout(&crowded);
+        assert_eq!(layout.left.width, (600 - 32 * 3) * 2 / 3);
+        assert!(layout.params.width > 0);
+        assert!(!layout.overflows(&crowded));
+    }
+}
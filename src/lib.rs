@@ -3,11 +3,57 @@
 //! This library provides functionality to process JPEG images by adding an information bar
 //! containing camera details and EXIF information.
 
+pub mod animation;
+#[cfg(feature = "astro")]
+pub mod astro;
+pub mod backdrop;
 pub mod cli;
+pub mod color;
+pub mod compare;
+pub mod config;
+pub mod dedupe;
+pub mod enhance;
+pub mod event;
 pub mod exif;
+pub mod geocode;
 pub mod image_processor;
+pub mod integrity;
+pub mod jpeg;
+pub mod layout;
 pub mod logo;
+pub mod logo_provider;
+pub mod makernote;
+pub mod metadata;
+pub mod options;
+pub mod output;
+pub mod overlay;
+pub mod paranoid;
+pub mod print;
+pub mod priority;
+pub mod profile;
+pub mod proof;
+pub mod raw;
+pub mod rename;
+pub mod report;
 pub mod resource;
+pub mod retag;
+pub mod safe_area;
+pub mod salvage;
+pub mod sharpness;
+pub mod sidecar;
+pub mod sink;
+pub mod source;
+pub mod space;
+pub mod template;
+pub mod theme;
+pub mod thumbs;
+pub mod time_of_day;
 pub mod util;
+pub mod validate;
+pub mod video;
+#[cfg(feature = "online")]
+pub mod weather;
+pub mod xmp;
 
-pub use util::{process_directory, process_single_file};
+pub use options::ProcessOptions;
+pub use util::{process_directory, process_single_file, process_source};
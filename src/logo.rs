@@ -1,10 +1,18 @@
 //! Camera logo module
 //!
-//! This module contains base64 encoded camera logos and functions to load them.
+//! This module contains base64 encoded camera logos and functions to load them. The logos are
+//! only built in with the `embedded-logos` feature, and each is decoded on first use.
 
+#[cfg(feature = "embedded-logos")]
 use base64::Engine as _;
 use image::DynamicImage;
 use std::error::Error;
+#[cfg(feature = "embedded-logos")]
+use std::sync::OnceLock;
+
+/// Brands with an embedded logo, matching the order of `EMBEDDED_LOGOS`
+#[cfg(feature = "embedded-logos")]
+const EMBEDDED_BRANDS: [&str; 5] = ["canon", "fujifilm", "nikon", "panasonic", "sony"];
 
 /// Base64 encoded camera logos
 pub struct CameraLogos;
@@ -17,14 +25,49 @@ impl CameraLogos {
     ///
     /// # Returns
     /// * `Result<DynamicImage, Box<dyn Error>>` - Decoded image if successful
+    #[cfg(feature = "embedded-logos")]
     pub fn load_from_base64(base64_str: &str) -> Result<DynamicImage, Box<dyn Error>> {
         let decoded = base64::engine::general_purpose::STANDARD.decode(base64_str)?;
         let img = image::load_from_memory(&decoded)?;
         Ok(img)
     }
+
+    /// Returns the embedded logo of a camera brand, decoded on first use
+    ///
+    /// # Arguments
+    /// * `brand` - Lowercase brand name, e.g. `canon`
+    ///
+    /// # Returns
+    /// * `Option<Result<DynamicImage, Box<dyn Error>>>` - Decoded logo, `None` if the brand has
+    ///   no embedded logo or the crate is built without the `embedded-logos` feature
+    #[cfg(feature = "embedded-logos")]
+    pub fn embedded(brand: &str) -> Option<Result<DynamicImage, Box<dyn Error>>> {
+        static DECODED: [OnceLock<Result<DynamicImage, String>>; 5] =
+            [const { OnceLock::new() }; 5];
+        let sources = [
+            logos::CANON,
+            logos::FUJIFILM,
+            logos::NIKON,
+            logos::PANASONIC,
+            logos::SONY,
+        ];
+        let idx = EMBEDDED_BRANDS.iter().position(|&name| name == brand)?;
+        let decoded = DECODED[idx]
+            .get_or_init(|| Self::load_from_base64(sources[idx]).map_err(|e| e.to_string()));
+        Some(decoded.clone().map_err(Into::into))
+    }
+
+    /// Returns the embedded logo of a camera brand
+    ///
+    /// Always `None`, since the crate is built without the `embedded-logos` feature.
+    #[cfg(not(feature = "embedded-logos"))]
+    pub fn embedded(_brand: &str) -> Option<Result<DynamicImage, Box<dyn Error>>> {
+        None
+    }
 }
 
 /// Hardcoded base64 encoded camera logos
+#[cfg(feature = "embedded-logos")]
 pub mod logos {
     pub const CANON: &str = include_str!("logos/canon.base64");
     pub const FUJIFILM: &str = include_str!("logos/fujifilm.base64");
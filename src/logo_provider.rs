@@ -0,0 +1,335 @@
+//! Logo provider module
+//!
+//! This module abstracts where brand logos come from, so the renderer asks a `LogoProvider` for
+//! the logo of a brand instead of probing directories and embedded assets itself. Embedders can
+//! ship their own brand art by implementing the trait.
+
+use crate::logo::CameraLogos;
+use clap::ValueEnum;
+use image::DynamicImage;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Source of brand logos
+pub trait LogoProvider: fmt::Debug + Send + Sync {
+    /// Returns the logo of a camera brand
+    ///
+    /// # Arguments
+    /// * `brand` - Lowercase brand name, e.g. `canon`
+    ///
+    /// # Returns
+    /// * `Result<Option<DynamicImage>, Box<dyn Error>>` - Logo, `None` if the provider has none
+    ///
+    /// # Errors
+    /// Returns an error if the provider has a logo for the brand but cannot load it
+    fn logo(&self, brand: &str) -> Result<Option<DynamicImage>, Box<dyn Error>>;
+}
+
+/// Where brand logos are looked up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LogoSource {
+    /// The logos directory, then the embedded logos
+    #[default]
+    Auto,
+    /// The embedded logos only
+    Builtin,
+    /// The logos directory only
+    Directory,
+    /// `<brand>` URLs of a remote server, requires the `online` feature
+    Remote,
+    /// No brand logos
+    None,
+}
+
+/// Logos compiled into the binary with the `embedded-logos` feature
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuiltinLogos;
+
+impl LogoProvider for BuiltinLogos {
+    fn logo(&self, brand: &str) -> Result<Option<DynamicImage>, Box<dyn Error>> {
+        let Some(logo) = CameraLogos::embedded(brand).transpose()? else {
+            return Ok(None);
+        };
+        println!("[INFO] Using hardcoded logo for camera brand '{}'", brand);
+        Ok(Some(logo))
+    }
+}
+
+/// `<brand>.png` files of a directory
+#[derive(Debug, Clone)]
+pub struct DirectoryLogos {
+    /// Directory holding the logo files
+    pub dir: PathBuf,
+}
+
+impl LogoProvider for DirectoryLogos {
+    fn logo(&self, brand: &str) -> Result<Option<DynamicImage>, Box<dyn Error>> {
+        let path = self.dir.join(format!("{}.png", brand));
+        if !path.exists() {
+            return Ok(None);
+        }
+        let logo =
+            image::open(&path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        println!(
+            "[INFO] Using external logo file for camera brand '{}'",
+            brand
+        );
+        Ok(Some(logo))
+    }
+}
+
+/// Logos downloaded from a URL template, cached in memory for the run
+#[cfg(feature = "online")]
+#[derive(Debug, Default)]
+pub struct RemoteLogos {
+    /// URL of a logo, with `{brand}` replaced by the lowercase brand name
+    pub url: String,
+    /// Downloaded logos by brand, `None` for brands the server has no logo for
+    cache: std::sync::Mutex<std::collections::HashMap<String, Option<DynamicImage>>>,
+}
+
+#[cfg(feature = "online")]
+impl RemoteLogos {
+    /// Hard limit of a download, including connecting and reading the response
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+    /// Creates a provider downloading from a URL template
+    ///
+    /// # Arguments
+    /// * `url` - URL of a logo, with `{brand}` replaced by the lowercase brand name
+    pub fn new(url: String) -> Self {
+        RemoteLogos {
+            url,
+            cache: Default::default(),
+        }
+    }
+}
+
+#[cfg(feature = "online")]
+impl LogoProvider for RemoteLogos {
+    fn logo(&self, brand: &str) -> Result<Option<DynamicImage>, Box<dyn Error>> {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(logo) = cache.get(brand) {
+            return Ok(logo.clone());
+        }
+        let url = self.url.replace("{brand}", brand);
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(Self::TIMEOUT))
+            .build()
+            .into();
+        let logo = match agent.get(&url).call() {
+            Ok(mut response) => {
+                let data = response.body_mut().read_to_vec()?;
+                println!("[INFO] Using remote logo for camera brand '{}'", brand);
+                Some(image::load_from_memory(&data)?)
+            }
+            Err(ureq::Error::StatusCode(404)) => None,
+            Err(e) => return Err(format!("Failed to download {}: {}", url, e).into()),
+        };
+        cache.insert(brand.to_string(), logo.clone());
+        Ok(logo)
+    }
+}
+
+/// Never provides a logo
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoLogos;
+
+impl LogoProvider for NoLogos {
+    fn logo(&self, _brand: &str) -> Result<Option<DynamicImage>, Box<dyn Error>> {
+        Ok(None)
+    }
+}
+
+/// Asks providers in order and returns the first logo found
+///
+/// A provider that fails is reported and the next one is asked.
+#[derive(Debug, Default)]
+pub struct LogoChain {
+    /// Providers in lookup order
+    pub providers: Vec<Box<dyn LogoProvider>>,
+}
+
+impl LogoChain {
+    /// Returns the standard lookup: the logos directory if any, then the embedded logos
+    ///
+    /// # Arguments
+    /// * `dir` - Directory with `<brand>.png` logos, `None` for the embedded logos only
+    pub fn standard(dir: Option<PathBuf>) -> Self {
+        let mut providers: Vec<Box<dyn LogoProvider>> = Vec::new();
+        if let Some(dir) = dir {
+            providers.push(Box::new(DirectoryLogos { dir }));
+        }
+        providers.push(Box::new(BuiltinLogos));
+        LogoChain { providers }
+    }
+}
+
+impl LogoProvider for LogoChain {
+    fn logo(&self, brand: &str) -> Result<Option<DynamicImage>, Box<dyn Error>> {
+        for provider in &self.providers {
+            match provider.logo(brand) {
+                Ok(Some(logo)) => return Ok(Some(logo)),
+                Ok(None) => {}
+                Err(e) => println!(
+                    "[WARN] Failed to load logo for camera brand '{}': {}",
+                    brand, e
+                ),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Creates the logo provider selected on the command line
+///
+/// # Arguments
+/// * `source` - Where logos are looked up
+/// * `dir` - Logos directory, `None` if there is none
+/// * `url` - URL template of remote logos
+///
+/// # Returns
+/// * `Result<Arc<dyn LogoProvider>, Box<dyn Error>>` - Provider
+///
+/// # Errors
+/// Returns an error if the source lacks its directory or URL, or remote logos are requested
+/// without the `online` feature
+pub fn build_provider(
+    source: LogoSource,
+    dir: Option<PathBuf>,
+    url: Option<String>,
+) -> Result<Arc<dyn LogoProvider>, Box<dyn Error>> {
+    Ok(match source {
+        LogoSource::Auto => Arc::new(LogoChain::standard(dir)),
+        LogoSource::Builtin => Arc::new(BuiltinLogos),
+        LogoSource::Directory => Arc::new(DirectoryLogos {
+            dir: dir.ok_or("--logo-source directory requires --logos-dir")?,
+        }),
+        LogoSource::Remote => {
+            let url = url.ok_or("--logo-source remote requires --logo-url")?;
+            remote_provider(url)?
+        }
+        LogoSource::None => Arc::new(NoLogos),
+    })
+}
+
+#[cfg(feature = "online")]
+fn remote_provider(url: String) -> Result<Arc<dyn LogoProvider>, Box<dyn Error>> {
+    Ok(Arc::new(RemoteLogos::new(url)))
+}
+
+#[cfg(not(feature = "online"))]
+fn remote_provider(_url: String) -> Result<Arc<dyn LogoProvider>, Box<dyn Error>> {
+    Err("Remote logos require a build with the `online` feature".into())
+}
+
+/// Saves the logos of brands from a provider as `<brand>.png` files of a logos directory
+///
+/// Brands with an existing file are skipped unless `overwrite` is set, and brands not approved
+/// are skipped before anything is downloaded.
+///
+/// # Arguments
+/// * `provider` - Source of the logos
+/// * `brands` - Brand names, matched case-insensitively
+/// * `dir` - Logos directory, created if missing
+/// * `overwrite` - Whether to replace existing logo files
+/// * `approve` - Asked for every brand before its logo is fetched
+///
+/// # Returns
+/// * `Result<Vec<PathBuf>, Box<dyn Error>>` - Paths of the installed logos
+///
+/// # Errors
+/// Returns an error if the directory cannot be created or a logo cannot be saved
+pub fn install_logos(
+    provider: &dyn LogoProvider,
+    brands: &[String],
+    dir: &Path,
+    overwrite: bool,
+    approve: &mut dyn FnMut(&str) -> bool,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    std::fs::create_dir_all(dir)?;
+    let mut installed = Vec::new();
+    for brand in brands {
+        let brand = brand.trim().to_lowercase();
+        if brand.is_empty() {
+            continue;
+        }
+        let path = dir.join(format!("{}.png", brand));
+        if path.exists() && !overwrite {
+            println!(
+                "[INFO] Logo for '{}' already installed at {}, skipping",
+                brand,
+                path.display()
+            );
+            continue;
+        }
+        if !approve(&brand) {
+            println!("[INFO] Skipping logo for '{}'", brand);
+            continue;
+        }
+        match provider.logo(&brand) {
+            Ok(Some(logo)) => {
+                logo.save_with_format(&path, image::ImageFormat::Png)?;
+                println!(
+                    "[INFO] Installed logo for '{}' at {}",
+                    brand,
+                    path.display()
+                );
+                installed.push(path);
+            }
+            Ok(None) => println!("[WARN] No logo found for '{}'", brand),
+            Err(e) => println!("[WARN] Failed to fetch logo for '{}': {}", brand, e),
+        }
+    }
+    Ok(installed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logo_chain_order() {
+        let dir = tempfile::tempdir().unwrap();
+        image::RgbaImage::new(3, 2)
+            .save(dir.path().join("canon.png"))
+            .unwrap();
+        let chain = LogoChain::standard(Some(dir.path().to_path_buf()));
+        let logo = chain.logo("canon").unwrap().unwrap();
+        assert_eq!((logo.width(), logo.height()), (3, 2));
+        assert!(chain.logo("unknown").unwrap().is_none());
+        assert!(NoLogos.logo("canon").unwrap().is_none());
+
+        // A broken file falls through to the next provider
+        std::fs::write(dir.path().join("nikon.png"), b"not a png").unwrap();
+        let logo = chain.logo("nikon").unwrap();
+        assert_eq!(logo.is_some(), cfg!(feature = "embedded-logos"));
+
+        assert!(build_provider(LogoSource::Directory, None, None).is_err());
+    }
+
+    #[test]
+    fn test_install_logos() {
+        let dir = tempfile::tempdir().unwrap();
+        let logos = dir.path().join("logos");
+        let brands = vec!["Canon".to_string(), "sony".to_string(), "leica".to_string()];
+        let installed = install_logos(&BuiltinLogos, &brands, &logos, false, &mut |brand| {
+            brand != "sony"
+        })
+        .unwrap();
+        if cfg!(feature = "embedded-logos") {
+            assert_eq!(installed, vec![logos.join("canon.png")]);
+            assert!(image::open(logos.join("canon.png")).is_ok());
+        } else {
+            assert!(installed.is_empty());
+        }
+        assert!(!logos.join("sony.png").exists());
+
+        // Installed logos are kept unless overwriting
+        let installed =
+            install_logos(&BuiltinLogos, &brands, &logos, false, &mut |_| true).unwrap();
+        assert!(!installed.contains(&logos.join("canon.png")));
+    }
+}
@@ -4,61 +4,553 @@
 //! containing camera details and EXIF information. It can process single files
 //! or entire directories.
 
-mod cli;
-mod exif;
-mod image_processor;
-mod logo;
-mod resource;
-mod util;
-
-use crate::cli::Cli;
-use crate::util::{process_directory, process_single_file};
 use clap::Parser;
-use std::path::Path;
+use lensight::backdrop::{BackdropOptions, BackdropSource};
+use lensight::cli::{Cli, Command};
+use lensight::compare;
+use lensight::config::Config;
+use lensight::event::EventPack;
+use lensight::exif::ExifInheritance;
+use lensight::integrity;
+use lensight::layout::LayoutLock;
+use lensight::logo_provider::{self, LogoProvider, LogoSource};
+use lensight::metadata::{ExifOverrides, FilmInfo, MetadataTable};
+use lensight::overlay;
+use lensight::paranoid;
+use lensight::proof::PrintProfile;
+use lensight::raw::RAW_EXTENSIONS;
+use lensight::rename;
+use lensight::report::ExitStatus;
+use lensight::retag;
+use lensight::sink::{FileSink, Sink, ZipSink};
+use lensight::source::{DirectorySource, FileListSource, Source, DEFAULT_EXTENSIONS};
+use lensight::theme::Theme;
+use lensight::thumbs;
+use lensight::validate;
+use lensight::video::SlideshowOptions;
+use lensight::{process_directory, process_single_file, process_source, ProcessOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
 
 /// Main entry point of the application
 ///
 /// # Returns
-/// - `Result<(), Box<dyn std::error::Error>>`: Ok if successful, Err if any error occurs
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// - `ExitCode`: Exit status documented in `lensight::report::ExitStatus`
+fn main() -> ExitCode {
     let args = Cli::parse();
-    let input_path = Path::new(&args.input);
-    let output_path = Path::new(&args.output);
+    match run(&args) {
+        Ok(status) => status.into(),
+        Err(e) => {
+            println!("[ERROR] {}", e);
+            ExitStatus::Failure.into()
+        }
+    }
+}
+
+/// Frames INPUT into OUTPUT, or runs a subcommand
+///
+/// # Arguments
+/// * `args` - Parsed command line arguments
+///
+/// # Returns
+/// - `Result<ExitStatus, Box<dyn std::error::Error>>`: Exit status of the run, Err on fatal errors
+fn run(args: &Cli) -> Result<ExitStatus, Box<dyn std::error::Error>> {
+    // Stateless runs never fall back to the working directory
+    let default_dir = |dir: &str| (!args.stateless).then(|| PathBuf::from(dir));
+    let fonts_dir = args.fonts_dir.clone().or_else(|| default_dir("./fonts"));
+    let logos_dir = args.logos_dir.clone().or_else(|| default_dir("./logos"));
+    let logo_provider =
+        match logo_provider::build_provider(args.logo_source, logos_dir, args.logo_url.clone()) {
+            Ok(provider) => provider,
+            Err(e) => {
+                println!("[ERROR] {}", e);
+                return Ok(ExitStatus::InvalidArguments);
+            }
+        };
+    if let Some(command) = &args.command {
+        return run_command(command, fonts_dir, logo_provider, args.paranoid);
+    }
+    let (Some(input), Some(output)) = (&args.input, &args.output) else {
+        println!("[ERROR] INPUT and OUTPUT are required");
+        return Ok(ExitStatus::InvalidArguments);
+    };
+    let input_path = Path::new(input);
+    let output_path = Path::new(output);
+    if args.low_priority {
+        match lensight::priority::lower_priority() {
+            Ok(()) => println!("[INFO] Running at low priority"),
+            Err(e) => println!("[WARN] Could not lower the priority: {}", e),
+        }
+    }
 
     // Check if input path exists
-    if !input_path.exists() {
+    let from_stdin = args.files_from && input_path == Path::new("-");
+    if !from_stdin && !input_path.exists() {
         println!(
             "[ERROR] Input path does not exist: {}",
             input_path.display()
         );
-        return Ok(());
+        return Ok(ExitStatus::InvalidArguments);
+    }
+
+    // The file list is read once up front, so the paranoid check sees the listed images
+    let listed = if args.files_from {
+        let source = FileListSource {
+            list: input_path.to_path_buf(),
+        };
+        Some(source.items()?)
+    } else {
+        None
+    };
+
+    // Nothing may be written before the paranoid check, not even the output directory
+    if args.paranoid {
+        let inputs: Vec<&Path> = match &listed {
+            Some(items) => items.iter().map(|item| item.path.as_path()).collect(),
+            None => vec![input_path],
+        };
+        let outputs: Vec<&Path> = [
+            Some(args.zip.as_deref().unwrap_or(output_path)),
+            args.video.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if let Err(e) = paranoid::check_overlap(&inputs, &outputs) {
+            println!("[ERROR] {}", e);
+            return Ok(ExitStatus::InvalidArguments);
+        }
     }
 
     // Check if output directory exists, create it if not
-    if let Some(parent) = output_path.parent() {
-        if !parent.exists() {
+    if let Some(parent) = output_path.parent().filter(|_| args.zip.is_none()) {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
             println!("[INFO] Creating output directory: {}", parent.display());
             std::fs::create_dir_all(parent)?;
         }
     }
 
-    if input_path.is_dir() {
-        process_directory(
-            input_path,
-            output_path,
-            args.info_height,
-            args.force_16_9,
-            args.logo.as_deref(),
-        )?;
+    let theme = Theme {
+        camera_case: args.camera_case,
+        lens_case: args.lens_case,
+        params_case: args.params_case,
+        secondary_logo: args.secondary_logo.clone(),
+        logo_order: args.logo_order,
+        logo_gap: args.logo_gap,
+        logo_opacity: args.logo_opacity,
+        logo_treatment: args.logo_treatment,
+        logo_max_width: args.logo_max_width,
+        logo_scale: 1.0,
+        logo_offset: 0.0,
+        baseline_offset: args.baseline_offset,
+        drop_order: args.drop_order.clone(),
+        text_shadow: args.text_shadow,
+        text_outline: args.text_outline,
+        text_pill: args.text_pill,
+    };
+    let sink: Arc<dyn Sink> = match &args.zip {
+        Some(archive) => {
+            let root = if args.files_from || input_path.is_dir() {
+                output_path
+            } else {
+                output_path.parent().unwrap_or_else(|| Path::new(""))
+            };
+            println!(
+                "[INFO] Writing outputs to zip archive: {}",
+                archive.display()
+            );
+            Arc::new(ZipSink::create(archive, root)?)
+        }
+        None => Arc::new(FileSink),
+    };
+    let config = match &args.config {
+        Some(path) => {
+            println!("[INFO] Loading config: {}", path.display());
+            Config::load(path)?
+        }
+        None => Config::default(),
+    };
+    let metadata = match &args.metadata_csv {
+        Some(path) => {
+            println!("[INFO] Loading metadata table: {}", path.display());
+            Some(Arc::new(MetadataTable::load(path)?))
+        }
+        None => None,
+    };
+    let event_pack = match &args.event_pack {
+        Some(dir) => {
+            let pack = EventPack::load(dir)?;
+            println!(
+                "[INFO] Loaded event pack: {} frames, {} scheduled windows",
+                pack.frames.len(),
+                pack.schedule.len()
+            );
+            Some(Arc::new(pack))
+        }
+        None => None,
+    };
+    let print_profile = match &args.print_profile {
+        Some(path) => {
+            let profile = PrintProfile::load(path, args.rendering_intent)?;
+            println!(
+                "[INFO] Loaded print profile: {} ({:?} intent)",
+                profile.name, profile.intent
+            );
+            Some(Arc::new(profile))
+        }
+        None => None,
+    };
+    let mut options = ProcessOptions {
+        info_height: args.info_height,
+        scale: args.scale,
+        force_16_9: args.force_16_9,
+        canvas: args.canvas,
+        backdrop: BackdropOptions {
+            source: match (&args.background, &args.pad_color) {
+                (Some(path), _) => BackdropSource::Image {
+                    path: path.clone(),
+                    fit: args.background_fit,
+                },
+                (None, Some(source)) => source.clone(),
+                (None, None) => BackdropSource::Blur,
+            },
+            anchor: args.anchor,
+            grain: args.grain,
+            grain_scope: args.grain_scope,
+            vignette: args.vignette,
+            vignette_falloff: args.vignette_falloff,
+        },
+        logo: args.logo.clone(),
+        fonts_dir,
+        logo_provider,
+        caption: args.caption.clone(),
+        lens_suffix: args.lens_suffix.clone(),
+        show_location: args.show_location,
+        date_format: args.show_date.clone(),
+        show_copyright: args.show_copyright || args.copyright.is_some(),
+        copyright: args.copyright.clone(),
+        params_template: args.params_template.clone(),
+        param_elements: args.param_elements.clone(),
+        film: FilmInfo {
+            stock: args.film_stock.clone(),
+            developer: args.developer.clone(),
+            scan_resolution: args.scan_resolution.clone(),
+        },
+        exif_overrides: ExifOverrides {
+            camera: args.camera.clone(),
+            lens: args.lens.clone(),
+            focal: args.focal.clone(),
+            aperture: args.aperture.clone(),
+            shutter: args.shutter.clone(),
+            iso: args.iso.clone(),
+        },
+        metadata,
+        brand_styles: Arc::new(config.brand_styles()),
+        manual_lenses: config.manual_lenses(),
+        watermark_opt_out: Arc::new(config.watermark_opt_out.clone()),
+        apply_teleconverter: args.apply_teleconverter,
+        exif_inheritance: args.inherit_exif.then(ExifInheritance::default),
+        tolerant_exif: args.tolerant_exif,
+        companion_exif: args.companion_exif,
+        overlay: if !args.overlay_slot.is_empty() {
+            Some(args.overlay_slot.clone())
+        } else {
+            args.overlay.then(overlay::default_slots)
+        },
+        event_pack,
+        bar_color: args.bar_color,
+        debug_layout: args.debug_layout,
+        safe_area: args.safe_area,
+        bleed: args.bleed,
+        crop_marks: args.crop_marks,
+        dpi: args.dpi,
+        paper: args.paper,
+        print_profile,
+        missing_exif: args.missing_exif,
+        placeholder_filename: args.placeholder_filename,
+        extensions: args.extensions.clone(),
+        sniff: args.sniff,
+        shard: args.shard,
+        small_images: args.small_images,
+        min_size: args.min_size,
+        theme,
+        convert_to: args.convert_to,
+        output_format: args.output_format,
+        effort: args.effort,
+        chroma_subsampling: args.chroma_subsampling,
+        quality: args.quality,
+        lossy_webp: args.lossy_webp,
+        export_sizes: args.export_sizes.clone(),
+        low_priority: args.low_priority,
+        space_check: !args.no_space_check,
+        paranoid: args.paranoid,
+        bar_layer: args.bar_layer,
+        bar_only: args.bar_only,
+        bar_width: args.bar_width,
+        layout_lock: args.lock_layout.then(LayoutLock::default),
+        slideshow: args.video.clone().map(|output| SlideshowOptions {
+            output,
+            seconds_per_frame: args.video_seconds,
+            size: args.video_size,
+        }),
+        sink: sink.clone(),
+        gamut_check: args.gamut_check,
+        enhance: args.enhance,
+        duplicates: args.duplicates,
+        duplicate_threshold: args.duplicate_threshold,
+        blur_check: args.blur_check,
+        sharpness_threshold: args.sharpness_threshold,
+        verify_decode: args.verify_decode,
+        keep_exif: args.keep_exif,
+        keep_xmp: args.keep_xmp,
+        strip_private: args.strip_private,
+        source_profile: None,
+    };
+    if let Some(profile) = args.source_profile {
+        profile.apply(&mut options);
+    }
+
+    let issues = validate::validate_options(&options);
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    if issues.iter().any(validate::Issue::is_error) {
+        return Ok(ExitStatus::InvalidArguments);
+    }
+
+    let status = if let Some(items) = &listed {
+        let report = process_source(items, output_path, &options)?;
+        report.print_summary();
+        report.exit_status()
+    } else if input_path.is_dir() {
+        let report = process_directory(input_path, output_path, &options)?;
+        report.print_summary();
+        report.exit_status()
     } else {
-        process_single_file(
-            input_path,
-            output_path,
-            args.info_height,
-            args.force_16_9,
-            args.logo.as_deref(),
-        )?;
+        if options.slideshow.is_some() {
+            println!("[WARN] Slideshow video requires a directory input, skipping video");
+        }
+        if options.convert_to.is_some() {
+            println!("[WARN] --convert-to applies to batches, the format follows OUTPUT instead");
+        }
+        if options.gamut_check {
+            if let Some(warning) = lensight::color::check_gamut(input_path) {
+                println!("[WARN] {}: {}", input_path.display(), warning);
+            }
+        }
+        lensight::output::check_export_paths(&[input_path], &[output_path], &options.export_sizes)?;
+        process_single_file(input_path, output_path, &options)?;
+        ExitStatus::Success
+    };
+    sink.finish()?;
+
+    Ok(status)
+}
+
+/// Runs a subcommand
+///
+/// # Arguments
+/// * `command` - Parsed subcommand
+/// * `fonts_dir` - Directory with the font files, `None` for the embedded font
+/// * `logo_provider` - Source of the brand logos
+/// * `paranoid` - Whether to refuse in-place changes and outputs overlapping the inputs
+///
+/// # Returns
+/// - `Result<(), Box<dyn std::error::Error>>`: Ok if successful, Err if any error occurs
+fn run_command(
+    command: &Command,
+    fonts_dir: Option<PathBuf>,
+    logo_provider: Arc<dyn LogoProvider>,
+    paranoid: bool,
+) -> Result<ExitStatus, Box<dyn std::error::Error>> {
+    if paranoid {
+        if let Err(e) = check_paranoid(command) {
+            println!("[ERROR] {}", e);
+            return Ok(ExitStatus::InvalidArguments);
+        }
+    }
+    match command {
+        Command::Compare {
+            paths,
+            height,
+            gap,
+            info_height,
+        } => {
+            let (output, inputs) = paths.split_last().ok_or("No paths given")?;
+            let options = ProcessOptions {
+                info_height: *info_height,
+                fonts_dir,
+                logo_provider,
+                ..ProcessOptions::default()
+            };
+            compare::compare_images(inputs, output, *height, *gap, &options)?;
+            Ok(ExitStatus::Success)
+        }
+        Command::FetchLogos {
+            brands,
+            dir,
+            url,
+            yes,
+            overwrite,
+        } => {
+            let provider =
+                logo_provider::build_provider(LogoSource::Remote, None, Some(url.clone()))?;
+            let mut approve = |brand: &str| *yes || confirm_logo(brand, url);
+            let installed = logo_provider::install_logos(
+                provider.as_ref(),
+                brands,
+                dir,
+                *overwrite,
+                &mut approve,
+            )?;
+            println!("[INFO] Installed {} logo(s)", installed.len());
+            Ok(ExitStatus::Success)
+        }
+        Command::Check { dir } => {
+            let source = photo_source(dir);
+            let report = integrity::check_source(&source)?;
+            report.print_summary();
+            Ok(report.exit_status())
+        }
+        Command::Rename {
+            dir,
+            pattern,
+            dry_run,
+        } => {
+            let source = photo_source(dir);
+            let report = rename::rename_source(&source, pattern, *dry_run)?;
+            report.print_summary();
+            Ok(report.exit_status())
+        }
+        Command::Thumbs {
+            dir,
+            output,
+            size,
+            quality,
+        } => {
+            let source = photo_source(dir);
+            let options = ProcessOptions {
+                quality: *quality,
+                ..ProcessOptions::default()
+            };
+            let report = thumbs::make_thumbs(&source, output, *size, &options)?;
+            report.print_summary();
+            Ok(report.exit_status())
+        }
+        Command::Retag {
+            path,
+            artist,
+            copyright,
+            output,
+            in_place,
+        } => {
+            let updates: Vec<(exif::Tag, String)> = [
+                (exif::Tag::Artist, artist),
+                (exif::Tag::Copyright, copyright),
+            ]
+            .into_iter()
+            .filter_map(|(tag, text)| text.clone().map(|text| (tag, text)))
+            .collect();
+            if updates.is_empty() {
+                println!("[ERROR] Nothing to write, pass --artist and/or --copyright");
+                return Ok(ExitStatus::InvalidArguments);
+            }
+            if path.is_file() {
+                let target = match output {
+                    _ if *in_place => path.clone(),
+                    Some(output) => output.join(path.file_name().ok_or("Invalid input path")?),
+                    None => retag::default_output(path),
+                };
+                retag::retag_file(path, &target, &updates)?;
+                println!("[INFO] Retagged {}", target.display());
+                return Ok(ExitStatus::Success);
+            }
+            let source = DirectorySource {
+                root: path.clone(),
+                extensions: DEFAULT_EXTENSIONS
+                    .iter()
+                    .map(|ext| ext.to_string())
+                    .collect(),
+                sniff: false,
+            };
+            let output = match output {
+                _ if *in_place => None,
+                Some(output) => Some(output.clone()),
+                None => Some(retag::default_output(path)),
+            };
+            let report = retag::retag_source(&source, output.as_deref(), &updates)?;
+            report.print_summary();
+            Ok(report.exit_status())
+        }
     }
+}
 
-    Ok(())
+/// Checks that a subcommand leaves its inputs untouched, for `--paranoid`
+///
+/// # Arguments
+/// * `command` - Parsed subcommand
+///
+/// # Returns
+/// - `Result<(), Box<dyn std::error::Error>>`: Ok if the inputs stay untouched, Err with the
+///   reason otherwise
+fn check_paranoid(command: &Command) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Compare { paths, .. } => {
+            let (output, inputs) = paths.split_last().ok_or("No paths given")?;
+            let inputs: Vec<&Path> = inputs.iter().map(PathBuf::as_path).collect();
+            paranoid::check_overlap(&inputs, &[output])
+        }
+        Command::Thumbs { dir, output, .. } => paranoid::check_overlap(&[dir], &[output]),
+        Command::Rename { dry_run: false, .. } => {
+            Err("rename changes the input files in place, refused by --paranoid".into())
+        }
+        Command::Retag { in_place: true, .. } => {
+            Err("retag --in-place changes the input files, refused by --paranoid".into())
+        }
+        Command::Retag { path, output, .. } => {
+            let output = output
+                .clone()
+                .unwrap_or_else(|| retag::default_output(path));
+            paranoid::check_overlap(&[path], &[&output])
+        }
+        Command::FetchLogos { .. } | Command::Check { .. } | Command::Rename { .. } => Ok(()),
+    }
+}
+
+/// Returns the JPEG and RAW files of a directory, for the subcommands organizing a shoot
+///
+/// # Arguments
+/// * `dir` - Directory to walk
+fn photo_source(dir: &Path) -> DirectorySource {
+    DirectorySource {
+        root: dir.to_path_buf(),
+        extensions: DEFAULT_EXTENSIONS
+            .iter()
+            .chain(RAW_EXTENSIONS)
+            .map(|ext| ext.to_string())
+            .collect(),
+        sniff: false,
+    }
+}
+
+/// Asks on standard input whether to install the logo of a brand
+///
+/// # Arguments
+/// * `brand` - Lowercase brand name
+/// * `url` - URL template the logo is downloaded from
+///
+/// # Returns
+/// - `bool`: Whether the user answered yes
+fn confirm_logo(brand: &str, url: &str) -> bool {
+    print!(
+        "Install the logo of '{}' from {}? [y/N] ",
+        brand,
+        url.replace("{brand}", brand)
+    );
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
 }
@@ -0,0 +1,212 @@
+//! MakerNote module
+//!
+//! Many bodies leave the `LensModel` EXIF field empty and only describe the lens in their
+//! MakerNote, a vendor block whose layout differs per brand. This module decodes the lens from
+//! the Canon, Nikon and Sony MakerNotes, as a model name when the body writes one or as the
+//! focal length and aperture range otherwise.
+
+use crate::salvage::TiffReader;
+use exif::{Exif, In, Tag, Value};
+
+/// Header of Nikon type 3 MakerNotes, followed by a version and a TIFF header of their own
+const NIKON_HEADER: &[u8] = b"Nikon\0";
+
+/// Offset of the TIFF header of Nikon type 3 MakerNotes
+const NIKON_TIFF_OFFSET: usize = 10;
+
+/// Headers of Sony MakerNotes, whose IFD follows at offset 12
+const SONY_HEADERS: &[&[u8]] = &[b"SONY DSC \0\0\0", b"SONY CAM \0\0\0"];
+
+/// Canon LensModel tag
+const CANON_LENS_MODEL: u16 = 0x0095;
+
+/// Canon CameraSettings tag, with the focal range at indices 23 to 25
+const CANON_CAMERA_SETTINGS: u16 = 0x0001;
+
+/// Nikon Lens tag: minimum and maximum focal lengths and their maximum apertures
+const NIKON_LENS: u16 = 0x0084;
+
+/// Sony LensSpec tag: BCD-encoded focal lengths and maximum apertures
+const SONY_LENS_SPEC: u16 = 0xb02a;
+
+/// Reads the lens of a photo from the MakerNote of its EXIF
+///
+/// # Arguments
+/// * `exif` - EXIF of the photo
+///
+/// # Returns
+/// * `Option<String>` - Lens model, e.g. `EF100mm f/2.8L Macro IS USM`, or focal and aperture
+///   range, e.g. `24-70mm f/2.8`, `None` if the brand is unsupported or the lens is unknown
+pub fn lens_model(exif: &Exif) -> Option<String> {
+    let make = exif
+        .get_field(Tag::Make, In::PRIMARY)?
+        .display_value()
+        .to_string()
+        .to_lowercase();
+    let Value::Undefined(note, offset) = &exif.get_field(Tag::MakerNote, In::PRIMARY)?.value else {
+        return None;
+    };
+    let tiff = TiffReader::new(exif.buf())?;
+    let offset = *offset as usize;
+    if make.contains("canon") {
+        canon_lens(&tiff, offset)
+    } else if make.contains("nikon") {
+        nikon_lens(note)
+    } else if make.contains("sony") {
+        let header = SONY_HEADERS
+            .iter()
+            .find(|header| note.starts_with(header))?;
+        sony_lens(&tiff, offset + header.len())
+    } else {
+        None
+    }
+}
+
+/// Decodes the lens of a Canon MakerNote, an IFD whose offsets are relative to the EXIF block
+fn canon_lens(tiff: &TiffReader, ifd: usize) -> Option<String> {
+    if let Some(Value::Ascii(parts)) = tiff.find_entry(ifd, CANON_LENS_MODEL) {
+        let model = parts
+            .first()
+            .map(|part| String::from_utf8_lossy(part).trim().to_string())
+            .filter(|model| !model.is_empty());
+        if model.is_some() {
+            return model;
+        }
+    }
+    let Some(Value::Short(settings)) = tiff.find_entry(ifd, CANON_CAMERA_SETTINGS) else {
+        return None;
+    };
+    let (long, short) = (*settings.get(23)?, *settings.get(24)?);
+    let units = settings
+        .get(25)
+        .copied()
+        .filter(|units| *units > 0)
+        .unwrap_or(1);
+    let millimeters = |value: u16| value as f64 / units as f64;
+    format_focal_range(millimeters(short), millimeters(long))
+}
+
+/// Decodes the lens of a Nikon type 3 MakerNote, which embeds a TIFF block of its own
+fn nikon_lens(note: &[u8]) -> Option<String> {
+    if !note.starts_with(NIKON_HEADER) {
+        return None;
+    }
+    let tiff = TiffReader::new(note.get(NIKON_TIFF_OFFSET..)?)?;
+    let Value::Rational(lens) = tiff.find_entry(tiff.first_ifd()?, NIKON_LENS)? else {
+        return None;
+    };
+    let value = |idx: usize| {
+        lens.get(idx)
+            .filter(|part| part.denom != 0)
+            .map(|part| part.to_f64())
+    };
+    let range = format_focal_range(value(0)?, value(1)?)?;
+    Some(match (value(2), value(3)) {
+        (Some(wide), Some(tele)) => format!("{} {}", range, format_aperture_range(wide, tele)),
+        _ => range,
+    })
+}
+
+/// Decodes the lens of a Sony MakerNote, an IFD whose offsets are relative to the EXIF block
+fn sony_lens(tiff: &TiffReader, ifd: usize) -> Option<String> {
+    let spec = match tiff.find_entry(ifd, SONY_LENS_SPEC)? {
+        Value::Byte(spec) | Value::Undefined(spec, _) => spec,
+        _ => return None,
+    };
+    if spec.len() < 8 {
+        return None;
+    }
+    let short = bcd(&spec[1..3])? as f64;
+    let long = bcd(&spec[3..5])? as f64;
+    let range = format_focal_range(short, long)?;
+    let aperture = |byte: u8| bcd(&[byte]).map(|value| value as f64 / 10.0);
+    Some(match (aperture(spec[5]), aperture(spec[6])) {
+        (Some(wide), Some(tele)) if wide > 0.0 && tele > 0.0 => {
+            format!(
+                "{} {}",
+                range,
+                format_aperture_range(wide, tele).replace("f/", "F")
+            )
+        }
+        _ => range,
+    })
+}
+
+/// Decodes big-endian binary-coded decimal digits, `None` if a nibble is not a digit
+fn bcd(bytes: &[u8]) -> Option<u32> {
+    bytes.iter().try_fold(0, |value, &byte| {
+        let (high, low) = (byte >> 4, byte & 0x0f);
+        (high < 10 && low < 10).then_some(value * 100 + high as u32 * 10 + low as u32)
+    })
+}
+
+/// Formats a focal range such as `24-70mm`, or `50mm` for primes, `None` if unknown
+fn format_focal_range(short: f64, long: f64) -> Option<String> {
+    if short <= 0.0 || long < short {
+        return None;
+    }
+    Some(if long == short {
+        format!("{}mm", short)
+    } else {
+        format!("{}-{}mm", short, long)
+    })
+}
+
+/// Formats a maximum aperture range such as `f/2.8` or `f/3.5-5.6`
+fn format_aperture_range(wide: f64, tele: f64) -> String {
+    if wide == tele {
+        format!("f/{}", wide)
+    } else {
+        format!("f/{}-{}", wide, tele)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_makernote_lenses() {
+        // Nikon: header, version, TIFF header and an IFD with the Lens tag
+        let mut note = b"Nikon\0\x02\x10\0\0MM\0*\0\0\0\x08".to_vec();
+        note.extend_from_slice(&1u16.to_be_bytes());
+        note.extend_from_slice(&NIKON_LENS.to_be_bytes());
+        note.extend_from_slice(&5u16.to_be_bytes());
+        note.extend_from_slice(&4u32.to_be_bytes());
+        note.extend_from_slice(&26u32.to_be_bytes());
+        note.extend_from_slice(&0u32.to_be_bytes());
+        for (num, denom) in [(18, 1), (55, 1), (35, 10), (56, 10)] {
+            note.extend_from_slice(&(num as u32).to_be_bytes());
+            note.extend_from_slice(&(denom as u32).to_be_bytes());
+        }
+        assert_eq!(nikon_lens(&note).as_deref(), Some("18-55mm f/3.5-5.6"));
+        assert_eq!(nikon_lens(b"Olympus\0"), None);
+
+        // Canon and Sony: IFDs inside a little endian EXIF block
+        let mut block = b"II*\0\x08\0\0\0".to_vec();
+        block.extend_from_slice(&1u16.to_le_bytes());
+        block.extend_from_slice(&CANON_CAMERA_SETTINGS.to_le_bytes());
+        block.extend_from_slice(&3u16.to_le_bytes());
+        block.extend_from_slice(&26u32.to_le_bytes());
+        block.extend_from_slice(&26u32.to_le_bytes());
+        block.extend_from_slice(&0u32.to_le_bytes());
+        let mut settings = [0u16; 26];
+        settings[23..26].copy_from_slice(&[105, 24, 1]);
+        for value in settings {
+            block.extend_from_slice(&value.to_le_bytes());
+        }
+        let sony_ifd = block.len();
+        block.extend_from_slice(&1u16.to_le_bytes());
+        block.extend_from_slice(&SONY_LENS_SPEC.to_le_bytes());
+        block.extend_from_slice(&1u16.to_le_bytes());
+        block.extend_from_slice(&8u32.to_le_bytes());
+        block.extend_from_slice(&((sony_ifd + 18) as u32).to_le_bytes());
+        block.extend_from_slice(&0u32.to_le_bytes());
+        block.extend_from_slice(&[0, 0x00, 0x70, 0x02, 0x00, 0x28, 0x28, 0]);
+
+        let tiff = TiffReader::new(&block).unwrap();
+        assert_eq!(canon_lens(&tiff, 8).as_deref(), Some("24-105mm"));
+        assert_eq!(sony_lens(&tiff, sony_ifd).as_deref(), Some("70-200mm F2.8"));
+        assert_eq!(sony_lens(&tiff, 8), None);
+    }
+}
@@ -0,0 +1,303 @@
+//! Metadata table module
+//!
+//! This module loads a CSV table mapping file names to camera, lens and shooting values, so
+//! scanned negatives can be framed with the data logged on paper. Values of the table override
+//! or fill the EXIF of the matching file.
+
+use crate::exif::{ExifInfo, UNKNOWN};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+/// Row of the metadata table, empty cells keep the EXIF value
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MetadataRow {
+    /// File name of the image, e.g. `scan_01.jpg`
+    pub filename: String,
+    /// Camera model
+    pub camera: Option<String>,
+    /// Lens model
+    pub lens: Option<String>,
+    /// Focal length in millimeters, e.g. `50`
+    pub focal: Option<String>,
+    /// Aperture f-number, e.g. `2.8`
+    pub aperture: Option<String>,
+    /// Exposure time, e.g. `1/125`
+    pub shutter: Option<String>,
+    /// ISO sensitivity, e.g. `400`
+    pub iso: Option<String>,
+    /// Film stock, e.g. `Kodak Portra 400`
+    pub film_stock: Option<String>,
+    /// Film developer, e.g. `Rodinal 1+50`
+    pub developer: Option<String>,
+    /// Scan resolution, e.g. `3200 dpi`
+    pub scan_resolution: Option<String>,
+    /// Photographer for the attribution line, e.g. `Jane Doe`
+    pub artist: Option<String>,
+    /// Copyright notice for the attribution line
+    pub copyright: Option<String>,
+}
+
+impl MetadataRow {
+    /// Applies the row on top of EXIF information
+    ///
+    /// # Arguments
+    /// * `exif` - EXIF information read from the file, `None` if it has no readable EXIF
+    ///
+    /// # Returns
+    /// * `ExifInfo` - EXIF information with the values of the row
+    pub fn apply(&self, exif: Option<ExifInfo>) -> ExifInfo {
+        let mut exif = self.overrides().apply(exif);
+        for (field, value) in [
+            (&mut exif.artist, &self.artist),
+            (&mut exif.copyright, &self.copyright),
+        ] {
+            if let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+                *field = Some(value.to_string());
+            }
+        }
+        exif
+    }
+
+    /// Returns the camera and shooting values of the row
+    pub fn overrides(&self) -> ExifOverrides {
+        ExifOverrides {
+            camera: self.camera.clone(),
+            lens: self.lens.clone(),
+            focal: self.focal.clone(),
+            aperture: self.aperture.clone(),
+            shutter: self.shutter.clone(),
+            iso: self.iso.clone(),
+        }
+    }
+
+    /// Returns the film fields of the row
+    pub fn film(&self) -> FilmInfo {
+        FilmInfo {
+            stock: self.film_stock.clone(),
+            developer: self.developer.clone(),
+            scan_resolution: self.scan_resolution.clone(),
+        }
+    }
+}
+
+/// Camera and shooting values overriding or filling EXIF, from the command line or a table row
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExifOverrides {
+    /// Camera model
+    pub camera: Option<String>,
+    /// Lens model
+    pub lens: Option<String>,
+    /// Focal length in millimeters, e.g. `50`
+    pub focal: Option<String>,
+    /// Aperture f-number, e.g. `2.8`
+    pub aperture: Option<String>,
+    /// Exposure time, e.g. `1/125`
+    pub shutter: Option<String>,
+    /// ISO sensitivity, e.g. `400`
+    pub iso: Option<String>,
+}
+
+impl ExifOverrides {
+    /// Returns whether no value is set
+    pub fn is_empty(&self) -> bool {
+        self.values().iter().all(|value| value.is_none())
+    }
+
+    /// Applies the values on top of EXIF information, empty values keep the EXIF value
+    ///
+    /// # Arguments
+    /// * `exif` - EXIF information read from the file, `None` if it has no readable EXIF
+    ///
+    /// # Returns
+    /// * `ExifInfo` - EXIF information with the values set here
+    pub fn apply(&self, exif: Option<ExifInfo>) -> ExifInfo {
+        let mut exif = exif.unwrap_or_else(|| ExifInfo {
+            camera_model: UNKNOWN.to_string(),
+            lens_model: UNKNOWN.to_string(),
+            focal_length: UNKNOWN.to_string(),
+            aperture: UNKNOWN.to_string(),
+            shutter_speed: UNKNOWN.to_string(),
+            iso: UNKNOWN.to_string(),
+            gps: None,
+            date_time: None,
+            artist: None,
+            copyright: None,
+        });
+        for (field, value) in [
+            &mut exif.camera_model,
+            &mut exif.lens_model,
+            &mut exif.focal_length,
+            &mut exif.aperture,
+            &mut exif.shutter_speed,
+            &mut exif.iso,
+        ]
+        .into_iter()
+        .zip(self.values())
+        {
+            if let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) {
+                *field = value.to_string();
+            }
+        }
+        exif
+    }
+
+    /// Returns the values in the order of the `ExifInfo` fields
+    fn values(&self) -> [Option<&str>; 6] {
+        [
+            self.camera.as_deref(),
+            self.lens.as_deref(),
+            self.focal.as_deref(),
+            self.aperture.as_deref(),
+            self.shutter.as_deref(),
+            self.iso.as_deref(),
+        ]
+    }
+}
+
+/// Analog fields available to templates as `{film}`, `{developer}` and `{scan}`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilmInfo {
+    /// Film stock, e.g. `Kodak Portra 400`
+    pub stock: Option<String>,
+    /// Film developer, e.g. `Rodinal 1+50`
+    pub developer: Option<String>,
+    /// Scan resolution, e.g. `3200 dpi`
+    pub scan_resolution: Option<String>,
+}
+
+impl FilmInfo {
+    /// Overrides the fields set in another film information
+    ///
+    /// # Arguments
+    /// * `other` - Film information taking precedence, empty fields are ignored
+    pub fn override_with(&mut self, other: &FilmInfo) {
+        for (field, value) in [
+            (&mut self.stock, &other.stock),
+            (&mut self.developer, &other.developer),
+            (&mut self.scan_resolution, &other.scan_resolution),
+        ] {
+            if let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+                *field = Some(value.to_string());
+            }
+        }
+    }
+}
+
+/// Metadata rows indexed by file name
+#[derive(Debug, Clone, Default)]
+pub struct MetadataTable {
+    rows: HashMap<String, MetadataRow>,
+}
+
+impl MetadataTable {
+    /// Loads a metadata table from a CSV file with a header row
+    ///
+    /// The `filename` column is required, the `camera`, `lens`, `focal`, `aperture`, `shutter`,
+    /// `iso`, `film_stock`, `developer` and `scan_resolution` columns are optional.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the CSV file
+    ///
+    /// # Returns
+    /// * `Result<MetadataTable, Box<dyn Error>>` - Loaded table
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, a row cannot be parsed or a row has no
+    /// file name
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_path(path)?;
+        Self::from_reader(reader)
+    }
+
+    fn from_reader<R: std::io::Read>(mut reader: csv::Reader<R>) -> Result<Self, Box<dyn Error>> {
+        let mut rows = HashMap::new();
+        for (idx, row) in reader.deserialize::<MetadataRow>().enumerate() {
+            let row = row?;
+            if row.filename.is_empty() {
+                return Err(format!("Metadata row {} has no file name", idx + 1).into());
+            }
+            rows.insert(row.filename.clone(), row);
+        }
+        Ok(MetadataTable { rows })
+    }
+
+    /// Returns the row of an image
+    ///
+    /// # Arguments
+    /// * `input` - Path to the image file, matched by file name
+    ///
+    /// # Returns
+    /// * `Option<&MetadataRow>` - Row of the image if the table has one
+    pub fn row_for(&self, input: &Path) -> Option<&MetadataRow> {
+        let name = input.file_name()?.to_str()?;
+        self.rows.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_table_overrides_and_fills_exif() {
+        let csv =
+            "filename,camera,lens,iso,artist\nscan_01.jpg,Nikon FM2,Nikkor 50mm f/1.4,,Jane Doe\n";
+        let reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(csv.as_bytes());
+        let table = MetadataTable::from_reader(reader).unwrap();
+        assert!(table.row_for(Path::new("scan_02.jpg")).is_none());
+
+        let row = table.row_for(Path::new("film/scan_01.jpg")).unwrap();
+        let exif = row.apply(None);
+        assert_eq!(exif.camera_model, "Nikon FM2");
+        assert_eq!(exif.lens_model, "Nikkor 50mm f/1.4");
+        assert_eq!(exif.iso, UNKNOWN);
+        assert_eq!(exif.artist.as_deref(), Some("Jane Doe"));
+        assert_eq!(exif.copyright, None);
+
+        let scanned = ExifInfo {
+            iso: "100".to_string(),
+            gps: None,
+            date_time: None,
+            ..exif.clone()
+        };
+        assert_eq!(row.apply(Some(scanned)).iso, "100");
+
+        let overrides = ExifOverrides {
+            lens: Some("Helios 44-2".to_string()),
+            aperture: Some(" ".to_string()),
+            ..ExifOverrides::default()
+        };
+        assert!(!overrides.is_empty());
+        assert!(ExifOverrides::default().is_empty());
+        let merged = overrides.apply(Some(exif.clone()));
+        assert_eq!(merged.camera_model, "Nikon FM2");
+        assert_eq!(merged.lens_model, "Helios 44-2");
+        assert_eq!(merged.aperture, UNKNOWN);
+    }
+
+    #[test]
+    fn test_film_info_override() {
+        let csv = "filename,film_stock,developer\nscan_01.jpg,Kodak Portra 400,\n";
+        let reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(csv.as_bytes());
+        let table = MetadataTable::from_reader(reader).unwrap();
+        let mut film = FilmInfo {
+            stock: Some("Ilford HP5".to_string()),
+            developer: Some("Rodinal 1+50".to_string()),
+            scan_resolution: None,
+        };
+        film.override_with(&table.row_for(Path::new("scan_01.jpg")).unwrap().film());
+        assert_eq!(film.stock.as_deref(), Some("Kodak Portra 400"));
+        assert_eq!(film.developer.as_deref(), Some("Rodinal 1+50"));
+        assert_eq!(film.scan_resolution, None);
+    }
+}
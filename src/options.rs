@@ -0,0 +1,409 @@
+//! Processing options module
+//!
+//! This module defines the options shared by every image processed in a run.
+
+use crate::backdrop::BackdropOptions;
+use crate::config::{BrandStyle, ManualLenses, WatermarkOptOut};
+use crate::dedupe::DuplicateMode;
+use crate::event::EventPack;
+use crate::exif::ExifInheritance;
+use crate::layout::LayoutLock;
+use crate::logo_provider::{LogoChain, LogoProvider};
+use crate::metadata::{ExifOverrides, FilmInfo, MetadataTable};
+use crate::output::{ChromaSubsampling, EncoderEffort, OutputFormat, DEFAULT_JPEG_QUALITY};
+use crate::overlay::OverlaySlot;
+use crate::print::{Length, PaperSize};
+use crate::profile::SourceProfile;
+use crate::proof::PrintProfile;
+use crate::safe_area::SafeArea;
+use crate::sharpness::BlurMode;
+use crate::sink::{FileSink, Sink};
+use crate::source::{Shard, DEFAULT_EXTENSIONS};
+use crate::theme::Theme;
+use crate::video::SlideshowOptions;
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Behavior for images without readable EXIF
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum MissingExif {
+    /// Leave the bar empty
+    #[default]
+    Blank,
+    /// Render a minimal bar with the caption and optionally the file name
+    Placeholder,
+}
+
+/// Behavior for images whose long edge is below the minimum size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SmallImages {
+    /// Upscale the photo to the minimum size, so the bar text fits
+    Scale,
+    /// Keep the photo size and shrink the bar in proportion
+    Compact,
+    /// Leave the image out of the batch and list it in the summary
+    Skip,
+}
+
+/// Background color of the information bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum BarColor {
+    /// Plain white bar
+    #[default]
+    White,
+    /// Average color of the bottom edge of the photo, extending the photo into the bar
+    Edge,
+    /// Warm, cool or dark tint picked from the local capture hour
+    TimeOfDay,
+}
+
+/// Optional element appended to the shooting parameters when EXIF has it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ParamElement {
+    /// GPS altitude, e.g. `Alt 2,345 m` for mountain and drone shots
+    Altitude,
+    /// Water depth written by action and underwater cameras, e.g. `Depth 12.5 m`
+    Depth,
+    /// Exposure compensation, e.g. `+0.7 EV`
+    Ev,
+    /// Whether the flash fired, `Flash`, left out when it didn't
+    Flash,
+    /// White balance mode, e.g. `WB Auto`
+    WhiteBalance,
+    /// Metering mode, e.g. `Metering Spot`
+    Metering,
+}
+
+impl ParamElement {
+    /// Returns the template token holding the value of the element
+    pub fn token(self) -> &'static str {
+        match self {
+            ParamElement::Altitude => "altitude",
+            ParamElement::Depth => "depth",
+            ParamElement::Ev => "ev",
+            ParamElement::Flash => "flash",
+            ParamElement::WhiteBalance => "wb",
+            ParamElement::Metering => "metering",
+        }
+    }
+
+    /// Returns the label written before the value, `None` for values that read on their own
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            ParamElement::Altitude => Some("Alt"),
+            ParamElement::Depth => Some("Depth"),
+            ParamElement::WhiteBalance => Some("WB"),
+            ParamElement::Metering => Some("Metering"),
+            ParamElement::Ev | ParamElement::Flash => None,
+        }
+    }
+}
+
+/// Options controlling how an image is framed
+#[derive(Debug, Clone)]
+pub struct ProcessOptions {
+    /// Height of the information bar in pixels
+    pub info_height: u32,
+    /// Pixel density multiplier of the bar, its text and logos, the photo keeps its size
+    pub scale: u32,
+    /// Whether to force 16:9 aspect ratio
+    pub force_16_9: bool,
+    /// Exact output dimensions to fit the framed photo into, `None` to keep its size
+    pub canvas: Option<(u32, u32)>,
+    /// Look of the padded backdrop
+    pub backdrop: BackdropOptions,
+    /// Optional path to a custom logo file
+    pub logo: Option<PathBuf>,
+    /// Directory with the font files, `None` for the embedded font
+    pub fonts_dir: Option<PathBuf>,
+    /// Source of the brand logos
+    pub logo_provider: Arc<dyn LogoProvider>,
+    /// Optional caption rendered below the shooting parameters
+    pub caption: Option<String>,
+    /// Annotation appended to the lens line, e.g. `via M42 adapter`
+    pub lens_suffix: Option<String>,
+    /// Whether to show the place name of the GPS position below the lens
+    pub show_location: bool,
+    /// Format of the capture date appended to the shooting parameters, `None` to leave it out
+    pub date_format: Option<String>,
+    /// Whether to show the attribution line built from EXIF Copyright or Artist
+    pub show_copyright: bool,
+    /// Copyright notice replacing the EXIF one in the attribution line
+    pub copyright: Option<String>,
+    /// Template of the shooting parameters, `None` for the default
+    /// `{focal}mm | f{aperture} | {shutter}/s | ISO {iso}`
+    pub params_template: Option<String>,
+    /// Elements appended to the shooting parameters when EXIF has them
+    pub param_elements: Vec<ParamElement>,
+    /// Film stock, developer and scan resolution available to templates
+    pub film: FilmInfo,
+    /// Camera and shooting values overriding or filling the EXIF of every photo
+    pub exif_overrides: ExifOverrides,
+    /// Table of values overriding or filling the EXIF of matching files
+    pub metadata: Option<Arc<MetadataTable>>,
+    /// Styles keyed by lowercase camera brand
+    pub brand_styles: Arc<HashMap<String, BrandStyle>>,
+    /// Lens labels applied to photos taken with a manual lens
+    pub manual_lenses: ManualLenses,
+    /// Rules disabling the caption, overlay text and secondary logo of matching photos
+    pub watermark_opt_out: Arc<Vec<WatermarkOptOut>>,
+    /// Whether to multiply the focal length and aperture by a detected teleconverter
+    pub apply_teleconverter: bool,
+    /// EXIF memory used to inherit missing fields from the previous file, `None` to disable
+    pub exif_inheritance: Option<ExifInheritance>,
+    /// Whether to salvage the readable fields of malformed EXIF blocks
+    pub tolerant_exif: bool,
+    /// Whether to fill missing EXIF fields from a same-stem RAW or HEIC file
+    pub companion_exif: bool,
+    /// Templates drawn over the photo instead of the bar, `None` for the bar layout
+    pub overlay: Option<Vec<(OverlaySlot, String)>>,
+    /// Event frames layered over the outputs in rotation, `None` to disable
+    pub event_pack: Option<Arc<EventPack>>,
+    /// Background color of the information bar
+    pub bar_color: BarColor,
+    /// Whether to draw the layout boxes, margins and rule-of-thirds guides on the output
+    pub debug_layout: bool,
+    /// Platform whose safe area guides are drawn over the output, `None` to disable
+    pub safe_area: Option<SafeArea>,
+    /// Bleed added around the output for lab printing, `None` for no bleed
+    pub bleed: Option<Length>,
+    /// Whether to draw crop marks outside the bleed
+    pub crop_marks: bool,
+    /// Print resolution written to JPEG outputs and used to convert lengths, `None` to leave
+    /// the resolution unset
+    pub dpi: Option<u16>,
+    /// Paper size the canvas is sized to at the print resolution, `None` to use `canvas`
+    pub paper: Option<PaperSize>,
+    /// Printer profile the outputs are converted to and tagged with, `None` to keep sRGB
+    pub print_profile: Option<Arc<PrintProfile>>,
+    /// Behavior for images without readable EXIF
+    pub missing_exif: MissingExif,
+    /// Whether the placeholder bar shows the file name
+    pub placeholder_filename: bool,
+    /// Extensions of the files picked up from an input directory
+    pub extensions: Vec<String>,
+    /// Whether to also pick up directory files whose content matches one of the extensions
+    pub sniff: bool,
+    /// Part of the batch processed by this run, `None` to process every image
+    pub shard: Option<Shard>,
+    /// Behavior for images below the minimum size, `None` to frame them as they are
+    pub small_images: Option<SmallImages>,
+    /// Minimum long edge in pixels for images to be framed as they are
+    pub min_size: u32,
+    /// Visual style of the information bar
+    pub theme: Theme,
+    /// Format the outputs of a batch are converted to, `None` to keep the input extensions
+    pub convert_to: Option<OutputFormat>,
+    /// Format the final image is encoded in, `None` to follow the output file extension
+    pub output_format: Option<OutputFormat>,
+    /// Effort of the PNG, lossy WebP and AVIF encoders
+    pub effort: EncoderEffort,
+    /// Chroma subsampling of JPEG output
+    pub chroma_subsampling: ChromaSubsampling,
+    /// Quality of JPEG, lossy WebP and AVIF output, 1 to 100
+    pub quality: u8,
+    /// Whether WebP outputs are encoded lossy at `quality` instead of lossless
+    pub lossy_webp: bool,
+    /// Long edges of additional downscaled exports written next to the output
+    pub export_sizes: Vec<u32>,
+    /// Whether to encode the export sizes one after the other instead of in parallel
+    pub low_priority: bool,
+    /// Whether to check the destination has room for the outputs before a batch starts
+    pub space_check: bool,
+    /// Whether to refuse a batch whose outputs overlap its inputs
+    pub paranoid: bool,
+    /// Whether to also export the bar alone as a transparent PNG layer
+    pub bar_layer: bool,
+    /// Whether to render only the bar instead of the framed photo
+    pub bar_only: bool,
+    /// Width of the bar in bar-only mode, defaults to the photo width
+    pub bar_width: Option<u32>,
+    /// Layout shared by all frames of a sequence, `None` to lay out every image on its own
+    pub layout_lock: Option<LayoutLock>,
+    /// Slideshow video assembled from the outputs of a directory, `None` to skip
+    pub slideshow: Option<SlideshowOptions>,
+    /// Destination of the encoded outputs
+    pub sink: Arc<dyn Sink>,
+    /// Whether to warn about photos with a gamut wider than sRGB
+    pub gamut_check: bool,
+    /// Whether to apply automatic levels to the photo before framing
+    pub enhance: bool,
+    /// Handling of near-duplicate frames in a batch, `None` to process every frame
+    pub duplicates: Option<DuplicateMode>,
+    /// Maximum number of differing hash bits between near-duplicates
+    pub duplicate_threshold: u32,
+    /// Handling of blurry frames in a batch, `None` to skip the sharpness check
+    pub blur_check: Option<BlurMode>,
+    /// Sharpness score below which a frame counts as blurry
+    pub sharpness_threshold: f64,
+    /// Whether to decode every photo of a batch before processing and set aside broken ones
+    pub verify_decode: bool,
+    /// Whether to copy the EXIF of the input into JPEG outputs
+    pub keep_exif: bool,
+    /// Whether to also copy the XMP packet of the input into JPEG outputs
+    pub keep_xmp: bool,
+    /// Whether to leave the GPS position, serial numbers and owner name out of the copied EXIF
+    pub strip_private: bool,
+    /// Editor the inputs were exported from, `None` for files straight from the camera
+    pub source_profile: Option<SourceProfile>,
+}
+
+impl Default for ProcessOptions {
+    fn default() -> Self {
+        ProcessOptions {
+            info_height: 180,
+            scale: 1,
+            force_16_9: false,
+            canvas: None,
+            backdrop: BackdropOptions::default(),
+            logo: None,
+            fonts_dir: Some(PathBuf::from("./fonts")),
+            logo_provider: Arc::new(LogoChain::standard(Some(PathBuf::from("./logos")))),
+            caption: None,
+            lens_suffix: None,
+            show_location: false,
+            date_format: None,
+            show_copyright: false,
+            copyright: None,
+            params_template: None,
+            param_elements: Vec::new(),
+            film: FilmInfo::default(),
+            exif_overrides: ExifOverrides::default(),
+            metadata: None,
+            brand_styles: Arc::default(),
+            manual_lenses: ManualLenses::default(),
+            watermark_opt_out: Arc::default(),
+            apply_teleconverter: false,
+            exif_inheritance: None,
+            tolerant_exif: false,
+            companion_exif: false,
+            overlay: None,
+            event_pack: None,
+            bar_color: BarColor::White,
+            debug_layout: false,
+            safe_area: None,
+            bleed: None,
+            crop_marks: false,
+            dpi: None,
+            paper: None,
+            print_profile: None,
+            missing_exif: MissingExif::Blank,
+            placeholder_filename: false,
+            extensions: DEFAULT_EXTENSIONS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect(),
+            sniff: false,
+            shard: None,
+            small_images: None,
+            min_size: 600,
+            theme: Theme::default(),
+            convert_to: None,
+            output_format: None,
+            effort: EncoderEffort::Default,
+            chroma_subsampling: ChromaSubsampling::Yuv420,
+            quality: DEFAULT_JPEG_QUALITY,
+            lossy_webp: false,
+            export_sizes: Vec::new(),
+            low_priority: false,
+            space_check: true,
+            paranoid: false,
+            bar_layer: false,
+            bar_only: false,
+            bar_width: None,
+            layout_lock: None,
+            slideshow: None,
+            sink: Arc::new(FileSink),
+            gamut_check: false,
+            enhance: false,
+            duplicates: None,
+            duplicate_threshold: 6,
+            blur_check: None,
+            sharpness_threshold: 100.0,
+            verify_decode: false,
+            keep_exif: false,
+            keep_xmp: false,
+            strip_private: false,
+            source_profile: None,
+        }
+    }
+}
+
+impl ProcessOptions {
+    /// Returns whether the caption, parameters or overlay templates use the weather tokens
+    pub fn uses_weather(&self) -> bool {
+        let overlay = self.overlay.iter().flatten().map(|(_, template)| template);
+        let templates = self
+            .caption
+            .iter()
+            .chain(&self.params_template)
+            .chain(overlay);
+        crate::template::uses_weather(templates.map(String::as_str))
+    }
+
+    /// Returns whether to look up the weather of the photos
+    ///
+    /// The GPS position leaves the machine only when a template uses the weather tokens, and
+    /// never with `strip_private` or `paranoid`.
+    pub fn weather_lookup(&self) -> bool {
+        self.uses_weather() && !self.strip_private && !self.paranoid
+    }
+
+    /// Returns whether the photo is composited at 16 bits per channel for 16-bit output
+    pub fn sixteen_bit(&self) -> bool {
+        [self.output_format, self.convert_to].contains(&Some(OutputFormat::Tiff16))
+    }
+
+    /// Returns the options with the pixel sizes of the bar multiplied by the density `scale`
+    ///
+    /// The bar height, logo gap and width limit and the text effects are scaled here, the
+    /// spacing built into the renderer is scaled there. Apply once, right before rendering.
+    pub fn scaled(&self) -> ProcessOptions {
+        let scale = self.scale.max(1);
+        let mut scaled = self.clone();
+        scaled.info_height = self.info_height * scale;
+        let theme = &mut scaled.theme;
+        theme.logo_gap *= scale;
+        theme.logo_max_width = theme.logo_max_width.map(|width| width * scale);
+        theme.baseline_offset *= scale as i32;
+        if let Some(shadow) = &mut theme.text_shadow {
+            shadow.offset_x *= scale as i32;
+            shadow.offset_y *= scale as i32;
+            shadow.blur *= scale as f32;
+        }
+        if let Some(outline) = &mut theme.text_outline {
+            outline.width *= scale;
+        }
+        if let Some(pill) = &mut theme.text_pill {
+            pill.padding *= scale;
+        }
+        scaled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::TextOutline;
+
+    #[test]
+    fn test_scaled() {
+        let mut options = ProcessOptions {
+            scale: 2,
+            ..ProcessOptions::default()
+        };
+        options.theme.logo_max_width = Some(300);
+        options.theme.text_outline = Some(TextOutline {
+            width: 2,
+            color: image::Rgba([0, 0, 0, 255]),
+        });
+        let scaled = options.scaled();
+        assert_eq!(scaled.info_height, 360);
+        assert_eq!(scaled.theme.logo_gap, 48);
+        assert_eq!(scaled.theme.logo_max_width, Some(600));
+        assert_eq!(scaled.theme.text_outline.unwrap().width, 4);
+        assert_eq!(ProcessOptions::default().scaled().info_height, 180);
+    }
+}
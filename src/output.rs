@@ -0,0 +1,779 @@
+//! Output module
+//!
+//! This module encodes the final image and hands it to the output sink.
+
+use crate::options::ProcessOptions;
+use clap::ValueEnum;
+use image::codecs::png::{CompressionType, FilterType as PngFilterType, PngEncoder};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use jpeg_encoder::{ColorType, Encoder, PixelDensity, SamplingFactor};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// JPEG extensions the `image` crate doesn't map to a format
+const JPEG_ALIASES: &[&str] = &["jpe", "jfif"];
+
+/// Default JPEG quality, matching the quality previously used by the `image` crate
+pub const DEFAULT_JPEG_QUALITY: u8 = 75;
+
+/// Chroma subsampling of JPEG output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ChromaSubsampling {
+    /// Full chroma resolution, keeps fine text on the info bar crisp
+    #[value(name = "4:4:4")]
+    Yuv444,
+    /// Half horizontal chroma resolution
+    #[value(name = "4:2:2")]
+    Yuv422,
+    /// Half horizontal and vertical chroma resolution, smallest files, as in earlier releases
+    #[default]
+    #[value(name = "4:2:0")]
+    Yuv420,
+}
+
+impl ChromaSubsampling {
+    /// Returns the matching sampling factor of the JPEG encoder
+    fn sampling_factor(self) -> SamplingFactor {
+        match self {
+            ChromaSubsampling::Yuv444 => SamplingFactor::R_4_4_4,
+            ChromaSubsampling::Yuv422 => SamplingFactor::R_4_2_2,
+            ChromaSubsampling::Yuv420 => SamplingFactor::R_4_2_0,
+        }
+    }
+}
+
+/// Effort of the PNG, lossy WebP and AVIF encoders, trading encoding time for file size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum EncoderEffort {
+    /// Fastest encoding, largest files
+    Fast,
+    /// Balance of encoding time and file size
+    #[default]
+    Default,
+    /// Smallest files, slowest encoding
+    Best,
+}
+
+impl EncoderEffort {
+    /// Returns the matching compression and filter of the PNG encoder
+    fn png_settings(self) -> (CompressionType, PngFilterType) {
+        match self {
+            EncoderEffort::Fast => (CompressionType::Fast, PngFilterType::NoFilter),
+            EncoderEffort::Default => (CompressionType::Default, PngFilterType::Adaptive),
+            EncoderEffort::Best => (CompressionType::Best, PngFilterType::Adaptive),
+        }
+    }
+
+    /// Returns the matching method of the WebP encoder, from 0 (fastest) to 6 (smallest)
+    #[cfg(feature = "lossy-webp")]
+    fn webp_method(self) -> i32 {
+        match self {
+            EncoderEffort::Fast => 1,
+            EncoderEffort::Default => 4,
+            EncoderEffort::Best => 6,
+        }
+    }
+
+    /// Returns the matching speed of the AVIF encoder, from 1 (smallest) to 10 (fastest)
+    #[cfg(feature = "avif")]
+    fn avif_speed(self) -> u8 {
+        match self {
+            EncoderEffort::Fast => 9,
+            EncoderEffort::Default => 6,
+            EncoderEffort::Best => 3,
+        }
+    }
+}
+
+/// Format every output of a batch is converted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// JPEG with the configured chroma subsampling
+    #[value(name = "jpg")]
+    Jpeg,
+    /// Lossless PNG
+    Png,
+    /// Lossless WebP, or lossy at the configured quality with `--lossy-webp`
+    Webp,
+    /// AVIF at the configured quality (requires the `avif` feature)
+    Avif,
+    /// 16-bit TIFF for print, composited at 16 bits so the backdrop gradients don't band
+    #[value(name = "tiff16")]
+    Tiff16,
+}
+
+impl OutputFormat {
+    /// Returns the file extension of the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Png => "png",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Avif => "avif",
+            OutputFormat::Tiff16 => "tif",
+        }
+    }
+
+    /// Returns the matching format of the `image` crate
+    pub fn image_format(self) -> ImageFormat {
+        match self {
+            OutputFormat::Jpeg => ImageFormat::Jpeg,
+            OutputFormat::Png => ImageFormat::Png,
+            OutputFormat::Webp => ImageFormat::WebP,
+            OutputFormat::Avif => ImageFormat::Avif,
+            OutputFormat::Tiff16 => ImageFormat::Tiff,
+        }
+    }
+}
+
+/// Metadata of the input embedded into JPEG outputs
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddedMetadata {
+    /// EXIF block starting with the TIFF header
+    pub exif: Option<Vec<u8>>,
+    /// XMP packet
+    pub xmp: Option<String>,
+}
+
+/// Saves the final image in the output format, picking it from the output file extension if unset
+///
+/// # Arguments
+/// * `img` - Image to save
+/// * `output` - Output file path
+/// * `options` - Processing options
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - Ok if successful
+///
+/// # Errors
+/// Returns an error if the format is unknown, the image cannot be encoded or the sink fails
+pub fn save_image(
+    img: &DynamicImage,
+    output: &Path,
+    options: &ProcessOptions,
+) -> Result<(), Box<dyn Error>> {
+    save_image_with(img, output, &EmbeddedMetadata::default(), options)
+}
+
+/// Saves the final image like `save_image`, embedding metadata if the output is a JPEG file
+///
+/// # Arguments
+/// * `img` - Image to save
+/// * `output` - Output file path
+/// * `metadata` - Metadata to embed
+/// * `options` - Processing options
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - Ok if successful
+///
+/// # Errors
+/// Returns an error if the format is unknown, the image cannot be encoded or the sink fails
+pub fn save_image_with(
+    img: &DynamicImage,
+    output: &Path,
+    metadata: &EmbeddedMetadata,
+    options: &ProcessOptions,
+) -> Result<(), Box<dyn Error>> {
+    let format = output_format(output, options)?;
+    write_image(img, output, format, metadata, options)
+}
+
+/// Returns the format an output is encoded in, forced by `--output-format` or following the
+/// file extension
+///
+/// # Arguments
+/// * `output` - Output file path
+/// * `options` - Processing options
+///
+/// # Returns
+/// * `Result<ImageFormat, Box<dyn Error>>` - Format of the output
+///
+/// # Errors
+/// Returns an error if the extension is not an image format
+pub fn output_format(
+    output: &Path,
+    options: &ProcessOptions,
+) -> Result<ImageFormat, Box<dyn Error>> {
+    match (options.output_format, ImageFormat::from_path(output)) {
+        (Some(format), _) => Ok(format.image_format()),
+        (None, Ok(format)) => Ok(format),
+        (None, Err(_))
+            if output.extension().is_some_and(|ext| {
+                JPEG_ALIASES
+                    .iter()
+                    .any(|alias| ext.eq_ignore_ascii_case(alias))
+            }) =>
+        {
+            Ok(ImageFormat::Jpeg)
+        }
+        (None, Err(e)) => Err(e.into()),
+    }
+}
+
+/// Saves an image in the given format, whatever the output file extension
+///
+/// # Arguments
+/// * `img` - Image to save
+/// * `output` - Output file path
+/// * `format` - Format to encode
+/// * `options` - Processing options
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - Ok if successful
+///
+/// # Errors
+/// Returns an error if the image cannot be encoded or the sink fails
+pub fn save_image_as(
+    img: &DynamicImage,
+    output: &Path,
+    format: ImageFormat,
+    options: &ProcessOptions,
+) -> Result<(), Box<dyn Error>> {
+    write_image(img, output, format, &EmbeddedMetadata::default(), options)
+}
+
+/// Encodes an image in the given format and hands it to the sink
+fn write_image(
+    img: &DynamicImage,
+    output: &Path,
+    format: ImageFormat,
+    metadata: &EmbeddedMetadata,
+    options: &ProcessOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut data = Vec::new();
+    if format == ImageFormat::Tiff && options.sixteen_bit() {
+        DynamicImage::ImageRgb16(img.to_rgb16()).write_to(&mut Cursor::new(&mut data), format)?;
+        return options.sink.write(output, &data);
+    }
+    if format == ImageFormat::Png {
+        let (compression, filter) = options.effort.png_settings();
+        let encoder = PngEncoder::new_with_quality(&mut data, compression, filter);
+        img.write_with_encoder(encoder)?;
+        return options.sink.write(output, &data);
+    }
+    if format == ImageFormat::WebP && options.lossy_webp {
+        let data = encode_webp(img, options)?;
+        return options.sink.write(output, &data);
+    }
+    if format == ImageFormat::Avif {
+        let data = encode_avif(img, options)?;
+        return options.sink.write(output, &data);
+    }
+    if format != ImageFormat::Jpeg {
+        img.write_to(&mut Cursor::new(&mut data), format)?;
+        return options.sink.write(output, &data);
+    }
+
+    let rgb = img.to_rgb8();
+    let width = u16::try_from(rgb.width()).map_err(|_| "Image is too wide for JPEG output")?;
+    let height = u16::try_from(rgb.height()).map_err(|_| "Image is too tall for JPEG output")?;
+    let mut encoder = Encoder::new(&mut data, options.quality);
+    encoder.set_sampling_factor(options.chroma_subsampling.sampling_factor());
+    if let Some(dpi) = options.dpi {
+        encoder.set_density(PixelDensity::dpi(dpi));
+    }
+    if let Some(profile) = &options.print_profile {
+        if encoder.add_icc_profile(&profile.data).is_err() {
+            println!("[WARN] Print profile is too large to embed, leaving it out");
+        }
+    }
+    if let Some(exif) = &metadata.exif {
+        if encoder.add_exif_metadata(exif).is_err() {
+            println!("[WARN] EXIF is too large for a JPEG segment, leaving it out");
+        }
+    }
+    if let Some(xmp) = &metadata.xmp {
+        let segment = [crate::jpeg::XMP_SIGNATURE, xmp.as_bytes()].concat();
+        if encoder.add_app_segment(1, segment).is_err() {
+            println!("[WARN] XMP is too large for a JPEG segment, leaving it out");
+        }
+    }
+    encoder.encode(rgb.as_raw(), width, height, ColorType::Rgb)?;
+    options.sink.write(output, &data)
+}
+
+/// Encodes an image as lossy WebP at the configured quality and effort
+#[cfg(feature = "lossy-webp")]
+fn encode_webp(img: &DynamicImage, options: &ProcessOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (width, height) = img.dimensions();
+    let pixels;
+    let encoder = if img.color().has_alpha() {
+        pixels = img.to_rgba8().into_raw();
+        webp::Encoder::from_rgba(&pixels, width, height)
+    } else {
+        pixels = img.to_rgb8().into_raw();
+        webp::Encoder::from_rgb(&pixels, width, height)
+    };
+    let mut config = webp::WebPConfig::new().map_err(|_| "Cannot configure the WebP encoder")?;
+    config.quality = options.quality as f32;
+    config.method = options.effort.webp_method();
+    let data = encoder
+        .encode_advanced(&config)
+        .map_err(|e| format!("WebP encoding failed: {:?}", e))?;
+    Ok(data.to_vec())
+}
+
+/// Encodes an image as lossy WebP, which requires the `lossy-webp` feature
+#[cfg(not(feature = "lossy-webp"))]
+fn encode_webp(_: &DynamicImage, _: &ProcessOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+    Err("Lossy WebP output requires a build with the `lossy-webp` feature".into())
+}
+
+/// Encodes an image as AVIF at the configured quality and effort
+#[cfg(feature = "avif")]
+fn encode_avif(img: &DynamicImage, options: &ProcessOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let encoder = ravif::Encoder::new()
+        .with_quality(options.quality as f32)
+        .with_alpha_quality(options.quality as f32)
+        .with_speed(options.effort.avif_speed());
+    let encoded = if img.color().has_alpha() {
+        let pixels: Vec<ravif::RGBA8> = img
+            .to_rgba8()
+            .pixels()
+            .map(|p| ravif::RGBA8::new(p[0], p[1], p[2], p[3]))
+            .collect();
+        encoder.encode_rgba(ravif::Img::new(&pixels[..], width, height))?
+    } else {
+        let pixels: Vec<ravif::RGB8> = img
+            .to_rgb8()
+            .pixels()
+            .map(|p| ravif::RGB8::new(p[0], p[1], p[2]))
+            .collect();
+        encoder.encode_rgb(ravif::Img::new(&pixels[..], width, height))?
+    };
+    Ok(encoded.avif_file)
+}
+
+/// Encodes an image as AVIF, which requires the `avif` feature
+#[cfg(not(feature = "avif"))]
+fn encode_avif(_: &DynamicImage, _: &ProcessOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+    Err("AVIF output requires a build with the `avif` feature".into())
+}
+
+/// Saves the final image and its additional export sizes
+///
+/// Every size is resized from the same composited image and encoded on its own thread, since
+/// encoding dominates the time spent per output. Low-priority runs encode them one after the
+/// other instead.
+///
+/// # Arguments
+/// * `img` - Image to save
+/// * `output` - Output file path of the full-size image
+/// * `metadata` - Metadata embedded into every JPEG output
+/// * `options` - Processing options, including the export sizes
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - Ok if every output was saved
+///
+/// # Errors
+/// Returns the first error if any output cannot be encoded or written
+pub fn save_outputs(
+    img: &DynamicImage,
+    output: &Path,
+    metadata: &EmbeddedMetadata,
+    options: &ProcessOptions,
+) -> Result<(), Box<dyn Error>> {
+    if options.export_sizes.is_empty() {
+        return save_image_with(img, output, metadata, options);
+    }
+    let save_full = || {
+        save_image_with(img, output, metadata, options)
+            .map_err(|e| format!("{}: {}", output.display(), e))
+    };
+    let save_size = |size: u32| {
+        let path = sized_output_path(output, size);
+        let (width, height) = img.dimensions();
+        let result = if width.max(height) > size {
+            let resized = img.resize(size, size, FilterType::Lanczos3);
+            save_image_with(&resized, &path, metadata, options)
+        } else {
+            save_image_with(img, &path, metadata, options)
+        };
+        result.map_err(|e| format!("{}: {}", path.display(), e))
+    };
+
+    let results: Vec<Result<(), String>> = if options.low_priority {
+        std::iter::once(save_full())
+            .chain(options.export_sizes.iter().map(|&size| save_size(size)))
+            .collect()
+    } else {
+        std::thread::scope(|scope| {
+            let mut handles = vec![scope.spawn(save_full)];
+            for &size in &options.export_sizes {
+                handles.push(scope.spawn(move || save_size(size)));
+            }
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|_| Err("Encoder thread panicked".to_string()))
+                })
+                .collect()
+        })
+    };
+    for result in results {
+        result?;
+    }
+    println!(
+        "[INFO] Exported {} additional size(s) of {}",
+        options.export_sizes.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Returns the path of an additional export size written next to an output
+///
+/// # Arguments
+/// * `output` - Output file path
+/// * `size` - Long edge of the export in pixels
+///
+/// # Returns
+/// * `PathBuf` - Export path, e.g. `photo_1080.jpg` for `photo.jpg`
+pub fn sized_output_path(output: &Path, size: u32) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    match output.extension() {
+        Some(ext) => output.with_file_name(format!("{}_{}.{}", stem, size, ext.to_string_lossy())),
+        None => output.with_file_name(format!("{}_{}", stem, size)),
+    }
+}
+
+/// Checks that no additional export size overwrites an input or another output
+///
+/// The `photo_1080.jpg` export of `photo.jpg` collides with an input or output of that name,
+/// and a size listed twice collides with itself.
+///
+/// # Arguments
+/// * `inputs` - Input file paths
+/// * `outputs` - Output file paths of the full-size images
+/// * `export_sizes` - Long edges of the additional exports
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - Ok if every export has a path of its own
+///
+/// # Errors
+/// Returns an error naming the first export that would overwrite another file
+pub fn check_export_paths(
+    inputs: &[&Path],
+    outputs: &[&Path],
+    export_sizes: &[u32],
+) -> Result<(), Box<dyn Error>> {
+    if export_sizes.is_empty() {
+        return Ok(());
+    }
+    let mut taken = HashMap::new();
+    for input in inputs {
+        taken.insert(
+            crate::paranoid::resolve(input)?,
+            format!("the input {}", input.display()),
+        );
+    }
+    for output in outputs {
+        taken.insert(
+            crate::paranoid::resolve(output)?,
+            format!("the output {}", output.display()),
+        );
+    }
+    for output in outputs {
+        for &size in export_sizes {
+            let path = sized_output_path(output, size);
+            let export = format!("{}px export of {}", size, output.display());
+            let owner = format!("the {}", export);
+            if let Some(other) = taken.insert(crate::paranoid::resolve(&path)?, owner) {
+                return Err(format!("The {} would overwrite {}", export, other).into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the path of the transparent bar layer written next to an output
+///
+/// # Arguments
+/// * `output` - Output file path
+///
+/// # Returns
+/// * `PathBuf` - Layer path, e.g. `photo_bar.png` for `photo.jpg`
+pub fn bar_layer_path(output: &Path) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    output.with_file_name(format!("{}_bar.png", stem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Guesses the format of encoded data, including AVIF which `image` cannot detect
+    #[cfg(feature = "avif")]
+    fn guess_format(data: &[u8]) -> ImageFormat {
+        if data.get(4..12) == Some(b"ftypavif") {
+            return ImageFormat::Avif;
+        }
+        image::guess_format(data).unwrap()
+    }
+
+    #[test]
+    fn test_save_jpeg_with_subsampling() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.jpg");
+        let img = DynamicImage::new_rgb8(64, 48);
+        assert_eq!(
+            ProcessOptions::default().chroma_subsampling,
+            ChromaSubsampling::Yuv420
+        );
+        let options = ProcessOptions {
+            chroma_subsampling: ChromaSubsampling::Yuv444,
+            ..ProcessOptions::default()
+        };
+        save_image(&img, &output, &options).unwrap();
+        assert_eq!(image::open(&output).unwrap().dimensions(), (64, 48));
+
+        // A higher quality keeps more detail, so a noisy image grows
+        let noise = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 48, |x, y| {
+            image::Rgb([(x * 37 + y * 91) as u8, (x * y) as u8, ((x ^ y) * 8) as u8])
+        }));
+        let mut sizes = Vec::new();
+        for quality in [40, 95] {
+            let options = ProcessOptions {
+                quality,
+                ..ProcessOptions::default()
+            };
+            save_image(&noise, &output, &options).unwrap();
+            sizes.push(std::fs::metadata(&output).unwrap().len());
+        }
+        assert!(sizes[0] < sizes[1]);
+    }
+
+    #[test]
+    fn test_save_format_follows_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let img = DynamicImage::new_rgb8(32, 16);
+        for (name, format) in [
+            ("out.webp", ImageFormat::WebP),
+            ("out.png", ImageFormat::Png),
+            ("out.jfif", ImageFormat::Jpeg),
+        ] {
+            let output = dir.path().join(name);
+            save_image(&img, &output, &ProcessOptions::default()).unwrap();
+            let data = std::fs::read(&output).unwrap();
+            assert_eq!(image::guess_format(&data).unwrap(), format);
+        }
+
+        // WebP stays lossless unless lossy output is asked for
+        let noise = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 48, |x, y| {
+            image::Rgb([(x * 37 + y * 91) as u8, (x * y) as u8, ((x ^ y) * 8) as u8])
+        }));
+        let output = dir.path().join("lossless.webp");
+        save_image(&noise, &output, &ProcessOptions::default()).unwrap();
+        assert_eq!(image::open(&output).unwrap().to_rgb8(), noise.to_rgb8());
+
+        #[cfg(not(feature = "avif"))]
+        assert!(save_image(
+            &img,
+            &dir.path().join("out.avif"),
+            &ProcessOptions::default()
+        )
+        .is_err());
+        #[cfg(not(feature = "lossy-webp"))]
+        {
+            let options = ProcessOptions {
+                lossy_webp: true,
+                ..ProcessOptions::default()
+            };
+            assert!(save_image(&img, &dir.path().join("out.webp"), &options).is_err());
+        }
+    }
+
+    #[cfg(all(feature = "avif", feature = "lossy-webp"))]
+    #[test]
+    fn test_save_lossy_webp_and_avif() {
+        let dir = tempfile::tempdir().unwrap();
+        let noise = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 48, |x, y| {
+            image::Rgb([(x * 37 + y * 91) as u8, (x * y) as u8, ((x ^ y) * 8) as u8])
+        }));
+        for (name, format) in [
+            ("out.webp", ImageFormat::WebP),
+            ("out.avif", ImageFormat::Avif),
+        ] {
+            let output = dir.path().join(name);
+            let mut sizes = Vec::new();
+            for quality in [30, 90] {
+                let options = ProcessOptions {
+                    quality,
+                    lossy_webp: true,
+                    effort: EncoderEffort::Fast,
+                    ..ProcessOptions::default()
+                };
+                save_image(&noise, &output, &options).unwrap();
+                let data = std::fs::read(&output).unwrap();
+                assert_eq!(guess_format(&data), format);
+                sizes.push(data.len());
+            }
+            assert!(sizes[0] < sizes[1], "{}: {:?}", name, sizes);
+        }
+    }
+
+    #[test]
+    fn test_save_forced_output_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let img = DynamicImage::new_rgb8(32, 16);
+        for (format, expected) in [
+            (OutputFormat::Webp, ImageFormat::WebP),
+            (OutputFormat::Png, ImageFormat::Png),
+        ] {
+            let output = dir.path().join("out.jpg");
+            let options = ProcessOptions {
+                output_format: Some(format),
+                effort: EncoderEffort::Best,
+                ..ProcessOptions::default()
+            };
+            save_image(&img, &output, &options).unwrap();
+            let data = std::fs::read(&output).unwrap();
+            assert_eq!(image::guess_format(&data).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_save_with_metadata() {
+        use exif::{Field, In, Tag, Value};
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.jpg");
+        let mut writer = exif::experimental::Writer::new();
+        let fields = [
+            Field {
+                tag: Tag::Model,
+                ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![b"X100V".to_vec()]),
+            },
+            Field {
+                tag: Tag::Orientation,
+                ifd_num: In::PRIMARY,
+                value: Value::Short(vec![6]),
+            },
+            Field {
+                tag: Tag::BodySerialNumber,
+                ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![b"0123456".to_vec()]),
+            },
+            Field {
+                tag: Tag::GPSLatitudeRef,
+                ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![b"N".to_vec()]),
+            },
+            Field {
+                tag: Tag::MakerNote,
+                ifd_num: In::PRIMARY,
+                value: Value::Undefined(b"Nikon\0".to_vec(), 0),
+            },
+        ];
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let mut plain = Vec::new();
+        DynamicImage::new_rgb8(16, 8)
+            .write_to(&mut Cursor::new(&mut plain), ImageFormat::Jpeg)
+            .unwrap();
+        std::fs::write(
+            &input,
+            crate::jpeg::with_exif(&plain, tiff.get_ref()).unwrap(),
+        )
+        .unwrap();
+
+        let metadata = EmbeddedMetadata {
+            exif: crate::exif::output_exif(&input, false),
+            xmp: Some("<x:xmpmeta/>".to_string()),
+        };
+        let output = dir.path().join("out.jpg");
+        let img = DynamicImage::new_rgb8(32, 16);
+        save_image_with(&img, &output, &metadata, &ProcessOptions::default()).unwrap();
+        let exif = crate::exif::read_exif(&output).unwrap();
+        assert_eq!(
+            crate::exif::read_text_field(&output, Tag::Model).as_deref(),
+            Some("X100V")
+        );
+        assert!(exif.get_field(Tag::Orientation, In::PRIMARY).is_none());
+        assert!(exif.get_field(Tag::BodySerialNumber, In::PRIMARY).is_some());
+        // Maker notes cannot be moved without breaking their offsets
+        assert!(exif.get_field(Tag::MakerNote, In::PRIMARY).is_none());
+        let data = std::fs::read(&output).unwrap();
+        assert_eq!(
+            crate::jpeg::xmp_packet(&data).as_deref(),
+            Some("<x:xmpmeta/>")
+        );
+
+        // Privacy mode keeps the exposure data but not the serial number and position
+        let private = EmbeddedMetadata {
+            exif: crate::exif::output_exif(&input, true),
+            xmp: None,
+        };
+        save_image_with(&img, &output, &private, &ProcessOptions::default()).unwrap();
+        let exif = crate::exif::read_exif(&output).unwrap();
+        assert!(exif.get_field(Tag::Model, In::PRIMARY).is_some());
+        assert!(exif.get_field(Tag::BodySerialNumber, In::PRIMARY).is_none());
+        assert!(exif.get_field(Tag::GPSLatitudeRef, In::PRIMARY).is_none());
+
+        // Other formats are written without metadata
+        let png = dir.path().join("out.png");
+        save_image_with(&img, &png, &metadata, &ProcessOptions::default()).unwrap();
+        assert!(crate::exif::read_exif(&png).is_err());
+    }
+
+    #[test]
+    fn test_save_sixteen_bit_tiff() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("print.tif");
+        let img = DynamicImage::ImageRgba16(image::ImageBuffer::from_fn(64, 4, |x, _| {
+            image::Rgba([x as u16 * 1000, 0, 0, u16::MAX])
+        }));
+        let options = ProcessOptions {
+            output_format: Some(OutputFormat::Tiff16),
+            ..ProcessOptions::default()
+        };
+        save_image(&img, &output, &options).unwrap();
+        let saved = image::open(&output).unwrap();
+        assert_eq!(saved.color(), image::ColorType::Rgb16);
+        assert_eq!(saved.to_rgb16().get_pixel(63, 0)[0], 63000);
+    }
+
+    #[test]
+    fn test_save_outputs_with_export_sizes() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.png");
+        let img = DynamicImage::new_rgb8(200, 100);
+        let options = ProcessOptions {
+            export_sizes: vec![50, 400],
+            ..ProcessOptions::default()
+        };
+        save_outputs(&img, &output, &EmbeddedMetadata::default(), &options).unwrap();
+        assert_eq!(image::open(&output).unwrap().dimensions(), (200, 100));
+        let small = sized_output_path(&output, 50);
+        assert_eq!(small, dir.path().join("out_50.png"));
+        assert_eq!(image::open(&small).unwrap().dimensions(), (50, 25));
+        // Exports larger than the image are not upscaled
+        let large = sized_output_path(&output, 400);
+        assert_eq!(image::open(&large).unwrap().dimensions(), (200, 100));
+
+        let photo = dir.path().join("photo.jpg");
+        let taken = dir.path().join("photo_1080.jpg");
+        assert!(check_export_paths(&[&photo], &[&photo], &[1080]).is_ok());
+        assert!(check_export_paths(&[&photo, &taken], &[&photo], &[1080]).is_err());
+        assert!(check_export_paths(&[], &[&photo, &taken], &[1080]).is_err());
+        assert!(check_export_paths(&[], &[&photo], &[1080, 1080]).is_err());
+    }
+
+    #[test]
+    fn test_bar_layer_path() {
+        assert_eq!(
+            bar_layer_path(Path::new("out/photo.jpg")),
+            PathBuf::from("out/photo_bar.png")
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Overlay layout module
+//!
+//! This module draws text directly over the photo instead of adding a bar below it. The text
+//! sits in slots at the four corners and the bottom center, and every slot renders its own
+//! template.
+
+use crate::image_processor::{draw_pill, draw_styled_text, text_height, text_width, PhotoMetadata};
+use crate::layout::Rect;
+use crate::options::ProcessOptions;
+use crate::resource::Resources;
+use clap::ValueEnum;
+use image::{DynamicImage, Rgba};
+
+/// Position of an overlay text slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OverlaySlot {
+    /// Top-left corner
+    TopLeft,
+    /// Top-right corner
+    TopRight,
+    /// Bottom-left corner
+    BottomLeft,
+    /// Bottom-right corner
+    BottomRight,
+    /// Center of the bottom edge
+    BottomCenter,
+}
+
+/// Slots used by `--overlay` when no slot is assigned
+pub fn default_slots() -> Vec<(OverlaySlot, String)> {
+    vec![
+        (OverlaySlot::BottomLeft, "{camera} · {lens}".to_string()),
+        (
+            OverlaySlot::BottomRight,
+            "{focal}mm  f/{aperture}  {shutter}s  ISO {iso}".to_string(),
+        ),
+    ]
+}
+
+/// Parses a slot assignment given as `SLOT=TEMPLATE`, e.g. `top-right=© Jane Doe`
+pub fn parse_overlay_slot(value: &str) -> Result<(OverlaySlot, String), String> {
+    let (slot, template) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected SLOT=TEMPLATE, got '{}'", value))?;
+    let slot = OverlaySlot::from_str(slot.trim(), true).map_err(|_| {
+        format!(
+            "invalid slot: {} (expected top-left, top-right, bottom-left, bottom-right or bottom-center)",
+            slot
+        )
+    })?;
+    Ok((slot, template.to_string()))
+}
+
+/// Draws the overlay slots over a photo
+///
+/// The text size follows the information bar height, and the text color, shadow, outline and
+/// pill come from the theme. Slots whose template renders empty or uses a token without a value,
+/// such as the EXIF tokens of a photo without EXIF, are skipped.
+///
+/// # Arguments
+/// * `img` - The input image
+/// * `metadata` - Resolved metadata of the photo
+/// * `resources` - Font and scaling resources
+/// * `options` - Processing options
+/// * `slots` - Templates assigned to the slots
+///
+/// # Returns
+/// * `DynamicImage` - Photo with the overlay text
+pub fn add_overlay(
+    img: DynamicImage,
+    metadata: &PhotoMetadata,
+    resources: &Resources,
+    options: &ProcessOptions,
+    slots: &[(OverlaySlot, String)],
+) -> DynamicImage {
+    let mut canvas = img.to_rgba8();
+    let (width, height) = canvas.dimensions();
+    let mut context = metadata.context.clone();
+    if let Some(exif_info) = &metadata.exif {
+        context = context.with_exif(exif_info);
+    } else {
+        println!("[WARN] Failed to read EXIF information from image");
+    }
+
+    let scale = resources.scale_regular;
+    let font = &resources.font_regular;
+    let margin = (options.info_height / 4).max(8) as i32;
+    let text_height = text_height(font, scale) as i32;
+    for (slot, template) in slots {
+        if !context.resolves(template) {
+            println!(
+                "[WARN] Skipping {:?} overlay slot, not every token of '{}' has a value",
+                slot, template
+            );
+            continue;
+        }
+        let text = context.render(template);
+        if text.trim().is_empty() {
+            continue;
+        }
+        let text_w = text_width(font, scale, &text).max(0);
+        let x = match slot {
+            OverlaySlot::TopLeft | OverlaySlot::BottomLeft => margin,
+            OverlaySlot::TopRight | OverlaySlot::BottomRight => width as i32 - margin - text_w,
+            OverlaySlot::BottomCenter => (width as i32 - text_w) / 2,
+        };
+        let y = match slot {
+            OverlaySlot::TopLeft | OverlaySlot::TopRight => margin,
+            _ => height as i32 - margin - text_height,
+        };
+        if let Some(pill) = options.theme.text_pill {
+            let extents = Rect {
+                x,
+                y,
+                width: text_w as u32,
+                height: text_height as u32,
+            };
+            draw_pill(&mut canvas, &extents, &pill);
+        }
+        draw_styled_text(
+            &mut canvas,
+            &options.theme,
+            Rgba([255, 255, 255, 255]),
+            (x, y),
+            scale,
+            font,
+            &text,
+        );
+    }
+    DynamicImage::ImageRgba8(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_processor::resolve_metadata;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_overlay_slot() {
+        assert_eq!(
+            parse_overlay_slot("top-right=© {camera}"),
+            Ok((OverlaySlot::TopRight, "© {camera}".to_string()))
+        );
+        assert_eq!(
+            parse_overlay_slot("bottom-center=a=b").unwrap().1,
+            "a=b".to_string()
+        );
+        assert!(parse_overlay_slot("middle=text").is_err());
+        assert!(parse_overlay_slot("top-left").is_err());
+    }
+
+    #[test]
+    fn test_overlay_skips_unresolved_slots() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("noexif.png");
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            800,
+            600,
+            Rgba([40, 40, 40, 255]),
+        ));
+        img.save(&input).unwrap();
+        let resources = Resources::new(180, Some(Path::new("./fonts"))).unwrap();
+        let options = ProcessOptions::default();
+
+        let metadata = resolve_metadata(&input, &options);
+        let framed = add_overlay(
+            img.clone(),
+            &metadata,
+            &resources,
+            &options,
+            &default_slots(),
+        );
+        assert_eq!(framed.to_rgba8(), img.to_rgba8());
+
+        // Slots without EXIF tokens are still drawn
+        let slots = [(OverlaySlot::TopLeft, "{filename}".to_string())];
+        let framed = add_overlay(img.clone(), &metadata, &resources, &options, &slots);
+        assert_ne!(framed.to_rgba8(), img.to_rgba8());
+    }
+}
@@ -0,0 +1,125 @@
+//! Paranoid mode module
+//!
+//! This module guards the inputs of a run under `--paranoid`: it resolves the input and output
+//! paths through symbolic links and `..` components and refuses the run when an output would land
+//! on an input or inside the input tree, or the inputs sit inside the output tree. Inputs are
+//! only ever opened for reading; together with the refusal of the in-place subcommands, this
+//! guarantees a run leaves the source files as they were.
+
+use std::error::Error;
+use std::path::{Component, Path, PathBuf};
+
+/// Resolves a path to an absolute path without symbolic links, also for paths that don't exist
+/// yet
+///
+/// The longest existing ancestor is canonicalized and the rest of the path is appended with its
+/// `.` and `..` components applied.
+///
+/// # Arguments
+/// * `path` - Path to resolve
+///
+/// # Returns
+/// * `Result<PathBuf, Box<dyn Error>>` - Resolved path
+///
+/// # Errors
+/// Returns an error if the working directory cannot be read
+pub fn resolve(path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let absolute = std::env::current_dir()?.join(path);
+    let (mut resolved, rest) = absolute
+        .ancestors()
+        .find_map(|ancestor| {
+            let canonical = ancestor.canonicalize().ok()?;
+            let rest = absolute.strip_prefix(ancestor).ok()?;
+            Some((canonical, rest.to_path_buf()))
+        })
+        .unwrap_or_else(|| (PathBuf::new(), absolute.clone()));
+    for component in rest.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            component => resolved.push(component),
+        }
+    }
+    Ok(resolved)
+}
+
+/// Checks that no output path overlaps an input path
+///
+/// # Arguments
+/// * `inputs` - Input files or directories
+/// * `outputs` - Output files, directories or archives
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - Ok if every output stays out of the input trees
+///
+/// # Errors
+/// Returns an error naming the first output that is an input, lies inside an input directory or
+/// contains an input
+pub fn check_overlap(inputs: &[&Path], outputs: &[&Path]) -> Result<(), Box<dyn Error>> {
+    let outputs = outputs
+        .iter()
+        .map(|output| Ok((*output, resolve(output)?)))
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+    for input in inputs {
+        let resolved_input = resolve(input)?;
+        for (output, resolved_output) in &outputs {
+            if resolved_output == &resolved_input {
+                return Err(format!(
+                    "Output {} is the input {}, refused by --paranoid",
+                    output.display(),
+                    input.display()
+                )
+                .into());
+            }
+            if resolved_output.starts_with(&resolved_input) {
+                return Err(format!(
+                    "Output {} is inside the input tree {}, refused by --paranoid",
+                    output.display(),
+                    input.display()
+                )
+                .into());
+            }
+            if resolved_input.starts_with(resolved_output) {
+                return Err(format!(
+                    "Input {} is inside the output tree {}, refused by --paranoid",
+                    input.display(),
+                    output.display()
+                )
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_overlap() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("archive");
+        std::fs::create_dir(&input).unwrap();
+        let photo = input.join("photo.jpg");
+        std::fs::write(&photo, b"").unwrap();
+
+        assert!(check_overlap(&[&input], &[&dir.path().join("framed")]).is_ok());
+        assert!(check_overlap(&[&photo], &[&input.join("photo_framed.jpg")]).is_ok());
+        assert!(check_overlap(&[&input], &[&input.join("framed")]).is_err());
+        assert!(check_overlap(&[&input], &[dir.path()]).is_err());
+        assert!(check_overlap(&[&photo], &[&photo]).is_err());
+        // Paths that don't exist yet are resolved through `..`
+        let sneaky = dir.path().join("framed/../archive/out");
+        assert!(check_overlap(&[&input], &[&sneaky]).is_err());
+
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("link");
+            std::os::unix::fs::symlink(&input, &link).unwrap();
+            assert!(check_overlap(&[&input], &[&link.join("framed")]).is_err());
+        }
+    }
+}
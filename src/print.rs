@@ -0,0 +1,263 @@
+//! Print module
+//!
+//! This module prepares outputs for lab printing. Physical lengths are converted to pixels at
+//! the print resolution, and the framed output is surrounded by a bleed, which extends its edge
+//! pixels past the trim line, and optionally by crop marks showing where to cut.
+
+use clap::ValueEnum;
+use image::{DynamicImage, ImageBuffer, Pixel, Rgba};
+use std::error::Error;
+
+/// Print resolution used to convert lengths when `--dpi` is not given
+pub const DEFAULT_DPI: u16 = 300;
+
+/// Length of the crop marks in inches
+const MARK_LENGTH_INCHES: f32 = 0.25;
+
+/// Largest bleed in millimeters, well above the 2 to 5 mm labs ask for
+pub const MAX_BLEED_MILLIMETERS: f32 = 25.4;
+
+/// Physical length, e.g. of a bleed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Length {
+    /// Length in millimeters
+    pub millimeters: f32,
+}
+
+impl Length {
+    /// Converts the length to pixels at a print resolution
+    ///
+    /// # Arguments
+    /// * `dpi` - Print resolution in pixels per inch
+    pub fn to_pixels(self, dpi: u16) -> u32 {
+        (self.millimeters / 25.4 * dpi as f32).round() as u32
+    }
+}
+
+/// Standard print size the output canvas is sized to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PaperSize {
+    /// ISO A4, 210 × 297 mm
+    A4,
+    /// ISO A3, 297 × 420 mm
+    A3,
+    /// 4 × 6 in photo print
+    #[value(name = "4x6")]
+    Photo4x6,
+    /// 5 × 7 in photo print
+    #[value(name = "5x7")]
+    Photo5x7,
+}
+
+impl PaperSize {
+    /// Returns the width and height of the paper in portrait orientation, in millimeters
+    pub fn millimeters(self) -> (f32, f32) {
+        match self {
+            PaperSize::A4 => (210.0, 297.0),
+            PaperSize::A3 => (297.0, 420.0),
+            PaperSize::Photo4x6 => (4.0 * 25.4, 6.0 * 25.4),
+            PaperSize::Photo5x7 => (5.0 * 25.4, 7.0 * 25.4),
+        }
+    }
+
+    /// Returns the canvas of the paper in pixels
+    ///
+    /// # Arguments
+    /// * `dpi` - Print resolution in pixels per inch
+    /// * `landscape` - Whether to turn the paper to landscape, for wide photos
+    ///
+    /// # Returns
+    /// * `(u32, u32)` - Width and height of the canvas
+    pub fn canvas(self, dpi: u16, landscape: bool) -> (u32, u32) {
+        let (width, height) = self.millimeters();
+        let width = Length { millimeters: width }.to_pixels(dpi);
+        let height = Length {
+            millimeters: height,
+        }
+        .to_pixels(dpi);
+        if landscape {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+}
+
+/// Parses a length with a unit, e.g. `3mm`, `0.3cm` or `0.125in`
+pub fn parse_length(value: &str) -> Result<Length, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| c.is_ascii_alphabetic())
+        .ok_or_else(|| format!("missing unit in '{}' (expected mm, cm or in)", value))?;
+    let (number, unit) = value.split_at(split);
+    let number: f32 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid length: {}", value))?;
+    if !number.is_finite() || number < 0.0 {
+        return Err(format!("invalid length: {}", value));
+    }
+    let millimeters = match unit.to_lowercase().as_str() {
+        "mm" => number,
+        "cm" => number * 10.0,
+        "in" => number * 25.4,
+        _ => return Err(format!("invalid unit: {} (expected mm, cm or in)", unit)),
+    };
+    Ok(Length { millimeters })
+}
+
+/// Parses a bleed width, a length of at most `MAX_BLEED_MILLIMETERS`, e.g. `3mm`
+pub fn parse_bleed(value: &str) -> Result<Length, String> {
+    let length = parse_length(value)?;
+    if length.millimeters > MAX_BLEED_MILLIMETERS {
+        return Err(format!(
+            "bleed {} is too wide (expected at most {}mm)",
+            value.trim(),
+            MAX_BLEED_MILLIMETERS
+        ));
+    }
+    Ok(length)
+}
+
+/// Surrounds a framed output with a bleed and optional crop marks
+///
+/// The bleed repeats the edge pixels of the output. Crop marks are drawn on white paper
+/// outside the bleed, in line with the trim edges, so they never show on the trimmed print.
+///
+/// # Arguments
+/// * `img` - Framed output, trimmed to its final size
+/// * `bleed` - Width of the bleed in pixels
+/// * `crop_marks` - Whether to draw crop marks
+/// * `dpi` - Print resolution, for the length and weight of the crop marks
+///
+/// # Returns
+/// * `Result<DynamicImage, Box<dyn Error>>` - Output with the bleed and marks, at the bit depth
+///   of `img`
+///
+/// # Errors
+/// Returns an error if the output with the bleed and marks is too large
+pub fn add_bleed(
+    img: &DynamicImage,
+    bleed: u32,
+    crop_marks: bool,
+    dpi: u16,
+) -> Result<DynamicImage, Box<dyn Error>> {
+    let marks = crop_marks.then(|| {
+        let length = (MARK_LENGTH_INCHES * dpi as f32).round() as u32;
+        // 0.25 pt hairline
+        let weight = (dpi as f32 / 288.0).round().max(1.0) as u32;
+        (length, weight)
+    });
+    let slug = marks.map_or(0, |(length, _)| length);
+    let fits = |size: u32| {
+        bleed
+            .checked_add(slug)
+            .and_then(|margin| margin.checked_mul(2))
+            .and_then(|edges| edges.checked_add(size))
+            .is_some()
+    };
+    if !fits(img.width()) || !fits(img.height()) {
+        return Err(format!(
+            "A {}px bleed is too wide for a {}x{} output",
+            bleed,
+            img.width(),
+            img.height()
+        )
+        .into());
+    }
+    Ok(match img {
+        DynamicImage::ImageRgba16(photo) => DynamicImage::ImageRgba16(bleed_buffer(
+            photo,
+            bleed,
+            marks,
+            Rgba([u16::MAX; 4]),
+            Rgba([0, 0, 0, u16::MAX]),
+        )),
+        img => DynamicImage::ImageRgba8(bleed_buffer(
+            &img.to_rgba8(),
+            bleed,
+            marks,
+            Rgba([255; 4]),
+            Rgba([0, 0, 0, 255]),
+        )),
+    })
+}
+
+/// Builds the bleed canvas at any bit depth
+fn bleed_buffer<P: Pixel>(
+    photo: &ImageBuffer<P, Vec<P::Subpixel>>,
+    bleed: u32,
+    marks: Option<(u32, u32)>,
+    paper: P,
+    ink: P,
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    let (width, height) = photo.dimensions();
+    let slug = marks.map_or(0, |(length, _)| length);
+    let margin = bleed + slug;
+    let mut canvas = ImageBuffer::from_pixel(width + margin * 2, height + margin * 2, paper);
+
+    for y in slug..margin * 2 + height - slug {
+        let source_y = y.saturating_sub(margin).min(height - 1);
+        for x in slug..margin * 2 + width - slug {
+            let source_x = x.saturating_sub(margin).min(width - 1);
+            canvas.put_pixel(x, y, *photo.get_pixel(source_x, source_y));
+        }
+    }
+
+    if let Some((length, weight)) = marks {
+        let far_x = margin * 2 + width;
+        let far_y = margin * 2 + height;
+        for trim_y in [margin, margin + height - weight.min(height)] {
+            for x in (0..length).chain(far_x - length..far_x) {
+                for y in trim_y..trim_y + weight.min(height) {
+                    canvas.put_pixel(x, y, ink);
+                }
+            }
+        }
+        for trim_x in [margin, margin + width - weight.min(width)] {
+            for y in (0..length).chain(far_y - length..far_y) {
+                for x in trim_x..trim_x + weight.min(width) {
+                    canvas.put_pixel(x, y, ink);
+                }
+            }
+        }
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_bleed_and_crop_marks() {
+        assert_eq!(parse_length("3mm").unwrap().to_pixels(300), 35);
+        assert_eq!(parse_length("0.25in").unwrap().to_pixels(300), 75);
+        assert_eq!(parse_length(" 0.3 cm").unwrap().to_pixels(254), 30);
+        assert!(parse_length("3").is_err());
+        assert!(parse_length("3pt").is_err());
+        assert!(parse_bleed("5mm").is_ok());
+        assert!(parse_bleed("100000in").is_err());
+        assert_eq!(PaperSize::A4.canvas(300, false), (2480, 3508));
+        assert_eq!(PaperSize::Photo4x6.canvas(300, true), (1800, 1200));
+
+        let mut photo = RgbaImage::from_pixel(40, 30, Rgba([200, 0, 0, 255]));
+        photo.put_pixel(0, 0, Rgba([0, 200, 0, 255]));
+        let photo = DynamicImage::ImageRgba8(photo);
+
+        let bled = add_bleed(&photo, 5, false, 300).unwrap().to_rgba8();
+        assert_eq!(bled.dimensions(), (50, 40));
+        assert_eq!(bled.get_pixel(0, 0), &Rgba([0, 200, 0, 255]));
+        assert_eq!(bled.get_pixel(49, 39), &Rgba([200, 0, 0, 255]));
+
+        let marked = add_bleed(&photo, 5, true, 72).unwrap().to_rgba8();
+        // 18 px marks outside the 5 px bleed
+        assert_eq!(marked.dimensions(), (86, 76));
+        assert_eq!(marked.get_pixel(0, 23), &Rgba([0, 0, 0, 255]));
+        assert_eq!(marked.get_pixel(23, 75), &Rgba([0, 0, 0, 255]));
+        assert_eq!(marked.get_pixel(0, 0), &Rgba([255; 4]));
+        assert_eq!(marked.get_pixel(18, 18), &Rgba([0, 200, 0, 255]));
+        assert!(add_bleed(&photo, u32::MAX / 2, true, 300).is_err());
+    }
+}
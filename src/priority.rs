@@ -0,0 +1,34 @@
+//! Process priority module
+//!
+//! Overnight batches compete with editors for the CPU. This module lowers the scheduling
+//! priority of the process so interactive applications stay responsive.
+
+/// Niceness increment applied to the process, the usual value for background jobs
+#[cfg(unix)]
+const NICENESS: libc::c_int = 10;
+
+/// Lowers the scheduling priority of the process and of the threads it spawns afterwards
+///
+/// # Returns
+/// * `Result<(), String>` - Ok if the priority was lowered
+///
+/// # Errors
+/// Returns an error if the system refuses the change or the platform is unsupported
+#[cfg(unix)]
+pub fn lower_priority() -> Result<(), String> {
+    // Threads inherit the niceness of the thread creating them, so this runs before any spawn
+    // SAFETY: setpriority only reads its integer arguments
+    let current = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, current + NICENESS) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error().to_string())
+    }
+}
+
+/// Lowers the scheduling priority of the process, which is unsupported on this platform
+#[cfg(not(unix))]
+pub fn lower_priority() -> Result<(), String> {
+    Err("lowering the priority is only supported on Unix".to_string())
+}
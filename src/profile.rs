@@ -0,0 +1,173 @@
+//! Source profile module
+//!
+//! This module knows the export habits of photo editors, so metadata lost on export is looked
+//! up where the editor left it: the original next to or above the export, or the XMP packet.
+
+use crate::options::ProcessOptions;
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+
+/// Suffixes Lightroom appends to the names of edited copies
+const LIGHTROOM_SUFFIXES: &[&str] = &["-Edit", "-HDR", "-Pano", "-Enhanced-NR", "-Enhanced-SR"];
+
+/// Session folder Capture One imports originals to, next to its `Output` folder
+const CAPTURE_ONE_CAPTURE_DIR: &str = "Capture";
+
+/// Editor the inputs were exported from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SourceProfile {
+    /// Adobe Lightroom, with `-Edit`, `-HDR`, `-Pano` and `-2` copy suffixes
+    Lightroom,
+    /// Capture One sessions, exporting from `Capture` to `Output`
+    CaptureOne,
+}
+
+impl SourceProfile {
+    /// Adjusts the options to the quirks of the editor
+    ///
+    /// Both editors can strip lens data and export in color spaces wider than sRGB, so the
+    /// companion EXIF lookup and the gamut check are turned on.
+    pub fn apply(self, options: &mut ProcessOptions) {
+        options.source_profile = Some(self);
+        options.companion_exif = true;
+        options.gamut_check = true;
+    }
+
+    /// Returns the stem of the original of an exported file, e.g. `IMG_0197` for
+    /// `IMG_0197-Edit-2`
+    ///
+    /// Copy suffixes are numbers from `-1` to `-99` without a leading zero, and are never taken
+    /// off a stem ending in a date, so `2024-06-01` and `2024-12` stay as they are.
+    ///
+    /// # Arguments
+    /// * `stem` - Stem of the exported file
+    pub fn original_stem(self, stem: &str) -> String {
+        let mut stem = stem;
+        if self == SourceProfile::Lightroom {
+            loop {
+                let copy = stem
+                    .rsplit_once('-')
+                    .filter(|(base, number)| {
+                        !base.is_empty()
+                            && (1..=2).contains(&number.len())
+                            && !number.starts_with('0')
+                            && number.bytes().all(|b| b.is_ascii_digit())
+                            && !ends_with_date(stem)
+                    })
+                    .map(|(base, _)| base);
+                let edit = LIGHTROOM_SUFFIXES
+                    .iter()
+                    .find_map(|suffix| stem.strip_suffix(suffix))
+                    .filter(|base| !base.is_empty());
+                match copy.or(edit) {
+                    Some(base) => stem = base,
+                    None => break,
+                }
+            }
+        }
+        stem.to_string()
+    }
+
+    /// Lists where the original of an exported file may be, other than next to it
+    ///
+    /// The paths keep the extension of the export; companion files are found by replacing it.
+    ///
+    /// # Arguments
+    /// * `input` - Path to the exported file
+    ///
+    /// # Returns
+    /// * `Vec<PathBuf>` - Candidate paths of the original, most likely first
+    pub fn original_paths(self, input: &Path) -> Vec<PathBuf> {
+        let (Some(stem), Some(dir)) = (input.file_stem(), input.parent()) else {
+            return Vec::new();
+        };
+        let stem = self.original_stem(&stem.to_string_lossy());
+        let name = match input.extension() {
+            Some(ext) => format!("{}.{}", stem, ext.to_string_lossy()),
+            None => stem,
+        };
+        let mut paths = vec![dir.join(&name)];
+        if self == SourceProfile::CaptureOne {
+            // Session/Output/<sub folders>/photo.jpg exports Session/Capture/<sub folders>/photo
+            let mut ancestors = dir.ancestors();
+            let mut relative = Vec::new();
+            while let Some(ancestor) = ancestors.next() {
+                if ancestor.file_name().is_some_and(|name| name == "Output") {
+                    if let Some(session) = ancestors.next() {
+                        let mut capture = session.join(CAPTURE_ONE_CAPTURE_DIR);
+                        capture.extend(relative.iter().rev());
+                        paths.push(capture.join(&name));
+                    }
+                    break;
+                }
+                relative.extend(ancestor.file_name());
+            }
+        }
+        paths.retain(|path| path != input);
+        paths
+    }
+}
+
+/// Returns whether a stem ends with a `YYYY-MM-DD` date or is a `YYYY-MM` month
+fn ends_with_date(stem: &str) -> bool {
+    let number = |part: &str, digits: usize, min: u32, max: u32| {
+        part.len() == digits
+            && part.bytes().all(|b| b.is_ascii_digit())
+            && part
+                .parse()
+                .is_ok_and(|value: u32| (min..=max).contains(&value))
+    };
+    let year = |part: &str| number(part, 4, 1900, 2099);
+    let parts: Vec<&str> = stem.rsplitn(3, '-').collect();
+    match parts.as_slice() {
+        [month, year_part] => year(year_part) && number(month, 2, 1, 12),
+        [day, month, rest] => {
+            let year_part = rest
+                .len()
+                .checked_sub(4)
+                .and_then(|start| rest.get(start..))
+                .unwrap_or_default();
+            year(year_part) && number(month, 2, 1, 12) && number(day, 2, 1, 31)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_original_paths() {
+        let lightroom = SourceProfile::Lightroom;
+        assert_eq!(lightroom.original_stem("IMG_0197-Edit-2"), "IMG_0197");
+        assert_eq!(lightroom.original_stem("DSCF1234-HDR-Pano"), "DSCF1234");
+        assert_eq!(lightroom.original_stem("IMG_0197"), "IMG_0197");
+        assert_eq!(lightroom.original_stem("-2"), "-2");
+        assert_eq!(lightroom.original_stem("2024-06-01"), "2024-06-01");
+        assert_eq!(lightroom.original_stem("2024-12"), "2024-12");
+        assert_eq!(
+            lightroom.original_stem("Trip 2024-12-15-Edit-2"),
+            "Trip 2024-12-15"
+        );
+        assert_eq!(lightroom.original_stem("IMG_0197-0042"), "IMG_0197-0042");
+        assert_eq!(
+            lightroom.original_paths(Path::new("/shoot/IMG_0197-Edit.jpg")),
+            [PathBuf::from("/shoot/IMG_0197.jpg")]
+        );
+        assert!(lightroom
+            .original_paths(Path::new("/shoot/IMG_0197.jpg"))
+            .is_empty());
+
+        let capture_one = SourceProfile::CaptureOne;
+        assert_eq!(capture_one.original_stem("IMG_0197-2"), "IMG_0197-2");
+        assert_eq!(
+            capture_one.original_paths(Path::new("/Session/Output/Day 1/IMG_0197.jpg")),
+            [PathBuf::from("/Session/Capture/Day 1/IMG_0197.jpg")]
+        );
+
+        let mut options = ProcessOptions::default();
+        capture_one.apply(&mut options);
+        assert!(options.companion_exif && options.gamut_check);
+    }
+}
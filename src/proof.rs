@@ -0,0 +1,211 @@
+//! Print profile module
+//!
+//! This module converts framed outputs from sRGB to the ICC profile of a printer and paper, so
+//! labs printing without color management get the colors the photographer saw on screen. The
+//! conversion needs a build with the `color-management` feature; other builds reject
+//! `--print-profile` when it is loaded.
+
+use clap::ValueEnum;
+use image::DynamicImage;
+use std::error::Error;
+use std::path::Path;
+
+/// Rendering intent of the conversion to a print profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum RenderingIntent {
+    /// Compress the whole gamut to fit the paper, keeping the relations between colors
+    Perceptual,
+    /// Keep the colors the paper can print and clip the others, mapping white to paper white
+    #[default]
+    Relative,
+    /// Keep saturated colors saturated, for graphics rather than photos
+    Saturation,
+    /// Like `relative`, but simulate the paper white instead of mapping white to it
+    Absolute,
+}
+
+/// ICC profile of a printer and paper, ready to convert outputs to
+pub struct PrintProfile {
+    /// File name of the profile, e.g. `Frontier_Lustre.icc`
+    pub name: String,
+    /// Profile data, embedded into JPEG outputs
+    pub data: Vec<u8>,
+    /// Rendering intent of the conversion
+    pub intent: RenderingIntent,
+    #[cfg(feature = "color-management")]
+    transform: qcms::Transform,
+}
+
+impl std::fmt::Debug for PrintProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrintProfile")
+            .field("name", &self.name)
+            .field("intent", &self.intent)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PrintProfile {
+    /// Loads a print profile and prepares the conversion from sRGB
+    ///
+    /// # Arguments
+    /// * `path` - Path to the ICC profile of the printer and paper
+    /// * `intent` - Rendering intent of the conversion
+    ///
+    /// # Returns
+    /// * `Result<Self, Box<dyn Error>>` - Loaded profile
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, is not an RGB profile qcms can convert to,
+    /// or the build lacks the `color-management` feature
+    #[cfg(feature = "color-management")]
+    pub fn load(path: &Path, intent: RenderingIntent) -> Result<Self, Box<dyn Error>> {
+        let data = std::fs::read(path)?;
+        let invalid = || format!("Unsupported print profile {}", path.display());
+        let output = qcms::Profile::new_from_slice(&data, false).ok_or_else(invalid)?;
+        let intent_value = match intent {
+            RenderingIntent::Perceptual => qcms::Intent::Perceptual,
+            RenderingIntent::Relative => qcms::Intent::RelativeColorimetric,
+            RenderingIntent::Saturation => qcms::Intent::Saturation,
+            RenderingIntent::Absolute => qcms::Intent::AbsoluteColorimetric,
+        };
+        let transform = qcms::Transform::new(
+            &qcms::Profile::new_sRGB(),
+            &output,
+            qcms::DataType::RGBA8,
+            intent_value,
+        )
+        .ok_or_else(|| format!("{} (expected an RGB printer profile)", invalid()))?;
+        Ok(PrintProfile {
+            name: profile_name(path),
+            data,
+            intent,
+            transform,
+        })
+    }
+
+    /// Loads a print profile, which requires the `color-management` feature
+    #[cfg(not(feature = "color-management"))]
+    pub fn load(_path: &Path, _intent: RenderingIntent) -> Result<Self, Box<dyn Error>> {
+        Err("Print profiles require a build with the `color-management` feature".into())
+    }
+
+    /// Converts a finished output from sRGB to the print profile
+    ///
+    /// The conversion runs on 8-bit pixels, so 16-bit outputs are converted to 8 bits.
+    ///
+    /// # Arguments
+    /// * `img` - Finished output in sRGB
+    ///
+    /// # Returns
+    /// * `DynamicImage` - Output in the color space of the print profile
+    #[cfg(feature = "color-management")]
+    pub fn convert(&self, img: &DynamicImage) -> DynamicImage {
+        let mut rgba = img.to_rgba8();
+        self.transform.apply(&mut rgba);
+        DynamicImage::ImageRgba8(rgba)
+    }
+
+    /// Returns the output unchanged, print profiles are never loaded without the
+    /// `color-management` feature
+    #[cfg(not(feature = "color-management"))]
+    pub fn convert(&self, img: &DynamicImage) -> DynamicImage {
+        img.clone()
+    }
+}
+
+/// Returns the file name of a profile for the log
+#[cfg(feature = "color-management")]
+fn profile_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a matrix/TRC profile with the sRGB primaries and a pure gamma curve
+    #[cfg(feature = "color-management")]
+    fn gamma_profile(gamma: f32) -> Vec<u8> {
+        // D50-adapted sRGB colorants
+        let colorants: [(&[u8; 4], [f32; 3]); 3] = [
+            (b"rXYZ", [0.4361, 0.2225, 0.0139]),
+            (b"gXYZ", [0.3851, 0.7169, 0.0971]),
+            (b"bXYZ", [0.1431, 0.0606, 0.7141]),
+        ];
+        let tag_table = 128 + 4 + 6 * 12;
+        let mut tags = Vec::new();
+        let mut table = (6u32).to_be_bytes().to_vec();
+        for (signature, xyz) in colorants {
+            table.extend_from_slice(signature);
+            table.extend_from_slice(&((tag_table + tags.len()) as u32).to_be_bytes());
+            table.extend_from_slice(&20u32.to_be_bytes());
+            tags.extend_from_slice(b"XYZ \0\0\0\0");
+            for value in xyz {
+                tags.extend_from_slice(&((value * 65536.0).round() as i32).to_be_bytes());
+            }
+        }
+        let curve = (tag_table + tags.len()) as u32;
+        tags.extend_from_slice(b"curv\0\0\0\0");
+        tags.extend_from_slice(&1u32.to_be_bytes());
+        tags.extend_from_slice(&((gamma * 256.0).round() as u16).to_be_bytes());
+        tags.extend_from_slice(&[0, 0]);
+        for signature in [b"rTRC", b"gTRC", b"bTRC"] {
+            table.extend_from_slice(signature);
+            table.extend_from_slice(&curve.to_be_bytes());
+            table.extend_from_slice(&14u32.to_be_bytes());
+        }
+
+        let mut header = vec![0u8; 128];
+        header[0..4].copy_from_slice(&((tag_table + tags.len()) as u32).to_be_bytes());
+        header[8] = 2;
+        header[12..16].copy_from_slice(b"prtr");
+        header[16..20].copy_from_slice(b"RGB ");
+        header[20..24].copy_from_slice(b"XYZ ");
+        header[36..40].copy_from_slice(b"acsp");
+        [header, table, tags].concat()
+    }
+
+    #[test]
+    fn test_print_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let garbage = dir.path().join("garbage.icc");
+        std::fs::write(&garbage, b"not a profile").unwrap();
+        assert!(PrintProfile::load(&garbage, RenderingIntent::Relative).is_err());
+
+        #[cfg(feature = "color-management")]
+        {
+            let path = dir.path().join("linear.icc");
+            std::fs::write(&path, gamma_profile(1.0)).unwrap();
+            let profile = PrintProfile::load(&path, RenderingIntent::Perceptual).unwrap();
+            assert_eq!(profile.name, "linear.icc");
+
+            let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+                2,
+                2,
+                image::Rgba([128, 128, 128, 255]),
+            ));
+            let pixel = *profile.convert(&img).to_rgba8().get_pixel(0, 0);
+            // sRGB mid gray is about 22% in linear light
+            assert!((50..=60).contains(&pixel[0]), "{:?}", pixel);
+            assert_eq!(pixel[0], pixel[2]);
+            assert_eq!(pixel[3], 255);
+
+            // Only JPEG outputs carry the profile
+            use crate::output::OutputFormat;
+            use crate::validate::validate_options;
+            let mut options = crate::options::ProcessOptions {
+                print_profile: Some(std::sync::Arc::new(profile)),
+                convert_to: Some(OutputFormat::Jpeg),
+                ..Default::default()
+            };
+            assert!(validate_options(&options).is_empty());
+            for format in [OutputFormat::Png, OutputFormat::Tiff16] {
+                options.convert_to = Some(format);
+                assert!(validate_options(&options)[0].is_error());
+            }
+        }
+    }
+}
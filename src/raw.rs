@@ -0,0 +1,475 @@
+//! RAW preview module
+//!
+//! This module frames the JPEG preview cameras embed in RAW files next to the sensor data,
+//! which is the rendering shown on the camera screen. It is not a RAW decoder: the sensor data
+//! is never developed. Previews are found in the IFDs of
+//! TIFF-based files (NEF, ARW, CR2, DNG), at the offsets of the RAF header and in the first
+//! track of CR3 files. CR3 files keep their EXIF in CMT boxes instead of an EXIF block.
+//!
+//! Some bodies only embed a preview smaller than the sensor, e.g. 1616×1080 in many ARW files;
+//! such files are framed at the preview size with a warning.
+
+use crate::salvage::{salvage_ifd, TiffReader};
+use exif::{Context, Exif, Reader};
+use image::error::{DecodingError, ImageFormatHint};
+use image::{DynamicImage, ImageError, ImageFormat, ImageResult};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::ops::Range;
+use std::path::Path;
+
+/// Extensions of the RAW files decoded from their embedded preview
+pub const RAW_EXTENSIONS: &[&str] = &["nef", "nrw", "arw", "cr2", "cr3", "raf", "dng"];
+
+/// Magic bytes starting a Fujifilm RAF file
+const RAF_MAGIC: &[u8] = b"FUJIFILMCCD-RAW ";
+
+/// File type box of a Canon CR3 file
+const CR3_BRAND: &[u8] = b"ftypcrx ";
+
+/// UUID of the Canon box holding the CMT metadata boxes of a CR3 file
+const CANON_UUID: [u8; 16] = [
+    0x85, 0xc0, 0xb6, 0x87, 0x82, 0x0f, 0x11, 0xe0, 0x81, 0x11, 0xf4, 0xce, 0x46, 0x2b, 0x6a, 0x48,
+];
+
+/// Limit of IFDs visited in a TIFF-based file, against looping IFD chains
+const MAX_IFDS: usize = 32;
+
+/// Size of an IFD entry in bytes
+const ENTRY_SIZE: usize = 12;
+
+/// Share by which the sensor may exceed the preview before a warning, for cropped borders
+const PREVIEW_TOLERANCE: f64 = 1.1;
+
+const TAG_NEW_SUBFILE_TYPE: u16 = 0x00fe;
+const TAG_IMAGE_WIDTH: u16 = 0x0100;
+const TAG_IMAGE_LENGTH: u16 = 0x0101;
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014a;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+
+/// Returns whether a file is a RAW file, from its extension
+///
+/// # Arguments
+/// * `path` - Path of the file
+///
+/// # Returns
+/// * `bool` - Whether the extension is one of `RAW_EXTENSIONS`, ignoring case
+pub fn is_raw(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        RAW_EXTENSIONS
+            .iter()
+            .any(|raw| ext.eq_ignore_ascii_case(raw))
+    })
+}
+
+/// Decodes the largest JPEG preview embedded in a RAW file
+///
+/// Prints a warning if the preview is smaller than the sensor image, as the output is then framed
+/// at the preview size.
+///
+/// # Arguments
+/// * `path` - Path of the RAW file
+///
+/// # Returns
+/// * `ImageResult<DynamicImage>` - Decoded preview
+///
+/// # Errors
+/// Returns an error if the file cannot be read, or has no decodable preview
+pub fn decode(path: &Path) -> ImageResult<DynamicImage> {
+    let data = std::fs::read(path)?;
+    let jpeg = preview(&data).ok_or_else(no_preview)?;
+    let img = image::load_from_memory_with_format(jpeg, ImageFormat::Jpeg)?;
+    if let Some((width, height)) = sensor_dimensions(&data) {
+        let preview_edge = img.width().max(img.height()) as f64;
+        if width.max(height) as f64 > preview_edge * PREVIEW_TOLERANCE {
+            println!(
+                "[WARN] {} only embeds a {}x{} preview of its {}x{} sensor image, framing the preview",
+                path.display(),
+                img.width(),
+                img.height(),
+                width,
+                height
+            );
+        }
+    }
+    Ok(img)
+}
+
+/// Returns the dimensions of the sensor image of a RAW file
+///
+/// TIFF-based files give them in their full-resolution IFD, other files in the
+/// PixelXDimension and PixelYDimension EXIF fields.
+///
+/// # Arguments
+/// * `data` - Content of the RAW file
+///
+/// # Returns
+/// * `Option<(u32, u32)>` - Width and height, `None` if the file doesn't tell
+pub fn sensor_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    if let Some(reader) = TiffReader::new(data) {
+        let full_size = tiff_ifds(&reader)
+            .into_iter()
+            .filter(|ifd| ifd.single(TAG_NEW_SUBFILE_TYPE) == Some(0))
+            .filter_map(|ifd| Some((ifd.single(TAG_IMAGE_WIDTH)?, ifd.single(TAG_IMAGE_LENGTH)?)))
+            .max_by_key(|&(width, height)| width * height);
+        if let Some((width, height)) = full_size {
+            return Some((width as u32, height as u32));
+        }
+    }
+    let exif = read_exif(data).or_else(|| Reader::new().read_raw(data.to_vec()).ok())?;
+    let dimension = |tag| {
+        exif.get_field(tag, exif::In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+            .filter(|&value| value > 0)
+    };
+    Some((
+        dimension(exif::Tag::PixelXDimension)?,
+        dimension(exif::Tag::PixelYDimension)?,
+    ))
+}
+
+/// Reads the dimensions of the largest JPEG preview embedded in a RAW file
+///
+/// # Arguments
+/// * `path` - Path of the RAW file
+///
+/// # Returns
+/// * `ImageResult<(u32, u32)>` - Width and height of the preview
+///
+/// # Errors
+/// Returns an error if the file cannot be read, or has no readable preview
+pub fn dimensions(path: &Path) -> ImageResult<(u32, u32)> {
+    let data = std::fs::read(path)?;
+    let jpeg = preview(&data).ok_or_else(no_preview)?;
+    image::io::Reader::with_format(Cursor::new(jpeg), ImageFormat::Jpeg).into_dimensions()
+}
+
+/// Reads the EXIF of a RAW file that has no EXIF block of its own
+///
+/// RAF files carry it in their JPEG preview, and CR3 files in their CMT1 (IFD0) and CMT2 (Exif
+/// IFD) boxes, which are merged into a single EXIF block.
+///
+/// # Arguments
+/// * `data` - Content of the RAW file
+///
+/// # Returns
+/// * `Option<Exif>` - EXIF of the file, `None` if it is not a RAF or CR3 file or has no EXIF
+pub fn read_exif(data: &[u8]) -> Option<Exif> {
+    if data.get(4..12) != Some(CR3_BRAND) {
+        let jpeg = preview(data)?;
+        return Reader::new()
+            .read_from_container(&mut Cursor::new(jpeg))
+            .ok();
+    }
+    let canon = boxes(find_box(data, b"moov")?)
+        .into_iter()
+        .find(|(kind, payload)| kind == b"uuid" && payload.starts_with(&CANON_UUID))?
+        .1;
+    let canon = &canon[CANON_UUID.len()..];
+    let mut fields = find_box(canon, b"CMT1")
+        .map(|cmt| salvage_ifd(cmt, Context::Tiff))
+        .unwrap_or_default();
+    if let Some(cmt) = find_box(canon, b"CMT2") {
+        fields.extend(salvage_ifd(cmt, Context::Exif));
+    }
+    let mut writer = exif::experimental::Writer::new();
+    for field in &fields {
+        writer.push_field(field);
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    writer.write(&mut tiff, false).ok()?;
+    Reader::new().read_raw(tiff.into_inner()).ok()
+}
+
+/// Returns the largest JPEG preview of a RAW file
+fn preview(data: &[u8]) -> Option<&[u8]> {
+    let ranges = if data.starts_with(RAF_MAGIC) {
+        raf_preview(data).into_iter().collect()
+    } else if data.get(4..12) == Some(CR3_BRAND) {
+        cr3_preview(data).into_iter().collect()
+    } else {
+        tiff_previews(data)
+    };
+    ranges
+        .into_iter()
+        .filter_map(|range| data.get(range))
+        .filter(|jpeg| jpeg.starts_with(&[0xff, 0xd8]))
+        .max_by_key(|jpeg| jpeg.len())
+}
+
+fn no_preview() -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("RAW".to_string()),
+        "no embedded JPEG preview found",
+    ))
+}
+
+/// Preview location given by the RAF header
+fn raf_preview(data: &[u8]) -> Option<Range<usize>> {
+    let start = be_u32(data, 84)? as usize;
+    let length = be_u32(data, 88)? as usize;
+    Some(start..start.saturating_add(length))
+}
+
+/// Preview locations of the IFDs and sub-IFDs of a TIFF-based file
+///
+/// Previews are either JPEG interchange blocks or single JPEG strips. Strips compressed as
+/// new-style JPEG only count for reduced-resolution images, as DNG uses the same compression
+/// for its lossless sensor data.
+fn tiff_previews(data: &[u8]) -> Vec<Range<usize>> {
+    let Some(reader) = TiffReader::new(data) else {
+        return Vec::new();
+    };
+    let mut previews = Vec::new();
+    for ifd in tiff_ifds(&reader) {
+        let single = |tag: u16| ifd.single(tag);
+        if let (Some(start), Some(length)) = (single(TAG_JPEG_OFFSET), single(TAG_JPEG_LENGTH)) {
+            previews.push(start..start.saturating_add(length));
+        }
+        let compression = single(TAG_COMPRESSION);
+        if compression == Some(6)
+            || (compression == Some(7) && single(TAG_NEW_SUBFILE_TYPE) == Some(1))
+        {
+            if let (Some(start), Some(length)) =
+                (single(TAG_STRIP_OFFSETS), single(TAG_STRIP_BYTE_COUNTS))
+            {
+                previews.push(start..start.saturating_add(length));
+            }
+        }
+    }
+    previews
+}
+
+/// Entries of an IFD of a TIFF-based file
+struct Ifd<'a> {
+    reader: &'a TiffReader<'a>,
+    /// Offset of every entry by tag
+    entries: HashMap<u16, usize>,
+}
+
+impl Ifd<'_> {
+    /// Returns the value of an entry holding a single SHORT or LONG
+    fn single(&self, tag: u16) -> Option<usize> {
+        let &entry = self.entries.get(&tag)?;
+        (self.reader.u32_at(entry + 4)? == 1).then(|| entry_value(self.reader, entry))?
+    }
+}
+
+/// Lists the IFDs and sub-IFDs of a TIFF-based file
+fn tiff_ifds<'a>(reader: &'a TiffReader<'a>) -> Vec<Ifd<'a>> {
+    let mut ifds = Vec::new();
+    let mut pending: Vec<usize> = reader.first_ifd().into_iter().collect();
+    let mut visited = Vec::new();
+    while let Some(ifd) = pending.pop() {
+        if ifd == 0 || visited.contains(&ifd) || visited.len() >= MAX_IFDS {
+            continue;
+        }
+        visited.push(ifd);
+        let Some(count) = reader.u16_at(ifd) else {
+            continue;
+        };
+        let mut entries = HashMap::new();
+        for index in 0..count as usize {
+            let entry = ifd + 2 + index * ENTRY_SIZE;
+            let Some(tag) = reader.u16_at(entry) else {
+                break;
+            };
+            entries.insert(tag, entry);
+        }
+        if let Some(&entry) = entries.get(&TAG_SUB_IFDS) {
+            let count = reader.u32_at(entry + 4).unwrap_or(0) as usize;
+            if count == 1 {
+                pending.extend(reader.u32_at(entry + 8).map(|offset| offset as usize));
+            } else if let Some(list) = reader.u32_at(entry + 8) {
+                pending.extend(
+                    (0..count.min(MAX_IFDS))
+                        .filter_map(|index| reader.u32_at(list as usize + index * 4))
+                        .map(|offset| offset as usize),
+                );
+            }
+        }
+        let next = ifd + 2 + count as usize * ENTRY_SIZE;
+        pending.extend(reader.u32_at(next).map(|offset| offset as usize));
+        ifds.push(Ifd { reader, entries });
+    }
+    ifds
+}
+
+/// Reads the SHORT or LONG value stored inline in an IFD entry
+fn entry_value(reader: &TiffReader, entry: usize) -> Option<usize> {
+    match reader.u16_at(entry + 2)? {
+        3 => reader.u16_at(entry + 8).map(usize::from),
+        4 | 13 => reader.u32_at(entry + 8).map(|value| value as usize),
+        _ => None,
+    }
+}
+
+/// Preview location of the first track of a CR3 file, which holds the full-size JPEG
+fn cr3_preview(data: &[u8]) -> Option<Range<usize>> {
+    let stbl = [b"moov", b"trak", b"mdia", b"minf", b"stbl"]
+        .iter()
+        .try_fold(data, |parent, kind| find_box(parent, kind))?;
+    let stsz = find_box(stbl, b"stsz")?;
+    let length = match be_u32(stsz, 4)? {
+        0 => be_u32(stsz, 12)?,
+        size => size,
+    };
+    let start = find_box(stbl, b"co64")
+        .and_then(|co64| {
+            let bytes = co64.get(8..16)?.try_into().ok()?;
+            usize::try_from(u64::from_be_bytes(bytes)).ok()
+        })
+        .or_else(|| {
+            let stco = find_box(stbl, b"stco")?;
+            be_u32(stco, 8).map(|offset| offset as usize)
+        })?;
+    Some(start..start.saturating_add(length as usize))
+}
+
+/// Lists the ISO base media boxes of a block as their type and payload
+fn boxes(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut boxes = Vec::new();
+    let mut offset = 0;
+    while let (Some(size), Some(kind)) = (be_u32(data, offset), data.get(offset + 4..offset + 8)) {
+        let (header, size) = match size {
+            0 => (8, data.len() - offset),
+            1 => {
+                let Some(size) = data
+                    .get(offset + 8..offset + 16)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .and_then(|bytes| usize::try_from(u64::from_be_bytes(bytes)).ok())
+                else {
+                    break;
+                };
+                (16, size)
+            }
+            size => (8, size as usize),
+        };
+        let Some(payload) = offset
+            .checked_add(size)
+            .and_then(|end| data.get(offset + header..end))
+        else {
+            break;
+        };
+        boxes.push((kind.try_into().unwrap_or_default(), payload));
+        offset += size;
+    }
+    boxes
+}
+
+/// Returns the payload of the first box of a type
+fn find_box<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(data)
+        .into_iter()
+        .find(|(box_kind, _)| box_kind == kind)
+        .map(|(_, payload)| payload)
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        DynamicImage::new_rgb8(width, height)
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Jpeg)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_decode_previews() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // TIFF-based file with a small preview in IFD0 and a larger one in the sub-IFD of the
+        // 6000x4000 sensor image
+        let (small, large) = (jpeg(8, 6), jpeg(32, 24));
+        let mut nef = b"II*\0\x08\0\0\0".to_vec();
+        let sub_ifd = 8 + 2 + 3 * 12 + 4;
+        let small_at = sub_ifd + 2 + 5 * 12 + 4;
+        let large_at = small_at + small.len();
+        let entry = |tag: u16, kind: u16, value: usize| {
+            let mut entry = tag.to_le_bytes().to_vec();
+            entry.extend_from_slice(&kind.to_le_bytes());
+            entry.extend_from_slice(&1u32.to_le_bytes());
+            entry.extend_from_slice(&(value as u32).to_le_bytes());
+            entry
+        };
+        nef.extend_from_slice(&3u16.to_le_bytes());
+        nef.extend(entry(TAG_SUB_IFDS, 4, sub_ifd));
+        nef.extend(entry(TAG_JPEG_OFFSET, 4, small_at));
+        nef.extend(entry(TAG_JPEG_LENGTH, 4, small.len()));
+        nef.extend_from_slice(&0u32.to_le_bytes());
+        nef.extend_from_slice(&5u16.to_le_bytes());
+        nef.extend(entry(TAG_NEW_SUBFILE_TYPE, 4, 0));
+        nef.extend(entry(TAG_IMAGE_WIDTH, 3, 6000));
+        nef.extend(entry(TAG_IMAGE_LENGTH, 4, 4000));
+        nef.extend(entry(TAG_JPEG_OFFSET, 4, large_at));
+        nef.extend(entry(TAG_JPEG_LENGTH, 4, large.len()));
+        nef.extend_from_slice(&0u32.to_le_bytes());
+        nef.extend(&small);
+        nef.extend(&large);
+        let path = dir.path().join("photo.NEF");
+        std::fs::write(&path, &nef).unwrap();
+        assert!(is_raw(&path));
+        assert_eq!(dimensions(&path).unwrap(), (32, 24));
+        assert_eq!(decode(&path).unwrap().width(), 32);
+        assert_eq!(sensor_dimensions(&nef), Some((6000, 4000)));
+
+        // RAF file with the preview offsets in its header
+        let mut raf = RAF_MAGIC.to_vec();
+        raf.resize(84, 0);
+        raf.extend_from_slice(&100u32.to_be_bytes());
+        raf.extend_from_slice(&(small.len() as u32).to_be_bytes());
+        raf.resize(100, 0);
+        raf.extend(&small);
+        let path = dir.path().join("photo.raf");
+        std::fs::write(&path, &raf).unwrap();
+        assert_eq!(dimensions(&path).unwrap(), (8, 6));
+        assert_eq!(sensor_dimensions(&raf), None);
+
+        // CR3 file whose first track points at the full-size preview
+        let atom = |kind: &[u8], payload: &[u8]| {
+            let mut atom = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+            atom.extend_from_slice(kind);
+            atom.extend_from_slice(payload);
+            atom
+        };
+        let stsz = [
+            &[0u8; 4][..],
+            &(large.len() as u32).to_be_bytes(),
+            &1u32.to_be_bytes(),
+        ];
+        let mut stbl = atom(b"stsz", &stsz.concat());
+        let mut cr3 = atom(b"ftyp", b"crx \0\0\0\x01");
+        let moov_len = 8 * 5 + stbl.len() + 8 + 16;
+        let co64 = [
+            &[0u8; 4][..],
+            &1u32.to_be_bytes(),
+            &((cr3.len() + moov_len) as u64).to_be_bytes(),
+        ];
+        stbl.extend(atom(b"co64", &co64.concat()));
+        let moov = [b"stbl", b"minf", b"mdia", b"trak", b"moov"]
+            .iter()
+            .fold(stbl, |inner, kind| atom(*kind, &inner));
+        cr3.extend(moov);
+        cr3.extend(&large);
+        let path = dir.path().join("photo.CR3");
+        std::fs::write(&path, &cr3).unwrap();
+        assert_eq!(dimensions(&path).unwrap(), (32, 24));
+
+        let path = dir.path().join("broken.arw");
+        std::fs::write(&path, b"II*\0\xff\xff\xff\xff").unwrap();
+        assert!(decode(&path).is_err());
+        assert!(!is_raw(Path::new("photo.jpg")));
+    }
+}
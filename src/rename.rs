@@ -0,0 +1,273 @@
+//! Rename module
+//!
+//! This module renames photos from a template of their EXIF and file facts, e.g.
+//! `{date}_{camera}_{counter}`, to organize a shoot before framing it.
+
+use crate::report::BatchReport;
+use crate::source::{InputItem, Source};
+use crate::template::TemplateContext;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Characters that are not allowed in file names on common file systems
+const INVALID_FILE_NAME_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Builds the template context of a photo to rename
+///
+/// Provides the caption tokens plus `{counter}`, the 1-based position of the photo in the batch
+/// padded to four digits.
+///
+/// # Arguments
+/// * `input` - Path to the photo
+/// * `counter` - Position of the photo in the batch, starting at 1
+/// * `weather` - Whether to look up the weather tokens, only when the pattern uses them
+pub fn rename_context(input: &Path, counter: usize, weather: bool) -> TemplateContext {
+    let mut context = TemplateContext::for_file(input);
+    if let Ok(exif) = crate::exif::read_exif_info(input) {
+        context = context.with_exif(&exif);
+    }
+    for (token, value) in crate::exif::read_extra_fields(input) {
+        context.set(token, value);
+    }
+    #[cfg(feature = "online")]
+    let weather = if weather {
+        crate::exif::read_weather_fields(input)
+    } else {
+        Vec::new()
+    };
+    #[cfg(not(feature = "online"))]
+    let weather = {
+        let _ = weather;
+        Vec::new()
+    };
+    context.set_weather(weather);
+    context.set("counter", format!("{:04}", counter));
+    context
+}
+
+/// Replaces the characters a file name cannot hold with `_`
+///
+/// Leading and trailing spaces and dots are removed as well, so a rendered name never hides the
+/// file or escapes its directory.
+pub fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_control() || INVALID_FILE_NAME_CHARS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect::<String>()
+        .trim_matches(|c: char| c == ' ' || c == '.')
+        .to_string()
+}
+
+/// Renders the new path of every photo, next to the original
+///
+/// # Arguments
+/// * `items` - Photos to rename, in counter order
+/// * `pattern` - Template of the new file stem; the original extension is kept
+///
+/// # Returns
+/// * `Vec<Result<PathBuf, String>>` - New path of each photo, or why it cannot be renamed
+pub fn plan_renames(items: &[InputItem], pattern: &str) -> Vec<Result<PathBuf, String>> {
+    let weather = crate::template::uses_weather([pattern]);
+    items
+        .iter()
+        .enumerate()
+        .map(|(idx, item)| {
+            let rendered = rename_context(&item.path, idx + 1, weather).render(pattern);
+            if rendered.contains('{') {
+                return Err(format!("pattern token left unfilled in '{}'", rendered));
+            }
+            let stem = sanitize_file_name(&rendered);
+            if stem.is_empty() {
+                return Err("pattern renders an empty file name".to_string());
+            }
+            let name = match item.path.extension() {
+                Some(ext) => format!("{}.{}", stem, ext.to_string_lossy()),
+                None => stem,
+            };
+            Ok(item.path.with_file_name(name))
+        })
+        .collect()
+}
+
+/// Renames every photo of a source from a template
+///
+/// Photos whose new name is already taken, by an existing file or another photo of the batch,
+/// are reported and left as they are. The `.xmp` sidecars of a photo are renamed with it, and a
+/// photo whose sidecar name is taken is left as it is too.
+///
+/// # Arguments
+/// * `source` - Photos to rename
+/// * `pattern` - Template of the new file stem, e.g. `{date}_{camera}_{counter}`
+/// * `dry_run` - Whether to only print the new names
+///
+/// # Returns
+/// * `Result<BatchReport, Box<dyn Error>>` - Number of renamed photos and the failures
+///
+/// # Errors
+/// Returns an error if the source cannot be read
+pub fn rename_source(
+    source: &dyn Source,
+    pattern: &str,
+    dry_run: bool,
+) -> Result<BatchReport, Box<dyn Error>> {
+    let items = source.items()?;
+    let plan = plan_renames(&items, pattern);
+    let mut report = BatchReport::default();
+    for (idx, (item, target)) in items.iter().zip(&plan).enumerate() {
+        let target = match target {
+            Ok(target) => target,
+            Err(reason) => {
+                println!("[ERROR] Cannot rename {}: {}", item.path.display(), reason);
+                report.failures.push((item.path.clone(), reason.clone()));
+                continue;
+            }
+        };
+        if *target == item.path {
+            report
+                .skipped
+                .push((item.path.clone(), "already named".to_string()));
+            continue;
+        }
+        let taken_in_batch = plan[..idx]
+            .iter()
+            .any(|earlier| earlier.as_ref() == Ok(target));
+        let sidecars: Vec<(PathBuf, PathBuf)> = crate::xmp::sidecar_paths(&item.path)
+            .into_iter()
+            .map(|sidecar| {
+                let sidecar_target = sidecar_target(&item.path, &sidecar, target);
+                (sidecar, sidecar_target)
+            })
+            .collect();
+        let taken = if taken_in_batch {
+            Some(target)
+        } else {
+            std::iter::once(target)
+                .chain(sidecars.iter().map(|(_, sidecar_target)| sidecar_target))
+                .find(|path| path.exists())
+        };
+        if let Some(taken) = taken {
+            let reason = format!("{} is already taken", taken.display());
+            println!("[ERROR] Cannot rename {}: {}", item.path.display(), reason);
+            report.failures.push((item.path.clone(), reason));
+            continue;
+        }
+        if !dry_run {
+            if let Err(e) = std::fs::rename(&item.path, target) {
+                println!("[ERROR] Failed to rename {}: {}", item.path.display(), e);
+                report.failures.push((item.path.clone(), e.to_string()));
+                continue;
+            }
+        }
+        println!(
+            "[INFO] {} -> {}",
+            item.path.display(),
+            target.file_name().unwrap_or_default().to_string_lossy()
+        );
+        for (sidecar, sidecar_target) in &sidecars {
+            if !dry_run {
+                if let Err(e) = std::fs::rename(sidecar, sidecar_target) {
+                    println!("[WARN] Failed to rename {}: {}", sidecar.display(), e);
+                    continue;
+                }
+            }
+            println!(
+                "[INFO] {} -> {}",
+                sidecar.display(),
+                sidecar_target
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+            );
+        }
+        report.processed += 1;
+    }
+    Ok(report)
+}
+
+/// Returns the new path of a sidecar, keeping its `photo.jpg.xmp` or `photo.xmp` naming
+fn sidecar_target(photo: &Path, sidecar: &Path, target: &Path) -> PathBuf {
+    let mut appended = photo.as_os_str().to_owned();
+    appended.push(".xmp");
+    if sidecar == Path::new(&appended) {
+        let mut target = target.as_os_str().to_owned();
+        target.push(".xmp");
+        return PathBuf::from(target);
+    }
+    target.with_extension(sidecar.extension().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::DirectorySource;
+
+    #[test]
+    fn test_rename_source() {
+        assert_eq!(sanitize_file_name(" 1/250s: f/2.8 ."), "1_250s_ f_2.8");
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.jpg", "b.jpg", "c.jpg"] {
+            image::RgbImage::new(8, 6)
+                .save(dir.path().join(name))
+                .unwrap();
+        }
+        let source = DirectorySource {
+            root: dir.path().to_path_buf(),
+            extensions: vec!["jpg".to_string()],
+            sniff: false,
+        };
+
+        let report = rename_source(&source, "frame_{counter}", true).unwrap();
+        assert_eq!(report.processed, 3);
+        assert!(dir.path().join("a.jpg").exists());
+
+        let report = rename_source(&source, "frame_{counter}_{dimensions}", false).unwrap();
+        assert_eq!(report.processed, 3);
+        assert!(dir.path().join("frame_0001_8×6.jpg").exists());
+        assert!(!dir.path().join("a.jpg").exists());
+
+        // Collisions and missing values leave the files as they are
+        let report = rename_source(&source, "frame", false).unwrap();
+        assert_eq!((report.processed, report.failures.len()), (1, 2));
+        let report = rename_source(&source, "frame_0002_8×6", false).unwrap();
+        assert_eq!((report.processed, report.skipped.len()), (0, 1));
+        let report = rename_source(&source, "{date}", false).unwrap();
+        assert_eq!((report.processed, report.failures.len()), (0, 3));
+    }
+
+    #[test]
+    fn test_rename_moves_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.jpg", "b.jpg"] {
+            image::RgbImage::new(8, 6)
+                .save(dir.path().join(name))
+                .unwrap();
+        }
+        std::fs::write(dir.path().join("a.xmp"), "<x:xmpmeta/>").unwrap();
+        std::fs::write(dir.path().join("b.jpg.xmp"), "<x:xmpmeta/>").unwrap();
+        let source = DirectorySource {
+            root: dir.path().to_path_buf(),
+            extensions: vec!["jpg".to_string()],
+            sniff: false,
+        };
+
+        let report = rename_source(&source, "shot_{counter}", false).unwrap();
+        assert_eq!(report.processed, 2);
+        for name in ["shot_0001.xmp", "shot_0002.jpg.xmp"] {
+            assert!(dir.path().join(name).exists());
+        }
+        assert!(!dir.path().join("a.xmp").exists());
+
+        // A taken sidecar name leaves the photo and its sidecar as they are
+        std::fs::write(dir.path().join("frame_0001.xmp"), "<x:xmpmeta/>").unwrap();
+        let report = rename_source(&source, "frame_{counter}", false).unwrap();
+        assert_eq!((report.processed, report.failures.len()), (1, 1));
+        assert!(dir.path().join("shot_0001.jpg").exists());
+        assert!(dir.path().join("shot_0001.xmp").exists());
+    }
+}
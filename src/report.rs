@@ -0,0 +1,107 @@
+//! Batch report module
+//!
+//! This module collects the outcome of a batch and maps it to the process exit status.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Exit status of the process
+///
+/// | Code | Meaning                                          |
+/// |------|--------------------------------------------------|
+/// | 0    | Every input was processed                        |
+/// | 1    | Fatal error, or every input failed               |
+/// | 2    | Invalid arguments, e.g. missing input path       |
+/// | 3    | Some inputs failed, the others were processed    |
+/// | 4    | No input matched the input filters               |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Every input was processed
+    Success = 0,
+    /// Fatal error, or every input failed
+    Failure = 1,
+    /// Invalid arguments
+    InvalidArguments = 2,
+    /// Some inputs failed
+    PartialFailure = 3,
+    /// No input matched the input filters
+    NoInputs = 4,
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        ExitCode::from(status as u8)
+    }
+}
+
+/// Outcome of a batch
+#[derive(Debug, Clone, Default)]
+pub struct BatchReport {
+    /// Number of inputs processed successfully
+    pub processed: usize,
+    /// Inputs that failed, with the error message
+    pub failures: Vec<(PathBuf, String)>,
+    /// Inputs that were skipped on purpose, with the reason
+    pub skipped: Vec<(PathBuf, String)>,
+    /// Inputs that were processed but need a look, with the reason
+    pub flagged: Vec<(PathBuf, String)>,
+}
+
+impl BatchReport {
+    /// Returns the exit status matching the outcome of the batch
+    pub fn exit_status(&self) -> ExitStatus {
+        match (self.processed, self.failures.len()) {
+            (0, 0) => ExitStatus::NoInputs,
+            (_, 0) => ExitStatus::Success,
+            (0, _) => ExitStatus::Failure,
+            _ => ExitStatus::PartialFailure,
+        }
+    }
+
+    /// Prints a summary of the batch, listing the failed inputs
+    pub fn print_summary(&self) {
+        if self.processed == 0 && self.failures.is_empty() && self.skipped.is_empty() {
+            println!("[WARN] No input images found");
+            return;
+        }
+        println!(
+            "[INFO] Processed {} image(s), {} failed",
+            self.processed,
+            self.failures.len()
+        );
+        for (path, reason) in &self.skipped {
+            println!("[INFO] Skipped {}: {}", path.display(), reason);
+        }
+        for (path, reason) in &self.flagged {
+            println!("[WARN] Flagged {}: {}", path.display(), reason);
+        }
+        for (path, error) in &self.failures {
+            println!("[ERROR] {}: {}", path.display(), error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_status() {
+        let failure = (PathBuf::from("a.jpg"), "corrupt".to_string());
+        let report = |processed, failures: Vec<(PathBuf, String)>| BatchReport {
+            processed,
+            failures,
+            ..BatchReport::default()
+        };
+        assert_eq!(report(0, vec![]).exit_status(), ExitStatus::NoInputs);
+        assert_eq!(report(2, vec![]).exit_status(), ExitStatus::Success);
+        assert_eq!(
+            report(0, vec![failure.clone()]).exit_status(),
+            ExitStatus::Failure
+        );
+        assert_eq!(
+            report(1, vec![failure]).exit_status(),
+            ExitStatus::PartialFailure
+        );
+    }
+}
@@ -2,12 +2,14 @@
 //!
 //! This module handles loading and managing resources such as fonts and camera logos.
 
-use crate::logo::{logos, CameraLogos};
+use crate::logo_provider::LogoProvider;
 use rusttype::{Font, Scale};
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
+#[cfg(feature = "embedded-font")]
+use std::sync::OnceLock;
 
 /// Resources needed for image processing
 #[derive(Debug)]
@@ -27,17 +29,23 @@ impl Resources {
     ///
     /// # Arguments
     /// * `info_height` - Height of the information bar in pixels
+    /// * `fonts_dir` - Directory with `DejaVuSans.ttf` and `DejaVuSans-Bold.ttf`, `None` for the
+    ///   embedded font
     ///
     /// # Returns
     /// * `Result<Resources, Box<dyn std::error::Error>>` - Ok if successful
     ///
     /// # Errors
     /// Returns an error if fonts cannot be loaded
-    pub fn new(info_height: u32) -> Result<Self, Box<dyn Error>> {
-        let font_bold = Self::load_font_from_file("./fonts/DejaVuSans-Bold.ttf")
-            .unwrap_or_else(|_| Self::load_default_font());
-        let font_regular = Self::load_font_from_file("./fonts/DejaVuSans.ttf")
-            .unwrap_or_else(|_| Self::load_default_font());
+    pub fn new(info_height: u32, fonts_dir: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let load_font = |name: &str| match fonts_dir {
+            Some(dir) => {
+                Self::load_font_from_file(&dir.join(name)).or_else(|_| Self::load_default_font())
+            }
+            None => Self::load_default_font(),
+        };
+        let font_bold = load_font("DejaVuSans-Bold.ttf")?;
+        let font_regular = load_font("DejaVuSans.ttf")?;
 
         let scale_bold = Scale {
             x: info_height as f32 * 0.4,
@@ -66,10 +74,13 @@ impl Resources {
     ///
     /// # Errors
     /// Returns an error if the font file cannot be read or parsed
-    fn load_font_from_file(path: &str) -> Result<Font<'static>, Box<dyn Error>> {
-        if !Path::new(path).exists() {
-            println!("[INFO] Font file not found in {}, using default font", path);
-            return Ok(Self::load_default_font());
+    fn load_font_from_file(path: &Path) -> Result<Font<'static>, Box<dyn Error>> {
+        if !path.exists() {
+            println!(
+                "[INFO] Font file not found in {}, using default font",
+                path.display()
+            );
+            return Self::load_default_font();
         }
 
         let font_file = File::open(path)?;
@@ -81,13 +92,24 @@ impl Resources {
         Ok(font)
     }
 
-    /// Creates a default font
+    /// Returns the embedded default font, parsed on first use
     ///
     /// # Returns
-    /// * `Font<'static>` - Default font
-    fn load_default_font() -> Font<'static> {
-        Font::try_from_vec(include_bytes!("../fonts/DejaVuSans.ttf").to_vec())
-            .expect("Failed to load default font")
+    /// * `Result<Font<'static>, Box<dyn Error>>` - Default font
+    ///
+    /// # Errors
+    /// Returns an error if the crate is built without the `embedded-font` feature
+    fn load_default_font() -> Result<Font<'static>, Box<dyn Error>> {
+        #[cfg(feature = "embedded-font")]
+        {
+            static DEFAULT_FONT: OnceLock<Option<Font<'static>>> = OnceLock::new();
+            DEFAULT_FONT
+                .get_or_init(|| Font::try_from_bytes(include_bytes!("../fonts/DejaVuSans.ttf")))
+                .clone()
+                .ok_or_else(|| "Failed to parse the embedded font".into())
+        }
+        #[cfg(not(feature = "embedded-font"))]
+        Err("No font file found and the embedded font is not built in, set --fonts-dir".into())
     }
 }
 
@@ -118,6 +140,7 @@ pub fn infer_camera_brand(camera_model: &str) -> Option<String> {
 /// # Arguments
 /// * `camera_model` - Camera model name
 /// * `custom_logo_path` - Optional path to a custom logo file
+/// * `provider` - Source of the brand logos
 ///
 /// # Returns
 /// * `Result<Option<image::DynamicImage>, Box<dyn Error>>` - Ok if successful
@@ -127,6 +150,7 @@ pub fn infer_camera_brand(camera_model: &str) -> Option<String> {
 pub fn load_camera_logo(
     camera_model: &str,
     custom_logo_path: Option<&Path>,
+    provider: &dyn LogoProvider,
 ) -> Result<Option<image::DynamicImage>, Box<dyn Error>> {
     // First try to load from custom logo file if provided
     if let Some(logo_path) = custom_logo_path {
@@ -163,49 +187,14 @@ pub fn load_camera_logo(
         }
     };
 
-    // Then try to load from external file
-    let logo_path = format!("./logos/{}.png", brand);
-    if Path::new(&logo_path).exists() {
-        match image::open(&logo_path) {
-            Ok(img) => {
-                println!(
-                    "[INFO] Using external logo file for camera brand '{}'",
-                    brand
-                );
-                return Ok(Some(img));
-            }
-            Err(e) => {
-                println!(
-                    "[WARN] Failed to load logo for camera brand '{}' from file: {}",
-                    brand, e
-                );
-            }
-        }
-    }
-
-    // If external file not found or failed to load, try hardcoded base64 logo
-    let base64_logo = match brand.to_lowercase().as_str() {
-        "canon" => Some(logos::CANON),
-        "fujifilm" => Some(logos::FUJIFILM),
-        "nikon" => Some(logos::NIKON),
-        "panasonic" => Some(logos::PANASONIC),
-        "sony" => Some(logos::SONY),
-        _ => None,
-    };
-
-    if let Some(base64_str) = base64_logo {
-        match CameraLogos::load_from_base64(base64_str) {
-            Ok(img) => {
-                println!("[INFO] Using hardcoded logo for camera brand '{}'", brand);
-                return Ok(Some(img));
-            }
-            Err(e) => {
-                println!(
-                    "[WARN] Failed to load hardcoded logo for camera brand '{}': {}",
-                    brand, e
-                );
-            }
-        }
+    // Then ask the logo provider
+    match provider.logo(&brand) {
+        Ok(Some(img)) => return Ok(Some(img)),
+        Ok(None) => {}
+        Err(e) => println!(
+            "[WARN] Failed to load logo for camera brand '{}': {}",
+            brand, e
+        ),
     }
 
     println!(
@@ -215,6 +204,31 @@ pub fn load_camera_logo(
     Ok(None)
 }
 
+/// Loads a secondary logo (e.g. a studio mark) from a file
+///
+/// # Arguments
+/// * `logo_path` - Path to the logo file
+///
+/// # Returns
+/// * `Result<Option<image::DynamicImage>, Box<dyn Error>>` - Ok if successful
+///
+/// # Errors
+/// Returns an error if the logo file cannot be read or parsed
+pub fn load_secondary_logo(
+    logo_path: &Path,
+) -> Result<Option<image::DynamicImage>, Box<dyn Error>> {
+    if !logo_path.exists() {
+        println!(
+            "[WARN] Secondary logo file not found in {}, skipping secondary logo",
+            logo_path.display()
+        );
+        return Ok(None);
+    }
+    let img = image::open(logo_path)?;
+    println!("[INFO] Using secondary logo file: {}", logo_path.display());
+    Ok(Some(img))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -256,7 +270,7 @@ mod tests {
     #[test]
     fn test_resources_scale_calculation() {
         let info_height = 180;
-        let resources = Resources::new(info_height).unwrap();
+        let resources = Resources::new(info_height, Some(Path::new("./fonts"))).unwrap();
 
         // Test scale calculations
         assert_eq!(resources.scale_bold.x, info_height as f32 * 0.4);
@@ -0,0 +1,291 @@
+//! Retag module
+//!
+//! This module updates text fields of the EXIF of JPEG files, in copies or in place, so a batch
+//! can be tagged with its artist and copyright before a framing run.
+//!
+//! The existing EXIF block is kept byte for byte: a new primary IFD with the updated fields is
+//! appended and the header pointed at it. Maker notes address their data relative to the TIFF
+//! header, so a full rewrite that moves them would corrupt them.
+
+use crate::report::BatchReport;
+use crate::source::Source;
+use exif::Tag;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// ASCII field type of a TIFF entry
+const TYPE_ASCII: u16 = 2;
+
+/// Returns where the retagged copies of a file or directory go without `--output`
+///
+/// # Arguments
+/// * `path` - JPEG file or directory to retag
+///
+/// # Returns
+/// * `PathBuf` - `photo_retagged.jpg` next to `photo.jpg`, or `shoot_retagged` next to `shoot`
+pub fn default_output(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension().filter(|_| !path.is_dir()) {
+        Some(ext) => path.with_file_name(format!("{}_retagged.{}", stem, ext.to_string_lossy())),
+        None => path.with_file_name(format!("{}_retagged", stem)),
+    }
+}
+
+/// Writes EXIF text fields into a JPEG file
+///
+/// The other EXIF fields, including maker notes and fields of unknown types, and the image data
+/// are kept as they are.
+///
+/// # Arguments
+/// * `input` - JPEG file to read
+/// * `output` - File to write, may be `input` to update it in place
+/// * `updates` - Text fields to set, e.g. `(Tag::Artist, "Jane Doe")`
+///
+/// # Errors
+/// Returns an error if the file is not a JPEG file or cannot be read or written
+pub fn retag_file(
+    input: &Path,
+    output: &Path,
+    updates: &[(Tag, String)],
+) -> Result<(), Box<dyn Error>> {
+    let data = std::fs::read(input)?;
+    let tiff = match crate::exif::read_exif(input) {
+        Ok(exif) => append_primary_ifd(exif.buf(), updates)?,
+        Err(_) => crate::exif::encode_exif(None, updates, &|_| true)?,
+    };
+    let retagged = crate::jpeg::with_exif(&data, &tiff)?;
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Write next to the target first so an interrupted run never leaves a truncated photo
+    let partial = output.with_extension("retag-partial");
+    std::fs::write(&partial, retagged)?;
+    std::fs::rename(&partial, output)?;
+    Ok(())
+}
+
+/// Appends a copy of the primary IFD with text fields replaced to a TIFF block
+///
+/// The header is pointed at the new IFD and the rest of the block stays at its offsets, so
+/// every offset into it, including those inside maker notes, remains valid.
+///
+/// # Arguments
+/// * `tiff` - EXIF block starting with the TIFF header
+/// * `updates` - Text fields of the primary image to set
+///
+/// # Returns
+/// * `Result<Vec<u8>, Box<dyn Error>>` - Updated EXIF block
+///
+/// # Errors
+/// Returns an error if the TIFF header or the primary IFD is malformed
+fn append_primary_ifd(tiff: &[u8], updates: &[(Tag, String)]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let little_endian = match tiff.get(..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => return Err("Malformed EXIF header".into()),
+    };
+    let u16_at = |offset: usize| -> Option<u16> {
+        let bytes = tiff.get(offset..offset.checked_add(2)?)?.try_into().ok()?;
+        Some(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let u32_at = |offset: usize| -> Option<u32> {
+        let bytes = tiff.get(offset..offset.checked_add(4)?)?.try_into().ok()?;
+        Some(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+    let u16_bytes = |value: u16| {
+        if little_endian {
+            value.to_le_bytes()
+        } else {
+            value.to_be_bytes()
+        }
+    };
+    let u32_bytes = |value: u32| {
+        if little_endian {
+            value.to_le_bytes()
+        } else {
+            value.to_be_bytes()
+        }
+    };
+    let malformed = || "Malformed primary EXIF directory";
+
+    let ifd = u32_at(4).ok_or_else(malformed)? as usize;
+    let count = u16_at(ifd).ok_or_else(malformed)? as usize;
+    let next_ifd = u32_at(ifd + 2 + count * 12).ok_or_else(malformed)?;
+    let mut entries: Vec<(u16, [u8; 12])> = (0..count)
+        .map(|index| {
+            let start = ifd + 2 + index * 12;
+            let entry: [u8; 12] = tiff[start..start + 12].try_into().ok()?;
+            Some((u16_at(start)?, entry))
+        })
+        .collect::<Option<_>>()
+        .ok_or_else(malformed)?;
+    entries.retain(|(tag, _)| updates.iter().all(|(update, _)| update.number() != *tag));
+
+    let mut output = tiff.to_vec();
+    for (tag, text) in updates {
+        let mut value = text.clone().into_bytes();
+        value.push(0);
+        let mut entry = [0; 12];
+        entry[..2].copy_from_slice(&u16_bytes(tag.number()));
+        entry[2..4].copy_from_slice(&u16_bytes(TYPE_ASCII));
+        entry[4..8].copy_from_slice(&u32_bytes(value.len() as u32));
+        if value.len() <= 4 {
+            entry[8..8 + value.len()].copy_from_slice(&value);
+        } else {
+            output.resize(output.len() + output.len() % 2, 0);
+            let offset = u32::try_from(output.len()).map_err(|_| "EXIF block is too large")?;
+            entry[8..].copy_from_slice(&u32_bytes(offset));
+            output.extend_from_slice(&value);
+        }
+        entries.push((tag.number(), entry));
+    }
+    entries.sort_by_key(|(tag, _)| *tag);
+
+    output.resize(output.len() + output.len() % 2, 0);
+    let new_ifd = u32::try_from(output.len()).map_err(|_| "EXIF block is too large")?;
+    output.extend_from_slice(&u16_bytes(entries.len() as u16));
+    for (_, entry) in &entries {
+        output.extend_from_slice(entry);
+    }
+    output.extend_from_slice(&u32_bytes(next_ifd));
+    output[4..8].copy_from_slice(&u32_bytes(new_ifd));
+    Ok(output)
+}
+
+/// Writes EXIF text fields into every JPEG file of a source
+///
+/// # Arguments
+/// * `source` - Images to retag
+/// * `output` - Directory receiving the retagged copies, `None` to update the files in place
+/// * `updates` - Text fields to set
+///
+/// # Returns
+/// * `Result<BatchReport, Box<dyn Error>>` - Number of retagged files and the failures
+///
+/// # Errors
+/// Returns an error if the source cannot be read
+pub fn retag_source(
+    source: &dyn Source,
+    output: Option<&Path>,
+    updates: &[(Tag, String)],
+) -> Result<BatchReport, Box<dyn Error>> {
+    let mut report = BatchReport::default();
+    for item in source.items()? {
+        let target = match output {
+            Some(output) => output.join(&item.relative),
+            None => item.path.clone(),
+        };
+        match retag_file(&item.path, &target, updates) {
+            Ok(()) => {
+                println!("[INFO] Retagged {}", target.display());
+                report.processed += 1;
+            }
+            Err(e) => {
+                println!("[ERROR] Failed to retag {}: {}", item.path.display(), e);
+                report.failures.push((item.path, e.to_string()));
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retag_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("photo.jpg");
+        image::RgbImage::new(16, 8).save(&input).unwrap();
+        let copy = dir.path().join("out/photo.jpg");
+        let updates = [
+            (Tag::Artist, "Jane Doe".to_string()),
+            (Tag::Copyright, "CC-BY".to_string()),
+        ];
+        retag_file(&input, &copy, &updates).unwrap();
+        assert_eq!(
+            crate::exif::read_text_field(&copy, Tag::Artist).as_deref(),
+            Some("Jane Doe")
+        );
+        assert_eq!(image::open(&copy).unwrap().width(), 16);
+        assert!(crate::exif::read_text_field(&input, Tag::Artist).is_none());
+
+        // Updating in place keeps the other fields
+        retag_file(&copy, &copy, &[(Tag::Artist, "John Roe".to_string())]).unwrap();
+        assert_eq!(
+            crate::exif::read_text_field(&copy, Tag::Artist).as_deref(),
+            Some("John Roe")
+        );
+        assert_eq!(
+            crate::exif::read_text_field(&copy, Tag::Copyright).as_deref(),
+            Some("CC-BY")
+        );
+        assert!(retag_file(&dir.path().join("missing.jpg"), &copy, &updates).is_err());
+    }
+
+    #[test]
+    fn test_retag_keeps_maker_note() {
+        use exif::{Field, In, Value};
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("photo.jpg");
+        let mut writer = exif::experimental::Writer::new();
+        let maker_note: Vec<u8> = (0..64).map(|i| (i * 7 + 3) as u8).collect();
+        let fields = [
+            Field {
+                tag: Tag::Make,
+                ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![b"Canon".to_vec()]),
+            },
+            Field {
+                tag: Tag::Artist,
+                ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![b"Somebody Else".to_vec()]),
+            },
+            Field {
+                tag: Tag::MakerNote,
+                ifd_num: In::PRIMARY,
+                value: Value::Undefined(maker_note.clone(), 0),
+            },
+        ];
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        writer.write(&mut tiff, true).unwrap();
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new(&mut jpeg)
+            .encode(&[0; 16 * 8 * 3], 16, 8, image::ColorType::Rgb8)
+            .unwrap();
+        let jpeg = crate::jpeg::with_exif(&jpeg, tiff.get_ref()).unwrap();
+        std::fs::write(&input, jpeg).unwrap();
+
+        let copy = dir.path().join("copy.jpg");
+        retag_file(&input, &copy, &[(Tag::Artist, "Jane Doe".to_string())]).unwrap();
+        let before = crate::exif::read_exif(&input).unwrap();
+        let after = crate::exif::read_exif(&copy).unwrap();
+        assert_eq!(
+            crate::exif::read_text_field(&copy, Tag::Artist).as_deref(),
+            Some("Jane Doe")
+        );
+        assert_eq!(
+            crate::exif::read_text_field(&copy, Tag::Make).as_deref(),
+            Some("Canon")
+        );
+        // The maker note stays at its offset from the TIFF header
+        let position = |buf: &[u8]| buf.windows(maker_note.len()).position(|w| w == maker_note);
+        assert!(position(before.buf()).is_some());
+        assert_eq!(position(before.buf()), position(after.buf()));
+        let note = after.get_field(Tag::MakerNote, In::PRIMARY).unwrap();
+        assert!(matches!(&note.value, Value::Undefined(data, _) if *data == maker_note));
+    }
+}
@@ -0,0 +1,110 @@
+//! Safe area module
+//!
+//! This module draws the safe areas of social platforms over an output, so important bar text
+//! isn't placed where the app crops the image or covers it with its own interface.
+
+use crate::layout::Rect;
+use clap::ValueEnum;
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::draw_hollow_rect_mut;
+
+/// Platform whose safe area is drawn over the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SafeArea {
+    /// Centered 3:4 crop shown in the Instagram profile grid
+    InstagramGrid,
+    /// Instagram story, minus the top profile bar and the bottom reply field
+    InstagramStory,
+}
+
+impl SafeArea {
+    /// Returns the safe area of a canvas
+    ///
+    /// # Arguments
+    /// * `width` - Width of the canvas in pixels
+    /// * `height` - Height of the canvas in pixels
+    ///
+    /// # Returns
+    /// * `Rect` - Region of the canvas that stays visible and uncovered
+    pub fn safe_rect(self, width: u32, height: u32) -> Rect {
+        match self {
+            SafeArea::InstagramGrid => {
+                // Largest centered 3:4 region
+                let (crop_width, crop_height) = if width * 4 > height * 3 {
+                    (height * 3 / 4, height)
+                } else {
+                    (width, width * 4 / 3)
+                };
+                Rect {
+                    x: ((width - crop_width) / 2) as i32,
+                    y: ((height - crop_height) / 2) as i32,
+                    width: crop_width,
+                    height: crop_height,
+                }
+            }
+            SafeArea::InstagramStory => {
+                // Interface zones of a 1080x1920 story, scaled to the canvas
+                let top = height * 250 / 1920;
+                let bottom = height * 340 / 1920;
+                let side = width * 64 / 1080;
+                Rect {
+                    x: side as i32,
+                    y: top as i32,
+                    width: width - side * 2,
+                    height: height - top - bottom,
+                }
+            }
+        }
+    }
+}
+
+/// Dims everything outside the safe area and outlines it
+///
+/// # Arguments
+/// * `img` - Output to draw on
+/// * `area` - Platform whose safe area is drawn
+pub fn draw_guides(img: &mut RgbaImage, area: SafeArea) {
+    let safe = area.safe_rect(img.width(), img.height());
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let (x, y) = (x as i32, y as i32);
+        let inside =
+            x >= safe.x && x < safe.right() && y >= safe.y && y < safe.y + safe.height as i32;
+        if !inside {
+            for c in 0..3 {
+                pixel[c] /= 2;
+            }
+        }
+    }
+    if safe.width > 0 && safe.height > 0 {
+        draw_hollow_rect_mut(
+            img,
+            imageproc::rect::Rect::at(safe.x, safe.y).of_size(safe.width, safe.height),
+            Rgba([0, 255, 255, 255]),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_rect() {
+        let grid = SafeArea::InstagramGrid.safe_rect(1600, 900);
+        assert_eq!(
+            (grid.x, grid.y, grid.width, grid.height),
+            (462, 0, 675, 900)
+        );
+        let grid = SafeArea::InstagramGrid.safe_rect(1080, 1920);
+        assert_eq!(
+            (grid.x, grid.y, grid.width, grid.height),
+            (0, 240, 1080, 1440)
+        );
+
+        let story = SafeArea::InstagramStory.safe_rect(1080, 1920);
+        assert_eq!(
+            (story.x, story.y, story.width, story.height),
+            (64, 250, 952, 1330)
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! EXIF salvage module
+//!
+//! Some cameras and editors write EXIF blocks with truncated IFDs, entries pointing past the end
+//! of the block or broken sub-IFD pointers. The strict reader rejects the whole block in that
+//! case; this module walks the primary IFD and its Exif sub-IFD entry by entry instead, keeping
+//! every field whose value can be read and dropping the rest.
+
+use exif::{Context, Field, In, Rational, SRational, Tag, Value};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+/// Size of an IFD entry in bytes
+const ENTRY_SIZE: usize = 12;
+
+/// Reads the readable fields of the primary image from a file with malformed EXIF
+///
+/// # Arguments
+/// * `file_path` - Path to a JPEG or TIFF-based image file
+///
+/// # Returns
+/// * `Option<Vec<Field>>` - Salvaged fields, `None` if no EXIF block could be located
+pub fn salvage_file(file_path: &Path) -> Option<Vec<Field>> {
+    let data = fs::read(file_path).ok()?;
+    let tiff = if data.starts_with(&[0xff, 0xd8]) {
+        exif::get_exif_attr_from_jpeg(&mut Cursor::new(&data)).ok()?
+    } else {
+        data
+    };
+    let fields = salvage_fields(&tiff);
+    (!fields.is_empty()).then_some(fields)
+}
+
+/// Reads the readable fields of the primary image from a raw TIFF-structured EXIF block
+///
+/// # Arguments
+/// * `data` - EXIF block starting with the TIFF header
+///
+/// # Returns
+/// * `Vec<Field>` - Fields of IFD0 and the Exif sub-IFD that could be decoded
+pub fn salvage_fields(data: &[u8]) -> Vec<Field> {
+    salvage_ifd(data, Context::Tiff)
+}
+
+/// Reads the readable fields of the first IFD of a TIFF-structured block
+///
+/// # Arguments
+/// * `data` - Block starting with the TIFF header
+/// * `context` - Context of the tags of the first IFD, e.g. `Context::Exif` for a block holding
+///   the Exif IFD on its own
+///
+/// # Returns
+/// * `Vec<Field>` - Fields of the IFD, and of the Exif sub-IFD it points to, that could be decoded
+pub fn salvage_ifd(data: &[u8], context: Context) -> Vec<Field> {
+    let Some(reader) = TiffReader::new(data) else {
+        return Vec::new();
+    };
+    let mut fields = Vec::new();
+    if let Some(offset) = reader.first_ifd() {
+        reader.walk_ifd(offset, context, &mut fields);
+    }
+    fields
+}
+
+/// Byte-order aware accessor over a TIFF-structured block that never reads out of bounds
+pub struct TiffReader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> TiffReader<'a> {
+    /// Creates a reader over a block, `None` if it lacks a TIFF byte order mark
+    pub fn new(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        Some(TiffReader {
+            data,
+            little_endian,
+        })
+    }
+
+    /// Returns the offset of the first IFD
+    pub fn first_ifd(&self) -> Option<usize> {
+        self.u32_at(4).map(|offset| offset as usize)
+    }
+
+    fn bytes<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        let end = offset.checked_add(N)?;
+        self.data.get(offset..end)?.try_into().ok()
+    }
+
+    /// Reads a 16-bit value, `None` if it lies out of bounds
+    pub fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes = self.bytes(offset)?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    /// Reads a 32-bit value, `None` if it lies out of bounds
+    pub fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes = self.bytes(offset)?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// Finds an entry of an IFD and decodes its value
+    ///
+    /// # Arguments
+    /// * `offset` - Offset of the IFD
+    /// * `tag` - Number of the tag to find
+    ///
+    /// # Returns
+    /// * `Option<Value>` - Value of the first entry with the tag, `None` if the IFD has none or it
+    ///   cannot be decoded
+    pub fn find_entry(&self, offset: usize, tag: u16) -> Option<Value> {
+        let count = self.u16_at(offset)?;
+        (0..count as usize).find_map(|index| {
+            let entry = offset + 2 + index * ENTRY_SIZE;
+            if self.u16_at(entry)? != tag {
+                return None;
+            }
+            let kind = self.u16_at(entry + 2)?;
+            let count = self.u32_at(entry + 4)?;
+            self.value(kind, count as usize, entry + 8)
+        })
+    }
+
+    /// Collects the readable entries of an IFD, following the Exif sub-IFD pointer of IFD0
+    ///
+    /// A truncated entry array keeps the entries before the cut.
+    fn walk_ifd(&self, offset: usize, context: Context, fields: &mut Vec<Field>) {
+        let Some(count) = self.u16_at(offset) else {
+            return;
+        };
+        for index in 0..count as usize {
+            let entry = offset + 2 + index * ENTRY_SIZE;
+            let (Some(tag), Some(kind), Some(count)) = (
+                self.u16_at(entry),
+                self.u16_at(entry + 2),
+                self.u32_at(entry + 4),
+            ) else {
+                break;
+            };
+            let tag = Tag(context, tag);
+            if tag == Tag::ExifIFDPointer {
+                if let Some(sub_ifd) = self.u32_at(entry + 8) {
+                    self.walk_ifd(sub_ifd as usize, Context::Exif, fields);
+                }
+                continue;
+            }
+            if let Some(value) = self.value(kind, count as usize, entry + 8) {
+                fields.push(Field {
+                    tag,
+                    ifd_num: In::PRIMARY,
+                    value,
+                });
+            }
+        }
+    }
+
+    /// Decodes the value of an entry, `None` if its type is unsupported or it lies out of bounds
+    fn value(&self, kind: u16, count: usize, value_at: usize) -> Option<Value> {
+        let unit: usize = match kind {
+            1 | 2 | 7 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 10 => 8,
+            _ => return None,
+        };
+        let length = unit.checked_mul(count)?;
+        let start = if length <= 4 {
+            value_at
+        } else {
+            self.u32_at(value_at)? as usize
+        };
+        let bytes = self.data.get(start..start.checked_add(length)?)?;
+        let at = |index: usize| start + index * unit;
+        Some(match kind {
+            1 => Value::Byte(bytes.to_vec()),
+            7 => Value::Undefined(bytes.to_vec(), start as u32),
+            2 => Value::Ascii(
+                bytes
+                    .split(|&byte| byte == 0)
+                    .filter(|part| !part.is_empty())
+                    .map(<[u8]>::to_vec)
+                    .collect(),
+            ),
+            3 => Value::Short(
+                (0..count)
+                    .map(|i| self.u16_at(at(i)))
+                    .collect::<Option<_>>()?,
+            ),
+            4 => Value::Long(
+                (0..count)
+                    .map(|i| self.u32_at(at(i)))
+                    .collect::<Option<_>>()?,
+            ),
+            5 => Value::Rational(
+                (0..count)
+                    .map(|i| {
+                        Some(Rational {
+                            num: self.u32_at(at(i))?,
+                            denom: self.u32_at(at(i) + 4)?,
+                        })
+                    })
+                    .collect::<Option<_>>()?,
+            ),
+            _ => Value::SRational(
+                (0..count)
+                    .map(|i| {
+                        Some(SRational {
+                            num: self.u32_at(at(i))? as i32,
+                            denom: self.u32_at(at(i) + 4)? as i32,
+                        })
+                    })
+                    .collect::<Option<_>>()?,
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_salvage_fields() {
+        // Little endian block: IFD0 claims 3 entries but is cut after the second, whose value
+        // points past the end of the block
+        let mut data = b"II*\0\x08\0\0\0".to_vec();
+        data.extend_from_slice(&3u16.to_le_bytes());
+        // Model, ASCII, 4 bytes inline
+        data.extend_from_slice(&0x0110u16.to_le_bytes());
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&4u32.to_le_bytes());
+        data.extend_from_slice(b"X1\0\0");
+        // LensModel, ASCII, 20 bytes at an out of bounds offset
+        data.extend_from_slice(&0xa434u16.to_le_bytes());
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&20u32.to_le_bytes());
+        data.extend_from_slice(&0x1000u32.to_le_bytes());
+
+        assert!(exif::parse_exif(&data).is_err());
+        let fields = salvage_fields(&data);
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].tag, Tag::Model);
+        assert_eq!(fields[0].display_value().to_string(), "\"X1\"");
+
+        assert!(salvage_fields(b"not exif").is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+//! Sharpness module
+//!
+//! This module measures the sharpness of a photo as the variance of its Laplacian, so obviously
+//! blurry frames can be skipped or flagged in a batch. Flat, low-detail scenes also score low,
+//! so the threshold is a starting point to tune per shoot.
+
+use clap::ValueEnum;
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+/// Long edge the photo is reduced to before measuring, so scores compare across resolutions
+const MEASURE_SIZE: u32 = 1024;
+
+/// What to do with frames below the sharpness threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BlurMode {
+    /// Leave blurry frames out of the batch
+    Skip,
+    /// Process blurry frames and list them in the summary
+    Flag,
+}
+
+/// Returns the sharpness score of a photo, the variance of its 4-neighbor Laplacian
+///
+/// # Arguments
+/// * `img` - Photo to measure
+///
+/// # Returns
+/// * `f64` - Score, higher is sharper; well focused photos typically score above 100
+pub fn sharpness(img: &DynamicImage) -> f64 {
+    let img = if img.width().max(img.height()) > MEASURE_SIZE {
+        img.resize(MEASURE_SIZE, MEASURE_SIZE, FilterType::Triangle)
+    } else {
+        img.clone()
+    };
+    let gray = img.to_luma8();
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let at = |x: u32, y: u32| gray.get_pixel(x, y)[0] as f64;
+    let mut sum = 0.0;
+    let mut sum_squares = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let laplacian =
+                at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1) - 4.0 * at(x, y);
+            sum += laplacian;
+            sum_squares += laplacian * laplacian;
+        }
+    }
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    sum_squares / count - mean * mean
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn test_sharpness() {
+        let checkers = GrayImage::from_fn(64, 64, |x, y| {
+            Luma([if (x + y) % 2 == 0 { 0 } else { 255 }])
+        });
+        let sharp = DynamicImage::ImageLuma8(checkers);
+        let blurred = sharp.blur(4.0);
+        assert!(sharpness(&sharp) > 100.0);
+        assert!(sharpness(&blurred) < sharpness(&sharp) / 100.0);
+        let flat = DynamicImage::ImageLuma8(GrayImage::new(64, 64));
+        assert_eq!(sharpness(&flat), 0.0);
+    }
+}
@@ -0,0 +1,136 @@
+//! Sidecar module
+//!
+//! This module loads per-image style overrides from a `<image>.lensight.toml` file placed next
+//! to an input image, so a single photo of a batch can be tweaked without a separate run.
+
+use crate::metadata::FilmInfo;
+use crate::options::ProcessOptions;
+use crate::theme::StyleOverrides;
+use serde::Deserialize;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Suffix appended to an image file name to locate its sidecar
+pub const SIDECAR_SUFFIX: &str = ".lensight.toml";
+
+/// Per-image overrides read from a sidecar file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sidecar {
+    /// Height of the information bar in pixels
+    pub info_height: Option<u32>,
+    /// Path to a custom logo file, relative to the sidecar
+    pub logo: Option<PathBuf>,
+    /// Caption rendered below the shooting parameters
+    pub caption: Option<String>,
+    /// Annotation appended to the lens line
+    pub lens_suffix: Option<String>,
+    /// Film stock, developer and scan resolution
+    #[serde(default)]
+    pub film: FilmInfo,
+    /// Style overrides
+    #[serde(default)]
+    pub style: StyleOverrides,
+}
+
+impl Sidecar {
+    /// Returns the sidecar path of an image
+    ///
+    /// # Arguments
+    /// * `input` - Path to the image file
+    ///
+    /// # Returns
+    /// * `PathBuf` - Path of the sidecar, e.g. `photo.jpg.lensight.toml`
+    pub fn path_for(input: &Path) -> PathBuf {
+        let mut name = input.file_name().unwrap_or_default().to_os_string();
+        name.push(SIDECAR_SUFFIX);
+        input.with_file_name(name)
+    }
+
+    /// Loads the sidecar of an image if there is one
+    ///
+    /// # Arguments
+    /// * `input` - Path to the image file
+    ///
+    /// # Returns
+    /// * `Result<Option<Sidecar>, Box<dyn Error>>` - Sidecar if it exists
+    ///
+    /// # Errors
+    /// Returns an error if the sidecar exists but cannot be read or parsed
+    pub fn load_for(input: &Path) -> Result<Option<Sidecar>, Box<dyn Error>> {
+        let path = Self::path_for(input);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let mut sidecar: Sidecar = toml::from_str(&content)
+            .map_err(|e| format!("Invalid sidecar {}: {}", path.display(), e))?;
+
+        // Paths in the sidecar are relative to the sidecar itself
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        sidecar.logo = sidecar.logo.map(|logo| base.join(logo));
+        sidecar.style.secondary_logo = sidecar.style.secondary_logo.map(|logo| base.join(logo));
+        println!("[INFO] Using sidecar overrides from {}", path.display());
+        Ok(Some(sidecar))
+    }
+
+    /// Applies the overrides on top of the batch options
+    ///
+    /// # Arguments
+    /// * `options` - Options to override
+    pub fn apply(&self, options: &mut ProcessOptions) {
+        if let Some(info_height) = self.info_height {
+            options.info_height = info_height;
+        }
+        if let Some(logo) = &self.logo {
+            options.logo = Some(logo.clone());
+        }
+        if let Some(caption) = &self.caption {
+            options.caption = Some(caption.clone());
+        }
+        if let Some(lens_suffix) = &self.lens_suffix {
+            options.lens_suffix = Some(lens_suffix.clone());
+        }
+        options.film.override_with(&self.film);
+        self.style.apply(&mut options.theme);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::TextCase;
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(
+            Sidecar::path_for(Path::new("shoot/photo.jpg")),
+            PathBuf::from("shoot/photo.jpg.lensight.toml")
+        );
+    }
+
+    #[test]
+    fn test_sidecar_apply() {
+        let sidecar: Sidecar = toml::from_str(
+            r#"
+            caption = "Kyoto, spring"
+            lens_suffix = "via M42 adapter"
+            [film]
+            stock = "Kodak Portra 400"
+            [style]
+            camera_case = "upper"
+            logo_opacity = 0.5
+            "#,
+        )
+        .unwrap();
+        let mut options = ProcessOptions::default();
+        sidecar.apply(&mut options);
+        assert_eq!(options.caption.as_deref(), Some("Kyoto, spring"));
+        assert_eq!(options.info_height, 180);
+        assert_eq!(options.lens_suffix.as_deref(), Some("via M42 adapter"));
+        assert_eq!(options.film.stock.as_deref(), Some("Kodak Portra 400"));
+        assert_eq!(options.theme.camera_case, TextCase::Upper);
+        assert_eq!(options.theme.lens_case, TextCase::AsIs);
+        assert_eq!(options.theme.logo_opacity, 0.5);
+    }
+}
@@ -0,0 +1,216 @@
+//! Output sink module
+//!
+//! This module abstracts where encoded outputs are written, so the batch engine doesn't assume
+//! a local output directory. Sinks receive the output path computed by the engine together with
+//! the encoded bytes.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Destination of encoded outputs
+pub trait Sink: fmt::Debug + Send + Sync {
+    /// Writes an encoded output
+    ///
+    /// # Arguments
+    /// * `path` - Output path computed by the batch engine
+    /// * `data` - Encoded file content
+    ///
+    /// # Errors
+    /// Returns an error if the output cannot be written
+    fn write(&self, path: &Path, data: &[u8]) -> Result<(), Box<dyn Error>>;
+
+    /// Flushes the sink once every output was written, e.g. to close an archive
+    ///
+    /// # Errors
+    /// Returns an error if pending outputs cannot be flushed
+    fn finish(&self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Returns a path on the volume the outputs are written to, for the disk space and paranoid
+    /// checks
+    ///
+    /// # Arguments
+    /// * `output` - Output root of the batch
+    ///
+    /// # Returns
+    /// * `Option<PathBuf>` - Path on the destination volume, `None` if outputs don't go to disk
+    fn volume(&self, output: &Path) -> Option<PathBuf> {
+        Some(output.to_path_buf())
+    }
+}
+
+/// Writes outputs to the local filesystem, creating parent directories as needed
+#[derive(Debug, Default)]
+pub struct FileSink;
+
+impl Sink for FileSink {
+    fn write(&self, path: &Path, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+}
+
+/// Writes outputs as entries of a zip archive
+///
+/// Entry names are the output paths relative to the output root.
+pub struct ZipSink {
+    archive: PathBuf,
+    root: PathBuf,
+    writer: Mutex<Option<ZipWriter<File>>>,
+}
+
+impl ZipSink {
+    /// Creates the archive
+    ///
+    /// # Arguments
+    /// * `archive` - Path of the zip archive to create
+    /// * `root` - Output root the entry names are relative to
+    ///
+    /// # Errors
+    /// Returns an error if the archive cannot be created
+    pub fn create(archive: &Path, root: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(ZipSink {
+            archive: archive.to_path_buf(),
+            root: root.to_path_buf(),
+            writer: Mutex::new(Some(ZipWriter::new(File::create(archive)?))),
+        })
+    }
+}
+
+impl fmt::Debug for ZipSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZipSink").field("root", &self.root).finish()
+    }
+}
+
+impl Sink for ZipSink {
+    fn write(&self, path: &Path, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let name = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let writer = writer.as_mut().ok_or("Zip archive is already finished")?;
+        writer.start_file(name, SimpleFileOptions::default())?;
+        writer.write_all(data)?;
+        Ok(())
+    }
+
+    fn volume(&self, _output: &Path) -> Option<PathBuf> {
+        Some(self.archive.clone())
+    }
+
+    fn finish(&self) -> Result<(), Box<dyn Error>> {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(writer) = writer.take() {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes outputs to standard output, for piping a single output to another program
+#[derive(Debug, Default)]
+pub struct StdoutSink;
+
+impl Sink for StdoutSink {
+    fn write(&self, _path: &Path, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(data)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    fn volume(&self, _output: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Callback receiving the path and bytes of every output
+pub type SinkCallback = dyn Fn(&Path, &[u8]) -> Result<(), Box<dyn Error>> + Send + Sync;
+
+/// Hands outputs to a callback, e.g. to upload them or keep them in memory
+pub struct CallbackSink {
+    callback: Box<SinkCallback>,
+}
+
+impl CallbackSink {
+    /// Creates a sink calling `callback` for every output
+    pub fn new(
+        callback: impl Fn(&Path, &[u8]) -> Result<(), Box<dyn Error>> + Send + Sync + 'static,
+    ) -> Self {
+        CallbackSink {
+            callback: Box::new(callback),
+        }
+    }
+}
+
+impl fmt::Debug for CallbackSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallbackSink").finish_non_exhaustive()
+    }
+}
+
+impl Sink for CallbackSink {
+    fn write(&self, path: &Path, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        (self.callback)(path, data)
+    }
+
+    fn volume(&self, _output: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_zip_sink_uses_relative_entry_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("out.zip");
+        let sink = ZipSink::create(&archive, Path::new("out")).unwrap();
+        sink.write(Path::new("out/trip/a.jpg"), b"jpeg").unwrap();
+        sink.finish().unwrap();
+
+        let mut zip = zip::ZipArchive::new(File::open(&archive).unwrap()).unwrap();
+        let mut content = String::new();
+        zip.by_name("trip/a.jpg")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "jpeg");
+    }
+
+    #[test]
+    fn test_callback_sink_receives_outputs() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let captured = received.clone();
+        let sink = CallbackSink::new(move |path, data| {
+            captured
+                .lock()
+                .unwrap()
+                .push((path.to_path_buf(), data.len()));
+            Ok(())
+        });
+        sink.write(Path::new("a.jpg"), b"1234").unwrap();
+        assert_eq!(*received.lock().unwrap(), vec![(PathBuf::from("a.jpg"), 4)]);
+    }
+}
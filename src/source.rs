@@ -0,0 +1,422 @@
+//! Input source module
+//!
+//! This module abstracts how input images are enumerated, so new ingestion modes plug in as
+//! another `Source` instead of special cases in the batch loop.
+
+use image::codecs::jpeg::JpegDecoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageResult};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Extensions of the files picked up from a directory by default
+pub const DEFAULT_EXTENSIONS: &[&str] = &["jpg", "jpeg", "jpe", "jfif"];
+
+/// Number of leading bytes read to detect the format of a file
+const SNIFF_LENGTH: usize = 32;
+
+/// Image to process, as listed by a source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputItem {
+    /// Path of the image on disk
+    pub path: PathBuf,
+    /// Output path of the image, relative to the output root
+    pub relative: PathBuf,
+}
+
+/// Part of a batch processed by one of several machines running the same command
+///
+/// Each image belongs to the shard picked by a hash of its relative output path, so shards are
+/// disjoint and do not depend on the order the images are listed in on each machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    /// Index of the shard, from 1 to `count`
+    pub index: u32,
+    /// Number of shards the batch is split into
+    pub count: u32,
+}
+
+impl Shard {
+    /// Returns whether an image belongs to the shard
+    pub fn contains(&self, item: &InputItem) -> bool {
+        // FNV-1a over the path components, stable across runs, platforms and separators
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for (idx, component) in item.relative.iter().enumerate() {
+            let bytes = component.to_string_lossy();
+            let separator: &[u8] = if idx == 0 { b"" } else { b"/" };
+            for &byte in separator.iter().chain(bytes.as_bytes()) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        hash % self.count as u64 == (self.index - 1) as u64
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+/// Parses a shard such as `2/5`
+pub fn parse_shard(value: &str) -> Result<Shard, String> {
+    let invalid = || format!("invalid shard '{}' (expected INDEX/COUNT, e.g. 2/5)", value);
+    let (index, count) = value.trim().split_once('/').ok_or_else(invalid)?;
+    let index: u32 = index.trim().parse().map_err(|_| invalid())?;
+    let count: u32 = count.trim().parse().map_err(|_| invalid())?;
+    if count == 0 || index == 0 || index > count {
+        return Err(format!(
+            "shard index must be between 1 and {}",
+            count.max(1)
+        ));
+    }
+    Ok(Shard { index, count })
+}
+
+/// Enumerates the images of a batch
+pub trait Source {
+    /// Lists the images to process, in processing order
+    ///
+    /// # Errors
+    /// Returns an error if the source cannot be read
+    fn items(&self) -> Result<Vec<InputItem>, Box<dyn Error>>;
+}
+
+/// Images already listed, e.g. a file list read up front because standard input can only be read
+/// once
+impl Source for Vec<InputItem> {
+    fn items(&self) -> Result<Vec<InputItem>, Box<dyn Error>> {
+        Ok(self.clone())
+    }
+}
+
+/// Image files of a directory tree, sorted by file name
+#[derive(Debug, Clone)]
+pub struct DirectorySource {
+    /// Root directory to walk
+    pub root: PathBuf,
+    /// Extensions of the files to pick up, matched case-insensitively
+    pub extensions: Vec<String>,
+    /// Whether to also pick up files whose content matches one of the extensions
+    pub sniff: bool,
+}
+
+impl Source for DirectorySource {
+    fn items(&self) -> Result<Vec<InputItem>, Box<dyn Error>> {
+        Ok(WalkDir::new(&self.root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let relative = PathBuf::from(e.file_name());
+                let listed = e.path().extension().is_some_and(|ext| {
+                    self.extensions
+                        .iter()
+                        .any(|listed| ext.eq_ignore_ascii_case(listed))
+                });
+                let relative = if listed {
+                    relative
+                } else if self.sniff {
+                    // Give the output an extension its encoder recognizes
+                    let ext = sniffed_extension(e.path(), &self.extensions)?;
+                    let mut name = relative.into_os_string();
+                    name.push(".");
+                    name.push(ext);
+                    PathBuf::from(name)
+                } else {
+                    return None;
+                };
+                Some(InputItem {
+                    path: e.path().to_path_buf(),
+                    relative,
+                })
+            })
+            .collect())
+    }
+}
+
+/// Detects the format of a file from its leading bytes
+///
+/// # Arguments
+/// * `path` - Path of the file
+/// * `extensions` - Extensions of the accepted formats
+///
+/// # Returns
+/// * `Option<&'static str>` - Main extension of the detected format, `None` if the format is
+///   unknown or not accepted
+fn sniffed_extension(path: &Path, extensions: &[String]) -> Option<&'static str> {
+    let mut header = Vec::with_capacity(SNIFF_LENGTH);
+    File::open(path)
+        .ok()?
+        .take(SNIFF_LENGTH as u64)
+        .read_to_end(&mut header)
+        .ok()?;
+    let format_extensions = image::guess_format(&header).ok()?.extensions_str();
+    let accepted = format_extensions.iter().any(|format_ext| {
+        extensions
+            .iter()
+            .any(|listed| listed.eq_ignore_ascii_case(format_ext))
+    });
+    accepted
+        .then(|| format_extensions.first().copied())
+        .flatten()
+}
+
+/// Decodes an input image, detecting its format from the content rather than the extension
+///
+/// RAW files open as their embedded JPEG preview, their sensor data is not decoded.
+///
+/// # Arguments
+/// * `path` - Path of the image
+///
+/// # Returns
+/// * `ImageResult<DynamicImage>` - Decoded image
+///
+/// # Errors
+/// Returns an error if the file cannot be read or its format is unknown
+pub fn open_image(path: &Path) -> ImageResult<DynamicImage> {
+    if crate::raw::is_raw(path) {
+        return crate::raw::decode(path);
+    }
+    image::io::Reader::open(path)?
+        .with_guessed_format()?
+        .decode()
+}
+
+/// Decodes an input image downscaled to fit a square, for previews
+///
+/// JPEG files are scaled while decoding, skipping most of the inverse DCT work, and the rest of
+/// the way with a cheap filter.
+///
+/// # Arguments
+/// * `path` - Path of the image
+/// * `size` - Longest edge of the decoded image in pixels
+///
+/// # Returns
+/// * `ImageResult<DynamicImage>` - Decoded image, no larger than `size` on either edge
+///
+/// # Errors
+/// Returns an error if the file cannot be read or its format is unknown
+pub fn open_scaled(path: &Path, size: u32) -> ImageResult<DynamicImage> {
+    let reader = image::io::Reader::open(path)?.with_guessed_format()?;
+    let img = if reader.format() == Some(ImageFormat::Jpeg) && !crate::raw::is_raw(path) {
+        let mut decoder = JpegDecoder::new(reader.into_inner())?;
+        let requested = size.min(u16::MAX as u32) as u16;
+        decoder.scale(requested, requested)?;
+        DynamicImage::from_decoder(decoder)?
+    } else {
+        open_image(path)?
+    };
+    if img.width().max(img.height()) <= size {
+        return Ok(img);
+    }
+    Ok(img.resize(size, size, FilterType::Triangle))
+}
+
+/// Reads the dimensions of an input image without decoding it
+///
+/// # Arguments
+/// * `path` - Path of the image
+///
+/// # Returns
+/// * `ImageResult<(u32, u32)>` - Width and height, of the embedded preview for RAW files
+///
+/// # Errors
+/// Returns an error if the file cannot be read or its format is unknown
+pub fn image_dimensions(path: &Path) -> ImageResult<(u32, u32)> {
+    if crate::raw::is_raw(path) {
+        return crate::raw::dimensions(path);
+    }
+    image::io::Reader::open(path)?
+        .with_guessed_format()?
+        .into_dimensions()
+}
+
+/// Image paths listed one per line in a text file, or on standard input for `-`
+///
+/// Blank lines and lines starting with `#` are ignored. Relative paths are resolved against the
+/// directory of the list file. Outputs are named after the file name of the entries, so entries
+/// sharing a file name get a `_2`, `_3`... suffix instead of overwriting each other.
+#[derive(Debug, Clone)]
+pub struct FileListSource {
+    /// Path of the list file, `-` for standard input
+    pub list: PathBuf,
+}
+
+impl Source for FileListSource {
+    fn items(&self) -> Result<Vec<InputItem>, Box<dyn Error>> {
+        if self.list == Path::new("-") {
+            return parse_file_list(std::io::stdin().lock(), Path::new(""));
+        }
+        let file = std::fs::File::open(&self.list)
+            .map_err(|e| format!("Failed to open file list {}: {}", self.list.display(), e))?;
+        let base = self.list.parent().unwrap_or_else(|| Path::new(""));
+        parse_file_list(file, base)
+    }
+}
+
+/// Parses a list of image paths
+fn parse_file_list(reader: impl Read, base: &Path) -> Result<Vec<InputItem>, Box<dyn Error>> {
+    let mut items = Vec::new();
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let path = base.join(line);
+        let Some(file_name) = path.file_name() else {
+            println!("[WARN] Skipping list entry without a file name: {}", line);
+            continue;
+        };
+        items.push(InputItem {
+            relative: PathBuf::from(file_name),
+            path,
+        });
+    }
+    disambiguate_names(&mut items);
+    Ok(items)
+}
+
+/// Gives list entries that share a file name distinct output names
+///
+/// Names are compared case-insensitively, since the outputs may land on a case-insensitive
+/// file system. Later entries get the first free `_N` suffix, skipping the names of the list.
+fn disambiguate_names(items: &mut [InputItem]) {
+    let key = |path: &Path| path.to_string_lossy().to_lowercase();
+    let mut used: HashSet<String> = items.iter().map(|item| key(&item.relative)).collect();
+    let mut seen = HashSet::new();
+    for item in items.iter_mut() {
+        if seen.insert(key(&item.relative)) {
+            continue;
+        }
+        let stem = item
+            .relative
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let extension = item
+            .relative
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+        let mut suffix = 2;
+        let renamed = loop {
+            let candidate = PathBuf::from(format!("{}_{}{}", stem, suffix, extension));
+            if !used.contains(&key(&candidate)) {
+                break candidate;
+            }
+            suffix += 1;
+        };
+        println!(
+            "[WARN] {} shares its file name with an earlier list entry, writing it as {}",
+            item.path.display(),
+            renamed.display()
+        );
+        used.insert(key(&renamed));
+        seen.insert(key(&renamed));
+        item.relative = renamed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_list() {
+        let list = "# shoot\nday1/a.jpg\n\n/abs/b.jpeg\n";
+        let items = parse_file_list(list.as_bytes(), Path::new("lists")).unwrap();
+        assert_eq!(
+            items,
+            vec![
+                InputItem {
+                    path: PathBuf::from("lists/day1/a.jpg"),
+                    relative: PathBuf::from("a.jpg"),
+                },
+                InputItem {
+                    path: PathBuf::from("/abs/b.jpeg"),
+                    relative: PathBuf::from("b.jpeg"),
+                },
+            ]
+        );
+
+        let list = "day1/a.jpg\nday2/a.jpg\nday3/A.jpg\na_2.jpg\n";
+        let items = parse_file_list(list.as_bytes(), Path::new("")).unwrap();
+        let names: Vec<&Path> = items.iter().map(|item| item.relative.as_path()).collect();
+        assert_eq!(
+            names,
+            ["a.jpg", "a_3.jpg", "A_4.jpg", "a_2.jpg"].map(Path::new)
+        );
+    }
+
+    #[test]
+    fn test_shards_partition_the_batch() {
+        assert_eq!(parse_shard("2/5"), Ok(Shard { index: 2, count: 5 }));
+        assert!(parse_shard("0/5").is_err());
+        assert!(parse_shard("6/5").is_err());
+        assert!(parse_shard("2").is_err());
+
+        let items: Vec<InputItem> = (0..200)
+            .map(|idx| InputItem {
+                path: PathBuf::from(format!("in/day{}/{}.jpg", idx % 3, idx)),
+                relative: PathBuf::from(format!("day{}/{}.jpg", idx % 3, idx)),
+            })
+            .collect();
+        let shards: Vec<Shard> = (1..=5).map(|index| Shard { index, count: 5 }).collect();
+        for item in &items {
+            assert_eq!(
+                shards.iter().filter(|shard| shard.contains(item)).count(),
+                1
+            );
+        }
+        for shard in &shards {
+            assert!(items.iter().filter(|item| shard.contains(item)).count() > 20);
+        }
+        assert!(Shard { index: 1, count: 1 }.contains(&items[0]));
+    }
+
+    #[test]
+    fn test_directory_source_is_sorted() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.jpg", "a.jpeg", "c.JPG", "d.jfif", "notes.txt"] {
+            std::fs::write(dir.path().join(name), b"").unwrap();
+        }
+        std::fs::write(dir.path().join("export"), [0xff, 0xd8, 0xff, 0xe0]).unwrap();
+        let mut source = DirectorySource {
+            root: dir.path().to_path_buf(),
+            extensions: DEFAULT_EXTENSIONS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect(),
+            sniff: false,
+        };
+        let names = |source: &DirectorySource| -> Vec<_> {
+            source
+                .items()
+                .unwrap()
+                .into_iter()
+                .map(|item| item.relative)
+                .collect()
+        };
+        assert_eq!(
+            names(&source),
+            vec![
+                PathBuf::from("a.jpeg"),
+                PathBuf::from("b.jpg"),
+                PathBuf::from("c.JPG"),
+                PathBuf::from("d.jfif"),
+            ]
+        );
+        source.sniff = true;
+        assert_eq!(names(&source)[4], PathBuf::from("export.jpg"));
+        assert_eq!(names(&source).len(), 5);
+    }
+}
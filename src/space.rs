@@ -0,0 +1,171 @@
+//! Disk space module
+//!
+//! This module estimates the space the outputs of a batch will take from the input dimensions
+//! and the output format and quality, and compares it to the free space of the destination
+//! volume before the batch starts, so a full disk stops the run early instead of thousands of
+//! files in.
+
+use crate::options::ProcessOptions;
+use crate::source::InputItem;
+use crate::validate::Issue;
+use image::ImageFormat;
+use std::path::Path;
+
+/// Share of the free space above which the estimate triggers a warning
+const WARNING_SHARE: f64 = 0.9;
+
+/// Extra pixels of the bar and padding over the photo, as a share of the photo pixels
+const FRAME_OVERHEAD: f64 = 0.25;
+
+/// Returns the estimated bytes per pixel of an output format
+///
+/// # Arguments
+/// * `format` - Output format
+/// * `options` - Processing options, for the JPEG quality and bit depth
+fn bytes_per_pixel(format: ImageFormat, options: &ProcessOptions) -> f64 {
+    match format {
+        // Photos range from about 0.1 byte per pixel at low quality to 0.7 at 100
+        ImageFormat::Jpeg => 0.1 + 0.6 * (options.quality as f64 / 100.0).powi(3),
+        ImageFormat::Tiff if options.sixteen_bit() => 6.0,
+        ImageFormat::WebP if !options.lossy_webp => 2.0,
+        // Lossy WebP and AVIF come out smaller than JPEG, which bounds the estimate
+        ImageFormat::WebP | ImageFormat::Avif => {
+            0.1 + 0.6 * (options.quality as f64 / 100.0).powi(3)
+        }
+        ImageFormat::Png => 2.0,
+        _ => 3.0,
+    }
+}
+
+/// Estimates the size of the outputs of a batch
+///
+/// # Arguments
+/// * `items` - Images of the batch
+/// * `output` - Output directory
+/// * `options` - Processing options
+///
+/// # Returns
+/// * `u64` - Estimated bytes of every output, export sizes included
+pub fn estimate_output_bytes(items: &[InputItem], output: &Path, options: &ProcessOptions) -> u64 {
+    items
+        .iter()
+        .map(|item| {
+            let output_path = crate::util::batch_output_path(item, output, options);
+            let format = options
+                .output_format
+                .map(|format| format.image_format())
+                .or_else(|| ImageFormat::from_path(&output_path).ok())
+                .unwrap_or(ImageFormat::Jpeg);
+            let Ok((width, height)) = crate::source::image_dimensions(&item.path) else {
+                // Unreadable headers fall back to the input size
+                return std::fs::metadata(&item.path).map_or(0, |meta| meta.len());
+            };
+            let long_edge = width.max(height) as f64;
+            let pixels = width as f64 * height as f64 * (1.0 + FRAME_OVERHEAD);
+            let exports: f64 = options
+                .export_sizes
+                .iter()
+                .map(|&size| (size as f64 / long_edge).min(1.0).powi(2))
+                .sum();
+            (pixels * (1.0 + exports) * bytes_per_pixel(format, options)) as u64
+        })
+        .sum()
+}
+
+/// Returns the space available to the user on the volume of a path
+///
+/// The nearest existing ancestor is queried, since the output directory may not exist yet.
+///
+/// # Arguments
+/// * `path` - Path on the volume, existing or not
+///
+/// # Returns
+/// * `Option<u64>` - Available bytes, `None` if unknown on this platform
+#[cfg(unix)]
+pub fn available_bytes(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let absolute = std::path::absolute(path).ok()?;
+    let existing = absolute.ancestors().find(|ancestor| ancestor.exists())?;
+    let path = std::ffi::CString::new(existing.as_os_str().as_bytes()).ok()?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: path is NUL-terminated and stats is a valid statvfs to write to
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return None;
+    }
+    Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+}
+
+/// Returns the space available on the volume of a path, unknown on this platform
+#[cfg(not(unix))]
+pub fn available_bytes(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Checks that the destination volume has room for the outputs of a batch
+///
+/// # Arguments
+/// * `items` - Images of the batch
+/// * `output` - Output directory
+/// * `options` - Processing options, including the sink the outputs are written to
+///
+/// # Returns
+/// * `Option<Issue>` - Error if the estimate exceeds the free space, warning if it takes most
+///   of it, `None` otherwise or if the free space is unknown
+pub fn check_space(items: &[InputItem], output: &Path, options: &ProcessOptions) -> Option<Issue> {
+    let volume = options.sink.volume(output)?;
+    let available = available_bytes(&volume)?;
+    let needed = estimate_output_bytes(items, output, options);
+    println!(
+        "[INFO] Estimated output size: {}, {} free",
+        crate::template::format_file_size(needed),
+        crate::template::format_file_size(available)
+    );
+    let message = || {
+        format!(
+            "the outputs of {} images need about {}, but only {} is free on {}",
+            items.len(),
+            crate::template::format_file_size(needed),
+            crate::template::format_file_size(available),
+            volume.display()
+        )
+    };
+    if needed > available {
+        Some(Issue::Error(format!(
+            "Not enough disk space: {} (use --no-space-check to run anyway)",
+            message()
+        )))
+    } else if needed as f64 > available as f64 * WARNING_SHARE {
+        Some(Issue::Warning(format!(
+            "Disk space is tight: {}",
+            message()
+        )))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    #[test]
+    fn test_space_estimate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.png");
+        RgbImage::new(400, 300).save(&path).unwrap();
+        let items = vec![InputItem {
+            path,
+            relative: "a.png".into(),
+        }];
+        let mut options = ProcessOptions::default();
+        // 150,000 pixels framed at 2 bytes per pixel
+        assert_eq!(estimate_output_bytes(&items, dir.path(), &options), 300_000);
+        options.export_sizes = vec![200];
+        assert_eq!(estimate_output_bytes(&items, dir.path(), &options), 375_000);
+
+        #[cfg(unix)]
+        assert!(available_bytes(&dir.path().join("missing/out")).is_some_and(|bytes| bytes > 0));
+    }
+}
@@ -0,0 +1,266 @@
+//! Text template module
+//!
+//! This module renders user-supplied text templates such as the caption, replacing `{token}`
+//! placeholders with facts about the photo. Unknown tokens are kept as written, and `{{` / `}}`
+//! produce literal braces.
+
+use crate::exif::{ExifInfo, UNKNOWN};
+use crate::metadata::FilmInfo;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Values available to templates for a photo
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    values: HashMap<&'static str, String>,
+}
+
+impl TemplateContext {
+    /// Creates a context with the file facts of a photo
+    ///
+    /// Provides `{filename}`, `{filesize}`, `{dimensions}` and `{frame}`, the file index at the
+    /// end of the file name. Facts that cannot be read are left out.
+    ///
+    /// # Arguments
+    /// * `input` - Path to the photo
+    pub fn for_file(input: &Path) -> Self {
+        let mut context = TemplateContext::default();
+        if let Some(name) = input.file_name() {
+            context.set("filename", name.to_string_lossy().into_owned());
+        }
+        if let Some(index) = input
+            .file_stem()
+            .and_then(|stem| file_index(&stem.to_string_lossy()))
+        {
+            context.set("frame", format_thousands(index));
+        }
+        if let Ok(metadata) = std::fs::metadata(input) {
+            context.set("filesize", format_file_size(metadata.len()));
+        }
+        if let Ok((width, height)) = crate::source::image_dimensions(input) {
+            context.set("dimensions", format!("{}×{}", width, height));
+        }
+        context
+    }
+
+    /// Adds the camera and exposure fields of the EXIF information
+    ///
+    /// Provides `{camera}`, `{lens}`, `{focal}`, `{aperture}`, `{shutter}` and `{iso}`.
+    ///
+    /// # Arguments
+    /// * `exif` - EXIF information of the photo
+    pub fn with_exif(mut self, exif: &ExifInfo) -> Self {
+        self.set("camera", exif.camera_model.trim_matches('"').to_string());
+        self.set("lens", exif.lens_model.trim_matches('"').to_string());
+        self.set(
+            "focal",
+            exif.focal_length.trim_end_matches(" mm").replace('"', ""),
+        );
+        self.set("aperture", exif.aperture.replace('"', ""));
+        self.set("shutter", exif.shutter_speed.replace('"', ""));
+        self.set("iso", exif.iso.replace('"', ""));
+        self
+    }
+
+    /// Adds the analog fields that are set
+    ///
+    /// Provides `{film}`, `{developer}` and `{scan}`.
+    ///
+    /// # Arguments
+    /// * `film` - Film information of the photo
+    pub fn with_film(mut self, film: &FilmInfo) -> Self {
+        for (token, value) in [
+            ("film", &film.stock),
+            ("developer", &film.developer),
+            ("scan", &film.scan_resolution),
+        ] {
+            if let Some(value) = value {
+                self.set(token, value.clone());
+            }
+        }
+        self
+    }
+
+    /// Sets the value of a token
+    pub fn set(&mut self, token: &'static str, value: String) {
+        self.values.insert(token, value);
+    }
+
+    /// Returns the value of a token
+    pub fn get(&self, token: &str) -> Option<&str> {
+        self.values.get(token).map(String::as_str)
+    }
+
+    /// Returns whether a token has a known value, empty tokens and EXIF fields read as
+    /// `Unknown` have none
+    pub fn has_value(&self, token: &str) -> bool {
+        self.get(token)
+            .is_some_and(|value| !value.is_empty() && value != UNKNOWN)
+    }
+
+    /// Sets the weather tokens, which are left empty without a lookup result
+    ///
+    /// # Arguments
+    /// * `fields` - Weather fields looked up for the photo, empty when there was no lookup
+    pub fn set_weather(&mut self, fields: Vec<(&'static str, String)>) {
+        for token in WEATHER_TOKENS {
+            self.set(token, String::new());
+        }
+        for (token, value) in fields {
+            self.set(token, value);
+        }
+    }
+
+    /// Returns whether every token of a template has a known value in this context
+    ///
+    /// # Arguments
+    /// * `template` - Template text, e.g. `{focal}mm`
+    pub fn resolves(&self, template: &str) -> bool {
+        tokens(template).iter().all(|token| self.has_value(token))
+    }
+
+    /// Renders a template with the values of this context
+    ///
+    /// # Arguments
+    /// * `template` - Template text, e.g. `{filename} · {dimensions}`
+    ///
+    /// # Returns
+    /// * `String` - Rendered text
+    pub fn render(&self, template: &str) -> String {
+        let mut output = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(pos) = rest.find(['{', '}']) {
+            output.push_str(&rest[..pos]);
+            rest = &rest[pos..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                output.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+            if rest.starts_with('{') {
+                if let Some(end) = rest.find('}') {
+                    let token = &rest[1..end];
+                    match self.get(token) {
+                        Some(value) => output.push_str(value),
+                        None => output.push_str(&rest[..=end]),
+                    }
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+            output.push_str(&rest[..1]);
+            rest = &rest[1..];
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+/// Tokens looked up online from the GPS position, resolved only when a template uses them
+pub const WEATHER_TOKENS: [&str; 2] = ["weather", "temperature"];
+
+/// Returns whether any of the templates uses a weather token
+///
+/// # Arguments
+/// * `templates` - Templates rendered during the run
+pub fn uses_weather<'a>(templates: impl IntoIterator<Item = &'a str>) -> bool {
+    templates.into_iter().any(|template| {
+        tokens(template)
+            .iter()
+            .any(|token| WEATHER_TOKENS.contains(token))
+    })
+}
+
+/// Returns the tokens a template uses, in order, e.g. `["focal", "iso"]`
+pub fn tokens(template: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    while let Some(pos) = rest.find('{') {
+        rest = &rest[pos..];
+        if rest.starts_with("{{") {
+            rest = &rest[2..];
+            continue;
+        }
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        tokens.push(&rest[1..end]);
+        rest = &rest[end + 1..];
+    }
+    tokens
+}
+
+/// Returns the index at the end of a file stem, e.g. `197` for `IMG_0197`
+fn file_index(stem: &str) -> Option<u64> {
+    let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    stem[stem.len() - digits..].parse().ok()
+}
+
+/// Formats a number with thousands separators, e.g. `12,345`
+pub fn format_thousands(value: u64) -> String {
+    let digits = value.to_string();
+    let mut output = String::with_capacity(digits.len() + digits.len() / 3);
+    for (idx, c) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx).is_multiple_of(3) {
+            output.push(',');
+        }
+        output.push(c);
+    }
+    output
+}
+
+/// Formats a file size with a binary unit, e.g. `12.3 MB`
+pub fn format_file_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_tokens() {
+        let mut context = TemplateContext::default();
+        context.set("filename", "IMG_0001.jpg".to_string());
+        context.set("dimensions", "6000×4000".to_string());
+        assert_eq!(
+            context.render("{filename} · {dimensions} · {unknown} {{literal}}"),
+            "IMG_0001.jpg · 6000×4000 · {unknown} {literal}"
+        );
+        assert_eq!(context.render("open { brace"), "open { brace");
+        context.set_weather(vec![("temperature", "18 °C".to_string())]);
+        assert_eq!(context.render("{weather}|{temperature}"), "|18 °C");
+        assert!(!context.resolves("{weather}"));
+        assert_eq!(
+            tokens("{focal}mm {{x}} f/{aperture} {"),
+            vec!["focal", "aperture"]
+        );
+    }
+
+    #[test]
+    fn test_frame_number() {
+        assert_eq!(file_index("IMG_0197"), Some(197));
+        assert_eq!(file_index("DSC12345"), Some(12345));
+        assert_eq!(file_index("sunset"), None);
+        assert_eq!(format_thousands(12345), "12,345");
+        assert_eq!(format_thousands(999), "999");
+        assert_eq!(format_thousands(1_000_000), "1,000,000");
+    }
+
+    #[test]
+    fn test_format_file_size() {
+        assert_eq!(format_file_size(512), "512 B");
+        assert_eq!(format_file_size(1536), "1.5 KB");
+        assert_eq!(format_file_size(12_900_000), "12.3 MB");
+    }
+}
@@ -0,0 +1,431 @@
+//! Theme module
+//!
+//! This module defines the visual style options applied when rendering the information bar.
+
+use clap::ValueEnum;
+use image::Rgba;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Casing transform applied to a text field before rendering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextCase {
+    /// Render the text exactly as read from EXIF
+    #[default]
+    AsIs,
+    /// Convert the text to UPPERCASE
+    Upper,
+    /// Convert the text to lowercase
+    Lower,
+    /// Capitalize the first letter of every word
+    Title,
+}
+
+impl TextCase {
+    /// Applies the casing transform to a text
+    ///
+    /// # Arguments
+    /// * `text` - Text to transform
+    ///
+    /// # Returns
+    /// * `String` - Transformed text
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            TextCase::AsIs => text.to_string(),
+            TextCase::Upper => text.to_uppercase(),
+            TextCase::Lower => text.to_lowercase(),
+            TextCase::Title => title_case(text),
+        }
+    }
+}
+
+/// Order of the brand logo and the secondary logo in the bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogoOrder {
+    /// Camera brand logo on the left, secondary logo on the right
+    #[default]
+    BrandFirst,
+    /// Secondary logo on the left, camera brand logo on the right
+    SecondaryFirst,
+}
+
+/// Color treatment applied to the brand logo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogoTreatment {
+    /// Keep the original logo colors
+    #[default]
+    Color,
+    /// Convert the logo to grayscale
+    Grayscale,
+    /// Reduce the logo to black and white
+    Monochrome,
+}
+
+/// Field of the shooting parameters that can be dropped when the bar runs out of space
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParamField {
+    /// Focal length
+    Focal,
+    /// Aperture
+    Aperture,
+    /// Shutter speed
+    Shutter,
+    /// ISO sensitivity
+    Iso,
+    /// Elements appended with `--param-elements`
+    Extras,
+    /// Capture date added with `--show-date`
+    Date,
+}
+
+/// Fields dropped from the shooting parameters when space is tight, first to last
+pub const DEFAULT_DROP_ORDER: [ParamField; 4] = [
+    ParamField::Date,
+    ParamField::Extras,
+    ParamField::Iso,
+    ParamField::Shutter,
+];
+
+/// Drop shadow drawn behind text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextShadow {
+    /// Horizontal offset of the shadow in pixels
+    pub offset_x: i32,
+    /// Vertical offset of the shadow in pixels
+    pub offset_y: i32,
+    /// Blur radius of the shadow in pixels, 0 for a hard shadow, at most `MAX_SHADOW_BLUR`
+    pub blur: f32,
+    /// Color of the shadow
+    pub color: Rgba<u8>,
+}
+
+/// Outline stroked around text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextOutline {
+    /// Width of the outline in pixels
+    pub width: u32,
+    /// Color of the outline
+    pub color: Rgba<u8>,
+}
+
+/// Rounded translucent pill drawn behind each text block
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextPill {
+    /// Fill color of the pill, usually translucent
+    pub color: Rgba<u8>,
+    /// Space between the text extents and the pill edge in pixels
+    pub padding: u32,
+}
+
+/// Parses a hex color such as `#1a1a1a`, or `#1a1a1a80` with alpha
+pub fn parse_hex_color(value: &str) -> Result<Rgba<u8>, String> {
+    let hex = value.trim().trim_start_matches('#');
+    if !matches!(hex.len(), 6 | 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "invalid color: {} (expected a hex color like #1a1a1a)",
+            value
+        ));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or_default();
+    let alpha = if hex.len() == 8 { channel(6) } else { 255 };
+    Ok(Rgba([channel(0), channel(2), channel(4), alpha]))
+}
+
+/// Largest text shadow blur radius in pixels, the shadow layer grows with three times the blur
+pub const MAX_SHADOW_BLUR: f32 = 64.0;
+
+/// Parses a text shadow given as `OFFSET_X,OFFSET_Y,BLUR[,COLOR]`, e.g. `2,2,3,#000000a0`
+///
+/// The color defaults to translucent black.
+pub fn parse_text_shadow(value: &str) -> Result<TextShadow, String> {
+    let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+    if !matches!(parts.len(), 3 | 4) {
+        return Err(format!(
+            "expected OFFSET_X,OFFSET_Y,BLUR[,COLOR], got '{}'",
+            value
+        ));
+    }
+    let offset = |part: &str| {
+        part.parse::<i32>()
+            .map_err(|_| format!("invalid shadow offset: {}", part))
+    };
+    let blur: f32 = parts[2]
+        .parse()
+        .ok()
+        .filter(|blur: &f32| (0.0..=MAX_SHADOW_BLUR).contains(blur))
+        .ok_or_else(|| {
+            format!(
+                "invalid shadow blur: {} (expected 0 to {})",
+                parts[2], MAX_SHADOW_BLUR
+            )
+        })?;
+    Ok(TextShadow {
+        offset_x: offset(parts[0])?,
+        offset_y: offset(parts[1])?,
+        blur,
+        color: match parts.get(3) {
+            Some(color) => parse_hex_color(color)?,
+            None => Rgba([0, 0, 0, 160]),
+        },
+    })
+}
+
+/// Parses a text outline given as `WIDTH[,COLOR]`, e.g. `2,#000000`
+///
+/// The color defaults to black.
+pub fn parse_text_outline(value: &str) -> Result<TextOutline, String> {
+    let (width, color) = match value.split_once(',') {
+        Some((width, color)) => (width, parse_hex_color(color)?),
+        None => (value, Rgba([0, 0, 0, 255])),
+    };
+    let width = width
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|width| (1..=16).contains(width))
+        .ok_or_else(|| format!("invalid outline width: {} (expected 1 to 16)", width))?;
+    Ok(TextOutline { width, color })
+}
+
+/// Parses a text pill given as `COLOR[,PADDING]`, e.g. `#00000080,12`
+///
+/// The padding defaults to 12 pixels.
+pub fn parse_text_pill(value: &str) -> Result<TextPill, String> {
+    let (color, padding) = match value.split_once(',') {
+        Some((color, padding)) => (
+            color,
+            padding
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| format!("invalid pill padding: {}", padding))?,
+        ),
+        None => (value, 12),
+    };
+    Ok(TextPill {
+        color: parse_hex_color(color)?,
+        padding,
+    })
+}
+
+/// Visual style of the information bar
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// Casing of the camera model line
+    pub camera_case: TextCase,
+    /// Casing of the lens model line
+    pub lens_case: TextCase,
+    /// Casing of the shooting parameters line
+    pub params_case: TextCase,
+    /// Optional secondary logo (e.g. a studio mark) drawn next to the brand logo
+    pub secondary_logo: Option<PathBuf>,
+    /// Order of the brand logo and the secondary logo
+    pub logo_order: LogoOrder,
+    /// Gap between the brand logo and the secondary logo in pixels
+    pub logo_gap: u32,
+    /// Opacity of the brand logo, from 0.0 (invisible) to 1.0 (opaque)
+    pub logo_opacity: f32,
+    /// Color treatment of the brand logo
+    pub logo_treatment: LogoTreatment,
+    /// Maximum total width of the logos in pixels
+    pub logo_max_width: Option<u32>,
+    /// Optical scale factor of the brand logo relative to the logo slot
+    pub logo_scale: f32,
+    /// Vertical offset of the brand logo as a fraction of its slot height, positive moves down
+    pub logo_offset: f32,
+    /// Vertical offset of all text from its computed position in pixels, positive moves down
+    pub baseline_offset: i32,
+    /// Shooting parameter fields dropped, first to last, while the parameters don't fit
+    pub drop_order: Vec<ParamField>,
+    /// Drop shadow drawn behind the text, `None` to disable
+    pub text_shadow: Option<TextShadow>,
+    /// Outline stroked around the text, `None` to disable
+    pub text_outline: Option<TextOutline>,
+    /// Pill drawn behind each text block, `None` to disable
+    pub text_pill: Option<TextPill>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            camera_case: TextCase::AsIs,
+            lens_case: TextCase::AsIs,
+            params_case: TextCase::AsIs,
+            secondary_logo: None,
+            logo_order: LogoOrder::BrandFirst,
+            logo_gap: 24,
+            logo_opacity: 1.0,
+            logo_treatment: LogoTreatment::Color,
+            logo_max_width: None,
+            logo_scale: 1.0,
+            logo_offset: 0.0,
+            baseline_offset: 0,
+            drop_order: DEFAULT_DROP_ORDER.to_vec(),
+            text_shadow: None,
+            text_outline: None,
+            text_pill: None,
+        }
+    }
+}
+
+/// Partial theme used to override individual style options
+///
+/// Every field left empty keeps the value of the theme it is applied to.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StyleOverrides {
+    /// Casing of the camera model line
+    pub camera_case: Option<TextCase>,
+    /// Casing of the lens model line
+    pub lens_case: Option<TextCase>,
+    /// Casing of the shooting parameters line
+    pub params_case: Option<TextCase>,
+    /// Secondary logo drawn next to the brand logo
+    pub secondary_logo: Option<PathBuf>,
+    /// Order of the brand logo and the secondary logo
+    pub logo_order: Option<LogoOrder>,
+    /// Gap between the brand logo and the secondary logo in pixels
+    pub logo_gap: Option<u32>,
+    /// Opacity of the brand logo
+    pub logo_opacity: Option<f32>,
+    /// Color treatment of the brand logo
+    pub logo_treatment: Option<LogoTreatment>,
+    /// Maximum total width of the logos in pixels
+    pub logo_max_width: Option<u32>,
+    /// Optical scale factor of the brand logo, e.g. 1.2 for a thin wordmark
+    pub logo_scale: Option<f32>,
+    /// Vertical offset of the brand logo as a fraction of its slot height, e.g. -0.05 for a
+    /// wordmark with descenders
+    pub logo_offset: Option<f32>,
+    /// Shooting parameter fields dropped, first to last, while the parameters don't fit
+    pub drop_order: Option<Vec<ParamField>>,
+}
+
+impl StyleOverrides {
+    /// Applies the overrides to a theme
+    ///
+    /// # Arguments
+    /// * `theme` - Theme to override
+    pub fn apply(&self, theme: &mut Theme) {
+        if let Some(camera_case) = self.camera_case {
+            theme.camera_case = camera_case;
+        }
+        if let Some(lens_case) = self.lens_case {
+            theme.lens_case = lens_case;
+        }
+        if let Some(params_case) = self.params_case {
+            theme.params_case = params_case;
+        }
+        if let Some(secondary_logo) = &self.secondary_logo {
+            theme.secondary_logo = Some(secondary_logo.clone());
+        }
+        if let Some(logo_order) = self.logo_order {
+            theme.logo_order = logo_order;
+        }
+        if let Some(logo_gap) = self.logo_gap {
+            theme.logo_gap = logo_gap;
+        }
+        if let Some(logo_opacity) = self.logo_opacity {
+            theme.logo_opacity = logo_opacity;
+        }
+        if let Some(logo_treatment) = self.logo_treatment {
+            theme.logo_treatment = logo_treatment;
+        }
+        if let Some(logo_max_width) = self.logo_max_width {
+            theme.logo_max_width = Some(logo_max_width);
+        }
+        if let Some(logo_scale) = self.logo_scale {
+            theme.logo_scale = logo_scale;
+        }
+        if let Some(logo_offset) = self.logo_offset {
+            theme.logo_offset = logo_offset;
+        }
+        if let Some(drop_order) = &self.drop_order {
+            theme.drop_order = drop_order.clone();
+        }
+    }
+}
+
+/// Capitalizes the first letter of every whitespace separated word
+///
+/// Letters after the first one are kept, so acronyms such as "FE" or "GM" stay intact, and
+/// words that contain digits (e.g. "rf24-105mm") are kept as-is so model numbers stay readable.
+fn title_case(text: &str) -> String {
+    text.split(' ')
+        .map(|word| {
+            if word.chars().any(|c| c.is_ascii_digit()) {
+                return word.to_string();
+            }
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text_effects() {
+        assert_eq!(
+            parse_text_shadow("2, -1, 3, #11223380"),
+            Ok(TextShadow {
+                offset_x: 2,
+                offset_y: -1,
+                blur: 3.0,
+                color: Rgba([0x11, 0x22, 0x33, 0x80]),
+            })
+        );
+        assert_eq!(
+            parse_text_shadow("2,2,0").unwrap().color,
+            Rgba([0, 0, 0, 160])
+        );
+        assert!(parse_text_shadow("2,2").is_err());
+        assert!(parse_text_shadow("2,2,-1").is_err());
+        assert!(parse_text_shadow("2,2,64").is_ok());
+        assert!(parse_text_shadow("0,0,inf").is_err());
+        assert!(parse_text_shadow("0,0,NaN").is_err());
+        assert!(parse_text_shadow("0,0,100000").is_err());
+        assert_eq!(
+            parse_text_outline("2,#ffffff"),
+            Ok(TextOutline {
+                width: 2,
+                color: Rgba([255, 255, 255, 255]),
+            })
+        );
+        assert!(parse_text_outline("0").is_err());
+        assert_eq!(
+            parse_text_pill("#00000080"),
+            Ok(TextPill {
+                color: Rgba([0, 0, 0, 0x80]),
+                padding: 12,
+            })
+        );
+        assert_eq!(parse_text_pill("#000000,4").unwrap().padding, 4);
+    }
+
+    #[test]
+    fn test_text_case_apply() {
+        assert_eq!(TextCase::AsIs.apply("Canon EOS R5"), "Canon EOS R5");
+        assert_eq!(TextCase::Upper.apply("Canon EOS R5"), "CANON EOS R5");
+        assert_eq!(TextCase::Lower.apply("Canon EOS R5"), "canon eos r5");
+        assert_eq!(
+            TextCase::Title.apply("sony FE 24-70mm F2.8 GM lens"),
+            "Sony FE 24-70mm F2.8 GM Lens"
+        );
+        assert_eq!(
+            TextCase::Title.apply("SONY FE 24-70mm F2.8 GM"),
+            "SONY FE 24-70mm F2.8 GM"
+        );
+        assert_eq!(TextCase::Title.apply("ILCE-7M4"), "ILCE-7M4");
+        assert_eq!(TextCase::Title.apply(""), "");
+    }
+}
@@ -0,0 +1,122 @@
+//! Thumbnail module
+//!
+//! This module writes small unframed JPEG proxies of a batch, for culling a shoot before the
+//! slower framing run.
+
+use crate::options::ProcessOptions;
+use crate::report::BatchReport;
+use crate::source::Source;
+use image::ImageFormat;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Writes a JPEG proxy of every image of a source
+///
+/// Proxies keep the relative path of their image, with a `.jpg` extension. An image whose proxy
+/// would replace an image of the source, such as itself when OUTPUT is the source directory, or
+/// the proxy of an earlier image with the same stem, e.g. `a.png` after `a.jpg`, fails instead.
+///
+/// # Arguments
+/// * `source` - Images to make proxies of
+/// * `output` - Directory receiving the proxies
+/// * `size` - Longest edge of the proxies in pixels
+/// * `options` - Processing options, for the JPEG encoder settings and the output sink
+///
+/// # Returns
+/// * `Result<BatchReport, Box<dyn Error>>` - Number of proxies written and the failures
+///
+/// # Errors
+/// Returns an error if the source cannot be read
+pub fn make_thumbs(
+    source: &dyn Source,
+    output: &Path,
+    size: u32,
+    options: &ProcessOptions,
+) -> Result<BatchReport, Box<dyn Error>> {
+    let mut report = BatchReport::default();
+    let items = source.items()?;
+    let inputs: HashMap<PathBuf, &Path> = items
+        .iter()
+        .map(|item| Ok((crate::paranoid::resolve(&item.path)?, item.path.as_path())))
+        .collect::<Result<_, Box<dyn Error>>>()?;
+    let mut proxies: HashMap<PathBuf, &Path> = HashMap::new();
+    for item in &items {
+        let target = output.join(&item.relative).with_extension("jpg");
+        let resolved = crate::paranoid::resolve(&target)?;
+        let result = if let Some(input) = inputs.get(&resolved) {
+            Err(format!("the proxy would overwrite the original {}", input.display()).into())
+        } else if let Some(first) = proxies.get(&resolved) {
+            Err(format!("the proxy would overwrite the one of {}", first.display()).into())
+        } else {
+            crate::source::open_scaled(&item.path, size)
+                .map_err(|e| e.into())
+                .and_then(|thumb| {
+                    crate::output::save_image_as(&thumb, &target, ImageFormat::Jpeg, options)
+                })
+        };
+        match result {
+            Ok(()) => {
+                proxies.insert(resolved, &item.path);
+                report.processed += 1;
+            }
+            Err(e) => {
+                println!(
+                    "[ERROR] Failed to make a proxy of {}: {}",
+                    item.path.display(),
+                    e
+                );
+                report.failures.push((item.path.clone(), e.to_string()));
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::DirectorySource;
+
+    #[test]
+    fn test_make_thumbs() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in");
+        std::fs::create_dir(&input).unwrap();
+        image::RgbImage::new(1600, 1200)
+            .save(input.join("large.jpg"))
+            .unwrap();
+        image::RgbImage::new(120, 80)
+            .save(input.join("small.png"))
+            .unwrap();
+        std::fs::write(input.join("broken.jpg"), b"not a jpeg").unwrap();
+        let source = DirectorySource {
+            root: input,
+            extensions: vec!["jpg".to_string(), "png".to_string()],
+            sniff: false,
+        };
+
+        let output = dir.path().join("out");
+        let report = make_thumbs(&source, &output, 400, &ProcessOptions::default()).unwrap();
+        assert_eq!((report.processed, report.failures.len()), (2, 1));
+        let large = image::open(output.join("large.jpg")).unwrap();
+        assert_eq!((large.width(), large.height()), (400, 300));
+        let small = image::open(output.join("small.jpg")).unwrap();
+        assert_eq!((small.width(), small.height()), (120, 80));
+
+        // Proxies never replace the originals or each other
+        image::RgbImage::new(64, 48)
+            .save(source.root.join("large.png"))
+            .unwrap();
+        let report = make_thumbs(&source, &output, 400, &ProcessOptions::default()).unwrap();
+        assert_eq!((report.processed, report.failures.len()), (2, 2));
+        assert_eq!(image::open(output.join("large.jpg")).unwrap().width(), 400);
+        let original = std::fs::read(source.root.join("large.jpg")).unwrap();
+        let report = make_thumbs(&source, &source.root, 16, &ProcessOptions::default()).unwrap();
+        assert_eq!(
+            std::fs::read(source.root.join("large.jpg")).unwrap(),
+            original
+        );
+        assert_eq!((report.processed, report.failures.len()), (1, 3));
+    }
+}
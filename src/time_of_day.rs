@@ -0,0 +1,252 @@
+//! Time of day module
+//!
+//! This module picks a bar tint from the local capture time of a photo, so golden hour shots get
+//! a warm bar, daylight shots a cool one and night shots a dark one. It also converts EXIF
+//! capture times to UTC Julian days for the astronomy and weather lookups.
+
+use image::Rgba;
+
+/// Format of the capture date when `--show-date` has no format, e.g. `2024-06-01`
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Light of the hour a photo was taken in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeOfDay {
+    /// Around sunrise and sunset, 05:00–08:00 and 17:00–20:00
+    GoldenHour,
+    /// Daylight between the golden hours
+    Midday,
+    /// After dusk and before dawn
+    Night,
+}
+
+impl TimeOfDay {
+    /// Classifies a local hour of the day
+    ///
+    /// # Arguments
+    /// * `hour` - Local hour, 0 to 23
+    pub fn from_hour(hour: u32) -> Self {
+        match hour {
+            5..=7 | 17..=19 => TimeOfDay::GoldenHour,
+            8..=16 => TimeOfDay::Midday,
+            _ => TimeOfDay::Night,
+        }
+    }
+
+    /// Returns the bar background of this time of day
+    ///
+    /// The night tint is dark, so the bar text switches to white.
+    pub fn bar_color(self) -> Rgba<u8> {
+        match self {
+            TimeOfDay::GoldenHour => Rgba([255, 241, 222, 255]),
+            TimeOfDay::Midday => Rgba([240, 246, 252, 255]),
+            TimeOfDay::Night => Rgba([22, 28, 46, 255]),
+        }
+    }
+}
+
+/// Parses the hour of an EXIF date time such as `2024:06:01 19:42:10`
+pub fn parse_hour(date_time: &str) -> Option<u32> {
+    let (_, time) = date_time.trim_matches('"').trim().split_once(' ')?;
+    let hour: u32 = time.split(':').next()?.trim().parse().ok()?;
+    (hour < 24).then_some(hour)
+}
+
+/// Parses the minutes after midnight of an EXIF date time such as `2024:06:01 19:42:10`
+pub fn parse_minute_of_day(date_time: &str) -> Option<u32> {
+    let (_, time) = date_time.trim_matches('"').trim().split_once(' ')?;
+    let mut parts = time.split(':');
+    let hour: u32 = parts.next()?.trim().parse().ok()?;
+    let minute: u32 = parts.next()?.trim().parse().ok()?;
+    (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+}
+
+/// Returns the Julian day of a UTC date and time
+///
+/// # Arguments
+/// * `year` - Gregorian year
+/// * `month` - Month, 1 to 12
+/// * `day` - Day of the month
+/// * `hours` - UTC time of day in hours, may be negative or past 24 after an offset
+pub fn julian_day(year: i32, month: u32, day: u32, hours: f64) -> f64 {
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 12)
+    } else {
+        (year, month)
+    };
+    let century = (year as f64 / 100.0).floor();
+    let leap = 2.0 - century + (century / 4.0).floor();
+    (365.25 * (year as f64 + 4716.0)).floor()
+        + (30.6001 * (month as f64 + 1.0)).floor()
+        + day as f64
+        + leap
+        - 1524.5
+        + hours / 24.0
+}
+
+/// Parses an EXIF date time such as `2024:06:01 19:42:10`
+///
+/// # Returns
+/// * `Option<(i32, u32, u32, f64)>` - Year, month, day and time of day in hours
+pub fn parse_date_time(value: &str) -> Option<(i32, u32, u32, f64)> {
+    let (date, time) = value.trim_matches('"').trim().split_once(' ')?;
+    let mut date = date.split([':', '-']).map(str::parse::<u32>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut time = time.split(':').map(str::parse::<f64>);
+    let hours = time.next()?.ok()? + time.next()?.ok()? / 60.0 + time.next()?.ok()? / 3600.0;
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some((
+        year as i32,
+        month,
+        day,
+        hours,
+    ))
+}
+
+/// Month names used by `%B` and `%b`
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Weekday names used by `%A` and `%a`, from Sunday
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// Formats an EXIF date time with strftime-style specifiers
+///
+/// Supports `%Y`, `%y`, `%m`, `%d`, `%e`, `%B`, `%b`, `%A`, `%a`, `%H`, `%I`, `%M`, `%S`, `%p`
+/// and `%%`. Other specifiers are kept as written.
+///
+/// # Arguments
+/// * `date_time` - EXIF date time such as `2024:06:01 19:42:10`
+/// * `format` - Format such as `%Y-%m-%d %H:%M`
+///
+/// # Returns
+/// * `Option<String>` - Formatted date time, `None` if the date time cannot be parsed
+pub fn format_date_time(date_time: &str, format: &str) -> Option<String> {
+    let (year, month, day, hours) = parse_date_time(date_time)?;
+    let seconds = (hours * 3600.0).round() as u32;
+    let (hour, minute, second) = (seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+    let weekday = (julian_day(year, month, day, 0.0) + 1.5).floor() as i64;
+    let weekday = WEEKDAYS[weekday.rem_euclid(7) as usize];
+    let month_name = MONTHS[month as usize - 1];
+
+    let mut formatted = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            formatted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => formatted.push_str(&year.to_string()),
+            Some('y') => formatted.push_str(&format!("{:02}", year.rem_euclid(100))),
+            Some('m') => formatted.push_str(&format!("{:02}", month)),
+            Some('d') => formatted.push_str(&format!("{:02}", day)),
+            Some('e') => formatted.push_str(&day.to_string()),
+            Some('B') => formatted.push_str(month_name),
+            Some('b') => formatted.push_str(&month_name[..3]),
+            Some('A') => formatted.push_str(weekday),
+            Some('a') => formatted.push_str(&weekday[..3]),
+            Some('H') => formatted.push_str(&format!("{:02}", hour)),
+            Some('I') => formatted.push_str(&format!("{:02}", (hour + 11) % 12 + 1)),
+            Some('M') => formatted.push_str(&format!("{:02}", minute)),
+            Some('S') => formatted.push_str(&format!("{:02}", second)),
+            Some('p') => formatted.push_str(if hour < 12 { "AM" } else { "PM" }),
+            Some('%') => formatted.push('%'),
+            Some(other) => {
+                formatted.push('%');
+                formatted.push(other);
+            }
+            None => formatted.push('%'),
+        }
+    }
+    Some(formatted)
+}
+
+/// Parses an EXIF UTC offset such as `+02:00` into hours
+pub fn parse_utc_offset(value: &str) -> Option<f64> {
+    let value = value.trim_matches('"').trim();
+    let sign = match value.chars().next()? {
+        '+' => 1.0,
+        '-' => -1.0,
+        _ => return None,
+    };
+    let (hours, minutes) = value[1..].split_once(':')?;
+    Some(sign * (hours.parse::<f64>().ok()? + minutes.parse::<f64>().ok()? / 60.0))
+}
+
+/// Returns the UTC calendar date of a Julian day
+///
+/// # Returns
+/// * `(i32, u32, u32, f64)` - Year, month, day and time of day in hours
+pub fn calendar_date(julian_day: f64) -> (i32, u32, u32, f64) {
+    let shifted = julian_day + 0.5;
+    let whole = shifted.floor();
+    let fraction = shifted - whole;
+    let alpha = ((whole - 1867216.25) / 36524.25).floor();
+    let a = whole + 1.0 + alpha - (alpha / 4.0).floor();
+    let b = a + 1524.0;
+    let c = ((b - 122.1) / 365.25).floor();
+    let d = (365.25 * c).floor();
+    let e = ((b - d) / 30.6001).floor();
+    let day = (b - d - (30.6001 * e).floor()) as u32;
+    let month = if e < 14.0 { e - 1.0 } else { e - 13.0 } as u32;
+    let year = if month > 2 { c - 4716.0 } else { c - 4715.0 } as i32;
+    (year, month, day, fraction * 24.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_of_day() {
+        assert_eq!(parse_hour("2024:06:01 19:42:10"), Some(19));
+        assert_eq!(parse_hour("2024-06-01 07:05:00"), Some(7));
+        assert_eq!(parse_hour("2024:06:01"), None);
+        assert_eq!(parse_hour("2024:06:01 25:00:00"), None);
+        assert_eq!(parse_minute_of_day("2024:06:01 19:42:10"), Some(1182));
+        assert_eq!(
+            format_date_time("2024:06:01 19:42:10", "%a %e %b %Y, %I:%M %p").as_deref(),
+            Some("Sat 1 Jun 2024, 07:42 PM")
+        );
+        assert_eq!(
+            format_date_time("2024-06-01 07:05:09", "%y/%m/%d %H:%M:%S 100%%").as_deref(),
+            Some("24/06/01 07:05:09 100%")
+        );
+        assert_eq!(format_date_time("2024:06:01", "%Y"), None);
+
+        assert_eq!(julian_day(2000, 1, 1, 12.0), 2451545.0);
+        assert_eq!(calendar_date(2451545.0), (2000, 1, 1, 12.0));
+        assert_eq!(calendar_date(julian_day(2024, 3, 1, -2.0)).2, 29);
+        assert_eq!(
+            parse_date_time("2024:06:21 12:30:00"),
+            Some((2024, 6, 21, 12.5))
+        );
+        assert_eq!(parse_utc_offset("-05:30"), Some(-5.5));
+
+        assert_eq!(TimeOfDay::from_hour(6), TimeOfDay::GoldenHour);
+        assert_eq!(TimeOfDay::from_hour(18), TimeOfDay::GoldenHour);
+        assert_eq!(TimeOfDay::from_hour(12), TimeOfDay::Midday);
+        assert_eq!(TimeOfDay::from_hour(23), TimeOfDay::Night);
+        assert_eq!(TimeOfDay::from_hour(3), TimeOfDay::Night);
+    }
+}
@@ -2,63 +2,292 @@
 //!
 //! This module contains helper functions for processing files and directories.
 
-use std::path::Path;
-use walkdir::WalkDir;
+use crate::backdrop::{BackdropSource, GrainScope};
+use crate::config::{BrandStyle, WatermarkOptOut};
+use crate::dedupe::DuplicateMode;
+use crate::options::{BarColor, ProcessOptions, SmallImages};
+use crate::output::EmbeddedMetadata;
+use crate::report::BatchReport;
+use crate::sharpness::BlurMode;
+use crate::sidecar::Sidecar;
+use crate::source::{DirectorySource, InputItem, Source};
+use crate::validate::Issue;
+use crate::video::SlideshowWriter;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// Processes all JPEG files in a directory
 ///
 /// # Arguments
 /// * `input` - Input directory path
 /// * `output` - Output directory path
-/// * `info_height` - Height of the information bar in pixels
-/// * `force_16_9` - Whether to force 16:9 aspect ratio
-/// * `custom_logo_path` - Optional path to a custom logo file
+/// * `options` - Processing options
 ///
 /// # Returns
-/// * `Result<(), Box<dyn std::error::Error>>` - Ok if successful
+/// * `Result<BatchReport, Box<dyn std::error::Error>>` - Report of processed and failed files
 ///
 /// # Errors
-/// Returns an error if:
-/// - The output directory cannot be created
-/// - Any file cannot be processed
+/// Returns an error if the slideshow video cannot be written
 pub fn process_directory(
     input: &Path,
     output: &Path,
-    info_height: u32,
-    force_16_9: bool,
-    custom_logo_path: Option<&Path>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    if !output.exists() {
-        std::fs::create_dir_all(output)?;
+    options: &ProcessOptions,
+) -> Result<BatchReport, Box<dyn std::error::Error>> {
+    let source = DirectorySource {
+        root: input.to_path_buf(),
+        extensions: options.extensions.clone(),
+        sniff: options.sniff,
+    };
+    process_source(&source, output, options)
+}
+
+/// Returns the output path of an image of a batch
+///
+/// # Arguments
+/// * `item` - Image of the batch
+/// * `output` - Output directory path
+/// * `options` - Processing options, for the format the outputs are converted to
+///
+/// # Returns
+/// * `PathBuf` - Output path, with the extension of the converted format or `jpg` for RAW files
+pub fn batch_output_path(item: &InputItem, output: &Path, options: &ProcessOptions) -> PathBuf {
+    let mut output_path = output.join(&item.relative);
+    if let Some(format) = options.convert_to {
+        output_path.set_extension(format.extension());
+    } else if crate::raw::is_raw(&item.path) {
+        output_path.set_extension("jpg");
     }
+    output_path
+}
 
-    let entries: Vec<_> = WalkDir::new(input)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter(|e| {
-            if let Some(ext) = e.path().extension() {
-                ext == "jpg" || ext == "jpeg"
-            } else {
-                false
+/// Removes the images of a batch whose output path is taken by an earlier image
+///
+/// With `--convert-to`, `a.jpg` and `a.jpeg` both map to `a.png`, and a RAW file maps to the
+/// `jpg` of a JPEG next to it. The later images are recorded as failures instead of silently
+/// overwriting the earlier outputs.
+///
+/// # Arguments
+/// * `items` - Images of the batch, in processing order
+/// * `output` - Output directory path
+/// * `options` - Processing options
+/// * `report` - Report the removed images are recorded in
+fn remove_output_collisions(
+    items: &mut Vec<InputItem>,
+    output: &Path,
+    options: &ProcessOptions,
+    report: &mut BatchReport,
+) {
+    let mut claimed: HashMap<PathBuf, PathBuf> = HashMap::new();
+    items.retain(|item| {
+        let output_path = batch_output_path(item, output, options);
+        if let Some(first) = claimed.get(&output_path) {
+            let reason = format!(
+                "its output {} is also the output of {}",
+                output_path.display(),
+                first.display()
+            );
+            println!("[ERROR] {}: {}", item.path.display(), reason);
+            report.failures.push((item.path.clone(), reason));
+            return false;
+        }
+        claimed.insert(output_path, item.path.clone());
+        true
+    });
+}
+
+/// Processes every image listed by a source
+///
+/// A file that fails is recorded in the report and the batch continues with the next file.
+///
+/// # Arguments
+/// * `source` - Source enumerating the input images
+/// * `output` - Output directory path
+/// * `options` - Processing options
+///
+/// # Returns
+/// * `Result<BatchReport, Box<dyn std::error::Error>>` - Report of processed and failed files
+///
+/// # Errors
+/// Returns an error if the source cannot be read or the slideshow video cannot be written
+pub fn process_source(
+    source: &dyn Source,
+    output: &Path,
+    options: &ProcessOptions,
+) -> Result<BatchReport, Box<dyn std::error::Error>> {
+    let mut items = source.items()?;
+    if let Some(shard) = options.shard {
+        let listed = items.len();
+        items.retain(|item| shard.contains(item));
+        println!(
+            "[INFO] Shard {}: {} of {} images",
+            shard,
+            items.len(),
+            listed
+        );
+    }
+    let mut report = BatchReport::default();
+    if options.verify_decode {
+        crate::integrity::remove_broken(&mut items, &mut report);
+    }
+    if let Some(mode) = options.duplicates {
+        let clusters = crate::dedupe::find_duplicates(&items, options.duplicate_threshold);
+        let mut duplicates = Vec::new();
+        for cluster in &clusters {
+            let names: Vec<String> = cluster
+                .iter()
+                .map(|&idx| items[idx].path.display().to_string())
+                .collect();
+            println!("[INFO] Near-duplicates: {}", names.join(", "));
+            duplicates.extend(cluster.iter().skip(1).map(|&idx| (idx, cluster[0])));
+        }
+        if mode == DuplicateMode::Skip {
+            for &(idx, first) in duplicates.iter().rev() {
+                let reason = format!("near-duplicate of {}", items[first].path.display());
+                report.skipped.insert(0, (items.remove(idx).path, reason));
             }
-        })
+        }
+    }
+    remove_output_collisions(&mut items, output, options, &mut report);
+    if options.paranoid {
+        let inputs: Vec<&Path> = items.iter().map(|item| item.path.as_path()).collect();
+        let volume = options.sink.volume(output);
+        let outputs: Vec<&Path> = [
+            volume.as_deref(),
+            options
+                .slideshow
+                .as_ref()
+                .map(|slideshow| slideshow.output.as_path()),
+        ]
+        .into_iter()
+        .flatten()
         .collect();
-    let total = entries.len();
-    for (idx, entry) in entries.iter().enumerate() {
-        let path = entry.path();
-        let output_path = output.join(path.file_name().unwrap());
-        println!("Processing {}/{}: {}", idx + 1, total, path.display());
-        process_single_file(
-            path,
-            &output_path,
-            info_height,
-            force_16_9,
-            custom_logo_path,
-        )?;
+        crate::paranoid::check_overlap(&inputs, &outputs)?;
+    }
+    if !options.export_sizes.is_empty() {
+        let inputs: Vec<&Path> = items.iter().map(|item| item.path.as_path()).collect();
+        let outputs: Vec<PathBuf> = items
+            .iter()
+            .map(|item| batch_output_path(item, output, options))
+            .collect();
+        let outputs: Vec<&Path> = outputs.iter().map(PathBuf::as_path).collect();
+        crate::output::check_export_paths(&inputs, &outputs, &options.export_sizes)?;
+    }
+    if options.space_check {
+        match crate::space::check_space(&items, output, options) {
+            Some(Issue::Error(message)) => return Err(message.into()),
+            Some(warning) => println!("{}", warning),
+            None => {}
+        }
+    }
+    let total = items.len();
+    let mut slideshow = match &options.slideshow {
+        Some(slideshow) => Some(SlideshowWriter::spawn(slideshow)?),
+        None => None,
+    };
+    for (idx, item) in items.iter().enumerate() {
+        let output_path = batch_output_path(item, output, options);
+        println!("Processing {}/{}: {}", idx + 1, total, item.path.display());
+        if options.small_images == Some(SmallImages::Skip) {
+            if let Some(reason) = small_image_reason(&item.path, options.min_size) {
+                println!("[WARN] {} is too small: {}", item.path.display(), reason);
+                report.skipped.push((item.path.clone(), reason));
+                continue;
+            }
+        }
+        if let Some(mode) = options.blur_check {
+            if let Some(reason) = blur_reason(&item.path, options.sharpness_threshold) {
+                println!("[WARN] {} is blurry: {}", item.path.display(), reason);
+                if mode == BlurMode::Skip {
+                    report.skipped.push((item.path.clone(), reason));
+                    continue;
+                }
+                report.flagged.push((item.path.clone(), reason));
+            }
+        }
+        if options.gamut_check {
+            if let Some(reason) = crate::color::check_gamut(&item.path) {
+                println!("[WARN] {}: {}", item.path.display(), reason);
+                report.flagged.push((item.path.clone(), reason));
+            }
+        }
+        if let Err(e) = process_single_file(&item.path, &output_path, options) {
+            println!("[ERROR] Failed to process {}: {}", item.path.display(), e);
+            report.failures.push((item.path.clone(), e.to_string()));
+            continue;
+        }
+        report.processed += 1;
+        if let Some(writer) = slideshow.as_mut() {
+            crate::video::push_file(writer, &output_path)?;
+        }
+    }
+    if let Some(writer) = slideshow {
+        writer.finish()?;
     }
 
-    Ok(())
+    Ok(report)
+}
+
+/// Returns why a photo counts as blurry, `None` if it is sharp enough or cannot be read
+fn blur_reason(input: &Path, threshold: f64) -> Option<String> {
+    let score = crate::sharpness::sharpness(&crate::source::open_image(input).ok()?);
+    (score < threshold).then(|| format!("sharpness {:.1} below {:.1}", score, threshold))
+}
+
+/// Returns why an image counts as too small, `None` if it is large enough or cannot be read
+fn small_image_reason(input: &Path, min_size: u32) -> Option<String> {
+    let (width, height) = crate::source::image_dimensions(input).ok()?;
+    (width.max(height) < min_size)
+        .then(|| format!("{}x{} is below the {}px minimum", width, height, min_size))
+}
+
+/// Returns whether a watermark opt-out rule matches the EXIF rights fields of a photo
+fn watermark_opted_out(input: &Path, options: &ProcessOptions) -> bool {
+    if options.watermark_opt_out.is_empty() {
+        return false;
+    }
+    let artist = crate::exif::read_text_field(input, exif::Tag::Artist);
+    let copyright = crate::exif::read_text_field(input, exif::Tag::Copyright);
+    options
+        .watermark_opt_out
+        .iter()
+        .any(|rule| rule.matches(artist.as_deref(), copyright.as_deref()))
+}
+
+/// Looks up the brand style of a photo
+///
+/// The brand is inferred from the EXIF manufacturer, falling back to the camera model of the
+/// metadata table or EXIF.
+fn brand_style<'a>(input: &Path, options: &'a ProcessOptions) -> Option<(String, &'a BrandStyle)> {
+    if options.brand_styles.is_empty() {
+        return None;
+    }
+    let camera_model = || {
+        let row_camera = options
+            .metadata
+            .as_ref()
+            .and_then(|table| table.row_for(input))
+            .and_then(|row| row.camera.clone());
+        row_camera.or_else(|| {
+            crate::exif::read_exif_info(input)
+                .ok()
+                .or_else(|| {
+                    if options.tolerant_exif {
+                        crate::exif::salvage_exif_info(input)
+                    } else {
+                        None
+                    }
+                })
+                .map(|exif| exif.camera_model.trim_matches('"').to_string())
+        })
+    };
+    [crate::exif::read_camera_make(input), camera_model()]
+        .into_iter()
+        .flatten()
+        .filter_map(|name| crate::resource::infer_camera_brand(&name))
+        .find_map(|brand| {
+            let style = options.brand_styles.get(&brand)?;
+            Some((brand, style))
+        })
 }
 
 /// Processes a single image file
@@ -66,9 +295,7 @@ pub fn process_directory(
 /// # Arguments
 /// * `input` - Input file path
 /// * `output` - Output file path
-/// * `info_height` - Height of the information bar in pixels
-/// * `force_16_9` - Whether to force 16:9 aspect ratio
-/// * `custom_logo_path` - Optional path to a custom logo file
+/// * `options` - Processing options
 ///
 /// # Returns
 /// * `Result<(), Box<dyn std::error::Error>>` - Ok if successful
@@ -81,24 +308,239 @@ pub fn process_directory(
 pub fn process_single_file(
     input: &Path,
     output: &Path,
-    info_height: u32,
-    force_16_9: bool,
-    custom_logo_path: Option<&Path>,
+    options: &ProcessOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let orig_img = image::open(input)?;
-    let resources = crate::resource::Resources::new(info_height)?;
-    let watermarked = crate::image_processor::add_info_bar(
-        orig_img.clone(),
-        input,
-        info_height,
-        &resources,
-        custom_logo_path,
-    )?;
-    let final_img = if force_16_9 {
-        crate::image_processor::pad_to_16_9(&orig_img, &watermarked)
+    let brand_style_options;
+    let options = match brand_style(input, options) {
+        Some((brand, style)) => {
+            println!("[INFO] Using {} brand style", brand);
+            let mut overridden = options.clone();
+            style.apply(&mut overridden);
+            brand_style_options = overridden;
+            &brand_style_options
+        }
+        None => options,
+    };
+    let sidecar_options;
+    let options = match Sidecar::load_for(input)? {
+        Some(sidecar) => {
+            let mut overridden = options.clone();
+            sidecar.apply(&mut overridden);
+            sidecar_options = overridden;
+            &sidecar_options
+        }
+        None => options,
+    };
+    let opt_out_options;
+    let options = if watermark_opted_out(input, options) {
+        println!("[INFO] Watermark disabled by an opt-out rule");
+        let mut clean = options.clone();
+        WatermarkOptOut::strip(&mut clean);
+        opt_out_options = clean;
+        &opt_out_options
+    } else {
+        options
+    };
+    let brand_options;
+    let options = if matches!(options.backdrop.source, BackdropSource::BrandColor) {
+        let mut resolved = options.clone();
+        resolved.backdrop.resolve_brand_color(
+            input,
+            options.logo.as_deref(),
+            options.logo_provider.as_ref(),
+        );
+        brand_options = resolved;
+        &brand_options
+    } else {
+        options
+    };
+
+    let compact_options;
+    let options = match (options.small_images, crate::source::image_dimensions(input)) {
+        (Some(SmallImages::Compact), Ok((width, height)))
+            if width.max(height) < options.min_size =>
+        {
+            let mut compact = options.clone();
+            // Below `info_height` since the image is smaller than `min_size`, so it fits a u32
+            let compact_height =
+                options.info_height as u64 * width.max(height) as u64 / options.min_size as u64;
+            compact.info_height = (compact_height as u32).max(48);
+            println!(
+                "[INFO] Small image, using a compact {}px bar",
+                compact.info_height
+            );
+            compact_options = compact;
+            &compact_options
+        }
+        _ => options,
+    };
+    let scaled_options;
+    let options = if options.scale > 1 {
+        scaled_options = options.scaled();
+        &scaled_options
+    } else {
+        options
+    };
+
+    let resources =
+        crate::resource::Resources::new(options.info_height, options.fonts_dir.as_deref())?;
+    let metadata = crate::image_processor::resolve_metadata(input, options);
+    if !options.bar_only {
+        if let Some(frames) = crate::animation::read_animation(input)? {
+            return crate::animation::process_animation(
+                frames, input, output, &metadata, &resources, options,
+            );
+        }
+    }
+    if options.bar_only {
+        let width = match options.bar_width {
+            Some(width) => width,
+            None => crate::source::image_dimensions(input)?.0,
+        };
+        let background = match options.bar_color {
+            BarColor::White => image::Rgba([255, 255, 255, 255]),
+            bar_color => crate::image_processor::bar_background(
+                &crate::source::open_image(input)?.to_rgba8(),
+                input,
+                bar_color,
+            ),
+        };
+        let bar = crate::image_processor::render_info_bar(
+            width, input, &metadata, &resources, options, background,
+        )?;
+        crate::output::save_image(&image::DynamicImage::ImageRgba8(bar), output, options)?;
+        return Ok(());
+    }
+
+    let mut orig_img = crate::source::open_image(input)?;
+    let long_edge = orig_img.width().max(orig_img.height());
+    if options.small_images == Some(SmallImages::Scale) && long_edge < options.min_size {
+        println!(
+            "[INFO] Small image, upscaling to {}px on the long edge",
+            options.min_size
+        );
+        orig_img = orig_img.resize(
+            options.min_size,
+            options.min_size,
+            image::imageops::FilterType::Lanczos3,
+        );
+    }
+    if let Some(issue) = crate::validate::check_photo(options, orig_img.width(), orig_img.height())
+    {
+        println!("{}", issue);
+    }
+    if options.enhance {
+        let mut photo = orig_img.to_rgba8();
+        crate::enhance::auto_levels(&mut photo);
+        orig_img = image::DynamicImage::ImageRgba8(photo);
+    }
+    if options.sixteen_bit() {
+        orig_img = image::DynamicImage::ImageRgba16(orig_img.to_rgba16());
+    }
+    let watermarked = match &options.overlay {
+        Some(slots) => {
+            crate::overlay::add_overlay(orig_img.clone(), &metadata, &resources, options, slots)
+        }
+        None => crate::image_processor::add_info_bar(
+            orig_img.clone(),
+            input,
+            &metadata,
+            &resources,
+            options,
+        )?,
+    };
+    let canvas = match options.paper {
+        Some(paper) => Some(paper.canvas(
+            options.dpi.unwrap_or(crate::print::DEFAULT_DPI),
+            watermarked.width() > watermarked.height(),
+        )),
+        None => options.canvas,
+    };
+    let mut final_img = if let Some(canvas) = canvas {
+        crate::image_processor::fit_to_canvas(&orig_img, &watermarked, canvas, &options.backdrop)
+    } else if options.force_16_9 {
+        crate::image_processor::pad_to_16_9(&orig_img, &watermarked, &options.backdrop)
     } else {
         watermarked
     };
-    final_img.save(output)?;
+    if let (Some(intensity), GrainScope::Canvas) =
+        (options.backdrop.grain, options.backdrop.grain_scope)
+    {
+        final_img = match final_img {
+            image::DynamicImage::ImageRgba16(mut canvas) => {
+                crate::backdrop::apply_grain(&mut canvas, intensity);
+                image::DynamicImage::ImageRgba16(canvas)
+            }
+            final_img => {
+                let mut canvas = final_img.to_rgba8();
+                crate::backdrop::apply_grain(&mut canvas, intensity);
+                image::DynamicImage::ImageRgba8(canvas)
+            }
+        };
+    }
+    if let Some(pack) = &options.event_pack {
+        final_img = pack.apply(final_img, input);
+    }
+    if let Some(area) = options.safe_area {
+        let mut canvas = final_img.to_rgba8();
+        crate::safe_area::draw_guides(&mut canvas, area);
+        final_img = image::DynamicImage::ImageRgba8(canvas);
+    }
+    if options.bleed.is_some() || options.crop_marks {
+        let dpi = options.dpi.unwrap_or(crate::print::DEFAULT_DPI);
+        let bleed = options.bleed.map_or(0, |bleed| bleed.to_pixels(dpi));
+        println!("[INFO] Adding a {}px bleed", bleed);
+        final_img = crate::print::add_bleed(&final_img, bleed, options.crop_marks, dpi)?;
+    }
+    if let Some(profile) = &options.print_profile {
+        // Only JPEG outputs are tagged with the profile, others would pass for sRGB
+        if crate::output::output_format(output, options)? != image::ImageFormat::Jpeg {
+            return Err(format!(
+                "Print profiles are only embedded in JPEG outputs, {} would be left untagged",
+                output.display()
+            )
+            .into());
+        }
+        println!("[INFO] Converting to print profile {}", profile.name);
+        final_img = profile.convert(&final_img);
+    }
+    let embedded = EmbeddedMetadata {
+        exif: if options.keep_exif {
+            crate::exif::output_exif(input, options.strip_private)
+        } else {
+            None
+        },
+        // XMP repeats the GPS position and serial numbers in properties of its own
+        xmp: if options.keep_xmp && !options.strip_private {
+            std::fs::read(input)
+                .ok()
+                .and_then(|data| crate::jpeg::xmp_packet(&data))
+        } else {
+            None
+        },
+    };
+    crate::output::save_outputs(&final_img, output, &embedded, options)?;
+
+    if options.bar_layer {
+        let bar = crate::image_processor::render_info_bar(
+            orig_img.width(),
+            input,
+            &metadata,
+            &resources,
+            options,
+            image::Rgba([0, 0, 0, 0]),
+        )?;
+        let layer_path = crate::output::bar_layer_path(output);
+        crate::output::save_image_as(
+            &image::DynamicImage::ImageRgba8(bar),
+            &layer_path,
+            image::ImageFormat::Png,
+            options,
+        )?;
+        println!(
+            "[INFO] Saved transparent bar layer: {}",
+            layer_path.display()
+        );
+    }
     Ok(())
 }
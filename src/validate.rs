@@ -0,0 +1,247 @@
+//! Option validation module
+//!
+//! This module sanity-checks combinations of processing options before a batch starts, so
+//! mistakes surface as one actionable message instead of a failure or a surprising output per
+//! photo.
+
+use crate::options::ProcessOptions;
+use crate::output::OutputFormat;
+use std::fmt;
+use std::path::Path;
+
+/// Problem found in the processing options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// Processing cannot succeed with these options
+    Error(String),
+    /// Processing works, but likely not as intended
+    Warning(String),
+}
+
+impl Issue {
+    /// Returns whether the issue prevents processing
+    pub fn is_error(&self) -> bool {
+        matches!(self, Issue::Error(_))
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Error(message) => write!(f, "[ERROR] {}", message),
+            Issue::Warning(message) => write!(f, "[WARN] {}", message),
+        }
+    }
+}
+
+/// Checks the processing options for invalid or conflicting settings
+///
+/// # Arguments
+/// * `options` - Processing options
+///
+/// # Returns
+/// * `Vec<Issue>` - Problems found, empty if the options are fine
+pub fn validate_options(options: &ProcessOptions) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if options.info_height == 0 {
+        issues.push(Issue::Error(
+            "The information bar height must be greater than zero".to_string(),
+        ));
+    }
+    for (name, path) in [
+        ("Logo", options.logo.as_deref()),
+        ("Secondary logo", options.theme.secondary_logo.as_deref()),
+    ] {
+        if let Some(issue) = path.and_then(|path| check_image(name, path)) {
+            issues.push(issue);
+        }
+    }
+    if options.canvas.is_some() && options.force_16_9 {
+        issues.push(Issue::Warning(
+            "--force-16-9 is ignored when --canvas sets the output size".to_string(),
+        ));
+    }
+    if options.paper.is_some() && (options.canvas.is_some() || options.force_16_9) {
+        issues.push(Issue::Warning(
+            "--paper sets the output size, --canvas and --force-16-9 are ignored".to_string(),
+        ));
+    }
+    if options.overlay.is_some() {
+        if options.bar_only {
+            issues.push(Issue::Warning(
+                "--bar-only renders the bar layout, the overlay slots are ignored".to_string(),
+            ));
+        } else if options.force_16_9 || options.canvas.is_some() || options.paper.is_some() {
+            issues.push(Issue::Warning(
+                "The overlay text stays on the photo, the padded backdrop around it is left bare"
+                    .to_string(),
+            ));
+        }
+    }
+    if options.bar_width.is_some() && !options.bar_only {
+        issues.push(Issue::Warning(
+            "--bar-width only applies with --bar-only and is ignored".to_string(),
+        ));
+    }
+    if options.sixteen_bit() {
+        for (enabled, name) in [
+            (options.overlay.is_some(), "--overlay"),
+            (options.safe_area.is_some(), "--safe-area"),
+        ] {
+            if enabled {
+                issues.push(Issue::Warning(format!(
+                    "{} works at 8 bits, the 16-bit output loses its extra depth",
+                    name
+                )));
+            }
+        }
+    }
+    if options.strip_private && options.keep_xmp {
+        issues.push(Issue::Warning(
+            "--strip-private leaves the XMP packet out, --keep-xmp is ignored".to_string(),
+        ));
+    }
+    if options.uses_weather() && (options.strip_private || options.paranoid) {
+        issues.push(Issue::Warning(
+            "The GPS position is not sent for the weather with --strip-private or --paranoid, \
+             {weather} and {temperature} stay empty"
+                .to_string(),
+        ));
+    }
+    if let Some(template) = options
+        .params_template
+        .as_deref()
+        .filter(|template| !template.contains('|'))
+    {
+        let exposure_tokens = crate::template::tokens(template)
+            .into_iter()
+            .filter(|token| ["focal", "aperture", "shutter", "iso"].contains(token))
+            .count();
+        if exposure_tokens > 1 {
+            issues.push(Issue::Warning(
+                "--params-template has no `|`, so its parameters wrap, drop and are left out \
+                 as one field"
+                    .to_string(),
+            ));
+        }
+    }
+    if options.strip_private && options.show_location {
+        issues.push(Issue::Warning(
+            "--show-location prints the place of the GPS position that --strip-private removes"
+                .to_string(),
+        ));
+    }
+    if options.print_profile.is_some() {
+        if options.sixteen_bit() {
+            issues.push(Issue::Error(
+                "--print-profile converts 8-bit pixels and is only embedded in JPEG outputs, \
+                 the 16-bit TIFF would lose its depth and the profile"
+                    .to_string(),
+            ));
+        } else if [options.convert_to, options.output_format]
+            .iter()
+            .flatten()
+            .any(|format| *format != OutputFormat::Jpeg)
+        {
+            issues.push(Issue::Error(
+                "--print-profile is only embedded in JPEG outputs, other formats would be read \
+                 as sRGB"
+                    .to_string(),
+            ));
+        }
+    }
+    #[cfg(not(feature = "lossy-webp"))]
+    if options.lossy_webp {
+        issues.push(Issue::Error(
+            "--lossy-webp requires a build with the `lossy-webp` feature".to_string(),
+        ));
+    }
+    #[cfg(not(feature = "avif"))]
+    if [options.convert_to, options.output_format].contains(&Some(OutputFormat::Avif)) {
+        issues.push(Issue::Error(
+            "AVIF output requires a build with the `avif` feature".to_string(),
+        ));
+    }
+    issues
+}
+
+/// Checks the options against the size of a photo
+///
+/// # Arguments
+/// * `options` - Processing options
+/// * `width` - Width of the photo in pixels
+/// * `height` - Height of the photo in pixels
+///
+/// # Returns
+/// * `Option<Issue>` - Problem found, `None` if the options fit the photo
+pub fn check_photo(options: &ProcessOptions, width: u32, height: u32) -> Option<Issue> {
+    (options.overlay.is_none() && options.info_height > height).then(|| {
+        Issue::Warning(format!(
+            "The {}px information bar is taller than the {}x{} photo, consider a smaller --info-height",
+            options.info_height, width, height
+        ))
+    })
+}
+
+/// Returns an error if a file cannot be read as an image
+fn check_image(name: &str, path: &Path) -> Option<Issue> {
+    image::image_dimensions(path).err().map(|e| {
+        Issue::Error(format!(
+            "{} {} is not a readable image: {}",
+            name,
+            path.display(),
+            e
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_validate_options() {
+        assert!(validate_options(&ProcessOptions::default()).is_empty());
+
+        let options = ProcessOptions {
+            info_height: 0,
+            logo: Some(PathBuf::from("missing-logo.png")),
+            force_16_9: true,
+            canvas: Some((1920, 1080)),
+            ..ProcessOptions::default()
+        };
+        let issues = validate_options(&options);
+        assert_eq!(issues.iter().filter(|issue| issue.is_error()).count(), 2);
+        assert_eq!(issues.len(), 3);
+
+        let options = ProcessOptions {
+            strip_private: true,
+            keep_xmp: true,
+            ..ProcessOptions::default()
+        };
+        assert!(!validate_options(&options)[0].is_error());
+
+        let mut options = ProcessOptions {
+            caption: Some("{weather}, {temperature}".to_string()),
+            ..ProcessOptions::default()
+        };
+        assert!(options.weather_lookup());
+        assert!(validate_options(&options).is_empty());
+        options.strip_private = true;
+        assert!(!options.weather_lookup());
+        assert_eq!(validate_options(&options).len(), 1);
+        options.caption = Some("{city}".to_string());
+        assert!(!options.weather_lookup());
+
+        let options = ProcessOptions {
+            params_template: Some("{focal}mm · f/{aperture}".to_string()),
+            ..ProcessOptions::default()
+        };
+        assert_eq!(validate_options(&options).len(), 1);
+
+        let options = ProcessOptions::default();
+        assert!(check_photo(&options, 320, 120).is_some());
+        assert!(check_photo(&options, 6000, 4000).is_none());
+    }
+}
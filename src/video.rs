@@ -0,0 +1,113 @@
+//! Slideshow video module
+//!
+//! This module pipes framed outputs to an `ffmpeg` subprocess to assemble a slideshow video.
+
+use image::{DynamicImage, ImageFormat};
+use std::error::Error;
+use std::io::{BufWriter, Cursor, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// Slideshow settings
+#[derive(Debug, Clone)]
+pub struct SlideshowOptions {
+    /// Output video path, e.g. `slideshow.mp4`
+    pub output: std::path::PathBuf,
+    /// Seconds every frame stays on screen
+    pub seconds_per_frame: f32,
+    /// Size of the video in pixels, frames are scaled and letterboxed to fit
+    pub size: (u32, u32),
+}
+
+/// Writer feeding frames to an `ffmpeg` subprocess
+pub struct SlideshowWriter {
+    child: Child,
+    stdin: Option<BufWriter<ChildStdin>>,
+    frames: usize,
+}
+
+impl SlideshowWriter {
+    /// Spawns `ffmpeg` reading PNG frames from its standard input
+    ///
+    /// # Arguments
+    /// * `options` - Slideshow settings
+    ///
+    /// # Returns
+    /// * `Result<SlideshowWriter, Box<dyn Error>>` - Writer if `ffmpeg` could be started
+    ///
+    /// # Errors
+    /// Returns an error if `ffmpeg` is not installed or cannot be started
+    pub fn spawn(options: &SlideshowOptions) -> Result<Self, Box<dyn Error>> {
+        let (width, height) = options.size;
+        let filter = format!(
+            "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,format=yuv420p",
+            w = width,
+            h = height
+        );
+        let mut child = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error", "-f", "image2pipe", "-framerate"])
+            .arg(format!("1/{}", options.seconds_per_frame))
+            .args(["-c:v", "png", "-i", "-", "-vf"])
+            .arg(filter)
+            .args(["-r", "30"])
+            .arg(&options.output)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to start ffmpeg, is it installed? {}", e))?;
+        let stdin = child.stdin.take().map(BufWriter::new);
+        println!(
+            "[INFO] Writing slideshow video to {}",
+            options.output.display()
+        );
+        Ok(SlideshowWriter {
+            child,
+            stdin,
+            frames: 0,
+        })
+    }
+
+    /// Sends a frame to the video
+    ///
+    /// # Arguments
+    /// * `frame` - Framed image
+    ///
+    /// # Errors
+    /// Returns an error if the frame cannot be encoded or `ffmpeg` exited early
+    pub fn push(&mut self, frame: &DynamicImage) -> Result<(), Box<dyn Error>> {
+        let stdin = self.stdin.as_mut().ok_or("ffmpeg input is closed")?;
+        let mut png = Cursor::new(Vec::new());
+        frame.write_to(&mut png, ImageFormat::Png)?;
+        stdin.write_all(png.get_ref())?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Closes the input of `ffmpeg` and waits for the video to be written
+    ///
+    /// # Errors
+    /// Returns an error if `ffmpeg` fails
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(mut stdin) = self.stdin.take() {
+            stdin.flush()?;
+        }
+        let status = self.child.wait()?;
+        if !status.success() {
+            return Err(format!("ffmpeg exited with {}", status).into());
+        }
+        println!("[INFO] Slideshow video written with {} frames", self.frames);
+        Ok(())
+    }
+}
+
+/// Pushes a saved output file to the slideshow
+///
+/// # Arguments
+/// * `writer` - Slideshow writer
+/// * `output` - Path of the framed output file
+///
+/// # Errors
+/// Returns an error if the output cannot be read or sent to `ffmpeg`
+pub fn push_file(writer: &mut SlideshowWriter, output: &Path) -> Result<(), Box<dyn Error>> {
+    let frame = image::open(output)?;
+    writer.push(&frame)
+}
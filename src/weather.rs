@@ -0,0 +1,158 @@
+//! Weather module
+//!
+//! This module looks up the historical weather at the place and hour of capture from the
+//! Open-Meteo archive. Responses are cached on disk per location and day, every request has a
+//! hard timeout, and lookups stop for the rest of the run after the first failure, so a batch
+//! never hangs on the network.
+
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Open-Meteo historical weather endpoint
+const ARCHIVE_URL: &str = "https://archive-api.open-meteo.com/v1/archive";
+/// Hard limit of a lookup, including connecting and reading the response
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Set after a failed lookup, so the remaining photos of a batch skip the network
+static UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// Hourly series of an archive response, in UTC
+#[derive(Debug, Deserialize)]
+struct Hourly {
+    temperature_2m: Vec<Option<f64>>,
+    weather_code: Vec<Option<u32>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArchiveResponse {
+    hourly: Hourly,
+}
+
+/// Returns the weather template values of a capture
+///
+/// Provides `{weather}` with the condition, e.g. `Light rain`, and `{temperature}`, e.g. `18 °C`.
+/// Returns no values when the weather cannot be looked up.
+///
+/// # Arguments
+/// * `julian_day` - Julian day of the capture time
+/// * `latitude` - Latitude in degrees, north positive
+/// * `longitude` - Longitude in degrees, east positive
+pub fn template_fields(
+    julian_day: f64,
+    latitude: f64,
+    longitude: f64,
+) -> Vec<(&'static str, String)> {
+    let (year, month, day, hours) = crate::time_of_day::calendar_date(julian_day);
+    let date = format!("{:04}-{:02}-{:02}", year, month, day);
+    let Some(hourly) = daily_weather(&date, latitude, longitude) else {
+        return Vec::new();
+    };
+    let hour = (hours.floor() as usize).min(23);
+    let mut fields = Vec::new();
+    if let Some(Some(code)) = hourly.weather_code.get(hour) {
+        fields.push(("weather", weather_label(*code).to_string()));
+    }
+    if let Some(Some(temperature)) = hourly.temperature_2m.get(hour) {
+        fields.push(("temperature", format!("{:.0} °C", temperature)));
+    }
+    fields
+}
+
+/// Returns the hourly weather of a day, from the cache or the archive
+fn daily_weather(date: &str, latitude: f64, longitude: f64) -> Option<Hourly> {
+    // Two decimals are about a kilometer, close enough to share a cached day
+    let cache_path = cache_dir().join(format!("{:.2}_{:.2}_{}.json", latitude, longitude, date));
+    if let Ok(body) = std::fs::read_to_string(&cache_path) {
+        if let Ok(response) = serde_json::from_str::<ArchiveResponse>(&body) {
+            return Some(response.hourly);
+        }
+    }
+    if UNAVAILABLE.load(Ordering::Relaxed) {
+        return None;
+    }
+    match fetch(date, latitude, longitude) {
+        Ok(body) => {
+            let response = serde_json::from_str::<ArchiveResponse>(&body).ok()?;
+            if std::fs::create_dir_all(cache_dir()).is_ok() {
+                let _ = std::fs::write(&cache_path, &body);
+            }
+            Some(response.hourly)
+        }
+        Err(e) => {
+            if !UNAVAILABLE.swap(true, Ordering::Relaxed) {
+                println!(
+                    "[WARN] Weather lookup failed, skipping weather for this run: {}",
+                    e
+                );
+            }
+            None
+        }
+    }
+}
+
+/// Requests the hourly weather of a day from the archive
+fn fetch(date: &str, latitude: f64, longitude: f64) -> Result<String, ureq::Error> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .build()
+        .into();
+    agent
+        .get(ARCHIVE_URL)
+        .query("latitude", format!("{:.4}", latitude))
+        .query("longitude", format!("{:.4}", longitude))
+        .query("start_date", date)
+        .query("end_date", date)
+        .query("hourly", "temperature_2m,weather_code")
+        .query("timezone", "GMT")
+        .call()?
+        .body_mut()
+        .read_to_string()
+}
+
+/// Returns the directory of cached weather responses
+fn cache_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("lensight").join("weather")
+}
+
+/// Names a WMO weather interpretation code
+fn weather_label(code: u32) -> &'static str {
+    match code {
+        0 => "Clear",
+        1 => "Mainly clear",
+        2 => "Partly cloudy",
+        3 => "Overcast",
+        45 | 48 => "Fog",
+        51..=57 => "Drizzle",
+        61 | 80 => "Light rain",
+        63 | 81 => "Rain",
+        65 | 82 => "Heavy rain",
+        66 | 67 => "Freezing rain",
+        71 | 85 => "Light snow",
+        73 | 75 | 77 | 86 => "Snow",
+        95..=99 => "Thunderstorm",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_response() {
+        let body = r#"{"hourly":{"time":["2024-06-01T00:00","2024-06-01T01:00"],
+            "temperature_2m":[12.4,null],"weather_code":[61,3]}}"#;
+        let hourly = serde_json::from_str::<ArchiveResponse>(body)
+            .unwrap()
+            .hourly;
+        assert_eq!(hourly.temperature_2m, vec![Some(12.4), None]);
+        assert_eq!(weather_label(hourly.weather_code[0].unwrap()), "Light rain");
+        assert_eq!(weather_label(hourly.weather_code[1].unwrap()), "Overcast");
+    }
+}
@@ -0,0 +1,266 @@
+//! XMP module
+//!
+//! This module reads properties of XMP packets, where editors keep the camera and lens data
+//! they drop from EXIF on export, either embedded in the image or in a `.xmp` sidecar file.
+
+use crate::exif::{ExifInfo, UNKNOWN};
+use std::path::{Path, PathBuf};
+
+/// Properties holding the lens model, in lookup order
+const LENS_PROPERTIES: &[&str] = &["exifEX:LensModel", "aux:Lens"];
+
+/// Properties holding the ISO sensitivity, in lookup order
+const ISO_PROPERTIES: &[&str] = &["exifEX:PhotographicSensitivity", "exif:ISOSpeedRatings"];
+
+/// Reads a simple property of an XMP packet
+///
+/// Both the attribute form `name="value"` and the element form `<name>value</name>` are
+/// understood, as well as the first item of an `rdf:Seq`, `rdf:Bag` or `rdf:Alt` element.
+///
+/// # Arguments
+/// * `packet` - XMP packet
+/// * `name` - Qualified property name, e.g. `tiff:Model`
+///
+/// # Returns
+/// * `Option<String>` - Unescaped value, `None` if the property is missing or empty
+pub fn property(packet: &str, name: &str) -> Option<String> {
+    let attribute = format!("{}=", name);
+    let from_attribute = packet.match_indices(&attribute).find_map(|(pos, _)| {
+        let preceded_by_space = packet[..pos].ends_with(|c: char| c.is_whitespace());
+        let rest = &packet[pos + attribute.len()..];
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = rest[1..].find(quote)?;
+        preceded_by_space.then(|| rest[1..1 + end].to_string())
+    });
+    let value = from_attribute.or_else(|| {
+        let open = format!("<{}>", name);
+        let start = packet.find(&open)? + open.len();
+        let end = start + packet[start..].find(&format!("</{}>", name))?;
+        let content = &packet[start..end];
+        let content = match content.find("<rdf:li") {
+            Some(item) => {
+                let item = &content[item..];
+                let start = item.find('>')? + 1;
+                &item[start..start + item[start..].find("</rdf:li>")?]
+            }
+            None => content,
+        };
+        Some(content.to_string())
+    })?;
+    let value = unescape(value.trim());
+    (!value.is_empty()).then_some(value)
+}
+
+/// Reads the camera, lens and exposure values of an XMP packet
+///
+/// Values are formatted like the matching EXIF fields, so templates render them the same.
+///
+/// # Arguments
+/// * `packet` - XMP packet
+///
+/// # Returns
+/// * `ExifInfo` - Values found in the packet, missing fields left unknown
+pub fn exif_info(packet: &str) -> ExifInfo {
+    let lens_model = LENS_PROPERTIES
+        .iter()
+        .find_map(|name| property(packet, name));
+    let rational = |name: &str| property(packet, name).and_then(|value| parse_rational(&value));
+    let decimal = |name: &str| rational(name).map(|value| value.to_string());
+    let shutter_speed = rational("exif:ExposureTime").map(|seconds| {
+        if seconds >= 1.0 {
+            seconds.to_string()
+        } else {
+            // A rounded `0.0166` would otherwise print as `1/60.24096385542168`
+            format!("1/{}", (1.0 / seconds).round())
+        }
+    });
+    let iso = ISO_PROPERTIES
+        .iter()
+        .find_map(|name| property(packet, name));
+    let latitude = property(packet, "exif:GPSLatitude").and_then(|value| parse_coordinate(&value));
+    let longitude =
+        property(packet, "exif:GPSLongitude").and_then(|value| parse_coordinate(&value));
+    let unknown = || UNKNOWN.to_string();
+    ExifInfo {
+        camera_model: property(packet, "tiff:Model").unwrap_or_else(unknown),
+        lens_model: lens_model.unwrap_or_else(unknown),
+        focal_length: decimal("exif:FocalLength").unwrap_or_else(unknown),
+        aperture: decimal("exif:FNumber").unwrap_or_else(unknown),
+        shutter_speed: shutter_speed.unwrap_or_else(unknown),
+        iso: iso.unwrap_or_else(unknown),
+        gps: latitude.zip(longitude),
+        date_time: ["exif:DateTimeOriginal", "xmp:CreateDate"]
+            .iter()
+            .find_map(|name| property(packet, name))
+            .and_then(|value| exif_date_time(&value)),
+        artist: None,
+        copyright: None,
+    }
+}
+
+/// Returns the `.xmp` sidecars of an image that exist
+///
+/// Both the `photo.jpg.xmp` naming of darktable and the `photo.xmp` naming of Lightroom and
+/// Capture One are looked up.
+///
+/// # Arguments
+/// * `file_path` - Path to the image file
+///
+/// # Returns
+/// * `Vec<PathBuf>` - Paths of the sidecars, in lookup order
+pub fn sidecar_paths(file_path: &Path) -> Vec<PathBuf> {
+    let mut appended = file_path.as_os_str().to_owned();
+    appended.push(".xmp");
+    let mut sidecars: Vec<PathBuf> = Vec::new();
+    for path in [
+        PathBuf::from(appended),
+        file_path.with_extension("xmp"),
+        file_path.with_extension("XMP"),
+    ] {
+        // `photo.xmp` and `photo.XMP` are one file on case-insensitive file systems
+        let known = sidecars
+            .iter()
+            .any(|sidecar| sidecar.canonicalize().ok() == path.canonicalize().ok());
+        if path.is_file() && !known {
+            sidecars.push(path);
+        }
+    }
+    sidecars
+}
+
+/// Reads the EXIF information of the `.xmp` sidecar of an image
+///
+/// # Arguments
+/// * `file_path` - Path to the image file
+///
+/// # Returns
+/// * `Option<(PathBuf, ExifInfo)>` - Path and EXIF information of the sidecar, `None` if
+///   there is no readable sidecar
+pub fn read_sidecar(file_path: &Path) -> Option<(PathBuf, ExifInfo)> {
+    sidecar_paths(file_path).into_iter().find_map(|path| {
+        let packet = std::fs::read_to_string(&path).ok()?;
+        let info = exif_info(&packet);
+        Some((path, info))
+    })
+}
+
+/// Parses an XMP rational such as `28/10`, or a plain number
+fn parse_rational(value: &str) -> Option<f64> {
+    let value = match value.split_once('/') {
+        Some((num, denom)) => {
+            let denom: f64 = denom.trim().parse().ok()?;
+            (denom != 0.0).then_some(num.trim().parse::<f64>().ok()? / denom)?
+        }
+        None => value.trim().parse().ok()?,
+    };
+    (value.is_finite() && value > 0.0).then_some(value)
+}
+
+/// Parses an XMP GPS coordinate such as `35,0.696N` or `135,46,5.2E` into signed degrees
+fn parse_coordinate(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let hemisphere = value.chars().last()?;
+    let sign = match hemisphere.to_ascii_uppercase() {
+        'N' | 'E' => 1.0,
+        'S' | 'W' => -1.0,
+        _ => return None,
+    };
+    let parts: Vec<f64> = value[..value.len() - 1]
+        .split(',')
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let degrees = match parts.as_slice() {
+        [degrees, minutes] => degrees + minutes / 60.0,
+        [degrees, minutes, seconds] => degrees + minutes / 60.0 + seconds / 3600.0,
+        _ => return None,
+    };
+    Some(sign * degrees)
+}
+
+/// Converts an XMP date such as `2024-05-04T18:30:00+02:00` to the EXIF form
+/// `2024:05:04 18:30:00`
+fn exif_date_time(value: &str) -> Option<String> {
+    let (date, time) = value.split_once('T').unwrap_or((value, "00:00:00"));
+    let date = date.get(..10).filter(|date| date.len() == 10)?;
+    let time: String = time
+        .chars()
+        .take_while(|c| *c != '+' && *c != '-' && *c != 'Z')
+        .collect();
+    let time = time.split('.').next().unwrap_or_default();
+    let time = match time.len() {
+        5 => format!("{}:00", time),
+        8 => time.to_string(),
+        _ => return None,
+    };
+    Some(format!("{} {}", date.replace('-', ":"), time))
+}
+
+/// Replaces the predefined XML entities of a value
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_property() {
+        let packet = r#"<x:xmpmeta><rdf:RDF><rdf:Description tiff:Model="X-T5"
+            aux:Lens="XF16-55mmF2.8 R LM WR" exif:FNumber="28/10">
+            <exifEX:LensModel>XF 16-55mm &amp; more</exifEX:LensModel>
+            <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Dunes</rdf:li></rdf:Alt></dc:title>
+            </rdf:Description></rdf:RDF></x:xmpmeta>"#;
+        assert_eq!(property(packet, "tiff:Model").as_deref(), Some("X-T5"));
+        assert_eq!(property(packet, "exif:FNumber").as_deref(), Some("28/10"));
+        assert_eq!(property(packet, "dc:title").as_deref(), Some("Dunes"));
+        assert_eq!(property(packet, "tiff:Make"), None);
+        assert_eq!(property(packet, "Model"), None);
+
+        let info = exif_info(packet);
+        assert_eq!(info.camera_model, "X-T5");
+        assert_eq!(info.lens_model, "XF 16-55mm & more");
+        assert_eq!(info.aperture, "2.8");
+        assert_eq!(info.iso, UNKNOWN);
+    }
+
+    #[test]
+    fn test_read_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let photo = dir.path().join("DSC_0042.jpg");
+        assert!(read_sidecar(&photo).is_none());
+
+        std::fs::write(
+            dir.path().join("DSC_0042.jpg.xmp"),
+            r#"<x:xmpmeta><rdf:RDF><rdf:Description tiff:Model="NIKON Z 6_2"
+            exif:FocalLength="350/10" exif:FNumber="18/10" exif:ExposureTime="1/250"
+            exif:DateTimeOriginal="2024-05-04T18:30:12.40+02:00"
+            exif:GPSLatitude="35,0.696N" exif:GPSLongitude="135,46,5.16E">
+            <exif:ISOSpeedRatings><rdf:Seq><rdf:li>400</rdf:li></rdf:Seq></exif:ISOSpeedRatings>
+            </rdf:Description></rdf:RDF></x:xmpmeta>"#,
+        )
+        .unwrap();
+        let (path, info) = read_sidecar(&photo).unwrap();
+        assert_eq!(path, dir.path().join("DSC_0042.jpg.xmp"));
+        assert_eq!(info.camera_model, "NIKON Z 6_2");
+        assert_eq!(info.focal_length, "35");
+        assert_eq!(info.aperture, "1.8");
+        assert_eq!(info.shutter_speed, "1/250");
+        assert_eq!(info.iso, "400");
+        assert_eq!(info.lens_model, UNKNOWN);
+        assert_eq!(info.date_time.as_deref(), Some("2024:05:04 18:30:12"));
+        let (latitude, longitude) = info.gps.unwrap();
+        assert!((latitude - 35.0116).abs() < 1e-4);
+        assert!((longitude - 135.7681).abs() < 1e-4);
+
+        for (exposure, shutter) in [("0.0166", "1/60"), ("0.3", "1/3"), ("10/1250", "1/125")] {
+            let packet = format!(r#"<rdf:Description exif:ExposureTime="{}"/>"#, exposure);
+            assert_eq!(exif_info(&packet).shutter_speed, shutter);
+        }
+    }
+}